@@ -0,0 +1,12 @@
+fn main() -> Result<(), Box<dyn std::error::Error>> {
+    println!("cargo:rerun-if-changed=proto/raft.proto");
+
+    // Use the protoc provided by the environment if there is one, otherwise the bundled one, so
+    // that building this crate does not require installing protoc.
+    if std::env::var_os("PROTOC").is_none() {
+        std::env::set_var("PROTOC", protoc_bin_vendored::protoc_bin_path()?);
+    }
+
+    tonic_build::configure().btree_map(["."]).compile_protos(&["proto/raft.proto"], &["proto"])?;
+    Ok(())
+}
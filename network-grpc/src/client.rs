@@ -0,0 +1,461 @@
+use std::collections::HashMap;
+use std::future::Future;
+use std::io;
+use std::sync::Arc;
+use std::sync::Mutex;
+use std::time::Duration;
+
+use openraft::alias::VoteOf;
+use openraft::error::ClientWriteError;
+use openraft::error::NetworkError;
+use openraft::error::PayloadTooLarge;
+use openraft::error::RPCError;
+use openraft::error::RaftError;
+use openraft::error::RemoteError;
+use openraft::error::ReplicationClosed;
+use openraft::error::StreamingError;
+use openraft::error::Timeout;
+use openraft::error::Unreachable;
+use openraft::network::v2::snapshot_stream::send_snapshot;
+use openraft::network::v2::snapshot_stream::SnapshotChunk;
+use openraft::network::v2::snapshot_stream::SnapshotChunkSink;
+use openraft::network::v2::RaftNetworkV2;
+use openraft::network::Backoff;
+use openraft::network::ProtocolVersion;
+use openraft::network::RPCOption;
+use openraft::network::RPCTypes;
+use openraft::raft::AppendEntriesRequest;
+use openraft::raft::AppendEntriesResponse;
+use openraft::raft::CatchUpRequest;
+use openraft::raft::CatchUpResponse;
+use openraft::raft::ClientWriteResponse;
+use openraft::raft::HandshakeRequest;
+use openraft::raft::HandshakeResponse;
+use openraft::raft::HibernateRequest;
+use openraft::raft::SnapshotResponse;
+use openraft::raft::TransferLeaderRequest;
+use openraft::raft::VoteRequest;
+use openraft::raft::VoteResponse;
+use openraft::BasicNode;
+use openraft::OptionalSend;
+use openraft::RaftNetworkFactory;
+use openraft::Snapshot;
+use prost::Message;
+use tokio::io::AsyncRead;
+use tokio::sync::mpsc;
+use tokio_stream::wrappers::ReceiverStream;
+use tonic::transport::Channel;
+use tonic::transport::Endpoint;
+use tonic::Code;
+use tonic::Status;
+use tonic::Streaming;
+
+use crate::convert;
+use crate::pb;
+use crate::pb::raft_service_client::RaftServiceClient;
+use crate::Codec;
+use crate::GrpcConfig;
+use crate::GrpcTypeConfig;
+use crate::JsonCodec;
+
+/// A node that can be reached at a gRPC address.
+pub trait NodeAddr {
+    /// The address to connect to, such as `127.0.0.1:21001`, or a URI such as
+    /// `http://raft-1:21001`.
+    fn addr(&self) -> &str;
+}
+
+impl NodeAddr for BasicNode {
+    fn addr(&self) -> &str {
+        &self.addr
+    }
+}
+
+/// Channels by address, shared by every [`GrpcConnection`] built by the same factory.
+type Channels = Arc<Mutex<HashMap<String, Channel>>>;
+
+/// The [`RaftNetworkFactory`] of a node that sends RPCs over gRPC.
+///
+/// RPCs to the same address share one HTTP/2 channel, which connects on the first RPC and
+/// reconnects after it breaks. [`impl_grpc_network!`](crate::impl_grpc_network) must be invoked
+/// once for the type config to make [`GrpcConnection`] a `RaftNetworkV2`.
+pub struct GrpcNetworkFactory<C, K = JsonCodec>
+where C: GrpcTypeConfig
+{
+    id: C::NodeId,
+    config: Arc<GrpcConfig>,
+    codec: K,
+    channels: Channels,
+}
+
+impl<C, K> Clone for GrpcNetworkFactory<C, K>
+where
+    C: GrpcTypeConfig,
+    K: Codec,
+{
+    fn clone(&self) -> Self {
+        Self {
+            id: self.id,
+            config: self.config.clone(),
+            codec: self.codec.clone(),
+            channels: self.channels.clone(),
+        }
+    }
+}
+
+impl<C, K> GrpcNetworkFactory<C, K>
+where
+    C: GrpcTypeConfig,
+    K: Codec,
+{
+    /// Create a factory for node `id` with the default codec.
+    pub fn new(id: C::NodeId, config: GrpcConfig) -> Self
+    where K: Default {
+        Self::with_codec(id, config, K::default())
+    }
+
+    /// Create a factory for node `id` that encodes application data with `codec`.
+    pub fn with_codec(id: C::NodeId, config: GrpcConfig, codec: K) -> Self {
+        Self {
+            id,
+            config: Arc::new(config),
+            codec,
+            channels: Default::default(),
+        }
+    }
+}
+
+impl<C, K> RaftNetworkFactory<C> for GrpcNetworkFactory<C, K>
+where
+    C: GrpcTypeConfig,
+    C::Node: NodeAddr,
+    K: Codec,
+    GrpcConnection<C, K>: RaftNetworkV2<C>,
+{
+    type Network = GrpcConnection<C, K>;
+
+    async fn new_client(&mut self, target: C::NodeId, node: &C::Node) -> Self::Network {
+        GrpcConnection {
+            id: self.id,
+            target,
+            addr: node.addr().to_string(),
+            config: self.config.clone(),
+            codec: self.codec.clone(),
+            channels: self.channels.clone(),
+        }
+    }
+
+    /// [`GrpcConnection`] implements every RPC of the latest protocol version.
+    fn protocol_version(&self) -> ProtocolVersion {
+        ProtocolVersion::CURRENT
+    }
+}
+
+/// A connection from one node to another over gRPC.
+pub struct GrpcConnection<C, K = JsonCodec>
+where C: GrpcTypeConfig
+{
+    id: C::NodeId,
+    target: C::NodeId,
+    addr: String,
+    config: Arc<GrpcConfig>,
+    codec: K,
+    channels: Channels,
+}
+
+impl<C, K> GrpcConnection<C, K>
+where
+    C: GrpcTypeConfig,
+    C::SnapshotData: AsyncRead + Unpin,
+    K: Codec,
+{
+    /// Send an AppendEntries RPC to the target.
+    ///
+    /// If the encoded request is larger than [`GrpcConfig::max_message_size`], a
+    /// [`PayloadTooLarge`] error asks the caller to send half of the entries.
+    pub async fn send_append_entries(
+        &mut self,
+        rpc: AppendEntriesRequest<C>,
+        option: RPCOption,
+    ) -> Result<AppendEntriesResponse<C>, RPCError<C>> {
+        let n = rpc.entries.len() as u64;
+        let req = convert::append_entries_request_to_pb(&rpc, &self.codec).map_err(|e| NetworkError::new(&e))?;
+
+        let size = req.encoded_len();
+        if size > self.config.max_message_size {
+            if n > 1 {
+                return Err(PayloadTooLarge::new_entries_hint(n / 2).into());
+            }
+            // A single entry that does not fit in a message can never be sent.
+            let e = io::Error::new(
+                io::ErrorKind::InvalidInput,
+                format!("AppendEntries of {} bytes exceeds max_message_size", size),
+            );
+            return Err(NetworkError::new(&e).into());
+        }
+
+        let mut client = self.client()?;
+        let resp = self.with_timeout(RPCTypes::AppendEntries, &option, client.append_entries(req)).await?;
+
+        convert::append_entries_response_from_pb(resp).map_err(|e| NetworkError::new(&e).into())
+    }
+
+    /// Send a RequestVote RPC to the target.
+    pub async fn send_vote(&mut self, rpc: VoteRequest<C>, option: RPCOption) -> Result<VoteResponse<C>, RPCError<C>> {
+        let req = convert::vote_request_to_pb(&rpc);
+
+        let mut client = self.client()?;
+        let resp = self.with_timeout(RPCTypes::Vote, &option, client.vote(req)).await?;
+
+        convert::vote_response_from_pb(resp).map_err(|e| NetworkError::new(&e).into())
+    }
+
+    /// Stream a snapshot to the target in chunks of
+    /// [`Config::snapshot_max_chunk_size`](openraft::Config::snapshot_max_chunk_size).
+    ///
+    /// The snapshot data is read chunk by chunk, and at most [`GrpcConfig::snapshot_window`]
+    /// chunks are sent before they are acknowledged by the target.
+    pub async fn send_full_snapshot(
+        &mut self,
+        vote: VoteOf<C>,
+        snapshot: Snapshot<C>,
+        cancel: impl Future<Output = ReplicationClosed> + OptionalSend + 'static,
+        option: RPCOption,
+    ) -> Result<SnapshotResponse<C>, StreamingError<C>> {
+        let window = self.config.snapshot_window.max(1);
+        let (tx, rx) = mpsc::channel(window);
+
+        let mut cancel = Box::pin(cancel);
+
+        let mut client = self.client()?;
+        let acks = self.with_timeout(
+            RPCTypes::InstallSnapshot,
+            &option,
+            client.snapshot(ReceiverStream::new(rx)),
+        );
+        let acks = tokio::select! {
+            closed = &mut cancel => return Err(closed.into()),
+            acks = acks => acks?,
+        };
+
+        let mut sink = ChunkSink {
+            conn: self,
+            tx,
+            acks,
+            ttl: option.hard_ttl(),
+        };
+        send_snapshot(&mut sink, vote, snapshot, cancel, &option, window).await
+    }
+
+    /// Send a TransferLeader message to the target.
+    pub async fn send_transfer_leader(
+        &mut self,
+        req: TransferLeaderRequest<C>,
+        option: RPCOption,
+    ) -> Result<(), RPCError<C>> {
+        let req = convert::transfer_leader_request_to_pb(&req);
+
+        let mut client = self.client()?;
+        self.with_timeout(RPCTypes::TransferLeader, &option, client.transfer_leader(req)).await?;
+        Ok(())
+    }
+
+    /// Forward a client write to the target, which is believed to be the Leader.
+    ///
+    /// An error returned by the target's [`Raft`](openraft::Raft), such as `ForwardToLeader`, is
+    /// returned as a [`RemoteError`].
+    pub async fn send_forward_client_write(
+        &mut self,
+        app_data: C::D,
+        option: RPCOption,
+    ) -> Result<ClientWriteResponse<C>, RPCError<C, RaftError<C, ClientWriteError<C>>>> {
+        let data = self.codec.encode(&app_data).map_err(|e| NetworkError::new(&e))?;
+        let req = pb::ClientWriteRequest { data };
+
+        let mut client = self.client()?;
+        let resp = self
+            .with_timeout(RPCTypes::ClientWrite, &option, client.forward_client_write(req))
+            .await
+            .map_err(with_remote_error)?;
+
+        match convert::client_write_result_from_pb(resp, &self.codec).map_err(|e| NetworkError::new(&e))? {
+            Ok(resp) => Ok(resp),
+            Err(e) => Err(RemoteError::new(self.target, e).into()),
+        }
+    }
+
+    /// Exchange protocol versions with the target.
+    pub async fn send_handshake(
+        &mut self,
+        req: HandshakeRequest<C>,
+        option: RPCOption,
+    ) -> Result<HandshakeResponse, RPCError<C>> {
+        let req = convert::handshake_request_to_pb(&req);
+
+        let mut client = self.client()?;
+        let resp = self.with_timeout(RPCTypes::Handshake, &option, client.handshake(req)).await?;
+
+        Ok(convert::handshake_response_from_pb(resp))
+    }
+
+    /// Ask the target to bring another node up to date on behalf of the Leader.
+    pub async fn send_catch_up(
+        &mut self,
+        req: CatchUpRequest<C>,
+        option: RPCOption,
+    ) -> Result<CatchUpResponse<C>, RPCError<C>> {
+        let req = convert::catch_up_request_to_pb(&req, &self.codec).map_err(|e| NetworkError::new(&e))?;
+
+        let mut client = self.client()?;
+        let resp = self.with_timeout(RPCTypes::CatchUp, &option, client.catch_up(req)).await?;
+
+        convert::catch_up_response_from_pb(resp).map_err(|e| NetworkError::new(&e).into())
+    }
+
+    /// Tell the target that the Leader stops sending heartbeats.
+    pub async fn send_hibernate(&mut self, req: HibernateRequest<C>, option: RPCOption) -> Result<(), RPCError<C>> {
+        let req = convert::hibernate_request_to_pb(&req);
+
+        let mut client = self.client()?;
+        self.with_timeout(RPCTypes::Hibernate, &option, client.hibernate(req)).await?;
+        Ok(())
+    }
+
+    /// The backoff to retry the target after it is found unreachable.
+    pub fn backoff_policy(&self) -> Backoff {
+        self.config.backoff()
+    }
+
+    /// Wait for an RPC for at most [`RPCOption::hard_ttl()`], and convert a failure [`Status`]
+    /// with [`status_to_error()`](Self::status_to_error).
+    async fn with_timeout<T>(
+        &self,
+        action: RPCTypes,
+        option: &RPCOption,
+        fu: impl Future<Output = Result<tonic::Response<T>, Status>>,
+    ) -> Result<T, RPCError<C>> {
+        let ttl = option.hard_ttl();
+
+        let res = match tokio::time::timeout(ttl, fu).await {
+            Ok(res) => res,
+            Err(_elapsed) => return Err(self.timeout(action, ttl).into()),
+        };
+
+        res.map(|resp| resp.into_inner()).map_err(|status| self.status_to_error(action, status))
+    }
+
+    fn timeout(&self, action: RPCTypes, ttl: Duration) -> Timeout<C> {
+        Timeout {
+            action,
+            id: self.id,
+            target: self.target,
+            timeout: ttl,
+        }
+    }
+
+    /// Convert a failure [`Status`] of an RPC into an [`RPCError`].
+    fn status_to_error(&self, action: RPCTypes, status: Status) -> RPCError<C> {
+        tracing::warn!("{} to {} failed: {}", action, self.target, status);
+
+        match status.code() {
+            // Failing to connect, or the remote Raft is shutting down.
+            Code::Unavailable => Unreachable::new(&status).into(),
+            _ => NetworkError::new(&status).into(),
+        }
+    }
+
+    /// Return a client on the channel to the target, creating the channel if there is none.
+    ///
+    /// The channel connects lazily, thus a target that can not be connected is reported by the
+    /// RPC with [`Code::Unavailable`].
+    fn client(&self) -> Result<RaftServiceClient<Channel>, Unreachable> {
+        let channel = {
+            let mut channels = self.channels.lock().unwrap();
+
+            match channels.get(&self.addr) {
+                Some(channel) => channel.clone(),
+                None => {
+                    let channel = self.endpoint().map_err(|e| Unreachable::new(&e))?.connect_lazy();
+                    channels.insert(self.addr.clone(), channel.clone());
+                    channel
+                }
+            }
+        };
+
+        Ok(RaftServiceClient::new(channel)
+            .max_encoding_message_size(self.config.max_message_size)
+            .max_decoding_message_size(self.config.max_message_size))
+    }
+
+    fn endpoint(&self) -> Result<Endpoint, tonic::transport::Error> {
+        let uri = if self.addr.contains("://") {
+            self.addr.clone()
+        } else {
+            format!("http://{}", self.addr)
+        };
+
+        Ok(Endpoint::from_shared(uri)?.connect_timeout(self.config.connect_timeout).tcp_nodelay(true))
+    }
+}
+
+/// Convert an error of an RPC that has no remote error into one of an RPC that has.
+fn with_remote_error<C, E>(e: RPCError<C>) -> RPCError<C, E>
+where
+    C: GrpcTypeConfig,
+    E: std::error::Error,
+{
+    match e {
+        RPCError::Timeout(e) => RPCError::Timeout(e),
+        RPCError::Unreachable(e) => RPCError::Unreachable(e),
+        RPCError::PayloadTooLarge(e) => RPCError::PayloadTooLarge(e),
+        RPCError::Network(e) => RPCError::Network(e),
+        RPCError::RemoteError(e) => match e.source {},
+    }
+}
+
+/// The sending half of a snapshot stream: chunks are sent to the request stream of a `Snapshot`
+/// call, and acknowledgements are read from its response stream.
+struct ChunkSink<'a, C, K>
+where C: GrpcTypeConfig
+{
+    conn: &'a GrpcConnection<C, K>,
+    tx: mpsc::Sender<pb::SnapshotChunk>,
+    acks: Streaming<pb::SnapshotResponse>,
+    ttl: Duration,
+}
+
+impl<C, K> SnapshotChunkSink<C> for ChunkSink<'_, C, K>
+where
+    C: GrpcTypeConfig,
+    C::SnapshotData: AsyncRead + Unpin,
+    K: Codec,
+{
+    async fn send_chunk(&mut self, chunk: SnapshotChunk<C>) -> Result<(), StreamingError<C>> {
+        let chunk = convert::snapshot_chunk_to_pb(chunk, &self.conn.codec).map_err(|e| NetworkError::new(&e))?;
+
+        // The request stream is dropped only if the call fails, which is then returned by
+        // `recv_ack()`.
+        self.tx.send(chunk).await.map_err(|e| NetworkError::new(&e))?;
+        Ok(())
+    }
+
+    async fn recv_ack(&mut self) -> Result<SnapshotResponse<C>, StreamingError<C>> {
+        let action = RPCTypes::InstallSnapshot;
+
+        let res = match tokio::time::timeout(self.ttl, self.acks.message()).await {
+            Ok(res) => res,
+            Err(_elapsed) => return Err(self.conn.timeout(action, self.ttl).into()),
+        };
+
+        let ack = match res {
+            Ok(Some(ack)) => ack,
+            Ok(None) => {
+                let e = io::Error::new(io::ErrorKind::UnexpectedEof, "snapshot stream closed by the target");
+                return Err(NetworkError::new(&e).into());
+            }
+            Err(status) => return Err(self.conn.status_to_error(action, status).into()),
+        };
+
+        convert::snapshot_response_from_pb(ack).map_err(|e| NetworkError::new(&e).into())
+    }
+}
@@ -0,0 +1,34 @@
+//! Serialization of the application types carried in `bytes` fields.
+
+use std::io;
+
+use serde::de::DeserializeOwned;
+use serde::Serialize;
+
+/// Encodes and decodes the application types that protobuf messages carry as opaque bytes: the
+/// data of normal log entries and the nodes in a membership config.
+///
+/// Both ends of a connection must use the same codec. [`JsonCodec`] is the default; an
+/// application that needs a more compact format implements this trait with a binary serde
+/// format.
+pub trait Codec: Clone + Send + Sync + 'static {
+    /// Serialize `value` into bytes.
+    fn encode<T: Serialize>(&self, value: &T) -> io::Result<Vec<u8>>;
+
+    /// Deserialize a value from bytes produced by [`encode()`](Self::encode).
+    fn decode<T: DeserializeOwned>(&self, buf: &[u8]) -> io::Result<T>;
+}
+
+/// A [`Codec`] that serializes values as JSON.
+#[derive(Debug, Clone, Copy, Default)]
+pub struct JsonCodec;
+
+impl Codec for JsonCodec {
+    fn encode<T: Serialize>(&self, value: &T) -> io::Result<Vec<u8>> {
+        Ok(serde_json::to_vec(value)?)
+    }
+
+    fn decode<T: DeserializeOwned>(&self, buf: &[u8]) -> io::Result<T> {
+        Ok(serde_json::from_slice(buf)?)
+    }
+}
@@ -0,0 +1,58 @@
+use std::time::Duration;
+
+use openraft::network::Backoff;
+
+/// Settings shared by the client and the server side of the gRPC network.
+#[derive(Debug, Clone)]
+pub struct GrpcConfig {
+    /// Timeout to establish a connection.
+    pub connect_timeout: Duration,
+
+    /// The max size in bytes of an encoded gRPC message, sent or received.
+    ///
+    /// An AppendEntries request is split if it is larger. A snapshot is not limited by it, because
+    /// it is sent in chunks of
+    /// [`Config::snapshot_max_chunk_size`](openraft::Config::snapshot_max_chunk_size), which has to
+    /// be smaller than this value.
+    pub max_message_size: usize,
+
+    /// The max number of snapshot chunks sent to a node before they are acknowledged.
+    pub snapshot_window: usize,
+
+    /// The max size in bytes of the snapshot data a server receives. A stream of a larger
+    /// snapshot is refused.
+    pub max_snapshot_size: u64,
+
+    /// The interval to wait before the first retry to an unreachable node.
+    pub backoff_min: Duration,
+
+    /// The interval between retries doubles after each failure until it reaches this value.
+    pub backoff_max: Duration,
+}
+
+impl Default for GrpcConfig {
+    fn default() -> Self {
+        Self {
+            connect_timeout: Duration::from_millis(1_000),
+            max_message_size: 16 * 1024 * 1024,
+            snapshot_window: 4,
+            max_snapshot_size: 1024 * 1024 * 1024,
+            backoff_min: Duration::from_millis(100),
+            backoff_max: Duration::from_millis(5_000),
+        }
+    }
+}
+
+impl GrpcConfig {
+    /// Build the exponential [`Backoff`] for an unreachable node.
+    pub fn backoff(&self) -> Backoff {
+        let max = self.backoff_max;
+        let mut next = self.backoff_min.min(max);
+
+        Backoff::new(std::iter::from_fn(move || {
+            let curr = next;
+            next = (next * 2).min(max);
+            Some(curr)
+        }))
+    }
+}
@@ -0,0 +1,638 @@
+//! Conversions between Openraft types and protobuf messages.
+//!
+//! Application types are encoded into `bytes` fields with a [`Codec`]. Decoding fails with
+//! [`io::ErrorKind::InvalidData`] if a required field is absent.
+
+use std::collections::BTreeMap;
+use std::collections::BTreeSet;
+use std::io;
+
+use openraft::alias::LogIdOf;
+use openraft::alias::VoteOf;
+use openraft::error::ClientWriteError;
+use openraft::error::RaftError;
+use openraft::network::compression::Compression;
+use openraft::network::v2::snapshot_stream::SnapshotChunk;
+use openraft::network::ProtocolVersion;
+use openraft::raft::AppendEntriesRequest;
+use openraft::raft::AppendEntriesResponse;
+use openraft::raft::CatchUpRequest;
+use openraft::raft::CatchUpResponse;
+use openraft::raft::ClientWriteResponse;
+use openraft::raft::HandshakeRequest;
+use openraft::raft::HandshakeResponse;
+use openraft::raft::HibernateRequest;
+use openraft::raft::SnapshotResponse;
+use openraft::raft::TransferLeaderRequest;
+use openraft::raft::VoteRequest;
+use openraft::raft::VoteResponse;
+use openraft::vote::RaftVote;
+use openraft::Entry;
+use openraft::EntryPayload;
+use openraft::LogId;
+use openraft::Membership;
+use openraft::SnapshotMeta;
+use openraft::StoredMembership;
+
+use crate::pb;
+use crate::Codec;
+use crate::GrpcTypeConfig;
+
+fn required<T>(field: Option<T>, name: &str) -> io::Result<T> {
+    field.ok_or_else(|| io::Error::new(io::ErrorKind::InvalidData, format!("missing field `{}`", name)))
+}
+
+pub(crate) fn vote_to_pb<C: GrpcTypeConfig>(vote: &VoteOf<C>) -> pb::Vote {
+    pb::Vote {
+        leader_id: vote.leader_id().map(C::leader_id_to_proto),
+        committed: vote.is_committed(),
+    }
+}
+
+pub(crate) fn vote_from_pb<C: GrpcTypeConfig>(vote: Option<pb::Vote>) -> io::Result<VoteOf<C>> {
+    let vote = required(vote, "vote")?;
+    let leader_id = C::leader_id_from_proto(required(vote.leader_id, "vote.leader_id")?);
+    Ok(VoteOf::<C>::from_leader_id(leader_id, vote.committed))
+}
+
+pub(crate) fn log_id_to_pb<C: GrpcTypeConfig>(log_id: &LogIdOf<C>) -> pb::LogId {
+    pb::LogId {
+        leader_id: Some(C::committed_leader_id_to_proto(log_id.committed_leader_id())),
+        index: log_id.index(),
+    }
+}
+
+pub(crate) fn log_id_from_pb<C: GrpcTypeConfig>(log_id: pb::LogId) -> io::Result<LogIdOf<C>> {
+    let leader_id = C::committed_leader_id_from_proto(required(log_id.leader_id, "log_id.leader_id")?);
+    Ok(LogId::new(leader_id, log_id.index))
+}
+
+fn opt_log_id_to_pb<C: GrpcTypeConfig>(log_id: Option<&LogIdOf<C>>) -> Option<pb::LogId> {
+    log_id.map(log_id_to_pb::<C>)
+}
+
+fn opt_log_id_from_pb<C: GrpcTypeConfig>(log_id: Option<pb::LogId>) -> io::Result<Option<LogIdOf<C>>> {
+    log_id.map(log_id_from_pb::<C>).transpose()
+}
+
+pub(crate) fn membership_to_pb<C, K>(membership: &Membership<C>, codec: &K) -> io::Result<pb::Membership>
+where
+    C: GrpcTypeConfig,
+    K: Codec,
+{
+    let configs = membership
+        .get_joint_config()
+        .iter()
+        .map(|ids| pb::NodeIdSet {
+            node_ids: ids.iter().copied().collect(),
+        })
+        .collect();
+
+    let mut nodes = BTreeMap::new();
+    for (id, node) in membership.nodes() {
+        nodes.insert(*id, codec.encode(node)?);
+    }
+
+    Ok(pb::Membership { configs, nodes })
+}
+
+pub(crate) fn membership_from_pb<C, K>(membership: pb::Membership, codec: &K) -> io::Result<Membership<C>>
+where
+    C: GrpcTypeConfig,
+    K: Codec,
+{
+    let configs = membership.configs.into_iter().map(|c| c.node_ids.into_iter().collect::<BTreeSet<_>>()).collect();
+
+    let mut nodes = BTreeMap::new();
+    for (id, node) in membership.nodes {
+        nodes.insert(id, codec.decode::<C::Node>(&node)?);
+    }
+
+    Membership::new(configs, nodes).map_err(|e| io::Error::new(io::ErrorKind::InvalidData, e))
+}
+
+pub(crate) fn entry_to_pb<C, K>(entry: &Entry<C>, codec: &K) -> io::Result<pb::Entry>
+where
+    C: GrpcTypeConfig,
+    K: Codec,
+{
+    let payload = match &entry.payload {
+        EntryPayload::Blank => None,
+        EntryPayload::Normal(data) => Some(pb::entry::Payload::Normal(codec.encode(data)?)),
+        EntryPayload::Membership(m) => Some(pb::entry::Payload::Membership(membership_to_pb(m, codec)?)),
+    };
+
+    Ok(pb::Entry {
+        log_id: Some(log_id_to_pb::<C>(&entry.log_id)),
+        payload,
+    })
+}
+
+pub(crate) fn entry_from_pb<C, K>(entry: pb::Entry, codec: &K) -> io::Result<Entry<C>>
+where
+    C: GrpcTypeConfig,
+    K: Codec,
+{
+    let payload = match entry.payload {
+        None => EntryPayload::Blank,
+        Some(pb::entry::Payload::Normal(data)) => EntryPayload::Normal(codec.decode(&data)?),
+        Some(pb::entry::Payload::Membership(m)) => EntryPayload::Membership(membership_from_pb(m, codec)?),
+    };
+
+    Ok(Entry {
+        log_id: log_id_from_pb::<C>(required(entry.log_id, "entry.log_id")?)?,
+        payload,
+    })
+}
+
+pub(crate) fn vote_request_to_pb<C: GrpcTypeConfig>(req: &VoteRequest<C>) -> pb::VoteRequest {
+    pb::VoteRequest {
+        vote: Some(vote_to_pb::<C>(&req.vote)),
+        last_log_id: opt_log_id_to_pb::<C>(req.last_log_id.as_ref()),
+    }
+}
+
+pub(crate) fn vote_request_from_pb<C: GrpcTypeConfig>(req: pb::VoteRequest) -> io::Result<VoteRequest<C>> {
+    Ok(VoteRequest::new(
+        vote_from_pb::<C>(req.vote)?,
+        opt_log_id_from_pb::<C>(req.last_log_id)?,
+    ))
+}
+
+pub(crate) fn vote_response_to_pb<C: GrpcTypeConfig>(resp: &VoteResponse<C>) -> pb::VoteResponse {
+    pb::VoteResponse {
+        vote: Some(vote_to_pb::<C>(&resp.vote)),
+        vote_granted: resp.vote_granted,
+        last_log_id: opt_log_id_to_pb::<C>(resp.last_log_id.as_ref()),
+    }
+}
+
+pub(crate) fn vote_response_from_pb<C: GrpcTypeConfig>(resp: pb::VoteResponse) -> io::Result<VoteResponse<C>> {
+    Ok(VoteResponse::new(
+        vote_from_pb::<C>(resp.vote)?,
+        opt_log_id_from_pb::<C>(resp.last_log_id)?,
+        resp.vote_granted,
+    ))
+}
+
+pub(crate) fn append_entries_request_to_pb<C, K>(
+    req: &AppendEntriesRequest<C>,
+    codec: &K,
+) -> io::Result<pb::AppendEntriesRequest>
+where
+    C: GrpcTypeConfig,
+    K: Codec,
+{
+    Ok(pb::AppendEntriesRequest {
+        vote: Some(vote_to_pb::<C>(&req.vote)),
+        prev_log_id: opt_log_id_to_pb::<C>(req.prev_log_id.as_ref()),
+        entries: req.entries.iter().map(|e| entry_to_pb(e, codec)).collect::<Result<_, _>>()?,
+        leader_commit: opt_log_id_to_pb::<C>(req.leader_commit.as_ref()),
+    })
+}
+
+pub(crate) fn append_entries_request_from_pb<C, K>(
+    req: pb::AppendEntriesRequest,
+    codec: &K,
+) -> io::Result<AppendEntriesRequest<C>>
+where
+    C: GrpcTypeConfig,
+    K: Codec,
+{
+    Ok(AppendEntriesRequest {
+        vote: vote_from_pb::<C>(req.vote)?,
+        prev_log_id: opt_log_id_from_pb::<C>(req.prev_log_id)?,
+        entries: req.entries.into_iter().map(|e| entry_from_pb(e, codec)).collect::<Result<_, _>>()?,
+        leader_commit: opt_log_id_from_pb::<C>(req.leader_commit)?,
+    })
+}
+
+pub(crate) fn append_entries_response_to_pb<C: GrpcTypeConfig>(
+    resp: &AppendEntriesResponse<C>,
+) -> pb::AppendEntriesResponse {
+    use pb::append_entries_response as r;
+
+    let result = match resp {
+        AppendEntriesResponse::Success => r::Result::Success(r::Success {}),
+        AppendEntriesResponse::PartialSuccess(matching) => r::Result::PartialSuccess(r::PartialSuccess {
+            matching: opt_log_id_to_pb::<C>(matching.as_ref()),
+        }),
+        AppendEntriesResponse::Conflict => r::Result::Conflict(r::Conflict {}),
+        AppendEntriesResponse::HigherVote(vote) => r::Result::HigherVote(vote_to_pb::<C>(vote)),
+    };
+
+    pb::AppendEntriesResponse { result: Some(result) }
+}
+
+pub(crate) fn append_entries_response_from_pb<C: GrpcTypeConfig>(
+    resp: pb::AppendEntriesResponse,
+) -> io::Result<AppendEntriesResponse<C>> {
+    use pb::append_entries_response as r;
+
+    let resp = match required(resp.result, "result")? {
+        r::Result::Success(_) => AppendEntriesResponse::Success,
+        r::Result::PartialSuccess(p) => AppendEntriesResponse::PartialSuccess(opt_log_id_from_pb::<C>(p.matching)?),
+        r::Result::Conflict(_) => AppendEntriesResponse::Conflict,
+        r::Result::HigherVote(vote) => AppendEntriesResponse::HigherVote(vote_from_pb::<C>(Some(vote))?),
+    };
+    Ok(resp)
+}
+
+pub(crate) fn snapshot_meta_to_pb<C, K>(meta: &SnapshotMeta<C>, codec: &K) -> io::Result<pb::SnapshotMeta>
+where
+    C: GrpcTypeConfig,
+    K: Codec,
+{
+    Ok(pb::SnapshotMeta {
+        last_log_id: opt_log_id_to_pb::<C>(meta.last_log_id.as_ref()),
+        last_membership_log_id: opt_log_id_to_pb::<C>(meta.last_membership.log_id().as_ref()),
+        last_membership: Some(membership_to_pb(meta.last_membership.membership(), codec)?),
+        snapshot_id: meta.snapshot_id.clone(),
+    })
+}
+
+pub(crate) fn snapshot_meta_from_pb<C, K>(meta: pb::SnapshotMeta, codec: &K) -> io::Result<SnapshotMeta<C>>
+where
+    C: GrpcTypeConfig,
+    K: Codec,
+{
+    let membership = membership_from_pb(required(meta.last_membership, "last_membership")?, codec)?;
+
+    Ok(SnapshotMeta {
+        last_log_id: opt_log_id_from_pb::<C>(meta.last_log_id)?,
+        last_membership: StoredMembership::new(opt_log_id_from_pb::<C>(meta.last_membership_log_id)?, membership),
+        snapshot_id: meta.snapshot_id,
+    })
+}
+
+pub(crate) fn snapshot_response_to_pb<C: GrpcTypeConfig>(resp: &SnapshotResponse<C>) -> pb::SnapshotResponse {
+    pb::SnapshotResponse {
+        vote: Some(vote_to_pb::<C>(&resp.vote)),
+    }
+}
+
+pub(crate) fn snapshot_response_from_pb<C: GrpcTypeConfig>(
+    resp: pb::SnapshotResponse,
+) -> io::Result<SnapshotResponse<C>> {
+    Ok(SnapshotResponse::new(vote_from_pb::<C>(resp.vote)?))
+}
+
+/// Convert a chunk of a snapshot stream.
+///
+/// The data of a chunk is not compressed by this crate, thus a compressed chunk is refused.
+pub(crate) fn snapshot_chunk_to_pb<C, K>(chunk: SnapshotChunk<C>, codec: &K) -> io::Result<pb::SnapshotChunk>
+where
+    C: GrpcTypeConfig,
+    K: Codec,
+{
+    use pb::snapshot_chunk as c;
+
+    let chunk = match chunk {
+        SnapshotChunk::Start { vote, meta } => c::Chunk::Start(c::Start {
+            vote: Some(vote_to_pb::<C>(&vote)),
+            meta: Some(snapshot_meta_to_pb(&meta, codec)?),
+        }),
+        SnapshotChunk::Data {
+            offset,
+            compression,
+            data,
+        } => {
+            if compression != Compression::None {
+                return Err(io::Error::new(
+                    io::ErrorKind::InvalidInput,
+                    format!("snapshot chunk compressed with {} is not supported", compression),
+                ));
+            }
+            c::Chunk::Data(c::Data { offset, data })
+        }
+        SnapshotChunk::Finish { size } => c::Chunk::Finish(c::Finish { size }),
+    };
+
+    Ok(pb::SnapshotChunk { chunk: Some(chunk) })
+}
+
+pub(crate) fn snapshot_chunk_from_pb<C, K>(chunk: pb::SnapshotChunk, codec: &K) -> io::Result<SnapshotChunk<C>>
+where
+    C: GrpcTypeConfig,
+    K: Codec,
+{
+    use pb::snapshot_chunk as c;
+
+    let chunk = match required(chunk.chunk, "chunk")? {
+        c::Chunk::Start(start) => SnapshotChunk::Start {
+            vote: vote_from_pb::<C>(start.vote)?,
+            meta: snapshot_meta_from_pb(required(start.meta, "meta")?, codec)?,
+        },
+        c::Chunk::Data(data) => SnapshotChunk::Data {
+            offset: data.offset,
+            compression: Compression::None,
+            data: data.data,
+        },
+        c::Chunk::Finish(finish) => SnapshotChunk::Finish { size: finish.size },
+    };
+    Ok(chunk)
+}
+
+pub(crate) fn transfer_leader_request_to_pb<C: GrpcTypeConfig>(
+    req: &TransferLeaderRequest<C>,
+) -> pb::TransferLeaderRequest {
+    pb::TransferLeaderRequest {
+        from_leader: Some(vote_to_pb::<C>(req.from_leader())),
+        to_node_id: *req.to_node_id(),
+        last_log_id: opt_log_id_to_pb::<C>(req.last_log_id()),
+    }
+}
+
+pub(crate) fn transfer_leader_request_from_pb<C: GrpcTypeConfig>(
+    req: pb::TransferLeaderRequest,
+) -> io::Result<TransferLeaderRequest<C>> {
+    Ok(TransferLeaderRequest::new(
+        vote_from_pb::<C>(req.from_leader)?,
+        req.to_node_id,
+        opt_log_id_from_pb::<C>(req.last_log_id)?,
+    ))
+}
+
+pub(crate) fn handshake_request_to_pb<C: GrpcTypeConfig>(req: &HandshakeRequest<C>) -> pb::HandshakeRequest {
+    pb::HandshakeRequest {
+        from: req.from,
+        version: req.version.get(),
+    }
+}
+
+pub(crate) fn handshake_request_from_pb<C: GrpcTypeConfig>(req: pb::HandshakeRequest) -> HandshakeRequest<C> {
+    HandshakeRequest::new(req.from, ProtocolVersion::new(req.version))
+}
+
+pub(crate) fn handshake_response_to_pb(resp: &HandshakeResponse) -> pb::HandshakeResponse {
+    pb::HandshakeResponse {
+        version: resp.version.get(),
+    }
+}
+
+pub(crate) fn handshake_response_from_pb(resp: pb::HandshakeResponse) -> HandshakeResponse {
+    HandshakeResponse::new(ProtocolVersion::new(resp.version))
+}
+
+/// The result of a client write forwarded to the Leader.
+type ForwardedWriteResult<C> = Result<ClientWriteResponse<C>, RaftError<C, ClientWriteError<C>>>;
+
+/// Convert the result of a forwarded client write.
+///
+/// An error returned by the Leader is encoded by the codec, so that the sender gets it back as is.
+pub(crate) fn client_write_result_to_pb<C, K>(
+    res: &ForwardedWriteResult<C>,
+    codec: &K,
+) -> io::Result<pb::ClientWriteResponse>
+where
+    C: GrpcTypeConfig,
+    K: Codec,
+{
+    use pb::client_write_response as r;
+
+    let result = match res {
+        Ok(resp) => r::Result::Applied(r::Applied {
+            log_id: Some(log_id_to_pb::<C>(&resp.log_id)),
+            data: codec.encode(&resp.data)?,
+            membership: resp.membership.as_ref().map(|m| membership_to_pb(m, codec)).transpose()?,
+        }),
+        Err(e) => r::Result::Error(codec.encode(e)?),
+    };
+
+    Ok(pb::ClientWriteResponse { result: Some(result) })
+}
+
+pub(crate) fn client_write_result_from_pb<C, K>(
+    resp: pb::ClientWriteResponse,
+    codec: &K,
+) -> io::Result<ForwardedWriteResult<C>>
+where
+    C: GrpcTypeConfig,
+    K: Codec,
+{
+    use pb::client_write_response as r;
+
+    let res = match required(resp.result, "result")? {
+        r::Result::Applied(a) => Ok(ClientWriteResponse {
+            log_id: log_id_from_pb::<C>(required(a.log_id, "log_id")?)?,
+            data: codec.decode(&a.data)?,
+            membership: a.membership.map(|m| membership_from_pb(m, codec)).transpose()?,
+        }),
+        r::Result::Error(e) => Err(codec.decode(&e)?),
+    };
+    Ok(res)
+}
+
+pub(crate) fn catch_up_request_to_pb<C, K>(req: &CatchUpRequest<C>, codec: &K) -> io::Result<pb::CatchUpRequest>
+where
+    C: GrpcTypeConfig,
+    K: Codec,
+{
+    Ok(pb::CatchUpRequest {
+        vote: Some(vote_to_pb::<C>(&req.vote)),
+        target: req.target,
+        target_node: codec.encode(&req.target_node)?,
+        prev_log_id: opt_log_id_to_pb::<C>(req.prev_log_id.as_ref()),
+        last_log_id: Some(log_id_to_pb::<C>(&req.last_log_id)),
+        leader_commit: opt_log_id_to_pb::<C>(req.leader_commit.as_ref()),
+    })
+}
+
+pub(crate) fn catch_up_request_from_pb<C, K>(req: pb::CatchUpRequest, codec: &K) -> io::Result<CatchUpRequest<C>>
+where
+    C: GrpcTypeConfig,
+    K: Codec,
+{
+    Ok(CatchUpRequest {
+        vote: vote_from_pb::<C>(req.vote)?,
+        target: req.target,
+        target_node: codec.decode(&req.target_node)?,
+        prev_log_id: opt_log_id_from_pb::<C>(req.prev_log_id)?,
+        last_log_id: log_id_from_pb::<C>(required(req.last_log_id, "last_log_id")?)?,
+        leader_commit: opt_log_id_from_pb::<C>(req.leader_commit)?,
+    })
+}
+
+pub(crate) fn catch_up_response_to_pb<C: GrpcTypeConfig>(resp: &CatchUpResponse<C>) -> pb::CatchUpResponse {
+    use pb::catch_up_response as r;
+
+    let result = match resp {
+        CatchUpResponse::Success(matching) => r::Result::Success(r::Success {
+            matching: opt_log_id_to_pb::<C>(matching.as_ref()),
+        }),
+        CatchUpResponse::Conflict => r::Result::Conflict(r::Conflict {}),
+        CatchUpResponse::HigherVote(vote) => r::Result::HigherVote(vote_to_pb::<C>(vote)),
+        CatchUpResponse::Declined(reason) => r::Result::Declined(reason.clone()),
+    };
+
+    pb::CatchUpResponse { result: Some(result) }
+}
+
+pub(crate) fn catch_up_response_from_pb<C: GrpcTypeConfig>(
+    resp: pb::CatchUpResponse,
+) -> io::Result<CatchUpResponse<C>> {
+    use pb::catch_up_response as r;
+
+    let resp = match required(resp.result, "result")? {
+        r::Result::Success(s) => CatchUpResponse::Success(opt_log_id_from_pb::<C>(s.matching)?),
+        r::Result::Conflict(_) => CatchUpResponse::Conflict,
+        r::Result::HigherVote(vote) => CatchUpResponse::HigherVote(vote_from_pb::<C>(Some(vote))?),
+        r::Result::Declined(reason) => CatchUpResponse::Declined(reason),
+    };
+    Ok(resp)
+}
+
+pub(crate) fn hibernate_request_to_pb<C: GrpcTypeConfig>(req: &HibernateRequest<C>) -> pb::HibernateRequest {
+    pb::HibernateRequest {
+        vote: Some(vote_to_pb::<C>(&req.vote)),
+        last_log_id: opt_log_id_to_pb::<C>(req.last_log_id.as_ref()),
+    }
+}
+
+pub(crate) fn hibernate_request_from_pb<C: GrpcTypeConfig>(
+    req: pb::HibernateRequest,
+) -> io::Result<HibernateRequest<C>> {
+    Ok(HibernateRequest {
+        vote: vote_from_pb::<C>(req.vote)?,
+        last_log_id: opt_log_id_from_pb::<C>(req.last_log_id)?,
+    })
+}
+
+#[cfg(test)]
+mod tests {
+    use std::collections::BTreeMap;
+    use std::collections::BTreeSet;
+
+    use openraft::entry::RaftEntry;
+    use openraft::raft::AppendEntriesRequest;
+    use openraft::raft::AppendEntriesResponse;
+    use openraft::vote::leader_id_adv;
+    use openraft::vote::leader_id_std;
+    use openraft::vote::RaftLeaderIdExt;
+    use openraft::BasicNode;
+    use openraft::Entry;
+    use openraft::LogId;
+    use openraft::Membership;
+    use openraft::Vote;
+
+    use super::*;
+    use crate::JsonCodec;
+
+    openraft::declare_raft_types!(
+        Adv:
+            D = String,
+            R = (),
+    );
+
+    openraft::declare_raft_types!(
+        Std:
+            D = String,
+            R = (),
+            LeaderId = leader_id_std::LeaderId<Std>,
+    );
+
+    fn members<C>() -> Membership<C>
+    where C: GrpcTypeConfig<Node = BasicNode> {
+        let nodes = (1..=3).map(|i| (i, BasicNode::new(format!("127.0.0.1:{}", i)))).collect::<BTreeMap<_, _>>();
+        Membership::new(vec![BTreeSet::from([1, 2]), BTreeSet::from([2, 3])], nodes).unwrap()
+    }
+
+    #[test]
+    fn test_append_entries_round_trip() -> anyhow::Result<()> {
+        let codec = JsonCodec;
+
+        let log_id = |t, n, i| LogId::<Adv>::new(leader_id_adv::LeaderId::new_committed(t, n), i);
+        let req = AppendEntriesRequest::<Adv> {
+            vote: Vote::new_committed(2, 1),
+            prev_log_id: Some(log_id(1, 3, 5)),
+            entries: vec![
+                Entry::new_blank(log_id(2, 1, 6)),
+                Entry::new_normal(log_id(2, 1, 7), "foo".to_string()),
+                Entry::new_membership(log_id(2, 1, 8), members()),
+            ],
+            leader_commit: None,
+        };
+
+        let pb = append_entries_request_to_pb(&req, &codec)?;
+        let got: AppendEntriesRequest<Adv> = append_entries_request_from_pb(pb, &codec)?;
+        assert_eq!(
+            (req.vote, req.prev_log_id, req.entries, req.leader_commit),
+            (got.vote, got.prev_log_id, got.entries, got.leader_commit)
+        );
+
+        for resp in [
+            AppendEntriesResponse::<Adv>::Success,
+            AppendEntriesResponse::PartialSuccess(None),
+            AppendEntriesResponse::PartialSuccess(Some(log_id(2, 1, 6))),
+            AppendEntriesResponse::Conflict,
+            AppendEntriesResponse::HigherVote(Vote::new(3, 2)),
+        ] {
+            let got: AppendEntriesResponse<Adv> =
+                append_entries_response_from_pb(append_entries_response_to_pb(&resp))?;
+            assert_eq!(resp, got);
+        }
+
+        Ok(())
+    }
+
+    /// The committed leader id of standard Raft is sent without a node id.
+    #[test]
+    fn test_std_leader_id() -> anyhow::Result<()> {
+        let log_id = LogId::<Std>::new(leader_id_std::LeaderId::<Std>::new_committed(3, 2), 9);
+
+        let pb = log_id_to_pb::<Std>(&log_id);
+        assert_eq!(Some(pb::LeaderId { term: 3, node_id: None }), pb.leader_id);
+        assert_eq!(log_id, log_id_from_pb::<Std>(pb)?);
+
+        let req = VoteRequest::<Std>::new(Vote::new(4, 1), Some(log_id));
+        let got: VoteRequest<Std> = vote_request_from_pb(vote_request_to_pb(&req))?;
+        assert_eq!(req, got);
+
+        let m = members::<Std>();
+        let got: Membership<Std> = membership_from_pb(membership_to_pb(&m, &JsonCodec)?, &JsonCodec)?;
+        assert_eq!(m, got);
+
+        Ok(())
+    }
+
+    #[test]
+    fn test_snapshot_chunk_round_trip() -> anyhow::Result<()> {
+        let codec = JsonCodec;
+
+        let log_id = LogId::<Adv>::new(leader_id_adv::LeaderId::new_committed(2, 1), 8);
+        let meta = SnapshotMeta {
+            last_log_id: Some(log_id),
+            last_membership: StoredMembership::new(Some(log_id), members()),
+            snapshot_id: "s1".to_string(),
+        };
+
+        for chunk in [
+            SnapshotChunk::<Adv>::Start {
+                vote: Vote::new_committed(2, 1),
+                meta,
+            },
+            SnapshotChunk::Data {
+                offset: 3,
+                compression: Compression::None,
+                data: vec![1, 2, 3],
+            },
+            SnapshotChunk::Finish { size: 6 },
+        ] {
+            let got: SnapshotChunk<Adv> = snapshot_chunk_from_pb(snapshot_chunk_to_pb(chunk.clone(), &codec)?, &codec)?;
+            assert_eq!(chunk, got);
+        }
+
+        Ok(())
+    }
+
+    #[test]
+    fn test_missing_field() {
+        let res = vote_request_from_pb::<Adv>(pb::VoteRequest {
+            vote: None,
+            last_log_id: None,
+        });
+        let err = res.unwrap_err();
+        assert_eq!(io::ErrorKind::InvalidData, err.kind());
+        assert_eq!("missing field `vote`", err.to_string());
+    }
+}
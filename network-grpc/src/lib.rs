@@ -0,0 +1,201 @@
+//! This crate provides a [`RaftNetworkV2`](openraft::network::v2::RaftNetworkV2) over gRPC, built
+//! with [tonic](https://docs.rs/tonic), for applications whose services already speak gRPC.
+//!
+//! ```ignore
+//! openraft::declare_raft_types!(
+//!     pub TypeConfig:
+//!         D = Request,
+//!         R = Response,
+//! );
+//!
+//! openraft_network_grpc::impl_grpc_network!(TypeConfig);
+//!
+//! let network = GrpcNetworkFactory::<TypeConfig>::new(node_id, GrpcConfig::default());
+//! let raft = Raft::new(node_id, config, network, log_store, state_machine).await?;
+//!
+//! tonic::transport::Server::builder()
+//!     .add_service(GrpcServer::<TypeConfig>::new(raft.clone(), GrpcConfig::default()).into_service())
+//!     .add_service(my_app_service)
+//!     .serve(addr)
+//!     .await?;
+//! ```
+//!
+//! The messages are defined in `proto/raft.proto` and generated into [`pb`]. Openraft types such as
+//! votes, log ids and membership configs are protobuf messages; application types, i.e., the data
+//! of normal entries and the nodes, are carried as opaque `bytes` encoded by a [`Codec`], JSON by
+//! default.
+//!
+//! A snapshot is sent with a bidirectional stream: the snapshot data is read in chunks and every
+//! chunk is acknowledged by the receiver, which writes it into the snapshot data returned by
+//! [`Raft::begin_receiving_snapshot()`](openraft::Raft::begin_receiving_snapshot), see
+//! [`snapshot_stream`](openraft::network::v2::snapshot_stream). Thus neither end holds the whole
+//! snapshot in memory.
+//!
+//! A type config must have `u64` node ids and terms, use the default [`Entry`](openraft::Entry),
+//! see [`GrpcTypeConfig`], and snapshot data that implements tokio's `AsyncRead` and `AsyncWrite`,
+//! such as the default `Cursor<Vec<u8>>`.
+//!
+//! Errors are reported as Openraft expects them:
+//!
+//! - Failing to connect, or a remote [`Raft`](openraft::Raft) that is shutting down, returns
+//!   [`Unreachable`](openraft::error::Unreachable), and the node is retried after the
+//!   [`Backoff`](openraft::network::Backoff) built from [`GrpcConfig`].
+//! - An RPC that does not complete within [`RPCOption::hard_ttl()`] returns
+//!   [`Timeout`](openraft::error::Timeout).
+//! - An AppendEntries request larger than [`GrpcConfig::max_message_size`] returns
+//!   [`PayloadTooLarge`](openraft::error::PayloadTooLarge), so that fewer entries are sent.
+//!
+//! [`RPCOption::hard_ttl()`]: openraft::network::RPCOption::hard_ttl
+
+mod client;
+mod codec;
+mod config;
+mod convert;
+mod server;
+mod type_config;
+
+pub use client::GrpcConnection;
+pub use client::GrpcNetworkFactory;
+pub use client::NodeAddr;
+pub use codec::Codec;
+pub use codec::JsonCodec;
+pub use config::GrpcConfig;
+pub use server::GrpcServer;
+pub use type_config::GrpcTypeConfig;
+pub use type_config::ProtoLeaderId;
+
+/// The protobuf messages and the tonic client and server of `RaftService`, generated from
+/// `proto/raft.proto`.
+pub mod pb {
+    #![allow(missing_docs)]
+    #![allow(clippy::all)]
+
+    tonic::include_proto!("openraftpb");
+}
+
+#[doc(hidden)]
+pub mod __private {
+    //! Re-exports used by [`impl_grpc_network!`](crate::impl_grpc_network).
+
+    pub use openraft::alias::VoteOf;
+    pub use openraft::error::ClientWriteError;
+    pub use openraft::error::RPCError;
+    pub use openraft::error::RaftError;
+    pub use openraft::error::ReplicationClosed;
+    pub use openraft::error::StreamingError;
+    pub use openraft::network::v2::RaftNetworkV2;
+    pub use openraft::network::Backoff;
+    pub use openraft::network::RPCOption;
+    pub use openraft::raft::AppendEntriesRequest;
+    pub use openraft::raft::AppendEntriesResponse;
+    pub use openraft::raft::CatchUpRequest;
+    pub use openraft::raft::CatchUpResponse;
+    pub use openraft::raft::ClientWriteResponse;
+    pub use openraft::raft::HandshakeRequest;
+    pub use openraft::raft::HandshakeResponse;
+    pub use openraft::raft::HibernateRequest;
+    pub use openraft::raft::SnapshotResponse;
+    pub use openraft::raft::TransferLeaderRequest;
+    pub use openraft::raft::VoteRequest;
+    pub use openraft::raft::VoteResponse;
+    pub use openraft::OptionalSend;
+    pub use openraft::RaftTypeConfig;
+    pub use openraft::Snapshot;
+}
+
+/// Implement `RaftNetworkV2` for [`GrpcConnection`] of a type config, with any [`Codec`].
+///
+/// A generic impl would conflict with the blanket impl that adapts a v1
+/// [`RaftNetwork`](openraft::RaftNetwork), thus it is generated in the crate that defines the type
+/// config:
+///
+/// ```ignore
+/// openraft::declare_raft_types!(pub TypeConfig: D = String, R = String);
+/// openraft_network_grpc::impl_grpc_network!(TypeConfig);
+/// ```
+#[macro_export]
+macro_rules! impl_grpc_network {
+    ($C:ty) => {
+        impl<K> $crate::__private::RaftNetworkV2<$C> for $crate::GrpcConnection<$C, K>
+        where K: $crate::Codec
+        {
+            async fn append_entries(
+                &mut self,
+                rpc: $crate::__private::AppendEntriesRequest<$C>,
+                option: $crate::__private::RPCOption,
+            ) -> Result<$crate::__private::AppendEntriesResponse<$C>, $crate::__private::RPCError<$C>> {
+                self.send_append_entries(rpc, option).await
+            }
+
+            async fn vote(
+                &mut self,
+                rpc: $crate::__private::VoteRequest<$C>,
+                option: $crate::__private::RPCOption,
+            ) -> Result<$crate::__private::VoteResponse<$C>, $crate::__private::RPCError<$C>> {
+                self.send_vote(rpc, option).await
+            }
+
+            async fn full_snapshot(
+                &mut self,
+                vote: $crate::__private::VoteOf<$C>,
+                snapshot: $crate::__private::Snapshot<$C>,
+                cancel: impl std::future::Future<Output = $crate::__private::ReplicationClosed>
+                    + $crate::__private::OptionalSend
+                    + 'static,
+                option: $crate::__private::RPCOption,
+            ) -> Result<$crate::__private::SnapshotResponse<$C>, $crate::__private::StreamingError<$C>> {
+                self.send_full_snapshot(vote, snapshot, cancel, option).await
+            }
+
+            async fn transfer_leader(
+                &mut self,
+                req: $crate::__private::TransferLeaderRequest<$C>,
+                option: $crate::__private::RPCOption,
+            ) -> Result<(), $crate::__private::RPCError<$C>> {
+                self.send_transfer_leader(req, option).await
+            }
+
+            async fn forward_client_write(
+                &mut self,
+                app_data: <$C as $crate::__private::RaftTypeConfig>::D,
+                option: $crate::__private::RPCOption,
+            ) -> Result<
+                $crate::__private::ClientWriteResponse<$C>,
+                $crate::__private::RPCError<
+                    $C,
+                    $crate::__private::RaftError<$C, $crate::__private::ClientWriteError<$C>>,
+                >,
+            > {
+                self.send_forward_client_write(app_data, option).await
+            }
+
+            async fn handshake(
+                &mut self,
+                req: $crate::__private::HandshakeRequest<$C>,
+                option: $crate::__private::RPCOption,
+            ) -> Result<Option<$crate::__private::HandshakeResponse>, $crate::__private::RPCError<$C>> {
+                self.send_handshake(req, option).await.map(Some)
+            }
+
+            async fn catch_up(
+                &mut self,
+                req: $crate::__private::CatchUpRequest<$C>,
+                option: $crate::__private::RPCOption,
+            ) -> Result<$crate::__private::CatchUpResponse<$C>, $crate::__private::RPCError<$C>> {
+                self.send_catch_up(req, option).await
+            }
+
+            async fn hibernate(
+                &mut self,
+                req: $crate::__private::HibernateRequest<$C>,
+                option: $crate::__private::RPCOption,
+            ) -> Result<(), $crate::__private::RPCError<$C>> {
+                self.send_hibernate(req, option).await
+            }
+
+            fn backoff(&self) -> $crate::__private::Backoff {
+                self.backoff_policy()
+            }
+        }
+    };
+}
@@ -0,0 +1,222 @@
+use std::future::Future;
+use std::sync::Arc;
+
+use openraft::alias::OneshotReceiverErrorOf;
+use openraft::alias::ResponderReceiverOf;
+use openraft::error::RaftError;
+use openraft::network::v2::snapshot_stream::SnapshotAssembler;
+use openraft::network::v2::snapshot_stream::SnapshotChunk;
+use openraft::raft::ClientWriteResult;
+use openraft::Raft;
+use tokio::io::AsyncWrite;
+use tokio::net::TcpListener;
+use tokio::sync::mpsc;
+use tokio_stream::wrappers::TcpListenerStream;
+use tokio_stream::wrappers::UnboundedReceiverStream;
+use tonic::Request;
+use tonic::Response;
+use tonic::Status;
+use tonic::Streaming;
+
+use crate::convert;
+use crate::pb;
+use crate::pb::raft_service_server::RaftService;
+use crate::pb::raft_service_server::RaftServiceServer;
+use crate::Codec;
+use crate::GrpcConfig;
+use crate::GrpcTypeConfig;
+use crate::JsonCodec;
+
+/// Serve the RPCs sent by [`GrpcConnection`](crate::GrpcConnection)s to a local [`Raft`].
+///
+/// It implements the generated [`RaftService`], so that it can be added to an application's own
+/// tonic server with [`into_service()`](Self::into_service), next to the application's services.
+///
+/// A forwarded client write is responded with the default
+/// [`Responder`](openraft::raft::responder::Responder) of the type config, whose receiver is a
+/// oneshot receiver.
+pub struct GrpcServer<C, K = JsonCodec>
+where C: GrpcTypeConfig
+{
+    raft: Raft<C>,
+    config: Arc<GrpcConfig>,
+    codec: K,
+}
+
+impl<C, K> GrpcServer<C, K>
+where
+    C: GrpcTypeConfig,
+    C::SnapshotData: AsyncWrite + Unpin,
+    ResponderReceiverOf<C>: Future<Output = Result<ClientWriteResult<C>, OneshotReceiverErrorOf<C>>> + Send,
+    K: Codec,
+{
+    /// Create a server for `raft` with the default codec.
+    pub fn new(raft: Raft<C>, config: GrpcConfig) -> Self
+    where K: Default {
+        Self::with_codec(raft, config, K::default())
+    }
+
+    /// Create a server for `raft` that decodes application data with `codec`.
+    pub fn with_codec(raft: Raft<C>, config: GrpcConfig, codec: K) -> Self {
+        Self {
+            raft,
+            config: Arc::new(config),
+            codec,
+        }
+    }
+
+    /// Build the tonic service, which accepts messages up to [`GrpcConfig::max_message_size`].
+    pub fn into_service(self) -> RaftServiceServer<Self> {
+        let max = self.config.max_message_size;
+        RaftServiceServer::new(self).max_decoding_message_size(max).max_encoding_message_size(max)
+    }
+
+    /// Serve only this service on the connections accepted from `listener`.
+    ///
+    /// It returns only if the server fails. Drop the future to stop serving.
+    pub async fn serve(self, listener: TcpListener) -> Result<(), tonic::transport::Error> {
+        tonic::transport::Server::builder()
+            .add_service(self.into_service())
+            .serve_with_incoming(TcpListenerStream::new(listener))
+            .await
+    }
+}
+
+fn invalid(e: std::io::Error) -> Status {
+    Status::invalid_argument(e.to_string())
+}
+
+/// Openraft errors returned by a [`Raft`] mean it is shutting down, the caller should back off.
+fn unavailable(e: impl std::fmt::Display) -> Status {
+    Status::unavailable(e.to_string())
+}
+
+#[tonic::async_trait]
+impl<C, K> RaftService for GrpcServer<C, K>
+where
+    C: GrpcTypeConfig,
+    C::SnapshotData: AsyncWrite + Unpin,
+    ResponderReceiverOf<C>: Future<Output = Result<ClientWriteResult<C>, OneshotReceiverErrorOf<C>>> + Send,
+    K: Codec,
+{
+    async fn append_entries(
+        &self,
+        request: Request<pb::AppendEntriesRequest>,
+    ) -> Result<Response<pb::AppendEntriesResponse>, Status> {
+        let rpc = convert::append_entries_request_from_pb(request.into_inner(), &self.codec).map_err(invalid)?;
+        let resp = self.raft.append_entries(rpc).await.map_err(unavailable)?;
+        Ok(Response::new(convert::append_entries_response_to_pb(&resp)))
+    }
+
+    async fn vote(&self, request: Request<pb::VoteRequest>) -> Result<Response<pb::VoteResponse>, Status> {
+        let rpc = convert::vote_request_from_pb(request.into_inner()).map_err(invalid)?;
+        let resp = self.raft.vote(rpc).await.map_err(unavailable)?;
+        Ok(Response::new(convert::vote_response_to_pb(&resp)))
+    }
+
+    type SnapshotStream = UnboundedReceiverStream<Result<pb::SnapshotResponse, Status>>;
+
+    /// Write the chunks of a snapshot stream into the snapshot data returned by
+    /// [`Raft::begin_receiving_snapshot()`] as they arrive, and acknowledge every chunk.
+    ///
+    /// The stream is closed with an error status if a chunk is refused, or if the snapshot data
+    /// exceeds [`GrpcConfig::max_snapshot_size`].
+    async fn snapshot(
+        &self,
+        request: Request<Streaming<pb::SnapshotChunk>>,
+    ) -> Result<Response<Self::SnapshotStream>, Status> {
+        let mut stream = request.into_inner();
+        let mut assembler = SnapshotAssembler::new(self.raft.clone());
+        let codec = self.codec.clone();
+        let max_size = self.config.max_snapshot_size;
+
+        let (tx, rx) = mpsc::unbounded_channel();
+
+        tokio::spawn(async move {
+            let res: Result<(), Status> = async {
+                while let Some(chunk) = stream.message().await? {
+                    let chunk = convert::snapshot_chunk_from_pb::<C, _>(chunk, &codec).map_err(invalid)?;
+
+                    if let SnapshotChunk::Data { offset, data, .. } = &chunk {
+                        if offset + data.len() as u64 > max_size {
+                            return Err(Status::resource_exhausted(format!(
+                                "snapshot data exceeds max_snapshot_size: {} bytes",
+                                max_size
+                            )));
+                        }
+                    }
+
+                    let ack = assembler.receive(chunk).await.map_err(|e| match e {
+                        RaftError::APIError(e) => Status::invalid_argument(e.to_string()),
+                        RaftError::Fatal(e) => unavailable(e),
+                    })?;
+
+                    if tx.send(Ok(convert::snapshot_response_to_pb(&ack))).is_err() {
+                        // The sender has gone.
+                        return Ok(());
+                    }
+                }
+                Ok(())
+            }
+            .await;
+
+            if let Err(status) = res {
+                tracing::warn!("failed to receive snapshot: {}", status);
+                let _ = tx.send(Err(status));
+            }
+        });
+
+        Ok(Response::new(UnboundedReceiverStream::new(rx)))
+    }
+
+    async fn transfer_leader(
+        &self,
+        request: Request<pb::TransferLeaderRequest>,
+    ) -> Result<Response<pb::TransferLeaderResponse>, Status> {
+        let req = convert::transfer_leader_request_from_pb(request.into_inner()).map_err(invalid)?;
+        self.raft.handle_transfer_leader(req).await.map_err(unavailable)?;
+        Ok(Response::new(pb::TransferLeaderResponse {}))
+    }
+
+    async fn handshake(
+        &self,
+        request: Request<pb::HandshakeRequest>,
+    ) -> Result<Response<pb::HandshakeResponse>, Status> {
+        let req = convert::handshake_request_from_pb::<C>(request.into_inner());
+        let resp = self.raft.handle_handshake(req).await.map_err(unavailable)?;
+        Ok(Response::new(convert::handshake_response_to_pb(&resp)))
+    }
+
+    /// Write the forwarded request on the local [`Raft`], and send back its result, unless the
+    /// [`Raft`] is shutting down.
+    async fn forward_client_write(
+        &self,
+        request: Request<pb::ClientWriteRequest>,
+    ) -> Result<Response<pb::ClientWriteResponse>, Status> {
+        let app_data = self.codec.decode(&request.into_inner().data).map_err(invalid)?;
+
+        let res = match self.raft.handle_forwarded_client_write(app_data).await {
+            Err(RaftError::Fatal(e)) => return Err(unavailable(e)),
+            res => res,
+        };
+
+        let resp =
+            convert::client_write_result_to_pb(&res, &self.codec).map_err(|e| Status::internal(e.to_string()))?;
+        Ok(Response::new(resp))
+    }
+
+    async fn catch_up(&self, request: Request<pb::CatchUpRequest>) -> Result<Response<pb::CatchUpResponse>, Status> {
+        let req = convert::catch_up_request_from_pb(request.into_inner(), &self.codec).map_err(invalid)?;
+        let resp = self.raft.handle_catch_up(req).await.map_err(unavailable)?;
+        Ok(Response::new(convert::catch_up_response_to_pb(&resp)))
+    }
+
+    async fn hibernate(
+        &self,
+        request: Request<pb::HibernateRequest>,
+    ) -> Result<Response<pb::HibernateResponse>, Status> {
+        let req = convert::hibernate_request_from_pb(request.into_inner()).map_err(invalid)?;
+        self.raft.handle_hibernate(req).await.map_err(unavailable)?;
+        Ok(Response::new(pb::HibernateResponse {}))
+    }
+}
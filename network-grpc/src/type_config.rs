@@ -0,0 +1,115 @@
+use openraft::alias::CommittedLeaderIdOf;
+use openraft::vote::leader_id_adv;
+use openraft::vote::leader_id_std;
+use openraft::vote::RaftLeaderId;
+use openraft::Entry;
+use openraft::RaftTypeConfig;
+
+use crate::pb;
+
+/// A leader id that can be sent as a [`pb::LeaderId`].
+///
+/// It is implemented for both leader ids provided by Openraft. An application with its own leader
+/// id implements it to use this crate.
+pub trait ProtoLeaderId<C>: RaftLeaderId<C>
+where C: RaftTypeConfig<NodeId = u64, Term = u64>
+{
+    fn to_proto(&self) -> pb::LeaderId;
+
+    fn from_proto(leader_id: pb::LeaderId) -> Self;
+
+    fn committed_to_proto(committed: &Self::Committed) -> pb::LeaderId;
+
+    fn committed_from_proto(leader_id: pb::LeaderId) -> Self::Committed;
+}
+
+impl<C> ProtoLeaderId<C> for leader_id_adv::LeaderId<C>
+where C: RaftTypeConfig<NodeId = u64, Term = u64, LeaderId = Self>
+{
+    fn to_proto(&self) -> pb::LeaderId {
+        pb::LeaderId {
+            term: self.term,
+            node_id: Some(self.node_id),
+        }
+    }
+
+    fn from_proto(leader_id: pb::LeaderId) -> Self {
+        Self {
+            term: leader_id.term,
+            node_id: leader_id.node_id.unwrap_or_default(),
+        }
+    }
+
+    fn committed_to_proto(committed: &Self) -> pb::LeaderId {
+        committed.to_proto()
+    }
+
+    fn committed_from_proto(leader_id: pb::LeaderId) -> Self {
+        Self::from_proto(leader_id)
+    }
+}
+
+impl<C> ProtoLeaderId<C> for leader_id_std::LeaderId<C>
+where C: RaftTypeConfig<NodeId = u64, Term = u64, LeaderId = Self>
+{
+    fn to_proto(&self) -> pb::LeaderId {
+        pb::LeaderId {
+            term: self.term,
+            node_id: self.voted_for,
+        }
+    }
+
+    fn from_proto(leader_id: pb::LeaderId) -> Self {
+        Self {
+            term: leader_id.term,
+            voted_for: leader_id.node_id,
+        }
+    }
+
+    fn committed_to_proto(committed: &leader_id_std::CommittedLeaderId<C>) -> pb::LeaderId {
+        pb::LeaderId {
+            term: committed.term,
+            node_id: None,
+        }
+    }
+
+    fn committed_from_proto(leader_id: pb::LeaderId) -> leader_id_std::CommittedLeaderId<C> {
+        leader_id_std::CommittedLeaderId::new(leader_id.term, 0)
+    }
+}
+
+/// A [`RaftTypeConfig`] whose messages can be sent over gRPC.
+///
+/// It is implemented for every type config with `u64` node ids and terms, the default [`Entry`],
+/// and a leader id that implements [`ProtoLeaderId`].
+pub trait GrpcTypeConfig: RaftTypeConfig<NodeId = u64, Term = u64, Entry = Entry<Self>> {
+    fn leader_id_to_proto(leader_id: &Self::LeaderId) -> pb::LeaderId;
+
+    fn leader_id_from_proto(leader_id: pb::LeaderId) -> Self::LeaderId;
+
+    fn committed_leader_id_to_proto(committed: &CommittedLeaderIdOf<Self>) -> pb::LeaderId;
+
+    fn committed_leader_id_from_proto(leader_id: pb::LeaderId) -> CommittedLeaderIdOf<Self>;
+}
+
+impl<C> GrpcTypeConfig for C
+where
+    C: RaftTypeConfig<NodeId = u64, Term = u64, Entry = Entry<C>>,
+    C::LeaderId: ProtoLeaderId<C>,
+{
+    fn leader_id_to_proto(leader_id: &C::LeaderId) -> pb::LeaderId {
+        leader_id.to_proto()
+    }
+
+    fn leader_id_from_proto(leader_id: pb::LeaderId) -> C::LeaderId {
+        C::LeaderId::from_proto(leader_id)
+    }
+
+    fn committed_leader_id_to_proto(committed: &CommittedLeaderIdOf<C>) -> pb::LeaderId {
+        C::LeaderId::committed_to_proto(committed)
+    }
+
+    fn committed_leader_id_from_proto(leader_id: pb::LeaderId) -> CommittedLeaderIdOf<C> {
+        C::LeaderId::committed_from_proto(leader_id)
+    }
+}
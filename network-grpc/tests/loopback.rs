@@ -0,0 +1,370 @@
+//! Run a cluster whose nodes talk to each other over gRPC on the loopback interface.
+
+use std::collections::BTreeMap;
+use std::future::pending;
+use std::io::Cursor;
+use std::sync::Arc;
+use std::sync::Mutex;
+use std::time::Duration;
+
+use memstore::LogStore;
+use openraft::alias::LogIdOf;
+use openraft::entry::RaftEntry;
+use openraft::error::RPCError;
+use openraft::error::StreamingError;
+use openraft::network::v2::RaftNetworkV2;
+use openraft::network::ProtocolVersion;
+use openraft::network::RPCOption;
+use openraft::raft::AppendEntriesRequest;
+use openraft::raft::VoteRequest;
+use openraft::storage::RaftStateMachine;
+use openraft::storage::Snapshot;
+use openraft::vote::leader_id_adv::LeaderId;
+use openraft::BasicNode;
+use openraft::Config;
+use openraft::Entry;
+use openraft::EntryPayload;
+use openraft::LogId;
+use openraft::OptionalSend;
+use openraft::Raft;
+use openraft::RaftNetworkFactory;
+use openraft::RaftSnapshotBuilder;
+use openraft::SnapshotMeta;
+use openraft::SnapshotPolicy;
+use openraft::StorageError;
+use openraft::StoredMembership;
+use openraft::Vote;
+use openraft_network_grpc::GrpcConfig;
+use openraft_network_grpc::GrpcNetworkFactory;
+use openraft_network_grpc::GrpcServer;
+use tokio::net::TcpListener;
+
+openraft::declare_raft_types!(
+    pub TypeConfig:
+        D = u64,
+        R = (),
+);
+
+openraft_network_grpc::impl_grpc_network!(TypeConfig);
+
+/// A state machine that records every applied value in order.
+#[derive(Clone, Default)]
+struct StateMachine {
+    inner: Arc<Mutex<SmInner>>,
+}
+
+#[derive(Default)]
+struct SmInner {
+    last_applied: Option<LogIdOf<TypeConfig>>,
+    last_membership: StoredMembership<TypeConfig>,
+    values: Vec<u64>,
+    snapshot: Option<(SnapshotMeta<TypeConfig>, Vec<u8>)>,
+    snapshot_idx: u64,
+}
+
+impl StateMachine {
+    fn values(&self) -> Vec<u64> {
+        self.inner.lock().unwrap().values.clone()
+    }
+}
+
+/// Snapshot data is the values in little-endian bytes.
+fn encode(values: &[u64]) -> Vec<u8> {
+    values.iter().flat_map(|v| v.to_le_bytes()).collect()
+}
+
+fn decode(data: &[u8]) -> Vec<u64> {
+    data.chunks_exact(8).map(|b| u64::from_le_bytes(b.try_into().unwrap())).collect()
+}
+
+impl RaftSnapshotBuilder<TypeConfig> for StateMachine {
+    async fn build_snapshot(&mut self) -> Result<Snapshot<TypeConfig>, StorageError<TypeConfig>> {
+        let mut sm = self.inner.lock().unwrap();
+        sm.snapshot_idx += 1;
+
+        let meta = SnapshotMeta {
+            last_log_id: sm.last_applied,
+            last_membership: sm.last_membership.clone(),
+            snapshot_id: format!("{}", sm.snapshot_idx),
+        };
+        let data = encode(&sm.values);
+        sm.snapshot = Some((meta.clone(), data.clone()));
+
+        Ok(Snapshot {
+            meta,
+            snapshot: Box::new(Cursor::new(data)),
+        })
+    }
+}
+
+impl RaftStateMachine<TypeConfig> for StateMachine {
+    type SnapshotBuilder = Self;
+
+    async fn applied_state(
+        &mut self,
+    ) -> Result<(Option<LogIdOf<TypeConfig>>, StoredMembership<TypeConfig>), StorageError<TypeConfig>> {
+        let sm = self.inner.lock().unwrap();
+        Ok((sm.last_applied, sm.last_membership.clone()))
+    }
+
+    async fn apply<I>(&mut self, entries: I) -> Result<Vec<()>, StorageError<TypeConfig>>
+    where
+        I: IntoIterator<Item = openraft::Entry<TypeConfig>> + OptionalSend,
+        I::IntoIter: OptionalSend,
+    {
+        let mut sm = self.inner.lock().unwrap();
+        let mut res = vec![];
+
+        for entry in entries {
+            sm.last_applied = Some(entry.log_id());
+            match entry.payload {
+                EntryPayload::Blank => {}
+                EntryPayload::Normal(v) => sm.values.push(v),
+                EntryPayload::Membership(m) => sm.last_membership = StoredMembership::new(Some(entry.log_id), m),
+            }
+            res.push(());
+        }
+        Ok(res)
+    }
+
+    async fn get_snapshot_builder(&mut self) -> Self::SnapshotBuilder {
+        self.clone()
+    }
+
+    async fn begin_receiving_snapshot(&mut self) -> Result<Box<Cursor<Vec<u8>>>, StorageError<TypeConfig>> {
+        Ok(Box::default())
+    }
+
+    async fn install_snapshot(
+        &mut self,
+        meta: &SnapshotMeta<TypeConfig>,
+        snapshot: Box<Cursor<Vec<u8>>>,
+    ) -> Result<(), StorageError<TypeConfig>> {
+        let data = snapshot.into_inner();
+
+        let mut sm = self.inner.lock().unwrap();
+        sm.last_applied = meta.last_log_id;
+        sm.last_membership = meta.last_membership.clone();
+        sm.values = decode(&data);
+        sm.snapshot = Some((meta.clone(), data));
+        Ok(())
+    }
+
+    async fn get_current_snapshot(&mut self) -> Result<Option<Snapshot<TypeConfig>>, StorageError<TypeConfig>> {
+        let sm = self.inner.lock().unwrap();
+        Ok(sm.snapshot.clone().map(|(meta, data)| Snapshot {
+            meta,
+            snapshot: Box::new(Cursor::new(data)),
+        }))
+    }
+}
+
+/// Start a node that listens on a random loopback port, return it and its address.
+async fn start_node(
+    id: u64,
+    config: Arc<Config>,
+    grpc_config: GrpcConfig,
+) -> anyhow::Result<(Raft<TypeConfig>, StateMachine, BasicNode)> {
+    let listener = TcpListener::bind("127.0.0.1:0").await?;
+    let node = BasicNode::new(listener.local_addr()?);
+
+    let sm = StateMachine::default();
+    let network = GrpcNetworkFactory::<TypeConfig>::new(id, grpc_config.clone());
+    let raft = Raft::new(id, config, network, LogStore::<TypeConfig>::default(), sm.clone()).await?;
+
+    let server = GrpcServer::<TypeConfig>::new(raft.clone(), grpc_config);
+    tokio::spawn(server.serve(listener));
+
+    Ok((raft, sm, node))
+}
+
+/// Elect a leader, replicate writes to every node, forward a write from a follower, bring up a
+/// learner from a snapshot streamed in chunks, then transfer the leadership.
+#[tokio::test(flavor = "multi_thread", worker_threads = 4)]
+async fn test_cluster_over_loopback() -> anyhow::Result<()> {
+    let config = Arc::new(
+        Config {
+            heartbeat_interval: 50,
+            election_timeout_min: 150,
+            election_timeout_max: 300,
+            snapshot_policy: SnapshotPolicy::Never,
+            max_in_snapshot_log_to_keep: 0,
+            purge_batch_size: 1,
+            // Use small chunks so that a snapshot is sent in more than one message.
+            snapshot_max_chunk_size: 8,
+            ..Default::default()
+        }
+        .validate()?,
+    );
+    let timeout = Some(Duration::from_secs(10));
+
+    let mut nodes = BTreeMap::new();
+    for id in 0..3u64 {
+        nodes.insert(id, start_node(id, config.clone(), GrpcConfig::default()).await?);
+    }
+
+    let members = nodes.iter().map(|(id, (_, _, node))| (*id, node.clone())).collect::<BTreeMap<_, _>>();
+    let (leader, _, _) = &nodes[&0];
+    leader.initialize(members).await?;
+    leader.wait(timeout).current_leader(0, "node 0 is elected").await?;
+
+    let mut last = None;
+    for v in 0..10 {
+        last = Some(leader.client_write(v).await?.log_id);
+    }
+    let last = last.map(|x| x.index);
+
+    for (id, (raft, sm, _)) in nodes.iter() {
+        raft.wait(timeout).applied_index_at_least(last, format!("node {} applies the writes", id)).await?;
+        assert_eq!((0..10).collect::<Vec<_>>(), sm.values(), "node {}", id);
+    }
+
+    // The leader learns the protocol versions of the followers with a handshake over gRPC.
+    let m = leader.wait(timeout).metrics(|m| m.peer_versions.len() == 2, "peer versions are known").await?;
+    assert!(!m.is_mixed_version());
+    assert_eq!(Some(&ProtocolVersion::CURRENT), m.peer_versions.get(&1));
+
+    // A follower forwards a client write to the leader over gRPC.
+    {
+        let (follower, _, _) = &nodes[&1];
+        follower
+            .wait(timeout)
+            .metrics(|m| m.peer_versions.contains_key(&0), "leader version is known")
+            .await?;
+
+        let resp = follower.client_write_forwarded(10, Duration::from_secs(5)).await?;
+        let last = Some(resp.log_id.index);
+
+        for (id, (raft, sm, _)) in nodes.iter() {
+            raft.wait(timeout)
+                .applied_index_at_least(last, format!("node {} applies the forwarded write", id))
+                .await?;
+            assert_eq!((0..11).collect::<Vec<_>>(), sm.values(), "node {}", id);
+        }
+    }
+
+    // Purge the logs so that a new learner can only be brought up to date with a snapshot.
+    let last_applied = leader.metrics().borrow().last_applied;
+    leader.trigger().snapshot().await?;
+    leader.wait(timeout).snapshot(last_applied.unwrap(), "snapshot built").await?;
+    leader.trigger().purge_log(last_applied.unwrap().index).await?;
+    leader.wait(timeout).purged(last_applied, "logs purged").await?;
+
+    let (learner, learner_sm, learner_node) = start_node(3, config.clone(), GrpcConfig::default()).await?;
+    leader.add_learner(3, learner_node, true).await?;
+
+    learner
+        .wait(timeout)
+        .applied_index_at_least(last_applied.map(|x| x.index), "learner installs the snapshot")
+        .await?;
+    assert_eq!((0..11).collect::<Vec<_>>(), learner_sm.values());
+    assert!(learner.metrics().borrow().snapshot.is_some());
+
+    leader.trigger().transfer_leader(1).await?;
+    for (id, (raft, _, _)) in nodes.iter() {
+        raft.wait(timeout).current_leader(1, format!("node {} sees the new leader", id)).await?;
+    }
+
+    for (raft, _, _) in nodes.values() {
+        raft.shutdown().await?;
+    }
+    learner.shutdown().await?;
+
+    Ok(())
+}
+
+/// A node that does not listen is unreachable, and a node that does not respond times out.
+#[tokio::test]
+async fn test_unreachable_and_timeout() -> anyhow::Result<()> {
+    let mut factory = GrpcNetworkFactory::<TypeConfig>::new(0, GrpcConfig::default());
+    let req = || VoteRequest::new(Vote::new(1, 0), None);
+
+    // Nothing listens on a port once the listener is dropped.
+    let addr = TcpListener::bind("127.0.0.1:0").await?.local_addr()?;
+    let mut conn = factory.new_client(1, &BasicNode::new(addr)).await;
+
+    let res = conn.vote(req(), RPCOption::new(Duration::from_secs(1))).await;
+    assert!(matches!(res, Err(RPCError::Unreachable(_))), "got: {:?}", res);
+
+    // A listener that accepts but never responds.
+    let listener = TcpListener::bind("127.0.0.1:0").await?;
+    let mut conn = factory.new_client(2, &BasicNode::new(listener.local_addr()?)).await;
+
+    let res = conn.vote(req(), RPCOption::new(Duration::from_millis(200))).await;
+    let Err(RPCError::Timeout(t)) = res else {
+        panic!("expect Timeout, got: {:?}", res);
+    };
+    assert_eq!((0, 2, Duration::from_millis(200)), (t.id, t.target, t.timeout),);
+
+    Ok(())
+}
+
+/// An AppendEntries request larger than `max_message_size` is split before it is sent.
+#[tokio::test]
+async fn test_payload_too_large() -> anyhow::Result<()> {
+    let config = GrpcConfig {
+        max_message_size: 256,
+        ..Default::default()
+    };
+    let mut factory = GrpcNetworkFactory::<TypeConfig>::new(0, config);
+
+    // Nothing listens here: the size is checked before connecting.
+    let addr = TcpListener::bind("127.0.0.1:0").await?.local_addr()?;
+    let mut conn = factory.new_client(1, &BasicNode::new(addr)).await;
+
+    let req = |n: u64| AppendEntriesRequest {
+        vote: Vote::new_committed(1, 0),
+        prev_log_id: None,
+        entries: (1..=n)
+            .map(|i| Entry::new_normal(LogId::new(LeaderId { term: 1, node_id: 0 }, i), u64::MAX))
+            .collect(),
+        leader_commit: None,
+    };
+    let option = || RPCOption::new(Duration::from_secs(1));
+
+    let res = conn.append_entries(req(20), option()).await;
+    let Err(RPCError::PayloadTooLarge(e)) = res else {
+        panic!("expect PayloadTooLarge, got: {:?}", res);
+    };
+    assert_eq!(10, e.entries_hint());
+
+    let res = conn.append_entries(req(2), option()).await;
+    assert!(matches!(res, Err(RPCError::Unreachable(_))), "got: {:?}", res);
+
+    Ok(())
+}
+
+/// A server refuses a snapshot larger than `max_snapshot_size`.
+#[tokio::test(flavor = "multi_thread", worker_threads = 2)]
+async fn test_snapshot_too_large() -> anyhow::Result<()> {
+    let config = Arc::new(Config::default().validate()?);
+    let grpc_config = GrpcConfig {
+        max_snapshot_size: 32,
+        ..Default::default()
+    };
+    let (raft, sm, node) = start_node(1, config, grpc_config).await?;
+
+    let mut factory = GrpcNetworkFactory::<TypeConfig>::new(0, GrpcConfig::default());
+    let mut conn = factory.new_client(1, &node).await;
+
+    let log_id = LogId::new(LeaderId { term: 1, node_id: 0 }, 5);
+    let snapshot = |n: u64| Snapshot {
+        meta: SnapshotMeta {
+            last_log_id: Some(log_id),
+            last_membership: StoredMembership::default(),
+            snapshot_id: format!("{}", n),
+        },
+        snapshot: Box::new(Cursor::new(encode(&(0..n).collect::<Vec<_>>()))),
+    };
+    let option = || RPCOption::new(Duration::from_secs(1));
+
+    let res = conn.full_snapshot(Vote::new_committed(1, 0), snapshot(8), pending(), option()).await;
+    assert!(matches!(res, Err(StreamingError::Network(_))), "got: {:?}", res);
+    assert!(sm.values().is_empty());
+
+    conn.full_snapshot(Vote::new_committed(1, 0), snapshot(4), pending(), option()).await?;
+    assert_eq!(vec![0, 1, 2, 3], sm.values());
+
+    raft.shutdown().await?;
+    Ok(())
+}
@@ -0,0 +1,503 @@
+use std::collections::HashMap;
+use std::future::Future;
+use std::io;
+use std::sync::atomic::AtomicU64;
+use std::sync::atomic::Ordering;
+use std::sync::Arc;
+use std::sync::Mutex;
+
+use anyerror::AnyError;
+use openraft::alias::SnapshotDataOf;
+use openraft::alias::VoteOf;
+use openraft::error::ClientWriteError;
+use openraft::error::NetworkError;
+use openraft::error::PayloadTooLarge;
+use openraft::error::RPCError;
+use openraft::error::RaftError;
+use openraft::error::RemoteError;
+use openraft::error::ReplicationClosed;
+use openraft::error::StreamingError;
+use openraft::error::Timeout;
+use openraft::error::Unreachable;
+use openraft::network::v2::RaftNetworkV2;
+use openraft::network::Backoff;
+use openraft::network::ProtocolVersion;
+use openraft::network::RPCOption;
+use openraft::network::RPCTypes;
+use openraft::raft::AppendEntriesRequest;
+use openraft::raft::AppendEntriesResponse;
+use openraft::raft::CatchUpRequest;
+use openraft::raft::CatchUpResponse;
+use openraft::raft::ClientWriteResponse;
+use openraft::raft::HandshakeRequest;
+use openraft::raft::HandshakeResponse;
+use openraft::raft::HibernateRequest;
+use openraft::raft::SnapshotResponse;
+use openraft::raft::TransferLeaderRequest;
+use openraft::raft::VoteRequest;
+use openraft::raft::VoteResponse;
+use openraft::BasicNode;
+use openraft::OptionalSend;
+use openraft::RaftNetworkFactory;
+use openraft::RaftTypeConfig;
+use openraft::Snapshot;
+use serde::de::DeserializeOwned;
+use serde::Serialize;
+use tokio::io::AsyncWriteExt;
+use tokio::net::tcp::OwnedReadHalf;
+use tokio::net::tcp::OwnedWriteHalf;
+use tokio::net::TcpStream;
+use tokio::sync::mpsc;
+use tokio::sync::oneshot;
+
+use crate::frame::encode_frame;
+use crate::frame::read_frame;
+use crate::message::Request;
+use crate::message::Response;
+use crate::Codec;
+use crate::JsonCodec;
+use crate::TcpConfig;
+
+/// A node that can be reached at a TCP address.
+pub trait NodeAddr {
+    /// The address to connect to, such as `127.0.0.1:21001`.
+    fn addr(&self) -> &str;
+}
+
+impl NodeAddr for BasicNode {
+    fn addr(&self) -> &str {
+        &self.addr
+    }
+}
+
+/// Connections by address, shared by every [`TcpConnection`] built by the same factory.
+type Pool = Arc<Mutex<HashMap<String, Arc<Mux>>>>;
+
+/// The [`RaftNetworkFactory`] of a node that sends RPCs over TCP.
+///
+/// RPCs to the same address share one TCP connection, which is established on the first RPC and
+/// re-established after it breaks. [`impl_tcp_network!`](crate::impl_tcp_network) must be invoked
+/// once for the type config to make [`TcpConnection`] a `RaftNetworkV2`.
+pub struct TcpNetworkFactory<C, K = JsonCodec>
+where C: RaftTypeConfig
+{
+    id: C::NodeId,
+    config: Arc<TcpConfig>,
+    codec: K,
+    pool: Pool,
+}
+
+impl<C, K> Clone for TcpNetworkFactory<C, K>
+where
+    C: RaftTypeConfig,
+    K: Codec,
+{
+    fn clone(&self) -> Self {
+        Self {
+            id: self.id.clone(),
+            config: self.config.clone(),
+            codec: self.codec.clone(),
+            pool: self.pool.clone(),
+        }
+    }
+}
+
+impl<C, K> TcpNetworkFactory<C, K>
+where
+    C: RaftTypeConfig,
+    K: Codec,
+{
+    /// Create a factory for node `id` with the default codec.
+    pub fn new(id: C::NodeId, config: TcpConfig) -> Self
+    where K: Default {
+        Self::with_codec(id, config, K::default())
+    }
+
+    /// Create a factory for node `id` that encodes messages with `codec`.
+    pub fn with_codec(id: C::NodeId, config: TcpConfig, codec: K) -> Self {
+        Self {
+            id,
+            config: Arc::new(config),
+            codec,
+            pool: Default::default(),
+        }
+    }
+}
+
+impl<C, K> RaftNetworkFactory<C> for TcpNetworkFactory<C, K>
+where
+    C: RaftTypeConfig,
+    C::Node: NodeAddr,
+    K: Codec,
+    TcpConnection<C, K>: RaftNetworkV2<C>,
+{
+    type Network = TcpConnection<C, K>;
+
+    async fn new_client(&mut self, target: C::NodeId, node: &C::Node) -> Self::Network {
+        TcpConnection {
+            id: self.id.clone(),
+            target,
+            addr: node.addr().to_string(),
+            config: self.config.clone(),
+            codec: self.codec.clone(),
+            pool: self.pool.clone(),
+        }
+    }
+
+    /// [`TcpConnection`] implements every RPC of the latest protocol version.
+    fn protocol_version(&self) -> ProtocolVersion {
+        ProtocolVersion::CURRENT
+    }
+}
+
+/// A connection from one node to another over TCP.
+pub struct TcpConnection<C, K = JsonCodec>
+where C: RaftTypeConfig
+{
+    id: C::NodeId,
+    target: C::NodeId,
+    addr: String,
+    config: Arc<TcpConfig>,
+    codec: K,
+    pool: Pool,
+}
+
+impl<C, K> TcpConnection<C, K>
+where
+    C: RaftTypeConfig,
+    SnapshotDataOf<C>: Serialize + DeserializeOwned,
+    K: Codec,
+{
+    /// Send an AppendEntries RPC to the target.
+    ///
+    /// If the request is larger than [`TcpConfig::max_frame_size`], a [`PayloadTooLarge`] error
+    /// asks the caller to send half of the entries.
+    pub async fn send_append_entries(
+        &mut self,
+        rpc: AppendEntriesRequest<C>,
+        option: RPCOption,
+    ) -> Result<AppendEntriesResponse<C>, RPCError<C>> {
+        let n = rpc.entries.len() as u64;
+
+        let res = self.send(Request::AppendEntries(rpc), RPCTypes::AppendEntries, &option).await;
+        match res {
+            Ok(Response::AppendEntries(resp)) => Ok(resp),
+            Ok(resp) => Err(unexpected(resp)),
+            Err(RPCError::PayloadTooLarge(_)) if n > 1 => Err(PayloadTooLarge::new_entries_hint(n / 2).into()),
+            // A single entry that does not fit in a frame can never be sent.
+            Err(RPCError::PayloadTooLarge(e)) => Err(NetworkError::new(&e).into()),
+            Err(e) => Err(e),
+        }
+    }
+
+    /// Send a RequestVote RPC to the target.
+    pub async fn send_vote(&mut self, rpc: VoteRequest<C>, option: RPCOption) -> Result<VoteResponse<C>, RPCError<C>> {
+        match self.send(Request::Vote(rpc), RPCTypes::Vote, &option).await? {
+            Response::Vote(resp) => Ok(resp),
+            resp => Err(unexpected(resp)),
+        }
+    }
+
+    /// Send a complete snapshot to the target in a single frame.
+    pub async fn send_full_snapshot(
+        &mut self,
+        vote: VoteOf<C>,
+        snapshot: Snapshot<C>,
+        cancel: impl Future<Output = ReplicationClosed> + OptionalSend + 'static,
+        option: RPCOption,
+    ) -> Result<SnapshotResponse<C>, StreamingError<C>> {
+        let req = Request::FullSnapshot {
+            vote,
+            meta: snapshot.meta,
+            data: *snapshot.snapshot,
+        };
+
+        let resp = tokio::select! {
+            closed = cancel => return Err(closed.into()),
+            resp = self.send(req, RPCTypes::InstallSnapshot, &option) => resp?,
+        };
+
+        match resp {
+            Response::FullSnapshot(resp) => Ok(resp),
+            resp => Err(unexpected(resp).into()),
+        }
+    }
+
+    /// Send a TransferLeader message to the target.
+    pub async fn send_transfer_leader(
+        &mut self,
+        req: TransferLeaderRequest<C>,
+        option: RPCOption,
+    ) -> Result<(), RPCError<C>> {
+        match self.send(Request::TransferLeader(req), RPCTypes::TransferLeader, &option).await? {
+            Response::TransferLeader => Ok(()),
+            resp => Err(unexpected(resp)),
+        }
+    }
+
+    /// Forward a client write to the target, which is believed to be the Leader.
+    ///
+    /// An error returned by the target's [`Raft`](openraft::Raft), such as `ForwardToLeader`, is
+    /// returned as a [`RemoteError`].
+    pub async fn send_forward_client_write(
+        &mut self,
+        app_data: C::D,
+        option: RPCOption,
+    ) -> Result<ClientWriteResponse<C>, RPCError<C, RaftError<C, ClientWriteError<C>>>> {
+        let resp = self.send(Request::ClientWrite(app_data), RPCTypes::ClientWrite, &option).await;
+
+        match resp.map_err(with_remote_error)? {
+            Response::ClientWrite(Ok(resp)) => Ok(resp),
+            Response::ClientWrite(Err(e)) => Err(RemoteError::new(self.target.clone(), e).into()),
+            resp => Err(with_remote_error(unexpected(resp))),
+        }
+    }
+
+    /// Exchange protocol versions with the target.
+    pub async fn send_handshake(
+        &mut self,
+        req: HandshakeRequest<C>,
+        option: RPCOption,
+    ) -> Result<HandshakeResponse, RPCError<C>> {
+        match self.send(Request::Handshake(req), RPCTypes::Handshake, &option).await? {
+            Response::Handshake(resp) => Ok(resp),
+            resp => Err(unexpected(resp)),
+        }
+    }
+
+    /// Ask the target to bring another node up to date on behalf of the Leader.
+    pub async fn send_catch_up(
+        &mut self,
+        req: CatchUpRequest<C>,
+        option: RPCOption,
+    ) -> Result<CatchUpResponse<C>, RPCError<C>> {
+        match self.send(Request::CatchUp(req), RPCTypes::CatchUp, &option).await? {
+            Response::CatchUp(resp) => Ok(resp),
+            resp => Err(unexpected(resp)),
+        }
+    }
+
+    /// Tell the target that the Leader stops sending heartbeats.
+    pub async fn send_hibernate(&mut self, req: HibernateRequest<C>, option: RPCOption) -> Result<(), RPCError<C>> {
+        match self.send(Request::Hibernate(req), RPCTypes::Hibernate, &option).await? {
+            Response::Hibernate => Ok(()),
+            resp => Err(unexpected(resp)),
+        }
+    }
+
+    /// The backoff to retry the target after it is found unreachable.
+    pub fn backoff_policy(&self) -> Backoff {
+        self.config.backoff()
+    }
+
+    /// Send a request and wait for its response, for at most [`RPCOption::hard_ttl()`].
+    async fn send(
+        &mut self,
+        req: Request<C>,
+        action: RPCTypes,
+        option: &RPCOption,
+    ) -> Result<Response<C>, RPCError<C>> {
+        let ttl = option.hard_ttl();
+
+        match tokio::time::timeout(ttl, self.call(req, action)).await {
+            Ok(res) => res,
+            Err(_elapsed) => Err(RPCError::Timeout(Timeout {
+                action,
+                id: self.id.clone(),
+                target: self.target.clone(),
+                timeout: ttl,
+            })),
+        }
+    }
+
+    async fn call(&mut self, req: Request<C>, action: RPCTypes) -> Result<Response<C>, RPCError<C>> {
+        let payload = self.codec.encode(&req).map_err(|e| NetworkError::new(&e))?;
+        let max_frame_size = self.config.max_frame_size;
+
+        let mux = self.connect().await.map_err(|e| Unreachable::new(&e))?;
+
+        let resp = mux.call(&payload, max_frame_size).await.map_err(|e| match e.kind() {
+            io::ErrorKind::InvalidInput if action == RPCTypes::AppendEntries => {
+                RPCError::PayloadTooLarge(PayloadTooLarge::new_entries_hint(1))
+            }
+            _ => RPCError::Network(NetworkError::new(&e)),
+        })?;
+
+        let resp = self.codec.decode::<Response<C>>(&resp).map_err(|e| NetworkError::new(&e))?;
+
+        if let Response::Error(e) = resp {
+            tracing::warn!("{} to {} failed on the remote: {}", action, self.target, e);
+            return Err(Unreachable::new(&AnyError::error(e)).into());
+        }
+
+        Ok(resp)
+    }
+
+    /// Return the connection to the target, establishing it if there is none or it is broken.
+    async fn connect(&self) -> io::Result<Arc<Mux>> {
+        {
+            let pool = self.pool.lock().unwrap();
+            if let Some(mux) = pool.get(&self.addr) {
+                if !mux.is_closed() {
+                    return Ok(mux.clone());
+                }
+            }
+        }
+
+        let stream = tokio::time::timeout(self.config.connect_timeout, TcpStream::connect(&self.addr))
+            .await
+            .map_err(|_elapsed| io::Error::new(io::ErrorKind::TimedOut, "connect timeout"))??;
+        stream.set_nodelay(true)?;
+
+        tracing::debug!("connected to {} at {}", self.target, self.addr);
+
+        let mux = Mux::start(stream, self.config.max_frame_size);
+        self.pool.lock().unwrap().insert(self.addr.clone(), mux.clone());
+        Ok(mux)
+    }
+}
+
+fn unexpected<C>(resp: Response<C>) -> RPCError<C>
+where C: RaftTypeConfig {
+    let name = match resp {
+        Response::AppendEntries(_) => "AppendEntries",
+        Response::Vote(_) => "Vote",
+        Response::FullSnapshot(_) => "FullSnapshot",
+        Response::TransferLeader => "TransferLeader",
+        Response::ClientWrite(_) => "ClientWrite",
+        Response::Handshake(_) => "Handshake",
+        Response::CatchUp(_) => "CatchUp",
+        Response::Hibernate => "Hibernate",
+        Response::Error(_) => "Error",
+    };
+    let e = AnyError::error(format!("unexpected response: {}", name));
+    RPCError::Network(NetworkError::new(&e))
+}
+
+/// Convert an error of an RPC that has no remote error to one of an RPC that has.
+fn with_remote_error<C, E>(e: RPCError<C>) -> RPCError<C, E>
+where
+    C: RaftTypeConfig,
+    E: std::error::Error,
+{
+    match e {
+        RPCError::Timeout(e) => RPCError::Timeout(e),
+        RPCError::Unreachable(e) => RPCError::Unreachable(e),
+        RPCError::PayloadTooLarge(e) => RPCError::PayloadTooLarge(e),
+        RPCError::Network(e) => RPCError::Network(e),
+        RPCError::RemoteError(e) => match e.source {},
+    }
+}
+
+/// Requests waiting for a response, by request id. `None` once the connection is broken.
+type Pending = Arc<Mutex<Option<HashMap<u64, oneshot::Sender<Vec<u8>>>>>>;
+
+/// A TCP connection shared by concurrent requests.
+///
+/// Frames are written by a dedicated task, so that a request cancelled in the middle never leaves
+/// a partial frame in the stream. Another task reads the responses and passes each to the request
+/// with the same id.
+struct Mux {
+    next_id: AtomicU64,
+    tx: mpsc::UnboundedSender<Vec<u8>>,
+    pending: Pending,
+}
+
+impl Mux {
+    fn start(stream: TcpStream, max_frame_size: usize) -> Arc<Self> {
+        let (r, w) = stream.into_split();
+        let (tx, rx) = mpsc::unbounded_channel();
+        let pending: Pending = Arc::new(Mutex::new(Some(HashMap::new())));
+
+        tokio::spawn(Self::write_loop(w, rx, pending.clone()));
+        tokio::spawn(Self::read_loop(r, max_frame_size, pending.clone()));
+
+        Arc::new(Self {
+            next_id: AtomicU64::new(0),
+            tx,
+            pending,
+        })
+    }
+
+    fn is_closed(&self) -> bool {
+        self.pending.lock().unwrap().is_none()
+    }
+
+    async fn call(&self, payload: &[u8], max_frame_size: usize) -> io::Result<Vec<u8>> {
+        let id = self.next_id.fetch_add(1, Ordering::Relaxed);
+        let frame = encode_frame(id, payload, max_frame_size)?;
+
+        let (tx, rx) = oneshot::channel();
+        {
+            let mut pending = self.pending.lock().unwrap();
+            let Some(pending) = pending.as_mut() else {
+                return Err(io::Error::new(io::ErrorKind::NotConnected, "connection is closed"));
+            };
+            pending.insert(id, tx);
+        }
+
+        // Remove the pending request if this call is cancelled, e.g., by a timeout.
+        let _guard = RemoveOnDrop {
+            pending: &self.pending,
+            id,
+        };
+
+        self.tx
+            .send(frame)
+            .map_err(|_| io::Error::new(io::ErrorKind::NotConnected, "connection is closed"))?;
+
+        rx.await
+            .map_err(|_| io::Error::new(io::ErrorKind::ConnectionAborted, "connection closed before response"))
+    }
+
+    async fn write_loop(mut w: OwnedWriteHalf, mut rx: mpsc::UnboundedReceiver<Vec<u8>>, pending: Pending) {
+        while let Some(frame) = rx.recv().await {
+            if let Err(e) = w.write_all(&frame).await {
+                tracing::warn!("failed to write to {:?}: {}", w.peer_addr(), e);
+                break;
+            }
+        }
+
+        close(&pending);
+        // The peer sees EOF and closes its side, which then stops the read loop.
+        let _ = w.shutdown().await;
+    }
+
+    async fn read_loop(mut r: OwnedReadHalf, max_frame_size: usize, pending: Pending) {
+        loop {
+            let (id, payload) = match read_frame(&mut r, max_frame_size).await {
+                Ok(Some(x)) => x,
+                Ok(None) => break,
+                Err(e) => {
+                    tracing::warn!("failed to read from {:?}: {}", r.peer_addr(), e);
+                    break;
+                }
+            };
+
+            let tx = pending.lock().unwrap().as_mut().and_then(|p| p.remove(&id));
+            if let Some(tx) = tx {
+                let _ = tx.send(payload);
+            }
+        }
+
+        close(&pending);
+    }
+}
+
+/// Mark the connection as broken and fail every request waiting on it.
+fn close(pending: &Pending) {
+    pending.lock().unwrap().take();
+}
+
+struct RemoveOnDrop<'a> {
+    pending: &'a Pending,
+    id: u64,
+}
+
+impl Drop for RemoveOnDrop<'_> {
+    fn drop(&mut self) {
+        if let Some(pending) = self.pending.lock().unwrap().as_mut() {
+            pending.remove(&self.id);
+        }
+    }
+}
@@ -0,0 +1,33 @@
+//! Serialization of the message carried by a frame.
+
+use std::io;
+
+use serde::de::DeserializeOwned;
+use serde::Serialize;
+
+/// Encodes and decodes the messages sent over a connection.
+///
+/// Both ends of a connection must use the same codec. [`JsonCodec`] is the default; an
+/// application that needs a more compact format implements this trait with a binary serde
+/// format.
+pub trait Codec: Clone + Send + Sync + 'static {
+    /// Serialize `value` into bytes.
+    fn encode<T: Serialize>(&self, value: &T) -> io::Result<Vec<u8>>;
+
+    /// Deserialize a value from bytes produced by [`encode()`](Self::encode).
+    fn decode<T: DeserializeOwned>(&self, buf: &[u8]) -> io::Result<T>;
+}
+
+/// A [`Codec`] that serializes messages as JSON.
+#[derive(Debug, Clone, Copy, Default)]
+pub struct JsonCodec;
+
+impl Codec for JsonCodec {
+    fn encode<T: Serialize>(&self, value: &T) -> io::Result<Vec<u8>> {
+        Ok(serde_json::to_vec(value)?)
+    }
+
+    fn decode<T: DeserializeOwned>(&self, buf: &[u8]) -> io::Result<T> {
+        Ok(serde_json::from_slice(buf)?)
+    }
+}
@@ -0,0 +1,47 @@
+use std::time::Duration;
+
+use openraft::network::Backoff;
+
+/// Settings shared by the client and the server side of the TCP network.
+#[derive(Debug, Clone)]
+pub struct TcpConfig {
+    /// Timeout to establish a connection.
+    pub connect_timeout: Duration,
+
+    /// The max size in bytes of a frame. A larger message is not sent, and a connection that
+    /// receives one is closed.
+    ///
+    /// It has to be large enough for a whole snapshot.
+    pub max_frame_size: usize,
+
+    /// The interval to wait before the first retry to an unreachable node.
+    pub backoff_min: Duration,
+
+    /// The interval between retries doubles after each failure until it reaches this value.
+    pub backoff_max: Duration,
+}
+
+impl Default for TcpConfig {
+    fn default() -> Self {
+        Self {
+            connect_timeout: Duration::from_millis(1_000),
+            max_frame_size: 64 * 1024 * 1024,
+            backoff_min: Duration::from_millis(100),
+            backoff_max: Duration::from_millis(5_000),
+        }
+    }
+}
+
+impl TcpConfig {
+    /// Build the exponential [`Backoff`] for an unreachable node.
+    pub fn backoff(&self) -> Backoff {
+        let max = self.backoff_max;
+        let mut next = self.backoff_min.min(max);
+
+        Backoff::new(std::iter::from_fn(move || {
+            let curr = next;
+            next = (next * 2).min(max);
+            Some(curr)
+        }))
+    }
+}
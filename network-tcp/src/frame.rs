@@ -0,0 +1,95 @@
+//! Length-delimited frames.
+//!
+//! A frame is `len: u32 | request_id: u64 | payload`, integers in big-endian, where `len` is the
+//! size of everything after it. The request id pairs a response with its request, so that
+//! requests sharing a connection can be answered in any order.
+
+use std::io;
+
+use tokio::io::AsyncRead;
+use tokio::io::AsyncReadExt;
+
+/// Size of the request id in a frame.
+const ID_SIZE: usize = 8;
+
+/// Build a frame of `payload` for request `request_id`.
+///
+/// It returns an [`io::ErrorKind::InvalidInput`] error if the frame would be larger than
+/// `max_frame_size`.
+pub(crate) fn encode_frame(request_id: u64, payload: &[u8], max_frame_size: usize) -> io::Result<Vec<u8>> {
+    let len = ID_SIZE + payload.len();
+    if len > max_frame_size {
+        return Err(io::Error::new(
+            io::ErrorKind::InvalidInput,
+            format!("frame size {} exceeds the limit {}", len, max_frame_size),
+        ));
+    }
+
+    let mut buf = Vec::with_capacity(4 + len);
+    buf.extend_from_slice(&(len as u32).to_be_bytes());
+    buf.extend_from_slice(&request_id.to_be_bytes());
+    buf.extend_from_slice(payload);
+    Ok(buf)
+}
+
+/// Read a frame and return the request id and the payload in it.
+///
+/// It returns `None` if the peer closed the connection.
+pub(crate) async fn read_frame<R>(r: &mut R, max_frame_size: usize) -> io::Result<Option<(u64, Vec<u8>)>>
+where R: AsyncRead + Unpin {
+    let mut len_buf = [0u8; 4];
+    match r.read_exact(&mut len_buf).await {
+        Ok(_) => {}
+        Err(e) if e.kind() == io::ErrorKind::UnexpectedEof => return Ok(None),
+        Err(e) => return Err(e),
+    }
+
+    let len = u32::from_be_bytes(len_buf) as usize;
+    if !(ID_SIZE..=max_frame_size).contains(&len) {
+        return Err(io::Error::new(
+            io::ErrorKind::InvalidData,
+            format!("invalid frame size {}, limit: {}", len, max_frame_size),
+        ));
+    }
+
+    let mut id_buf = [0u8; ID_SIZE];
+    r.read_exact(&mut id_buf).await?;
+
+    let mut payload = vec![0u8; len - ID_SIZE];
+    r.read_exact(&mut payload).await?;
+
+    Ok(Some((u64::from_be_bytes(id_buf), payload)))
+}
+
+#[cfg(test)]
+mod tests {
+    use std::io;
+
+    use super::encode_frame;
+    use super::read_frame;
+
+    #[tokio::test]
+    async fn test_frame_round_trip() -> io::Result<()> {
+        let mut buf = encode_frame(3, b"foo", 1024)?;
+        buf.extend_from_slice(&encode_frame(5, b"", 1024)?);
+
+        let mut r = &buf[..];
+        assert_eq!(Some((3, b"foo".to_vec())), read_frame(&mut r, 1024).await?);
+        assert_eq!(Some((5, vec![])), read_frame(&mut r, 1024).await?);
+        assert_eq!(None, read_frame(&mut r, 1024).await?);
+
+        Ok(())
+    }
+
+    #[tokio::test]
+    async fn test_frame_too_large() -> io::Result<()> {
+        let err = encode_frame(3, b"foo", 10).unwrap_err();
+        assert_eq!(io::ErrorKind::InvalidInput, err.kind());
+
+        let buf = encode_frame(3, b"foo", 1024)?;
+        let err = read_frame(&mut &buf[..], 10).await.unwrap_err();
+        assert_eq!(io::ErrorKind::InvalidData, err.kind());
+
+        Ok(())
+    }
+}
@@ -0,0 +1,181 @@
+//! This crate provides a [`RaftNetworkV2`](openraft::network::v2::RaftNetworkV2) over TCP for
+//! applications running Openraft on Tokio.
+//!
+//! ```ignore
+//! openraft::declare_raft_types!(
+//!     pub TypeConfig:
+//!         D = Request,
+//!         R = Response,
+//!         // Sent in a single frame, thus it has to be serializable.
+//!         SnapshotData = Vec<u8>,
+//! );
+//!
+//! openraft_network_tcp::impl_tcp_network!(TypeConfig);
+//!
+//! let network = TcpNetworkFactory::<TypeConfig>::new(node_id, TcpConfig::default());
+//! let raft = Raft::new(node_id, config, network, log_store, state_machine).await?;
+//!
+//! let listener = TcpListener::bind(addr).await?;
+//! tokio::spawn(TcpServer::<TypeConfig>::new(raft.clone(), TcpConfig::default()).serve(listener));
+//! ```
+//!
+//! A [`TcpNetworkFactory`] keeps one connection per address and sends concurrent RPCs over it,
+//! each tagged with a request id. Messages are serialized with a [`Codec`], JSON by default, and
+//! sent in length-delimited frames. The [`TcpServer`] on the receiving node reads the frames and
+//! passes each request to its [`Raft`](openraft::Raft).
+//!
+//! Errors are reported as Openraft expects them:
+//!
+//! - Failing to connect returns [`Unreachable`](openraft::error::Unreachable), and the node is
+//!   retried after the [`Backoff`](openraft::network::Backoff) built from [`TcpConfig`].
+//! - An RPC that does not complete within [`RPCOption::hard_ttl()`] returns
+//!   [`Timeout`](openraft::error::Timeout).
+//! - An AppendEntries request larger than [`TcpConfig::max_frame_size`] returns
+//!   [`PayloadTooLarge`](openraft::error::PayloadTooLarge), so that fewer entries are sent.
+//!
+//! [`RPCOption::hard_ttl()`]: openraft::network::RPCOption::hard_ttl
+
+mod client;
+mod codec;
+mod config;
+mod frame;
+mod message;
+mod server;
+
+pub use client::NodeAddr;
+pub use client::TcpConnection;
+pub use client::TcpNetworkFactory;
+pub use codec::Codec;
+pub use codec::JsonCodec;
+pub use config::TcpConfig;
+pub use server::TcpServer;
+
+#[doc(hidden)]
+pub mod __private {
+    //! Re-exports used by [`impl_tcp_network!`](crate::impl_tcp_network).
+
+    pub use openraft::alias::VoteOf;
+    pub use openraft::error::ClientWriteError;
+    pub use openraft::error::RPCError;
+    pub use openraft::error::RaftError;
+    pub use openraft::error::ReplicationClosed;
+    pub use openraft::error::StreamingError;
+    pub use openraft::network::v2::RaftNetworkV2;
+    pub use openraft::network::Backoff;
+    pub use openraft::network::RPCOption;
+    pub use openraft::raft::AppendEntriesRequest;
+    pub use openraft::raft::AppendEntriesResponse;
+    pub use openraft::raft::CatchUpRequest;
+    pub use openraft::raft::CatchUpResponse;
+    pub use openraft::raft::ClientWriteResponse;
+    pub use openraft::raft::HandshakeRequest;
+    pub use openraft::raft::HandshakeResponse;
+    pub use openraft::raft::HibernateRequest;
+    pub use openraft::raft::SnapshotResponse;
+    pub use openraft::raft::TransferLeaderRequest;
+    pub use openraft::raft::VoteRequest;
+    pub use openraft::raft::VoteResponse;
+    pub use openraft::OptionalSend;
+    pub use openraft::RaftTypeConfig;
+    pub use openraft::Snapshot;
+}
+
+/// Implement `RaftNetworkV2` for [`TcpConnection`] of a type config, with any [`Codec`].
+///
+/// Like [`openraft_rt_sim::impl_sim_network!`], the impl can not be generic over the type config
+/// because it would conflict with the blanket impl that adapts a v1
+/// [`RaftNetwork`](openraft::RaftNetwork), thus it is generated in the crate that defines the type
+/// config:
+///
+/// ```ignore
+/// openraft::declare_raft_types!(pub TypeConfig: SnapshotData = Vec<u8>);
+/// openraft_network_tcp::impl_tcp_network!(TypeConfig);
+/// ```
+///
+/// [`openraft_rt_sim::impl_sim_network!`]: https://docs.rs/openraft-rt-sim
+#[macro_export]
+macro_rules! impl_tcp_network {
+    ($C:ty) => {
+        impl<K> $crate::__private::RaftNetworkV2<$C> for $crate::TcpConnection<$C, K>
+        where K: $crate::Codec
+        {
+            async fn append_entries(
+                &mut self,
+                rpc: $crate::__private::AppendEntriesRequest<$C>,
+                option: $crate::__private::RPCOption,
+            ) -> Result<$crate::__private::AppendEntriesResponse<$C>, $crate::__private::RPCError<$C>> {
+                self.send_append_entries(rpc, option).await
+            }
+
+            async fn vote(
+                &mut self,
+                rpc: $crate::__private::VoteRequest<$C>,
+                option: $crate::__private::RPCOption,
+            ) -> Result<$crate::__private::VoteResponse<$C>, $crate::__private::RPCError<$C>> {
+                self.send_vote(rpc, option).await
+            }
+
+            async fn full_snapshot(
+                &mut self,
+                vote: $crate::__private::VoteOf<$C>,
+                snapshot: $crate::__private::Snapshot<$C>,
+                cancel: impl std::future::Future<Output = $crate::__private::ReplicationClosed>
+                    + $crate::__private::OptionalSend
+                    + 'static,
+                option: $crate::__private::RPCOption,
+            ) -> Result<$crate::__private::SnapshotResponse<$C>, $crate::__private::StreamingError<$C>> {
+                self.send_full_snapshot(vote, snapshot, cancel, option).await
+            }
+
+            async fn transfer_leader(
+                &mut self,
+                req: $crate::__private::TransferLeaderRequest<$C>,
+                option: $crate::__private::RPCOption,
+            ) -> Result<(), $crate::__private::RPCError<$C>> {
+                self.send_transfer_leader(req, option).await
+            }
+
+            async fn forward_client_write(
+                &mut self,
+                app_data: <$C as $crate::__private::RaftTypeConfig>::D,
+                option: $crate::__private::RPCOption,
+            ) -> Result<
+                $crate::__private::ClientWriteResponse<$C>,
+                $crate::__private::RPCError<
+                    $C,
+                    $crate::__private::RaftError<$C, $crate::__private::ClientWriteError<$C>>,
+                >,
+            > {
+                self.send_forward_client_write(app_data, option).await
+            }
+
+            async fn handshake(
+                &mut self,
+                req: $crate::__private::HandshakeRequest<$C>,
+                option: $crate::__private::RPCOption,
+            ) -> Result<Option<$crate::__private::HandshakeResponse>, $crate::__private::RPCError<$C>> {
+                self.send_handshake(req, option).await.map(Some)
+            }
+
+            async fn catch_up(
+                &mut self,
+                req: $crate::__private::CatchUpRequest<$C>,
+                option: $crate::__private::RPCOption,
+            ) -> Result<$crate::__private::CatchUpResponse<$C>, $crate::__private::RPCError<$C>> {
+                self.send_catch_up(req, option).await
+            }
+
+            async fn hibernate(
+                &mut self,
+                req: $crate::__private::HibernateRequest<$C>,
+                option: $crate::__private::RPCOption,
+            ) -> Result<(), $crate::__private::RPCError<$C>> {
+                self.send_hibernate(req, option).await
+            }
+
+            fn backoff(&self) -> $crate::__private::Backoff {
+                self.backoff_policy()
+            }
+        }
+    };
+}
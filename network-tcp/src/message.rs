@@ -0,0 +1,66 @@
+//! Messages exchanged between [`TcpConnection`](crate::TcpConnection) and
+//! [`TcpServer`](crate::TcpServer).
+
+use openraft::alias::SnapshotDataOf;
+use openraft::alias::VoteOf;
+use openraft::error::ClientWriteError;
+use openraft::error::RaftError;
+use openraft::raft::AppendEntriesRequest;
+use openraft::raft::AppendEntriesResponse;
+use openraft::raft::CatchUpRequest;
+use openraft::raft::CatchUpResponse;
+use openraft::raft::ClientWriteResponse;
+use openraft::raft::HandshakeRequest;
+use openraft::raft::HandshakeResponse;
+use openraft::raft::HibernateRequest;
+use openraft::raft::SnapshotResponse;
+use openraft::raft::TransferLeaderRequest;
+use openraft::raft::VoteRequest;
+use openraft::raft::VoteResponse;
+use openraft::RaftTypeConfig;
+use openraft::SnapshotMeta;
+use serde::de::DeserializeOwned;
+use serde::Deserialize;
+use serde::Serialize;
+
+#[derive(Serialize, Deserialize)]
+#[serde(bound(
+    serialize = "SnapshotDataOf<C>: Serialize",
+    deserialize = "SnapshotDataOf<C>: DeserializeOwned"
+))]
+pub(crate) enum Request<C>
+where C: RaftTypeConfig
+{
+    AppendEntries(AppendEntriesRequest<C>),
+    Vote(VoteRequest<C>),
+    FullSnapshot {
+        vote: VoteOf<C>,
+        meta: SnapshotMeta<C>,
+        data: SnapshotDataOf<C>,
+    },
+    TransferLeader(TransferLeaderRequest<C>),
+    ClientWrite(C::D),
+    Handshake(HandshakeRequest<C>),
+    CatchUp(CatchUpRequest<C>),
+    Hibernate(HibernateRequest<C>),
+}
+
+#[derive(Serialize, Deserialize)]
+#[serde(bound = "")]
+pub(crate) enum Response<C>
+where C: RaftTypeConfig
+{
+    AppendEntries(AppendEntriesResponse<C>),
+    Vote(VoteResponse<C>),
+    FullSnapshot(SnapshotResponse<C>),
+    TransferLeader,
+    ClientWrite(Result<ClientWriteResponse<C>, RaftError<C, ClientWriteError<C>>>),
+    Handshake(HandshakeResponse),
+    CatchUp(CatchUpResponse<C>),
+    Hibernate,
+
+    /// The request can not be handled, e.g., it can not be decoded or the [`Raft`] is shut down.
+    ///
+    /// [`Raft`]: openraft::Raft
+    Error(String),
+}
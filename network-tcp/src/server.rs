@@ -0,0 +1,192 @@
+use std::error::Error;
+use std::future::Future;
+use std::io;
+use std::sync::Arc;
+
+use openraft::alias::ResponderReceiverOf;
+use openraft::alias::SnapshotDataOf;
+use openraft::error::RaftError;
+use openraft::raft::ClientWriteResult;
+use openraft::Raft;
+use openraft::RaftTypeConfig;
+use openraft::Snapshot;
+use serde::de::DeserializeOwned;
+use serde::Serialize;
+use tokio::io::AsyncWriteExt;
+use tokio::net::tcp::OwnedWriteHalf;
+use tokio::net::TcpListener;
+use tokio::net::TcpStream;
+use tokio::sync::mpsc;
+
+use crate::frame::encode_frame;
+use crate::frame::read_frame;
+use crate::message::Request;
+use crate::message::Response;
+use crate::Codec;
+use crate::JsonCodec;
+use crate::TcpConfig;
+
+/// Serve the RPCs sent by [`TcpConnection`](crate::TcpConnection)s to a local [`Raft`].
+///
+/// Requests on the same connection are handled concurrently and each response is sent as soon as
+/// it is ready.
+pub struct TcpServer<C, K = JsonCodec>
+where C: RaftTypeConfig
+{
+    raft: Raft<C>,
+    config: Arc<TcpConfig>,
+    codec: K,
+}
+
+impl<C, K> Clone for TcpServer<C, K>
+where
+    C: RaftTypeConfig,
+    K: Codec,
+{
+    fn clone(&self) -> Self {
+        Self {
+            raft: self.raft.clone(),
+            config: self.config.clone(),
+            codec: self.codec.clone(),
+        }
+    }
+}
+
+impl<C, K> TcpServer<C, K>
+where
+    C: RaftTypeConfig,
+    SnapshotDataOf<C>: Serialize + DeserializeOwned,
+    K: Codec,
+{
+    /// Create a server for `raft` with the default codec.
+    pub fn new(raft: Raft<C>, config: TcpConfig) -> Self
+    where K: Default {
+        Self::with_codec(raft, config, K::default())
+    }
+
+    /// Create a server for `raft` that encodes messages with `codec`.
+    pub fn with_codec(raft: Raft<C>, config: TcpConfig, codec: K) -> Self {
+        Self {
+            raft,
+            config: Arc::new(config),
+            codec,
+        }
+    }
+
+    /// Accept connections from `listener` and serve each of them in its own task.
+    ///
+    /// It returns only if accepting a connection fails. Drop the future to stop accepting new
+    /// connections.
+    pub async fn serve<E>(self, listener: TcpListener) -> io::Result<()>
+    where
+        ResponderReceiverOf<C>: Future<Output = Result<ClientWriteResult<C>, E>> + Send,
+        E: Error + Send + 'static,
+    {
+        loop {
+            let (stream, peer) = listener.accept().await?;
+            stream.set_nodelay(true)?;
+
+            let this = self.clone();
+            tokio::spawn(async move {
+                if let Err(e) = this.serve_connection(stream).await {
+                    tracing::warn!("connection from {} is closed: {}", peer, e);
+                }
+            });
+        }
+    }
+
+    /// Read requests from a connection until the peer closes it.
+    pub async fn serve_connection<E>(&self, stream: TcpStream) -> io::Result<()>
+    where
+        ResponderReceiverOf<C>: Future<Output = Result<ClientWriteResult<C>, E>> + Send,
+        E: Error + Send + 'static,
+    {
+        let (mut r, w) = stream.into_split();
+
+        let (tx, rx) = mpsc::unbounded_channel();
+        tokio::spawn(write_loop(w, rx));
+
+        while let Some((id, payload)) = read_frame(&mut r, self.config.max_frame_size).await? {
+            let this = self.clone();
+            let tx = tx.clone();
+
+            tokio::spawn(async move {
+                let resp = this.handle(&payload).await;
+
+                let frame = this.codec.encode(&resp).and_then(|buf| encode_frame(id, &buf, this.config.max_frame_size));
+                match frame {
+                    Ok(frame) => {
+                        let _ = tx.send(frame);
+                    }
+                    Err(e) => {
+                        tracing::error!("failed to encode the response to request {}: {}", id, e);
+                    }
+                }
+            });
+        }
+
+        Ok(())
+    }
+
+    async fn handle<E>(&self, payload: &[u8]) -> Response<C>
+    where
+        ResponderReceiverOf<C>: Future<Output = Result<ClientWriteResult<C>, E>> + Send,
+        E: Error + Send + 'static,
+    {
+        let req = match self.codec.decode::<Request<C>>(payload) {
+            Ok(req) => req,
+            Err(e) => return Response::Error(format!("failed to decode request: {}", e)),
+        };
+
+        let res = match req {
+            Request::AppendEntries(rpc) => {
+                self.raft.append_entries(rpc).await.map(Response::AppendEntries).map_err(|e| e.to_string())
+            }
+            Request::Vote(rpc) => self.raft.vote(rpc).await.map(Response::Vote).map_err(|e| e.to_string()),
+            Request::FullSnapshot { vote, meta, data } => {
+                let snapshot = Snapshot {
+                    meta,
+                    snapshot: Box::new(data),
+                };
+                self.raft
+                    .install_full_snapshot(vote, snapshot)
+                    .await
+                    .map(Response::FullSnapshot)
+                    .map_err(|e| e.to_string())
+            }
+            Request::TransferLeader(req) => self
+                .raft
+                .handle_transfer_leader(req)
+                .await
+                .map(|_| Response::TransferLeader)
+                .map_err(|e| e.to_string()),
+            Request::ClientWrite(app_data) => {
+                let res = self.raft.handle_forwarded_client_write(app_data).await;
+                match res {
+                    Err(RaftError::Fatal(e)) => Err(e.to_string()),
+                    res => Ok(Response::ClientWrite(res)),
+                }
+            }
+            Request::Handshake(req) => {
+                self.raft.handle_handshake(req).await.map(Response::Handshake).map_err(|e| e.to_string())
+            }
+            Request::CatchUp(req) => {
+                self.raft.handle_catch_up(req).await.map(Response::CatchUp).map_err(|e| e.to_string())
+            }
+            Request::Hibernate(req) => {
+                self.raft.handle_hibernate(req).await.map(|_| Response::Hibernate).map_err(|e| e.to_string())
+            }
+        };
+
+        res.unwrap_or_else(Response::Error)
+    }
+}
+
+async fn write_loop(mut w: OwnedWriteHalf, mut rx: mpsc::UnboundedReceiver<Vec<u8>>) {
+    while let Some(frame) = rx.recv().await {
+        if let Err(e) = w.write_all(&frame).await {
+            tracing::warn!("failed to write to {:?}: {}", w.peer_addr(), e);
+            return;
+        }
+    }
+}
@@ -149,6 +149,11 @@ pub struct Config {
     ///
     /// If this is too low, it will take longer for the nodes to be brought up to
     /// consistency with the rest of the cluster.
+    ///
+    /// It is the upper bound: the number of entries sent to each target adapts to how the target
+    /// keeps up, and is reported in [`RaftMetrics::replication_batch_size`].
+    ///
+    /// [`RaftMetrics::replication_batch_size`]: crate::metrics::RaftMetrics::replication_batch_size
     #[clap(long, default_value = "300")]
     pub max_payload_entries: u64,
 
@@ -162,6 +167,94 @@ pub struct Config {
     #[clap(long, default_value = "5000")]
     pub replication_lag_threshold: u64,
 
+    /// The number of committed log entries a follower or learner must lack before the Leader
+    /// delegates bringing it up to date to another up-to-date follower.
+    ///
+    /// The delegated follower sends the entries, or its snapshot if they are purged, so that
+    /// several recovering nodes do not all load the Leader. The Leader resumes replicating to the
+    /// target itself near the tail of the log.
+    ///
+    /// `0` disables delegation.
+    #[clap(long, default_value = "0")]
+    pub catch_up_delegation_threshold: u64,
+
+    /// The time in milliseconds without client writes or reads after which a Leader hibernates.
+    ///
+    /// A hibernating Leader stops sending heartbeats, and its followers stop their election
+    /// timers, so that an idle group costs no network traffic and no timers. A client write or
+    /// read wakes the group up. A follower also wakes up when it receives any message, or when the
+    /// application calls [`Trigger::wake_up()`], e.g. because the Leader is found down by an
+    /// external liveness check.
+    ///
+    /// `0` disables hibernation.
+    ///
+    /// [`Trigger::wake_up()`]: crate::raft::trigger::Trigger::wake_up
+    #[clap(long, default_value = "0")]
+    pub hibernate_timeout: u64,
+
+    /// The maximum number of recently appended log entries a Leader keeps in memory for
+    /// replication.
+    ///
+    /// Replication streams read entries from this cache before reading them from the log store.
+    /// Cache hits and misses are reported in [`RaftMetrics::log_cache_hits`] and
+    /// [`RaftMetrics::log_cache_misses`].
+    ///
+    /// The cache is opt-in, because it requires log entries to be `Clone`: set this to a non-zero
+    /// value and call [`Raft::enable_log_cache()`]. The default `0` disables the cache.
+    ///
+    /// [`Raft::enable_log_cache()`]: crate::Raft::enable_log_cache
+    /// [`RaftMetrics::log_cache_hits`]: crate::metrics::RaftMetrics::log_cache_hits
+    /// [`RaftMetrics::log_cache_misses`]: crate::metrics::RaftMetrics::log_cache_misses
+    #[clap(long, default_value = "0")]
+    pub log_cache_size: u64,
+
+    /// The maximum number of committed but not yet applied log entries on a Leader, before client
+    /// writes are throttled.
+    ///
+    /// When the lag exceeds it, a client write waits until the state machine catches up, or it is
+    /// rejected with [`ClientWriteError::Overloaded`] if [`Config::reject_on_apply_lag`] is set.
+    /// At most `max_apply_lag` writes are held back at a time; further writes are rejected with
+    /// [`ClientWriteError::Overloaded`] too.
+    /// The lag and the time spent throttled are reported in [`RaftMetrics::apply_lag`] and
+    /// [`RaftMetrics::apply_throttled_ms`].
+    ///
+    /// `0` disables throttling.
+    ///
+    /// [`ClientWriteError::Overloaded`]: crate::error::ClientWriteError::Overloaded
+    /// [`RaftMetrics::apply_lag`]: crate::metrics::RaftMetrics::apply_lag
+    /// [`RaftMetrics::apply_throttled_ms`]: crate::metrics::RaftMetrics::apply_throttled_ms
+    #[clap(long, default_value = "0")]
+    pub max_apply_lag: u64,
+
+    /// Whether to reject a client write with [`ClientWriteError::Overloaded`] instead of holding it
+    /// back, when [`Config::max_apply_lag`] is exceeded.
+    ///
+    /// [`ClientWriteError::Overloaded`]: crate::error::ClientWriteError::Overloaded
+    // clap 4 requires `num_args = 0..=1`, or it complains about missing arg error
+    // https://github.com/clap-rs/clap/discussions/4374
+    #[clap(long,
+           default_value_t = false,
+           action = clap::ArgAction::Set,
+           num_args = 0..=1,
+           default_missing_value = "true"
+    )]
+    pub reject_on_apply_lag: bool,
+
+    /// The maximum number of client writes that are submitted to this node but not yet responded.
+    ///
+    /// When it is reached, [`Raft::client_write()`] waits for an in-flight write to finish, and
+    /// [`Raft::try_client_write()`] fails at once with [`ClientWriteError::Overloaded`]. The
+    /// number of in-flight writes is reported in [`RaftMetrics::in_flight_proposals`].
+    ///
+    /// `0` means unlimited.
+    ///
+    /// [`Raft::client_write()`]: crate::Raft::client_write
+    /// [`Raft::try_client_write()`]: crate::Raft::try_client_write
+    /// [`ClientWriteError::Overloaded`]: crate::error::ClientWriteError::Overloaded
+    /// [`RaftMetrics::in_flight_proposals`]: crate::metrics::RaftMetrics::in_flight_proposals
+    #[clap(long, default_value = "0")]
+    pub max_in_flight_proposals: u64,
+
     /// The snapshot policy to use for a Raft node.
     #[clap(
         long,
@@ -14,6 +14,12 @@ fn test_config_defaults() {
     assert_eq!(50, cfg.heartbeat_interval);
     assert_eq!(300, cfg.max_payload_entries);
     assert_eq!(5000, cfg.replication_lag_threshold);
+    assert_eq!(0, cfg.catch_up_delegation_threshold);
+    assert_eq!(0, cfg.hibernate_timeout);
+    assert_eq!(0, cfg.log_cache_size);
+    assert_eq!(0, cfg.max_apply_lag);
+    assert!(!cfg.reject_on_apply_lag);
+    assert_eq!(0, cfg.max_in_flight_proposals);
 
     assert_eq!(3 * 1024 * 1024, cfg.snapshot_max_chunk_size);
     assert_eq!(SnapshotPolicy::LogsSinceLast(5000), cfg.snapshot_policy);
@@ -62,6 +68,12 @@ fn test_build() -> anyhow::Result<()> {
         "--snapshot-max-chunk-size=204",
         "--max-in-snapshot-log-to-keep=205",
         "--purge-batch-size=207",
+        "--catch-up-delegation-threshold=208",
+        "--hibernate-timeout=209",
+        "--log-cache-size=210",
+        "--max-apply-lag=211",
+        "--reject-on-apply-lag",
+        "--max-in-flight-proposals=212",
     ])?;
 
     assert_eq!("bar", config.cluster_name);
@@ -80,6 +92,12 @@ fn test_build() -> anyhow::Result<()> {
     assert_eq!(204, config.snapshot_max_chunk_size);
     assert_eq!(205, config.max_in_snapshot_log_to_keep);
     assert_eq!(207, config.purge_batch_size);
+    assert_eq!(208, config.catch_up_delegation_threshold);
+    assert_eq!(209, config.hibernate_timeout);
+    assert_eq!(210, config.log_cache_size);
+    assert_eq!(211, config.max_apply_lag);
+    assert!(config.reject_on_apply_lag);
+    assert_eq!(212, config.max_in_flight_proposals);
 
     // Test config methods
     #[allow(deprecated)]
@@ -0,0 +1,126 @@
+//! Admission control of client write proposals.
+
+use std::sync::Arc;
+use std::sync::Mutex;
+
+use crate::async_runtime::OneshotSender;
+use crate::error::Overloaded;
+use crate::type_config::alias::OneshotSenderOf;
+use crate::type_config::TypeConfigExt;
+use crate::RaftTypeConfig;
+
+/// Limits the number of client write proposals that are submitted but not yet responded.
+///
+/// A proposal holds a [`ProposalPermit`] until it is responded, or until it is rejected.
+///
+/// See [`Config::max_in_flight_proposals`](crate::Config::max_in_flight_proposals).
+pub(crate) struct Admission<C>
+where C: RaftTypeConfig
+{
+    /// The maximum number of in-flight proposals. `0` means unlimited.
+    max: u64,
+
+    inner: Mutex<Inner<C>>,
+}
+
+struct Inner<C>
+where C: RaftTypeConfig
+{
+    /// The number of in-flight proposals.
+    in_flight: u64,
+
+    /// Proposals waiting for a permit.
+    ///
+    /// All of them are woken up when a permit is released, because a woken waiter may have been
+    /// cancelled.
+    waiters: Vec<OneshotSenderOf<C, ()>>,
+}
+
+impl<C> Admission<C>
+where C: RaftTypeConfig
+{
+    pub(crate) fn new(max: u64) -> Self {
+        Self {
+            max,
+            inner: Mutex::new(Inner {
+                in_flight: 0,
+                waiters: vec![],
+            }),
+        }
+    }
+
+    /// The number of in-flight proposals.
+    pub(crate) fn in_flight(&self) -> u64 {
+        self.inner.lock().unwrap().in_flight
+    }
+
+    /// Get a permit without waiting, or return an [`Overloaded`] error if there are too many
+    /// in-flight proposals.
+    pub(crate) fn try_acquire(self: &Arc<Self>) -> Result<ProposalPermit<C>, Overloaded> {
+        let mut inner = self.inner.lock().unwrap();
+
+        self.admit(&mut inner).ok_or_else(|| {
+            Overloaded::new(format!(
+                "{} in-flight proposals reach max_in_flight_proposals {}",
+                inner.in_flight, self.max
+            ))
+        })
+    }
+
+    /// Get a permit, waiting for one to be released if there are too many in-flight proposals.
+    pub(crate) async fn acquire(self: &Arc<Self>) -> ProposalPermit<C> {
+        loop {
+            let rx = {
+                let mut inner = self.inner.lock().unwrap();
+
+                if let Some(permit) = self.admit(&mut inner) {
+                    return permit;
+                }
+
+                let (tx, rx) = C::oneshot();
+                inner.waiters.push(tx);
+                rx
+            };
+
+            let _ = rx.await;
+        }
+    }
+
+    fn admit(self: &Arc<Self>, inner: &mut Inner<C>) -> Option<ProposalPermit<C>> {
+        if self.max > 0 && inner.in_flight >= self.max {
+            return None;
+        }
+
+        inner.in_flight += 1;
+        Some(ProposalPermit {
+            admission: self.clone(),
+        })
+    }
+
+    fn release(&self) {
+        let waiters = {
+            let mut inner = self.inner.lock().unwrap();
+            inner.in_flight -= 1;
+            std::mem::take(&mut inner.waiters)
+        };
+
+        for tx in waiters {
+            let _ = tx.send(());
+        }
+    }
+}
+
+/// Accounts a client write proposal as in-flight until it is dropped.
+pub(crate) struct ProposalPermit<C>
+where C: RaftTypeConfig
+{
+    admission: Arc<Admission<C>>,
+}
+
+impl<C> Drop for ProposalPermit<C>
+where C: RaftTypeConfig
+{
+    fn drop(&mut self) {
+        self.admission.release();
+    }
+}
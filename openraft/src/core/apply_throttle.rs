@@ -0,0 +1,85 @@
+//! Holds client writes back while the state machine lags too far behind the committed log.
+
+use std::collections::VecDeque;
+use std::time::Duration;
+
+use crate::core::admission::ProposalPermit;
+use crate::type_config::alias::InstantOf;
+use crate::type_config::alias::ResponderOf;
+use crate::type_config::TypeConfigExt;
+use crate::Instant;
+use crate::RaftTypeConfig;
+
+/// Client writes waiting for the state machine to catch up, and the time spent throttled.
+///
+/// See [`Config::max_apply_lag`](crate::Config::max_apply_lag).
+pub(crate) struct ApplyThrottle<C>
+where C: RaftTypeConfig
+{
+    /// Throttled writes, the oldest first.
+    pending: VecDeque<(C::D, ResponderOf<C>, ProposalPermit<C>)>,
+
+    /// When the current throttling started, if client writes are being throttled.
+    since: Option<InstantOf<C>>,
+
+    /// The total time spent throttled, excluding the current throttling.
+    total: Duration,
+}
+
+impl<C> Default for ApplyThrottle<C>
+where C: RaftTypeConfig
+{
+    fn default() -> Self {
+        Self {
+            pending: VecDeque::new(),
+            since: None,
+            total: Duration::default(),
+        }
+    }
+}
+
+impl<C> ApplyThrottle<C>
+where C: RaftTypeConfig
+{
+    /// Whether client writes are being held back or rejected.
+    pub(crate) fn is_throttling(&self) -> bool {
+        self.since.is_some()
+    }
+
+    pub(crate) fn start(&mut self) {
+        if self.since.is_none() {
+            self.since = Some(C::now());
+        }
+    }
+
+    /// Stop throttling, if no write is waiting.
+    pub(crate) fn try_stop(&mut self) {
+        if !self.pending.is_empty() {
+            return;
+        }
+
+        if let Some(since) = self.since.take() {
+            self.total += since.elapsed();
+        }
+    }
+
+    /// The number of writes waiting.
+    pub(crate) fn len(&self) -> usize {
+        self.pending.len()
+    }
+
+    pub(crate) fn push(&mut self, app_data: C::D, tx: ResponderOf<C>, permit: ProposalPermit<C>) {
+        self.pending.push_back((app_data, tx, permit));
+    }
+
+    /// Take the oldest waiting write.
+    pub(crate) fn pop(&mut self) -> Option<(C::D, ResponderOf<C>, ProposalPermit<C>)> {
+        self.pending.pop_front()
+    }
+
+    /// The total time in milliseconds client writes have been throttled, since this node started.
+    pub(crate) fn throttled_ms(&self) -> u64 {
+        let current = self.since.as_ref().map(|t| t.elapsed()).unwrap_or_default();
+        (self.total + current).as_millis() as u64
+    }
+}
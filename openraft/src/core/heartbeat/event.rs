@@ -31,6 +31,13 @@ where C: RaftTypeConfig
     /// When there are no new logs to replicate, the Leader sends a heartbeat to replicate committed
     /// log id to followers to update their committed log id.
     pub(crate) committed: Option<LogIdOf<C>>,
+
+    /// Whether this is the last heartbeat before the Leader hibernates.
+    ///
+    /// It is sent with [`RaftNetworkV2::hibernate()`] instead of an AppendEntries.
+    ///
+    /// [`RaftNetworkV2::hibernate()`]: crate::network::v2::RaftNetworkV2::hibernate
+    pub(crate) hibernate: bool,
 }
 
 impl<C> HeartbeatEvent<C>
@@ -41,6 +48,19 @@ where C: RaftTypeConfig
             time,
             session_id,
             committed,
+            hibernate: false,
+        }
+    }
+
+    /// Create the last heartbeat before the Leader hibernates.
+    pub(crate) fn new_hibernate(
+        time: InstantOf<C>,
+        session_id: ReplicationSessionId<C>,
+        last_log_id: Option<LogIdOf<C>>,
+    ) -> Self {
+        Self {
+            hibernate: true,
+            ..Self::new(time, session_id, last_log_id)
         }
     }
 }
@@ -51,10 +71,11 @@ where C: RaftTypeConfig
     fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
         write!(
             f,
-            "(time={}, leader_vote: {}, committed: {})",
+            "(time={}, leader_vote: {}, committed: {}, hibernate: {})",
             self.time.display(),
             self.session_id,
-            self.committed.display()
+            self.committed.display(),
+            self.hibernate
         )
     }
 }
@@ -69,7 +69,7 @@ where C: RaftTypeConfig
     {
         for (target, node) in targets {
             tracing::debug!("id={} spawn HeartbeatWorker target={}", self.id, target);
-            let network = network_factory.new_client(target.clone(), &node).await;
+            let network = network_factory.new_heartbeat_client(target.clone(), &node).await;
 
             let worker = HeartbeatWorker {
                 id: self.id.clone(),
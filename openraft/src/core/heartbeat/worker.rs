@@ -11,6 +11,7 @@ use crate::core::notification::Notification;
 use crate::network::v2::RaftNetworkV2;
 use crate::network::RPCOption;
 use crate::raft::AppendEntriesRequest;
+use crate::raft::HibernateRequest;
 use crate::type_config::alias::MpscUnboundedSenderOf;
 use crate::type_config::alias::OneshotReceiverOf;
 use crate::type_config::alias::WatchReceiverOf;
@@ -81,14 +82,25 @@ where
             let timeout = Duration::from_millis(self.config.heartbeat_interval);
             let option = RPCOption::new(timeout);
 
-            let payload = AppendEntriesRequest {
-                vote: heartbeat.session_id.leader_vote.clone().into_vote(),
-                prev_log_id: None,
-                leader_commit: heartbeat.committed.clone(),
-                entries: vec![],
-            };
+            let vote = heartbeat.session_id.leader_vote.clone().into_vote();
+
+            let res = if heartbeat.hibernate {
+                let payload = HibernateRequest {
+                    vote,
+                    last_log_id: heartbeat.committed.clone(),
+                };
+
+                C::timeout(timeout, self.network.hibernate(payload, option)).await.map(|r| r.map(|_| ()))
+            } else {
+                let payload = AppendEntriesRequest {
+                    vote,
+                    prev_log_id: None,
+                    leader_commit: heartbeat.committed.clone(),
+                    entries: vec![],
+                };
 
-            let res = C::timeout(timeout, self.network.append_entries(payload, option)).await;
+                C::timeout(timeout, self.network.append_entries(payload, option)).await.map(|r| r.map(|_| ()))
+            };
             tracing::debug!("{} sent a heartbeat: {}, result: {:?}", self, heartbeat, res);
 
             match res {
@@ -106,6 +118,20 @@ where
                 }
                 _ => {
                     tracing::warn!("{} failed to send a heartbeat: {:?}", self, res);
+
+                    if heartbeat.hibernate {
+                        // The target keeps its election timer, the Leader has to keep sending
+                        // heartbeats to it.
+                        let res = self.tx_notification.send(Notification::HibernateFailed {
+                            session_id: heartbeat.session_id.clone(),
+                            target: self.target.clone(),
+                        });
+
+                        if res.is_err() {
+                            tracing::error!("{} failed to send a hibernate failure to RaftCore. quit", self);
+                            return;
+                        }
+                    }
                 }
             }
         }
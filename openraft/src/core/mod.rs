@@ -4,9 +4,12 @@
 //! Also it receives and execute `Command` emitted by `Engine` to apply raft state to underlying
 //! storage or forward messages to other raft nodes.
 
+pub(crate) mod admission;
+mod apply_throttle;
 pub(crate) mod balancer;
 pub(crate) mod heartbeat;
 pub(crate) mod notification;
+mod peer_version;
 mod raft_core;
 pub(crate) mod raft_msg;
 mod replication_state;
@@ -14,10 +17,12 @@ mod server_state;
 pub(crate) mod sm;
 mod tick;
 
+pub(crate) use apply_throttle::ApplyThrottle;
 pub(crate) use raft_core::ApplyResult;
 pub(crate) use raft_core::ApplyingEntry;
 pub use raft_core::RaftCore;
 pub(crate) use replication_state::replication_lag;
 pub use server_state::ServerState;
+pub(crate) use tick::SharedTick;
 pub(crate) use tick::Tick;
 pub(crate) use tick::TickHandle;
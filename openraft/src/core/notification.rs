@@ -2,11 +2,14 @@ use std::fmt;
 
 use crate::core::sm;
 use crate::display_ext::DisplayInstantExt;
+use crate::display_ext::DisplayOptionExt;
+use crate::network::ProtocolVersion;
 use crate::raft::VoteResponse;
 use crate::raft_state::IOId;
 use crate::replication;
 use crate::replication::ReplicationSessionId;
 use crate::type_config::alias::InstantOf;
+use crate::type_config::alias::LogIdOf;
 use crate::type_config::alias::VoteOf;
 use crate::vote::committed::CommittedVote;
 use crate::vote::non_committed::NonCommittedVote;
@@ -58,6 +61,30 @@ where C: RaftTypeConfig
         target: C::NodeId,
     },
 
+    /// A catch-up delegated to another node is done.
+    ///
+    /// `matching` is the last log id the helper sent to `target`, or `None` if it sent nothing,
+    /// e.g., it declined or the request failed.
+    CatchUpDone {
+        session_id: ReplicationSessionId<C>,
+        target: C::NodeId,
+        matching: Option<LogIdOf<C>>,
+    },
+
+    /// A follower or learner was not told that the Leader hibernates.
+    HibernateFailed {
+        session_id: ReplicationSessionId<C>,
+        target: C::NodeId,
+    },
+
+    /// Result of a handshake with a peer.
+    ///
+    /// `version` is `None` if the handshake failed and the peer's version is still unknown.
+    Handshake {
+        target: C::NodeId,
+        version: Option<ProtocolVersion>,
+    },
+
     /// Result of executing a command sent from state machine worker.
     StateMachine { command_result: sm::CommandResult<C> },
 
@@ -108,6 +135,19 @@ where C: RaftTypeConfig
             Self::ReplicationProgress { progress } => {
                 write!(f, "{}", progress)
             }
+            Self::CatchUpDone {
+                session_id,
+                target,
+                matching,
+            } => {
+                write!(
+                    f,
+                    "CatchUpDone: target: {}, matching: {}, session_id: {}",
+                    target,
+                    matching.display(),
+                    session_id
+                )
+            }
             Self::HeartbeatProgress {
                 session_id: leader_vote,
                 sending_time,
@@ -121,6 +161,12 @@ where C: RaftTypeConfig
                     sending_time.display(),
                 )
             }
+            Self::HibernateFailed { session_id, target } => {
+                write!(f, "HibernateFailed: target={}, leader_vote: {}", target, session_id)
+            }
+            Self::Handshake { target, version } => {
+                write!(f, "Handshake: target={}, version: {}", target, version.display())
+            }
             Self::StateMachine { command_result } => {
                 write!(f, "{}", command_result)
             }
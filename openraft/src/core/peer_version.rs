@@ -0,0 +1,119 @@
+//! Tracks the handshakes with peers and the protocol versions learned from them.
+
+use std::time::Duration;
+
+use crate::network::ProtocolVersion;
+use crate::type_config::alias::InstantOf;
+use crate::RaftTypeConfig;
+
+/// The maximum number of times the handshake retry interval is doubled.
+const MAX_BACKOFF_SHIFT: u32 = 6;
+
+/// The protocol version of a peer, as far as it is learned by handshakes.
+#[derive(Debug, Clone)]
+pub(crate) enum PeerVersion<C>
+where C: RaftTypeConfig
+{
+    /// A handshake is sent and not yet responded. `failures` is the number of failed handshakes
+    /// before it.
+    Handshaking { failures: u32 },
+
+    /// The last handshake failed. The next one is not sent before `retry_at`.
+    Failed { failures: u32, retry_at: InstantOf<C> },
+
+    /// The version the peer responded or sent with its own handshake.
+    Known(ProtocolVersion),
+}
+
+impl<C> PeerVersion<C>
+where C: RaftTypeConfig
+{
+    /// Returns the version of the peer, if a handshake succeeded.
+    pub(crate) fn version(&self) -> Option<ProtocolVersion> {
+        match self {
+            PeerVersion::Known(v) => Some(*v),
+            _ => None,
+        }
+    }
+
+    /// Returns true if a new handshake should be sent to the peer at `now`.
+    pub(crate) fn should_handshake(&self, now: InstantOf<C>) -> bool {
+        match self {
+            PeerVersion::Handshaking { .. } => false,
+            PeerVersion::Failed { retry_at, .. } => now >= *retry_at,
+            PeerVersion::Known(_) => false,
+        }
+    }
+
+    /// The state when a new handshake is sent.
+    pub(crate) fn start(prev: Option<&Self>) -> Self {
+        let failures = match prev {
+            Some(PeerVersion::Failed { failures, .. }) => *failures,
+            Some(PeerVersion::Handshaking { failures }) => *failures,
+            _ => 0,
+        };
+        PeerVersion::Handshaking { failures }
+    }
+
+    /// Update the state with the result of a handshake.
+    ///
+    /// A failed handshake does not erase a version learned from the peer's own handshake. After
+    /// every failure, the interval before the next retry is doubled, starting from `base`.
+    pub(crate) fn update(&mut self, version: Option<ProtocolVersion>, now: InstantOf<C>, base: Duration) {
+        if let Some(v) = version {
+            *self = PeerVersion::Known(v);
+            return;
+        }
+
+        let failures = match self {
+            PeerVersion::Known(_) => return,
+            PeerVersion::Handshaking { failures } => *failures,
+            PeerVersion::Failed { failures, .. } => *failures,
+        };
+
+        let backoff = base * 2u32.pow(std::cmp::min(failures, MAX_BACKOFF_SHIFT));
+        *self = PeerVersion::Failed {
+            failures: failures + 1,
+            retry_at: now + backoff,
+        };
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::time::Duration;
+
+    use super::PeerVersion;
+    use crate::engine::testing::UTConfig;
+    use crate::network::ProtocolVersion;
+    use crate::type_config::TypeConfigExt;
+
+    type C = UTConfig;
+
+    #[test]
+    fn test_peer_version_retry_with_backoff() {
+        let base = Duration::from_millis(100);
+        let now = C::now();
+
+        let mut v = PeerVersion::<C>::start(None);
+        assert!(!v.should_handshake(now));
+        assert_eq!(None, v.version());
+
+        v.update(None, now, base);
+        assert!(!v.should_handshake(now));
+        assert!(v.should_handshake(now + Duration::from_millis(100)));
+
+        let mut v = PeerVersion::start(Some(&v));
+        v.update(None, now, base);
+        assert!(!v.should_handshake(now + Duration::from_millis(199)));
+        assert!(v.should_handshake(now + Duration::from_millis(200)));
+
+        v.update(Some(ProtocolVersion::V2), now, base);
+        assert_eq!(Some(ProtocolVersion::V2), v.version());
+        assert!(!v.should_handshake(now + Duration::from_secs(100)));
+
+        // A failed handshake does not erase a known version.
+        v.update(None, now, base);
+        assert_eq!(Some(ProtocolVersion::V2), v.version());
+    }
+}
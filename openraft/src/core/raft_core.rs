@@ -21,17 +21,22 @@ use crate::async_runtime::OneshotSender;
 use crate::async_runtime::TryRecvError;
 use crate::config::Config;
 use crate::config::RuntimeConfig;
+use crate::core::admission::Admission;
+use crate::core::admission::ProposalPermit;
 use crate::core::balancer::Balancer;
 use crate::core::heartbeat::event::HeartbeatEvent;
 use crate::core::heartbeat::handle::HeartbeatWorkersHandle;
 use crate::core::notification::Notification;
+use crate::core::peer_version::PeerVersion;
 use crate::core::raft_msg::external_command::ExternalCommand;
 use crate::core::raft_msg::AppendEntriesTx;
 use crate::core::raft_msg::ClientReadTx;
+use crate::core::raft_msg::ForwardClientWriteTx;
 use crate::core::raft_msg::RaftMsg;
 use crate::core::raft_msg::ResultSender;
 use crate::core::raft_msg::VoteTx;
 use crate::core::sm;
+use crate::core::ApplyThrottle;
 use crate::core::ServerState;
 use crate::display_ext::DisplayInstantExt;
 use crate::display_ext::DisplayOptionExt;
@@ -49,10 +54,13 @@ use crate::error::Fatal;
 use crate::error::ForwardToLeader;
 use crate::error::Infallible;
 use crate::error::InitializeError;
+use crate::error::Overloaded;
 use crate::error::QuorumNotEnough;
 use crate::error::RPCError;
 use crate::error::Timeout;
+use crate::error::Unreachable;
 use crate::log_id::option_raft_log_id_ext::OptionRaftLogIdExt;
+use crate::log_id_range::LogIdRange;
 use crate::metrics::HeartbeatMetrics;
 use crate::metrics::RaftDataMetrics;
 use crate::metrics::RaftMetrics;
@@ -60,6 +68,8 @@ use crate::metrics::RaftServerMetrics;
 use crate::metrics::ReplicationMetrics;
 use crate::metrics::SerdeInstant;
 use crate::network::v2::RaftNetworkV2;
+use crate::network::ProtocolFeature;
+use crate::network::ProtocolVersion;
 use crate::network::RPCOption;
 use crate::network::RPCTypes;
 use crate::network::RaftNetworkFactory;
@@ -70,12 +80,20 @@ use crate::raft::message::TransferLeaderRequest;
 use crate::raft::responder::Responder;
 use crate::raft::AppendEntriesRequest;
 use crate::raft::AppendEntriesResponse;
+use crate::raft::CatchUpRequest;
+use crate::raft::CatchUpResponse;
 use crate::raft::ClientWriteResponse;
+use crate::raft::HandshakeRequest;
+use crate::raft::HibernateRequest;
 use crate::raft::VoteRequest;
 use crate::raft::VoteResponse;
 use crate::raft_state::io_state::io_id::IOId;
 use crate::raft_state::LogStateReader;
+use crate::replication;
+use crate::replication::catch_up::CatchUp;
+use crate::replication::log_cache::LogCache;
 use crate::replication::request::Replicate;
+use crate::replication::response::ReplicationResult;
 use crate::replication::ReplicationCore;
 use crate::replication::ReplicationHandle;
 use crate::replication::ReplicationSessionId;
@@ -86,9 +104,12 @@ use crate::type_config::alias::InstantOf;
 use crate::type_config::alias::LogIdOf;
 use crate::type_config::alias::MpscUnboundedReceiverOf;
 use crate::type_config::alias::MpscUnboundedSenderOf;
+use crate::type_config::alias::MutexOf;
 use crate::type_config::alias::OneshotReceiverOf;
 use crate::type_config::alias::ResponderOf;
+use crate::type_config::alias::VoteOf;
 use crate::type_config::alias::WatchSenderOf;
+use crate::type_config::async_runtime::mutex::Mutex;
 use crate::type_config::async_runtime::MpscUnboundedReceiver;
 use crate::type_config::TypeConfigExt;
 use crate::vote::committed::CommittedVote;
@@ -182,6 +203,9 @@ where
     /// The [`RaftLogStorage`] implementation.
     pub(crate) log_store: LS,
 
+    /// Recently appended entries, shared by the replication streams.
+    pub(crate) log_cache: Arc<LogCache<C>>,
+
     /// A controlling handle to the [`RaftStateMachine`] worker.
     ///
     /// [`RaftStateMachine`]: `crate::storage::RaftStateMachine`
@@ -192,11 +216,40 @@ where
     /// Channels to send result back to client when logs are applied.
     pub(crate) client_resp_channels: BTreeMap<u64, ResponderOf<C>>,
 
+    /// Permits of the client writes in `client_resp_channels`, released once they are responded.
+    pub(crate) proposal_permits: BTreeMap<u64, ProposalPermit<C>>,
+
+    /// Limits the number of in-flight client writes.
+    pub(crate) admission: Arc<Admission<C>>,
+
+    /// Client writes held back because the state machine lags behind.
+    pub(crate) apply_throttle: ApplyThrottle<C>,
+
     /// A mapping of node IDs the replication state of the target node.
     pub(crate) replications: BTreeMap<C::NodeId, ReplicationHandle<C>>,
 
     pub(crate) heartbeat_handle: HeartbeatWorkersHandle<C>,
 
+    /// Protocol versions of the peers, learned by handshakes.
+    ///
+    /// A peer is present once a handshake with it is started.
+    pub(crate) peer_versions: BTreeMap<C::NodeId, PeerVersion<C>>,
+
+    /// The network client to forward client writes with, along with the Leader and its node it
+    /// connects to.
+    ///
+    /// It is rebuilt only when the Leader or its node changes. Forwarded writes share it and are
+    /// thus sent one at a time.
+    pub(crate) forward_client: Option<(C::NodeId, C::Node, Arc<MutexOf<C, NF::Network>>)>,
+
+    /// The vote of the hibernating Leader this node suspends its election timer for.
+    ///
+    /// The election timer is suspended only while the local vote is still this one: any change of
+    /// the vote, such as electing itself, ends the hibernation.
+    ///
+    /// The hibernation of this node as a Leader is tracked by `Leader::hibernating`.
+    pub(crate) hibernating: Option<VoteOf<C>>,
+
     #[allow(dead_code)]
     pub(crate) tx_api: MpscUnboundedSenderOf<C, RaftMsg<C>>,
     pub(crate) rx_api: MpscUnboundedReceiverOf<C, RaftMsg<C>>,
@@ -470,6 +523,84 @@ where
         self.write_entry(ent, Some(tx));
     }
 
+    /// Write a client request, unless the state machine lags too far behind.
+    ///
+    /// If [`Config::max_apply_lag`] is exceeded on a Leader, the request is held back until the
+    /// state machine catches up, or is rejected if [`Config::reject_on_apply_lag`] is set.
+    /// At most `max_apply_lag` requests are held back; more are rejected.
+    pub(crate) fn handle_client_write(&mut self, app_data: C::D, tx: ResponderOf<C>, permit: ProposalPermit<C>) {
+        if self.engine.leader.is_some() && (self.apply_throttle.is_throttling() || self.is_apply_lag_exceeded()) {
+            self.apply_throttle.start();
+
+            if self.config.reject_on_apply_lag {
+                let err = Overloaded::new(format!(
+                    "apply lag {} exceeds max_apply_lag {}",
+                    self.apply_lag(),
+                    self.config.max_apply_lag
+                ));
+                tx.send(Err(ClientWriteError::Overloaded(err)));
+            } else if self.apply_throttle.len() as u64 >= self.config.max_apply_lag {
+                let err = Overloaded::new(format!(
+                    "{} client writes are already held back for apply lag {}",
+                    self.apply_throttle.len(),
+                    self.apply_lag()
+                ));
+                tx.send(Err(ClientWriteError::Overloaded(err)));
+            } else {
+                tracing::debug!(apply_lag = self.apply_lag(), "hold back client write");
+                self.apply_throttle.push(app_data, tx, permit);
+            }
+            return;
+        }
+
+        self.write_client_entry(app_data, tx, permit);
+    }
+
+    /// Write the held back client requests, as many as the state machine lag allows.
+    ///
+    /// If this node is no longer a Leader, they are all written and thus rejected.
+    pub(crate) fn release_throttled_writes(&mut self) {
+        if !self.apply_throttle.is_throttling() {
+            return;
+        }
+
+        let budget = if self.engine.leader.is_some() {
+            self.config.max_apply_lag.saturating_sub(self.apply_lag())
+        } else {
+            u64::MAX
+        };
+
+        for _ in 0..budget {
+            let Some((app_data, tx, permit)) = self.apply_throttle.pop() else {
+                break;
+            };
+            self.write_client_entry(app_data, tx, permit);
+        }
+
+        if !self.is_apply_lag_exceeded() || self.engine.leader.is_none() {
+            self.apply_throttle.try_stop();
+        }
+    }
+
+    /// Write a client request, and keep its permit until it is responded.
+    fn write_client_entry(&mut self, app_data: C::D, tx: ResponderOf<C>, permit: ProposalPermit<C>) {
+        let index = self.write_entry(C::Entry::new_normal(LogIdOf::<C>::default(), app_data), Some(tx));
+
+        if let Some(index) = index {
+            self.proposal_permits.insert(index, permit);
+        }
+    }
+
+    /// The number of committed log entries that are not yet applied.
+    fn apply_lag(&self) -> u64 {
+        let st = &self.engine.state;
+        st.committed().next_index().saturating_sub(st.io_applied().next_index())
+    }
+
+    fn is_apply_lag_exceeded(&self) -> bool {
+        self.config.max_apply_lag > 0 && self.apply_lag() > self.config.max_apply_lag
+    }
+
     /// Write a log entry to the cluster through raft protocol.
     ///
     /// I.e.: append the log entry to local store, forward it to a quorum(including the leader),
@@ -477,13 +608,13 @@ where
     ///
     /// The result of applying it to state machine is sent to `resp_tx`, if it is not `None`.
     /// The calling side may not receive a result from `resp_tx`, if raft is shut down.
+    ///
+    /// It returns the log index of the entry, or `None` if the write is rejected.
     #[tracing::instrument(level = "debug", skip_all, fields(id = display(&self.id)))]
-    pub fn write_entry(&mut self, entry: C::Entry, resp_tx: Option<ResponderOf<C>>) {
+    pub fn write_entry(&mut self, entry: C::Entry, resp_tx: Option<ResponderOf<C>>) -> Option<u64> {
         tracing::debug!(payload = display(&entry), "write_entry");
 
-        let Some((mut lh, tx)) = self.engine.get_leader_handler_or_reject(resp_tx) else {
-            return;
-        };
+        let (mut lh, tx) = self.engine.get_leader_handler_or_reject(resp_tx)?;
 
         // If the leader is transferring leadership, forward writes to the new leader.
         if let Some(to) = lh.leader.get_transfer_to() {
@@ -491,7 +622,7 @@ where
                 let err = lh.state.new_forward_to_leader(to.clone());
                 tx.send(Err(ClientWriteError::ForwardToLeader(err)));
             }
-            return;
+            return None;
         }
 
         let entries = vec![entry];
@@ -504,6 +635,8 @@ where
         if let Some(tx) = tx {
             self.client_resp_channels.insert(index, tx);
         }
+
+        Some(index)
     }
 
     /// Send a heartbeat message to every follower/learners.
@@ -568,6 +701,17 @@ where
         let membership_config = st.membership_state.effective().stored_membership().clone();
         let current_leader = self.current_leader();
 
+        let replication_batch_size = replication
+            .as_ref()
+            .map(|_| self.replications.iter().map(|(id, h)| (id.clone(), h.batch_size())).collect());
+
+        let peer_versions = self
+            .peer_versions
+            .iter()
+            .filter(|(id, _)| st.membership_state.effective().get_node(id).is_some())
+            .filter_map(|(id, v)| v.version().map(|v| (id.clone(), v)))
+            .collect();
+
         #[allow(deprecated)]
         let m = RaftMetrics {
             running_state: Ok(()),
@@ -580,6 +724,9 @@ where
             last_applied: st.io_applied().cloned(),
             snapshot: st.io_snapshot_last_log_id().cloned(),
             purged: st.io_purged().cloned(),
+            apply_lag: self.apply_lag(),
+            apply_throttled_ms: self.apply_throttle.throttled_ms(),
+            in_flight_proposals: self.admission.in_flight(),
 
             // --- cluster ---
             state: st.server_state,
@@ -588,9 +735,14 @@ where
             last_quorum_acked: last_quorum_acked.map(SerdeInstant::new),
             membership_config: membership_config.clone(),
             heartbeat: heartbeat.clone(),
+            peer_versions,
 
             // --- replication ---
             replication: replication.clone(),
+            replication_batch_size,
+            log_cache_hits: self.log_cache.hits(),
+            log_cache_misses: self.log_cache.misses(),
+            last_checksum_error: self.engine.last_checksum_error.clone(),
         };
 
         #[allow(deprecated)]
@@ -780,6 +932,7 @@ where
             let ent = applying_entries.next().unwrap();
             let apply_res = results.next().unwrap();
             let tx = self.client_resp_channels.remove(&log_index);
+            self.proposal_permits.remove(&log_index);
 
             Self::send_response(ent, apply_res, tx);
         }
@@ -815,11 +968,15 @@ where
         progress_entry: ProgressEntry<C>,
     ) -> ReplicationHandle<C> {
         // Safe unwrap(): target must be in membership
-        let target_node = self.engine.state.membership_state.effective().get_node(&target).unwrap();
+        let target_node = self.engine.state.membership_state.effective().get_node(&target).unwrap().clone();
+
+        if self.should_handshake(&target) {
+            self.spawn_handshake(target.clone(), &target_node).await;
+        }
 
         let membership_log_id = self.engine.state.membership_state.effective().log_id();
-        let network = self.network_factory.new_client(target.clone(), target_node).await;
-        let snapshot_network = self.network_factory.new_client(target.clone(), target_node).await;
+        let network = self.network_factory.new_client(target.clone(), &target_node).await;
+        let snapshot_network = self.network_factory.new_client(target.clone(), &target_node).await;
 
         let leader = self.engine.leader.as_ref().unwrap();
 
@@ -834,6 +991,7 @@ where
             network,
             snapshot_network,
             self.log_store.get_log_reader().await,
+            self.log_cache.clone(),
             self.sm_handle.new_snapshot_reader(),
             self.tx_notification.clone(),
             tracing::span!(parent: &self.span, Level::DEBUG, "replication", id=display(&self.id), target=display(&target)),
@@ -960,6 +1118,8 @@ where
 
             self.run_engine_commands().await?;
 
+            self.retry_handshakes().await;
+
             // There is a message waking up the loop, process channels one by one.
 
             let raft_msg_processed = self.process_raft_msg(balancer.raft_msg()).await?;
@@ -1128,6 +1288,437 @@ where
         }
     }
 
+    /// Send a client write to the Leader `target` in a spawned task, and send back the response
+    /// via `tx`.
+    #[tracing::instrument(level = "debug", skip(self, target_node, app_data, tx))]
+    async fn forward_client_write(
+        &mut self,
+        target: C::NodeId,
+        target_node: C::Node,
+        app_data: C::D,
+        option: RPCOption,
+        tx: ForwardClientWriteTx<C>,
+    ) {
+        if !self.peer_supports(&target, ProtocolFeature::ForwardClientWrite) {
+            let err = AnyError::error(format!(
+                "forward_client_write is not supported by the protocol version of node {}",
+                target
+            ));
+            let _ = tx.send(Err(RPCError::Unreachable(Unreachable::new(&err))));
+            return;
+        }
+
+        let client = self.forward_client(target.clone(), target_node).await;
+
+        let ttl = option.hard_ttl();
+        let id = self.id.clone();
+
+        let fut = async move {
+            let mut client = client.lock().await;
+            let res = match C::timeout(ttl, client.forward_client_write(app_data, option)).await {
+                Ok(res) => res,
+                Err(_timeout) => Err(RPCError::Timeout(Timeout {
+                    action: RPCTypes::ClientWrite,
+                    id,
+                    target,
+                    timeout: ttl,
+                })),
+            };
+
+            if let Err(e) = &res {
+                tracing::warn!({ error = display(e) }, "error forwarding client write");
+            }
+            let _ = tx.send(res);
+        };
+
+        // False positive lint warning(`non-binding `let` on a future`): https://github.com/rust-lang/rust-clippy/issues/9932
+        #[allow(clippy::let_underscore_future)]
+        let _ = C::spawn(fut.instrument(tracing::debug_span!(parent: &Span::current(), "forward_client_write")));
+    }
+
+    /// Returns the cached client to forward client writes to the Leader `target`, or build a new
+    /// one if the Leader or its node has changed.
+    async fn forward_client(&mut self, target: C::NodeId, target_node: C::Node) -> Arc<MutexOf<C, NF::Network>> {
+        if let Some((id, node, client)) = &self.forward_client {
+            if id == &target && node == &target_node {
+                return client.clone();
+            }
+        }
+
+        let client = self.network_factory.new_client(target.clone(), &target_node).await;
+        let client = Arc::new(C::mutex(client));
+        self.forward_client = Some((target, target_node, client.clone()));
+        client
+    }
+
+    /// Returns true if `feature` can be used with the peer `target`.
+    ///
+    /// A feature is used only if both the local network and the peer support it. If the version
+    /// of the peer is unknown, it is assumed to speak [`ProtocolVersion::V1`]: a newer feature is
+    /// used only after a handshake with the peer succeeds.
+    fn peer_supports(&self, target: &C::NodeId, feature: ProtocolFeature) -> bool {
+        if !self.network_factory.protocol_version().supports(feature) {
+            return false;
+        }
+
+        let version = self.peer_versions.get(target).and_then(|v| v.version());
+        version.unwrap_or(ProtocolVersion::V1).supports(feature)
+    }
+
+    /// Returns true if no handshake with `target` is in flight or succeeded, and the backoff after
+    /// the last failed one has elapsed.
+    fn should_handshake(&self, target: &C::NodeId) -> bool {
+        match self.peer_versions.get(target) {
+            None => true,
+            Some(v) => v.should_handshake(C::now()),
+        }
+    }
+
+    /// Leader side: retry the failed handshakes with replication targets, once their backoff
+    /// elapsed.
+    async fn retry_handshakes(&mut self) {
+        let now = C::now();
+
+        let due = self
+            .replications
+            .keys()
+            .filter(|id| self.peer_versions.get(*id).is_some_and(|v| v.should_handshake(now)))
+            .cloned()
+            .collect::<Vec<_>>();
+
+        for target in due {
+            let Some(node) = self.engine.state.membership_state.effective().get_node(&target).cloned() else {
+                continue;
+            };
+            self.spawn_handshake(target, &node).await;
+        }
+    }
+
+    /// Exchange protocol versions with `target` in a spawned task, and send back the peer's
+    /// version via [`Notification::Handshake`].
+    async fn spawn_handshake(&mut self, target: C::NodeId, target_node: &C::Node) {
+        let state = PeerVersion::start(self.peer_versions.get(&target));
+        self.peer_versions.insert(target.clone(), state);
+
+        let mut client = self.network_factory.new_client(target.clone(), target_node).await;
+
+        let req = HandshakeRequest::new(self.id.clone(), self.network_factory.protocol_version());
+        let ttl = Duration::from_millis(self.config.election_timeout_min);
+        let option = RPCOption::new(ttl);
+        let tx = self.tx_notification.clone();
+
+        let fut = {
+            let target = target.clone();
+            async move {
+                let version = match C::timeout(ttl, client.handshake(req, option)).await {
+                    Ok(Ok(Some(resp))) => Some(resp.version),
+                    Ok(Ok(None)) => {
+                        tracing::debug!(
+                            { target = display(&target) },
+                            "handshake is not supported by the network, peer is taken as V1"
+                        );
+                        Some(ProtocolVersion::V1)
+                    }
+                    Ok(Err(e)) => {
+                        tracing::info!({error = display(e), target = display(&target)}, "handshake failed, peer protocol version is unknown");
+                        None
+                    }
+                    Err(timeout) => {
+                        tracing::info!({error = display(timeout), target = display(&target)}, "timeout sending handshake");
+                        None
+                    }
+                };
+
+                let _ = tx.send(Notification::Handshake { target, version });
+            }
+        };
+
+        let span = tracing::debug_span!(parent: &Span::current(), "send_handshake", target = display(&target));
+
+        // False positive lint warning(`non-binding `let` on a future`): https://github.com/rust-lang/rust-clippy/issues/9932
+        #[allow(clippy::let_underscore_future)]
+        let _ = C::spawn(fut.instrument(span));
+    }
+
+    /// Ask `helper` to send the logs in `log_id_range` to `target` in a spawned task, and report
+    /// the result as the replication progress of `target`.
+    ///
+    /// The logs not sent by `helper` are sent by the replication stream of `target`.
+    async fn delegate_replicate(&mut self, target: C::NodeId, helper: C::NodeId, log_id_range: LogIdRange<C>) {
+        let effective = self.engine.state.membership_state.effective();
+        let membership_log_id = effective.log_id().clone();
+
+        // Safe unwrap(): the command is only issued by a Leader.
+        let leader = self.engine.leader.as_ref().unwrap();
+        let session_id = ReplicationSessionId::new(leader.committed_vote.clone(), membership_log_id);
+
+        let nodes = (
+            effective.get_node(&target).cloned(),
+            effective.get_node(&helper).cloned(),
+        );
+
+        let (Some(target_node), Some(helper_node)) = nodes else {
+            tracing::info!("target or helper is not in membership, replicate logs without delegation");
+            self.engine.replication_handler().catch_up_done(target, None);
+            return;
+        };
+
+        if !self.peer_supports(&helper, ProtocolFeature::CatchUp) {
+            tracing::info!(
+                helper = display(&helper),
+                "catch-up is not supported by the helper protocol version, replicate logs without delegation"
+            );
+            self.engine.replication_handler().catch_up_done(target, None);
+            return;
+        }
+
+        let mut client = self.network_factory.new_client(helper.clone(), &helper_node).await;
+
+        // Safe unwrap(): a delegated range is never empty.
+        let last_log_id = log_id_range.last.clone().unwrap();
+        let rpc = CatchUpRequest {
+            vote: session_id.vote(),
+            target: target.clone(),
+            target_node,
+            prev_log_id: log_id_range.prev.clone(),
+            last_log_id: last_log_id.clone(),
+            leader_commit: self.engine.state.committed().cloned(),
+        };
+
+        // Enough time to send a snapshot and then every batch of logs.
+        let batches = log_id_range.len().div_ceil(std::cmp::max(self.config.max_payload_entries, 1));
+        let ttl = self.config.install_snapshot_timeout()
+            + Duration::from_millis(self.config.heartbeat_interval) * batches as u32;
+        let option = RPCOption::new(ttl);
+
+        let tx_notification = self.tx_notification.clone();
+        let span = tracing::debug_span!(parent: &Span::current(), "delegate_replicate", helper = display(&helper));
+
+        let fut = async move {
+            let res = C::timeout(ttl, client.catch_up(rpc, option)).await;
+            tracing::info!(res = debug(&res), "catch-up delegated to {} is done", helper);
+
+            let resp = match res {
+                Ok(Ok(resp)) => resp,
+                Ok(Err(e)) => CatchUpResponse::Declined(e.to_string()),
+                Err(timeout) => CatchUpResponse::Declined(timeout.to_string()),
+            };
+
+            let matching = match resp {
+                CatchUpResponse::Success(matching) if matching > log_id_range.prev => {
+                    std::cmp::min(matching, log_id_range.last)
+                }
+                CatchUpResponse::Conflict if log_id_range.prev.is_some() => {
+                    let _ = tx_notification.send(Notification::ReplicationProgress {
+                        progress: replication::Progress {
+                            target,
+                            result: Ok(ReplicationResult(Err(log_id_range.prev.unwrap()))),
+                            session_id,
+                        },
+                    });
+                    return;
+                }
+                CatchUpResponse::HigherVote(higher) => {
+                    let _ = tx_notification.send(Notification::HigherVote {
+                        target,
+                        higher,
+                        leader_vote: session_id.committed_vote(),
+                    });
+                    return;
+                }
+                resp => {
+                    tracing::info!(
+                        resp = display(&resp),
+                        "{} did not catch up {}, replicate logs without delegation",
+                        helper,
+                        target
+                    );
+                    None
+                }
+            };
+
+            let _ = tx_notification.send(Notification::CatchUpDone {
+                session_id,
+                target,
+                matching,
+            });
+        };
+
+        // False positive lint warning(`non-binding `let` on a future`): https://github.com/rust-lang/rust-clippy/issues/9932
+        #[allow(clippy::let_underscore_future)]
+        let _ = C::spawn(fut.instrument(span));
+    }
+
+    /// Send logs to another node on behalf of the Leader, in a spawned task.
+    ///
+    /// Declined if this node is not at the vote of the Leader, or does not have all the logs.
+    async fn handle_catch_up_request(&mut self, rpc: CatchUpRequest<C>, tx: ResultSender<C, CatchUpResponse<C>>) {
+        tracing::info!(req = display(&rpc), func = func_name!());
+
+        let vote = self.engine.state.vote_ref();
+
+        if rpc.vote.as_ref_vote() < vote.as_ref_vote() {
+            let _ = tx.send(Ok(CatchUpResponse::HigherVote(vote.clone())));
+            return;
+        }
+
+        if &rpc.vote != vote {
+            let _ = tx.send(Ok(CatchUpResponse::Declined(format!("vote mismatch: {}", vote))));
+            return;
+        }
+
+        if !self.engine.state.has_log_id(&rpc.last_log_id) {
+            let reason = format!("log {} not found", rpc.last_log_id);
+            let _ = tx.send(Ok(CatchUpResponse::Declined(reason)));
+            return;
+        }
+
+        let target = rpc.target.clone();
+        let network = self.network_factory.new_client(target.clone(), &rpc.target_node).await;
+
+        let catch_up = CatchUp::<C, NF, LS>::new(
+            rpc,
+            network,
+            self.log_store.get_log_reader().await,
+            self.sm_handle.new_snapshot_reader(),
+            self.engine.state.last_purged_log_id().cloned(),
+            self.config.clone(),
+        );
+
+        let fut = async move {
+            let resp = catch_up.run().await;
+            let _ = tx.send(Ok(resp));
+        };
+
+        let span = tracing::debug_span!(parent: &Span::current(), "catch_up", target = display(&target));
+
+        // False positive lint warning(`non-binding `let` on a future`): https://github.com/rust-lang/rust-clippy/issues/9932
+        #[allow(clippy::let_underscore_future)]
+        let _ = C::spawn(fut.instrument(span));
+    }
+
+    /// Hibernate this Leader if it has not received a client request for
+    /// [`Config::hibernate_timeout`].
+    ///
+    /// It does not hibernate if a voter does not support it, because the voter would not stop its
+    /// election timer.
+    fn try_hibernate(&mut self, now: InstantOf<C>) {
+        if self.config.hibernate_timeout == 0 {
+            return;
+        }
+
+        let Some(leader) = self.engine.leader_ref() else {
+            return;
+        };
+
+        if leader.hibernating || leader.get_transfer_to().is_some() {
+            return;
+        }
+
+        if now < leader.last_active + Duration::from_millis(self.config.hibernate_timeout) {
+            return;
+        }
+
+        let voter_ids = self.engine.state.membership_state.effective().voter_ids().collect::<Vec<_>>();
+        let unsupported =
+            voter_ids.iter().find(|id| **id != self.id && !self.peer_supports(id, ProtocolFeature::Hibernate));
+        if let Some(v) = unsupported {
+            tracing::debug!(voter = display(v), "do not hibernate, not supported by the peer");
+            return;
+        }
+
+        let Ok(mut lh) = self.engine.leader_handler() else {
+            return;
+        };
+
+        if lh.hibernate() {
+            tracing::info!(
+                "Leader hibernates, no client request in {} ms",
+                self.config.hibernate_timeout
+            );
+        }
+    }
+
+    /// Follower side: suspend the election timer, because the Leader stops sending heartbeats.
+    fn handle_hibernate(&mut self, rpc: HibernateRequest<C>) {
+        if self.engine.state.vote_ref() != &rpc.vote {
+            tracing::info!(
+                rpc = display(&rpc),
+                my_vote = display(self.engine.state.vote_ref()),
+                "do not hibernate, vote changed"
+            );
+            return;
+        }
+
+        // With a missing log, this node would never catch up while the Leader is hibernating.
+        if self.engine.state.last_log_id() != rpc.last_log_id.as_ref() {
+            tracing::info!(
+                rpc = display(&rpc),
+                last_log_id = display(self.engine.state.last_log_id().display()),
+                "do not hibernate, logs differ from the Leader"
+            );
+            return;
+        }
+
+        tracing::info!(rpc = display(&rpc), "hibernate, election timer is suspended");
+
+        // Every log of the Leader is committed before it hibernates.
+        self.engine.handle_commit_entries(rpc.last_log_id);
+        self.hibernating = Some(rpc.vote);
+    }
+
+    /// Returns true if the election timer is suspended because the Leader this node voted for
+    /// is hibernating.
+    fn is_hibernating(&self) -> bool {
+        self.hibernating.as_ref() == Some(self.engine.state.vote_ref())
+    }
+
+    /// Leave hibernation when there is traffic from a Leader or a Candidate, or the application
+    /// asks to.
+    ///
+    /// A follower resumes its election timer. A Leader resumes sending heartbeats, and restarts
+    /// counting the idle time before it hibernates again.
+    fn wake_up(&mut self, reason: impl fmt::Display) {
+        if self.hibernating.take().is_some() {
+            tracing::info!("wake up from hibernation: {}", reason);
+        }
+
+        self.wake_up_leader(reason);
+    }
+
+    /// Leave hibernation if this node is a Leader, when there is client traffic.
+    ///
+    /// A hibernating follower keeps its election timer suspended: a client request to it does not
+    /// mean the Leader is gone. It is replied with `ForwardToLeader` or forwarded to the Leader,
+    /// which wakes up the Leader.
+    fn wake_up_leader(&mut self, reason: impl fmt::Display) {
+        let Some(leader) = self.engine.leader_mut() else {
+            return;
+        };
+
+        leader.last_active = C::now();
+
+        if leader.hibernating {
+            tracing::info!("Leader wakes up from hibernation: {}", reason);
+            leader.hibernating = false;
+            self.send_heartbeat(reason);
+        }
+    }
+
+    /// Start a handshake with the Leader that sent an AppendEntries.
+    ///
+    /// The caller checks with [`Self::should_handshake()`] that the version of the Leader is not
+    /// known yet and no handshake is in flight. A failed handshake is retried with backoff on a
+    /// later AppendEntries.
+    async fn handshake_with_leader(&mut self, leader: C::NodeId) {
+        // The Leader may not be known until the membership is replicated to this node.
+        let Some(node) = self.engine.state.membership_state.effective().get_node(&leader).cloned() else {
+            return;
+        };
+
+        self.spawn_handshake(leader, &node).await;
+    }
+
     /// Spawn parallel vote requests to all cluster members.
     #[tracing::instrument(level = "trace", skip_all)]
     async fn broadcast_transfer_leader(&mut self, req: TransferLeaderRequest<C>) {
@@ -1138,6 +1729,14 @@ where
                 continue;
             }
 
+            if !self.peer_supports(&target, ProtocolFeature::TransferLeader) {
+                tracing::info!(
+                    target = display(&target),
+                    "skip sending transfer_leader, not supported by the peer protocol version"
+                );
+                continue;
+            }
+
             let r = req.clone();
 
             // Safe unwrap(): target must be in membership
@@ -1211,7 +1810,16 @@ where
 
         match msg {
             RaftMsg::AppendEntries { rpc, tx } => {
+                self.wake_up("AppendEntries");
+
+                let leader = rpc.vote.to_leader_node_id();
                 self.handle_append_entries_request(rpc, tx);
+
+                if let Some(leader) = leader {
+                    if leader != self.id && self.should_handshake(&leader) {
+                        self.handshake_with_leader(leader).await;
+                    }
+                }
             }
             RaftMsg::RequestVote { rpc, tx } => {
                 let now = C::now();
@@ -1222,19 +1830,32 @@ where
                     func_name!()
                 );
 
+                self.wake_up("RequestVote");
                 self.handle_vote_request(rpc, tx);
             }
             RaftMsg::BeginReceivingSnapshot { tx } => {
                 self.engine.handle_begin_receiving_snapshot(tx);
             }
             RaftMsg::InstallFullSnapshot { vote, snapshot, tx } => {
+                self.wake_up("InstallFullSnapshot");
                 self.engine.handle_install_full_snapshot(vote, snapshot, tx);
             }
             RaftMsg::CheckIsLeaderRequest { tx } => {
+                self.wake_up_leader("CheckIsLeaderRequest");
                 self.handle_check_is_leader_request(tx).await;
             }
-            RaftMsg::ClientWriteRequest { app_data, tx } => {
-                self.write_entry(C::Entry::new_normal(LogIdOf::<C>::default(), app_data), Some(tx));
+            RaftMsg::ClientWriteRequest { app_data, tx, permit } => {
+                self.wake_up_leader("ClientWriteRequest");
+                self.handle_client_write(app_data, tx, permit);
+            }
+            RaftMsg::ForwardClientWrite {
+                target,
+                target_node,
+                app_data,
+                option,
+                tx,
+            } => {
+                self.forward_client_write(target, target_node, app_data, option, tx).await;
             }
             RaftMsg::Initialize { members, tx } => {
                 tracing::info!(
@@ -1253,6 +1874,7 @@ where
                     func_name!()
                 );
 
+                self.wake_up_leader("ChangeMembership");
                 self.change_membership(changes, retain, tx);
             }
             RaftMsg::ExternalCoreRequest { req } => {
@@ -1271,11 +1893,23 @@ where
                     }
                 }
             }
+            RaftMsg::CatchUp { rpc, tx } => {
+                self.handle_catch_up_request(rpc, tx).await;
+            }
+            RaftMsg::HandleHibernate { rpc } => {
+                self.handle_hibernate(rpc);
+            }
+            RaftMsg::HandleHandshake { from, version } => {
+                tracing::info!("received handshake from: {}, protocol version: {}", from, version);
+                self.peer_versions.insert(from, PeerVersion::Known(version));
+            }
             RaftMsg::ExternalCommand { cmd } => {
                 tracing::info!(cmd = debug(&cmd), "received RaftMsg::ExternalCommand: {}", func_name!());
 
                 match cmd {
                     ExternalCommand::Elect => {
+                        self.wake_up("ExternalCommand::Elect");
+
                         if self.engine.state.membership_state.effective().is_voter(&self.id) {
                             // TODO: reject if it is already a leader?
                             self.engine.elect();
@@ -1285,6 +1919,7 @@ where
                         }
                     }
                     ExternalCommand::Heartbeat => {
+                        self.wake_up_leader("ExternalCommand::Heartbeat");
                         self.send_heartbeat("ExternalCommand");
                     }
                     ExternalCommand::Snapshot => self.trigger_snapshot(),
@@ -1301,6 +1936,9 @@ where
                     ExternalCommand::TriggerTransferLeader { to } => {
                         self.engine.trigger_transfer_leader(to);
                     }
+                    ExternalCommand::WakeUp => {
+                        self.wake_up("ExternalCommand::WakeUp");
+                    }
                     ExternalCommand::AllowNextRevert { to, allow, tx } => {
                         //
                         let res = match self.engine.leader_handler() {
@@ -1380,10 +2018,15 @@ where
 
                 self.handle_tick_election();
 
+                // Throttled writes are rejected if this node is no longer a Leader.
+                self.release_throttled_writes();
+
                 // TODO: test: fixture: make isolated_nodes a single-way isolating.
 
-                // Leader send heartbeat
-                let heartbeat_at = self.engine.leader_ref().map(|l| l.next_heartbeat);
+                self.try_hibernate(now);
+
+                // Leader send heartbeat, unless it is hibernating
+                let heartbeat_at = self.engine.leader_ref().filter(|l| !l.hibernating).map(|l| l.next_heartbeat);
                 if let Some(t) = heartbeat_at {
                     if now >= t {
                         if self.runtime_config.enable_heartbeat.load(Ordering::Relaxed) {
@@ -1458,6 +2101,16 @@ where
                 }
             }
 
+            Notification::CatchUpDone {
+                session_id,
+                target,
+                matching,
+            } => {
+                if self.does_replication_session_match(&session_id, "CatchUpDone") {
+                    self.engine.replication_handler().catch_up_done(target, matching);
+                }
+            }
+
             Notification::HeartbeatProgress {
                 session_id,
                 sending_time,
@@ -1476,6 +2129,29 @@ where
                 }
             }
 
+            Notification::HibernateFailed { session_id, target } => {
+                tracing::info!(
+                    target = display(&target),
+                    "failed to tell the target to hibernate, keep sending heartbeats"
+                );
+
+                if self.does_leader_vote_match(&session_id.committed_vote(), "HibernateFailed") {
+                    self.wake_up("HibernateFailed");
+                }
+            }
+
+            Notification::Handshake { target, version } => {
+                tracing::info!(
+                    "handshake done with: {}, protocol version: {}",
+                    target,
+                    version.display()
+                );
+
+                let base = Duration::from_millis(self.config.election_timeout_min);
+                let v = self.peer_versions.entry(target).or_insert(PeerVersion::Handshaking { failures: 0 });
+                v.update(version, C::now(), base);
+            }
+
             Notification::StateMachine { command_result } => {
                 tracing::debug!("sm::StateMachine command result: {:?}", command_result);
 
@@ -1518,6 +2194,7 @@ where
                         self.engine.state.io_state_mut().update_applied(Some(res.last_applied.clone()));
 
                         self.handle_apply_result(res);
+                        self.release_throttled_writes();
                     }
                 }
             }
@@ -1548,6 +2225,11 @@ where
             return;
         }
 
+        if self.is_hibernating() {
+            tracing::debug!("the Leader is hibernating, election timer is suspended");
+            return;
+        }
+
         if self.engine.state.membership_state.effective().voter_ids().count() == 1 {
             tracing::debug!("this is the only voter, do election at once");
         } else {
@@ -1751,6 +2433,11 @@ where
                 // because `append()` may call the callback before returning.
                 self.engine.state.io_state.io_progress.submit(io_id);
 
+                // Only the Leader reads entries back for replication.
+                if self.engine.leader.is_some() {
+                    self.log_cache.append(&entries);
+                }
+
                 // Submit IO request, do not wait for the response.
                 self.log_store.append(entries, callback).await?;
             }
@@ -1779,9 +2466,11 @@ where
             }
             Command::TruncateLog { since } => {
                 self.log_store.truncate(since.clone()).await?;
+                self.log_cache.truncate(since.index());
 
                 // Inform clients waiting for logs to be applied.
                 let removed = self.client_resp_channels.split_off(&since.index());
+                self.proposal_permits.split_off(&since.index());
                 if !removed.is_empty() {
                     let leader_id = self.current_leader();
                     let leader_node = self.get_leader_node(leader_id.clone());
@@ -1811,6 +2500,10 @@ where
             Command::BroadcastHeartbeat { session_id, committed } => {
                 self.heartbeat_handle.broadcast(HeartbeatEvent::new(C::now(), session_id, committed))
             }
+            Command::BroadcastHibernate {
+                session_id,
+                last_log_id,
+            } => self.heartbeat_handle.broadcast(HeartbeatEvent::new_hibernate(C::now(), session_id, last_log_id)),
             Command::SaveCommitted { committed } => {
                 self.log_store.save_committed(Some(committed)).await?;
             }
@@ -1825,6 +2518,13 @@ where
                 let node = self.replications.get(&target).expect("replication to target node exists");
                 let _ = node.tx_repl.send(req);
             }
+            Command::DelegateReplicate {
+                target,
+                helper,
+                log_id_range,
+            } => {
+                self.delegate_replicate(target, helper, log_id_range).await;
+            }
             Command::BroadcastTransferLeader { req } => self.broadcast_transfer_leader(req).await,
 
             Command::RebuildReplicationStreams { targets } => {
@@ -37,6 +37,9 @@ pub(crate) enum ExternalCommand<C: RaftTypeConfig> {
     /// Submit a command to inform RaftCore to transfer leadership to the specified node.
     TriggerTransferLeader { to: C::NodeId },
 
+    /// Wake up a hibernating node.
+    WakeUp,
+
     /// Allow or not the next revert of the replication to the specified node.
     AllowNextRevert {
         to: C::NodeId,
@@ -80,6 +83,9 @@ where C: RaftTypeConfig
             ExternalCommand::TriggerTransferLeader { to } => {
                 write!(f, "TriggerTransferLeader: to {}", to)
             }
+            ExternalCommand::WakeUp => {
+                write!(f, "WakeUp")
+            }
             ExternalCommand::AllowNextRevert { to, allow, .. } => {
                 write!(
                     f,
@@ -2,12 +2,22 @@ use std::collections::BTreeMap;
 use std::fmt;
 
 use crate::base::BoxOnce;
+use crate::core::admission::ProposalPermit;
 use crate::core::raft_msg::external_command::ExternalCommand;
 use crate::error::CheckIsLeaderError;
+use crate::error::ClientWriteError;
 use crate::error::Infallible;
 use crate::error::InitializeError;
+use crate::error::RPCError;
+use crate::error::RaftError;
+use crate::network::ProtocolVersion;
+use crate::network::RPCOption;
 use crate::raft::AppendEntriesRequest;
 use crate::raft::AppendEntriesResponse;
+use crate::raft::CatchUpRequest;
+use crate::raft::CatchUpResponse;
+use crate::raft::ClientWriteResponse;
+use crate::raft::HibernateRequest;
 use crate::raft::SnapshotResponse;
 use crate::raft::VoteRequest;
 use crate::raft::VoteResponse;
@@ -35,6 +45,10 @@ pub(crate) type AppendEntriesTx<C> = ResultSender<C, AppendEntriesResponse<C>>;
 /// TX for Linearizable Read Response
 pub(crate) type ClientReadTx<C> = ResultSender<C, (Option<LogIdOf<C>>, Option<LogIdOf<C>>), CheckIsLeaderError<C>>;
 
+/// TX for the response of a client write forwarded to the Leader
+pub(crate) type ForwardClientWriteTx<C> =
+    ResultSender<C, ClientWriteResponse<C>, RPCError<C, RaftError<C, ClientWriteError<C>>>>;
+
 /// A message sent by application to the [`RaftCore`].
 ///
 /// [`RaftCore`]: crate::core::RaftCore
@@ -70,6 +84,18 @@ where C: RaftTypeConfig
     ClientWriteRequest {
         app_data: C::D,
         tx: ResponderOf<C>,
+
+        /// Accounts this request as in-flight until it is responded.
+        permit: ProposalPermit<C>,
+    },
+
+    /// Forward a client write to the Leader `target` via the network.
+    ForwardClientWrite {
+        target: C::NodeId,
+        target_node: C::Node,
+        app_data: C::D,
+        option: RPCOption,
+        tx: ForwardClientWriteTx<C>,
     },
 
     CheckIsLeaderRequest {
@@ -106,6 +132,23 @@ where C: RaftTypeConfig
         to: C::NodeId,
     },
 
+    /// Bring another node up to date in place of the Leader.
+    CatchUp {
+        rpc: CatchUpRequest<C>,
+        tx: ResultSender<C, CatchUpResponse<C>>,
+    },
+
+    /// The Leader stops sending heartbeats because the group is idle.
+    HandleHibernate {
+        rpc: HibernateRequest<C>,
+    },
+
+    /// A peer told its protocol version with a handshake.
+    HandleHandshake {
+        from: C::NodeId,
+        version: ProtocolVersion,
+    },
+
     ExternalCommand {
         cmd: ExternalCommand<C>,
     },
@@ -130,6 +173,7 @@ where C: RaftTypeConfig
                 write!(f, "InstallFullSnapshot: vote: {}, snapshot: {}", vote, snapshot)
             }
             RaftMsg::ClientWriteRequest { .. } => write!(f, "ClientWriteRequest"),
+            RaftMsg::ForwardClientWrite { target, .. } => write!(f, "ForwardClientWrite: target: {}", target),
             RaftMsg::CheckIsLeaderRequest { .. } => write!(f, "CheckIsLeaderRequest"),
             RaftMsg::Initialize { members, .. } => {
                 // TODO: avoid using Debug
@@ -143,6 +187,11 @@ where C: RaftTypeConfig
             RaftMsg::HandleTransferLeader { from, to } => {
                 write!(f, "TransferLeader: from_leader: vote={}, to: {}", from, to)
             }
+            RaftMsg::CatchUp { rpc, .. } => write!(f, "CatchUp: {}", rpc),
+            RaftMsg::HandleHibernate { rpc } => write!(f, "Hibernate: {}", rpc),
+            RaftMsg::HandleHandshake { from, version } => {
+                write!(f, "Handshake: from: {}, version: {}", from, version)
+            }
             RaftMsg::ExternalCommand { cmd } => {
                 write!(f, "ExternalCommand: {}", cmd)
             }
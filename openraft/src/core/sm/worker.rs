@@ -1,3 +1,5 @@
+use std::collections::BTreeSet;
+
 use anyerror::AnyError;
 use tracing_futures::Instrument;
 
@@ -5,6 +7,7 @@ use crate::async_runtime::MpscUnboundedReceiver;
 use crate::async_runtime::MpscUnboundedSender;
 use crate::async_runtime::OneshotSender;
 use crate::base::BoxAsyncOnceMut;
+use crate::base::BoxFuture;
 use crate::core::notification::Notification;
 use crate::core::raft_msg::ResultSender;
 use crate::core::sm::handle::Handle;
@@ -15,6 +18,7 @@ use crate::core::ApplyResult;
 use crate::core::ApplyingEntry;
 use crate::display_ext::DisplayOptionExt;
 use crate::display_ext::DisplaySliceExt;
+use crate::entry::raft_entry_ext::RaftEntryExt;
 use crate::entry::RaftEntry;
 use crate::entry::RaftPayload;
 use crate::storage::RaftStateMachine;
@@ -180,6 +184,10 @@ where
         }
         tracing::debug!(entries = display(entries.display()), "about to apply");
 
+        for entry in entries.iter() {
+            entry.verify_checksum()?;
+        }
+
         let last_applied = last;
 
         // Fake complain: avoid using `collect()` when not needed
@@ -189,7 +197,7 @@ where
 
         let n_entries = end - since;
 
-        let apply_results = self.state_machine.apply(entries).await?;
+        let apply_results = self.apply_entries(entries).await?;
 
         let n_replies = apply_results.len() as u64;
 
@@ -210,6 +218,103 @@ where
         Ok(resp)
     }
 
+    /// Apply entries to the state machine and return the responses in log order.
+    ///
+    /// Entries accepted by [`RaftStateMachine::try_apply_parallel()`] are applied in waves: the
+    /// tasks in a wave have distinct conflict keys and are spawned to run in parallel. Other
+    /// entries are applied sequentially with [`RaftStateMachine::apply()`]. A wave or a sequential
+    /// batch starts only after the previous one is done.
+    async fn apply_entries(&mut self, entries: Vec<C::Entry>) -> Result<Vec<C::R>, StorageError<C>> {
+        let mut results = Vec::with_capacity(entries.len());
+
+        // At most one of `sequential` and `wave` is non-empty.
+        let mut sequential = vec![];
+        let mut wave = vec![];
+        let mut wave_keys = BTreeSet::new();
+
+        for entry in entries {
+            let parallel = if entry.get_membership().is_some() {
+                Err(entry)
+            } else {
+                let log_id = entry.log_id();
+                self.state_machine.try_apply_parallel(entry).map(|(key, task)| (key, log_id, task))
+            };
+
+            match parallel {
+                Ok((key, log_id, task)) => {
+                    self.apply_sequential(&mut sequential, &mut results).await?;
+
+                    if !wave_keys.insert(key) {
+                        Self::apply_wave(&mut wave, &mut results).await?;
+                        wave_keys.clear();
+                        wave_keys.insert(key);
+                    }
+                    wave.push((log_id, task));
+                }
+                Err(entry) => {
+                    Self::apply_wave(&mut wave, &mut results).await?;
+                    wave_keys.clear();
+
+                    sequential.push(entry);
+                }
+            }
+        }
+
+        self.apply_sequential(&mut sequential, &mut results).await?;
+        Self::apply_wave(&mut wave, &mut results).await?;
+
+        Ok(results)
+    }
+
+    async fn apply_sequential(
+        &mut self,
+        entries: &mut Vec<C::Entry>,
+        results: &mut Vec<C::R>,
+    ) -> Result<(), StorageError<C>> {
+        if entries.is_empty() {
+            return Ok(());
+        }
+
+        let res = self.state_machine.apply(std::mem::take(entries)).await?;
+        results.extend(res);
+        Ok(())
+    }
+
+    /// Spawn the tasks of a wave and wait for all of them.
+    async fn apply_wave(
+        tasks: &mut Vec<(LogIdOf<C>, BoxFuture<'static, Result<C::R, StorageError<C>>>)>,
+        results: &mut Vec<C::R>,
+    ) -> Result<(), StorageError<C>> {
+        if tasks.is_empty() {
+            return Ok(());
+        }
+
+        tracing::debug!("{}: apply {} entries in parallel", func_name!(), tasks.len());
+
+        let handles = tasks.drain(..).map(|(log_id, task)| (log_id, C::spawn(task))).collect::<Vec<_>>();
+
+        // Wait for every task even if one fails, so that none is still running when this returns.
+        let mut first_err = None;
+        for (log_id, handle) in handles {
+            let res = match handle.await {
+                Ok(res) => res,
+                Err(join_err) => Err(StorageError::apply(log_id, AnyError::error(join_err))),
+            };
+
+            match res {
+                Ok(r) => results.push(r),
+                Err(e) => {
+                    first_err.get_or_insert(e);
+                }
+            }
+        }
+
+        match first_err {
+            None => Ok(()),
+            Some(e) => Err(e),
+        }
+    }
+
     /// Build a snapshot from the state machine.
     ///
     /// Building snapshot is a read-only operation, so it can be run in another task in parallel.
@@ -7,6 +7,7 @@ use std::sync::Mutex;
 use std::time::Duration;
 
 use futures::future::Either;
+use futures::FutureExt;
 use tracing::Instrument;
 use tracing::Level;
 use tracing::Span;
@@ -123,6 +124,104 @@ where C: RaftTypeConfig
     }
 }
 
+/// Emit `Tick` events to many Raft instances with a single task.
+///
+/// Each Raft subscribes with [`SharedTick::subscribe()`] and gets a [`TickHandle`] just like the
+/// one returned by [`Tick::spawn()`]. A subscriber is removed when its handle is shut down or
+/// dropped, or when its Raft quits.
+pub(crate) struct SharedTick<C>
+where C: RaftTypeConfig
+{
+    subscribers: Arc<Mutex<Vec<Subscriber<C>>>>,
+
+    /// Stops the tick loop when sent or dropped.
+    shutdown: Mutex<Option<OneshotSenderOf<C, ()>>>,
+}
+
+struct Subscriber<C>
+where C: RaftTypeConfig
+{
+    tx: MpscUnboundedSenderOf<C, Notification<C>>,
+    enabled: Arc<AtomicBool>,
+    i: u64,
+
+    /// Resolves when the [`TickHandle`] is shut down or dropped.
+    cancel: OneshotReceiverOf<C, ()>,
+}
+
+impl<C> SharedTick<C>
+where C: RaftTypeConfig
+{
+    pub(crate) fn spawn(interval: Duration) -> Self {
+        let subscribers = Arc::new(Mutex::new(Vec::<Subscriber<C>>::new()));
+        let (shutdown, mut shutdown_rx) = C::oneshot();
+
+        let fut = {
+            let subscribers = subscribers.clone();
+            async move {
+                loop {
+                    let sleep_fut = C::sleep_until(C::now() + interval);
+                    let sleep_fut = std::pin::pin!(sleep_fut);
+
+                    if let Either::Left(_) = futures::future::select(&mut shutdown_rx, sleep_fut).await {
+                        tracing::info!("SharedTick is dropped, quit");
+                        return;
+                    }
+
+                    let mut subscribers = subscribers.lock().unwrap();
+                    subscribers.retain_mut(|sub| {
+                        if (&mut sub.cancel).now_or_never().is_some() {
+                            return false;
+                        }
+
+                        if !sub.enabled.load(Ordering::Relaxed) {
+                            return true;
+                        }
+
+                        sub.i += 1;
+                        sub.tx.send(Notification::Tick { i: sub.i }).is_ok()
+                    });
+                }
+            }
+        };
+
+        // False positive lint warning(`non-binding `let` on a future`): https://github.com/rust-lang/rust-clippy/issues/9932
+        #[allow(clippy::let_underscore_future)]
+        let _ = C::spawn(fut.instrument(tracing::span!(parent: &Span::current(), Level::DEBUG, "shared_tick")));
+
+        Self {
+            subscribers,
+            shutdown: Mutex::new(Some(shutdown)),
+        }
+    }
+
+    /// Stop the tick loop. No more `Tick` is sent to any subscriber.
+    pub(crate) fn shutdown(&self) {
+        if let Some(shutdown) = self.shutdown.lock().unwrap().take() {
+            let _ = shutdown.send(());
+        }
+    }
+
+    /// Start emitting `Tick` events to `tx`.
+    pub(crate) fn subscribe(&self, tx: MpscUnboundedSenderOf<C, Notification<C>>, enabled: bool) -> TickHandle<C> {
+        let enabled = Arc::new(AtomicBool::from(enabled));
+        let (shutdown, cancel) = C::oneshot();
+
+        self.subscribers.lock().unwrap().push(Subscriber {
+            tx,
+            enabled: enabled.clone(),
+            i: 0,
+            cancel,
+        });
+
+        TickHandle {
+            enabled,
+            shutdown: Mutex::new(Some(shutdown)),
+            join_handle: Mutex::new(None),
+        }
+    }
+}
+
 impl<C> TickHandle<C>
 where C: RaftTypeConfig
 {
@@ -166,6 +265,7 @@ mod tests {
 
     use tokio::time::Duration;
 
+    use crate::core::SharedTick;
     use crate::core::Tick;
     use crate::impls::TokioRuntime;
     use crate::type_config::TypeConfigExt;
@@ -210,4 +310,47 @@ mod tests {
 
         Ok(())
     }
+
+    #[tokio::test]
+    async fn test_shared_tick() -> anyhow::Result<()> {
+        let shared = SharedTick::<TickUTConfig>::spawn(Duration::from_millis(100));
+
+        let (tx1, mut rx1) = TickUTConfig::mpsc_unbounded();
+        let (tx2, mut rx2) = TickUTConfig::mpsc_unbounded();
+        let th1 = shared.subscribe(tx1, true);
+        let th2 = shared.subscribe(tx2, false);
+
+        TickUTConfig::sleep(Duration::from_millis(500)).await;
+        assert!(th1.shutdown().is_none(), "no task to join for a shared tick");
+        th2.enable(true);
+        TickUTConfig::sleep(Duration::from_millis(500)).await;
+        drop(th2);
+        TickUTConfig::sleep(Duration::from_millis(200)).await;
+
+        let mut received1 = vec![];
+        while let Ok(x) = rx1.try_recv() {
+            received1.push(x);
+        }
+        let mut received2 = vec![];
+        while let Ok(x) = rx2.try_recv() {
+            received2.push(x);
+        }
+
+        assert!(
+            (2..10).contains(&received1.len()),
+            "ticks stop after shutdown: {}",
+            received1.len()
+        );
+        assert!(
+            (2..10).contains(&received2.len()),
+            "ticks start when enabled: {}",
+            received2.len()
+        );
+
+        // Both subscribers are removed.
+        assert!(rx1.recv().await.is_none());
+        assert!(rx2.recv().await.is_none());
+
+        Ok(())
+    }
 }
@@ -11,6 +11,7 @@ use crate::engine::CommandKind;
 use crate::error::Infallible;
 use crate::error::InitializeError;
 use crate::error::InstallSnapshotError;
+use crate::log_id_range::LogIdRange;
 use crate::raft::message::TransferLeaderRequest;
 use crate::raft::AppendEntriesResponse;
 use crate::raft::InstallSnapshotResponse;
@@ -73,6 +74,12 @@ where C: RaftTypeConfig
         committed: Option<LogIdOf<C>>,
     },
 
+    /// Tell all other nodes that the Leader stops sending heartbeats, because the group is idle.
+    BroadcastHibernate {
+        session_id: ReplicationSessionId<C>,
+        last_log_id: Option<LogIdOf<C>>,
+    },
+
     /// Save the committed log id to [`RaftLogStorage`].
     ///
     /// Upon startup, the saved committed log ids will be re-applied to state machine to restore the
@@ -98,6 +105,16 @@ where C: RaftTypeConfig
     /// Replicate log entries or snapshot to a target.
     Replicate { target: C::NodeId, req: Replicate<C> },
 
+    /// Ask the follower `helper` to replicate the committed logs in `log_id_range` to `target`,
+    /// instead of the Leader.
+    ///
+    /// The progress of `target` is reported the same way as for a [`Command::Replicate`].
+    DelegateReplicate {
+        target: C::NodeId,
+        helper: C::NodeId,
+        log_id_range: LogIdRange<C>,
+    },
+
     /// Broadcast transfer Leader message to all other nodes.
     BroadcastTransferLeader { req: TransferLeaderRequest<C> },
 
@@ -162,6 +179,17 @@ where C: RaftTypeConfig
                     committed.display()
                 )
             }
+            Command::BroadcastHibernate {
+                session_id,
+                last_log_id,
+            } => {
+                write!(
+                    f,
+                    "BroadcastHibernate: session_id:{}, last_log_id:{}",
+                    session_id,
+                    last_log_id.display()
+                )
+            }
             Command::SaveCommitted { committed } => write!(f, "SaveCommitted: {}", committed),
             Command::Apply {
                 already_committed,
@@ -170,6 +198,17 @@ where C: RaftTypeConfig
             Command::Replicate { target, req } => {
                 write!(f, "Replicate: target={}, req: {}", target, req)
             }
+            Command::DelegateReplicate {
+                target,
+                helper,
+                log_id_range,
+            } => {
+                write!(
+                    f,
+                    "DelegateReplicate: target={}, helper={}, log_id_range: {}",
+                    target, helper, log_id_range
+                )
+            }
             Command::BroadcastTransferLeader { req } => write!(f, "TransferLeader: {}", req),
             Command::RebuildReplicationStreams { targets } => {
                 write!(f, "RebuildReplicationStreams: {}", targets.display_n::<10>())
@@ -205,9 +244,11 @@ where
             (Command::AppendInputEntries { committed_vote: vote, entries },    Command::AppendInputEntries { committed_vote: vb, entries: b }, )               => vote == vb && entries == b,
             (Command::ReplicateCommitted { committed },        Command::ReplicateCommitted { committed: b }, )                       =>  committed == b,
             (Command::BroadcastHeartbeat { session_id, committed }, Command::BroadcastHeartbeat { session_id: sb, committed: b }, )  => session_id == sb && committed == b,
+            (Command::BroadcastHibernate { session_id, last_log_id }, Command::BroadcastHibernate { session_id: sb, last_log_id: b }, ) => session_id == sb && last_log_id == b,
             (Command::SaveCommitted { committed },             Command::SaveCommitted { committed: b })                              => committed == b,
             (Command::Apply { already_committed, upto, },      Command::Apply { already_committed: b_committed, upto: b_upto, }, )  => already_committed == b_committed && upto == b_upto,
             (Command::Replicate { target, req },               Command::Replicate { target: b_target, req: other_req, }, )           => target == b_target && req == other_req,
+            (Command::DelegateReplicate { target, helper, log_id_range }, Command::DelegateReplicate { target: bt, helper: bh, log_id_range: br }, ) => target == bt && helper == bh && log_id_range == br,
             (Command::BroadcastTransferLeader { req },         Command::BroadcastTransferLeader { req: b, }, )                       => req == b,
             (Command::RebuildReplicationStreams { targets },   Command::RebuildReplicationStreams { targets: b }, )                  => targets == b,
             (Command::SaveVote { vote },                       Command::SaveVote { vote: b })                                        => vote == b,
@@ -243,7 +284,9 @@ where C: RaftTypeConfig
 
             Command::ReplicateCommitted { .. }        => CommandKind::Network,
             Command::BroadcastHeartbeat { .. }        => CommandKind::Network,
+            Command::BroadcastHibernate { .. }        => CommandKind::Network,
             Command::Replicate { .. }                 => CommandKind::Network,
+            Command::DelegateReplicate { .. }         => CommandKind::Network,
             Command::BroadcastTransferLeader { .. }            => CommandKind::Network,
             Command::SendVote { .. }                  => CommandKind::Network,
 
@@ -269,7 +312,9 @@ where C: RaftTypeConfig
 
             Command::ReplicateCommitted { .. }        => None,
             Command::BroadcastHeartbeat { .. }        => None,
+            Command::BroadcastHibernate { .. }        => None,
             Command::Replicate { .. }                 => None,
+            Command::DelegateReplicate { .. }         => None,
             Command::BroadcastTransferLeader { .. }            => None,
             Command::SendVote { .. }                  => None,
 
@@ -433,4 +478,10 @@ where
     pub(crate) fn send(self) {
         let _ = self.tx.send(self.value);
     }
+
+    /// Take the value without sending it, for a caller that delivers it by itself.
+    #[cfg_attr(not(feature = "sans-io"), allow(dead_code))]
+    pub(crate) fn into_value(self) -> T {
+        self.value
+    }
 }
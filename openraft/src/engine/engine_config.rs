@@ -27,6 +27,10 @@ pub(crate) struct EngineConfig<C: RaftTypeConfig> {
 
     pub(crate) allow_log_reversion: bool,
 
+    /// Delegate replication to a target lagging behind by more than this many committed entries
+    /// to an up-to-date follower. `0` disables delegation.
+    pub(crate) catch_up_delegation_threshold: u64,
+
     pub(crate) timer_config: time_state::Config,
 }
 
@@ -42,6 +46,7 @@ where C: RaftTypeConfig
             purge_batch_size: config.purge_batch_size,
             max_payload_entries: config.max_payload_entries,
             allow_log_reversion: config.get_allow_log_reversion(),
+            catch_up_delegation_threshold: config.catch_up_delegation_threshold,
 
             timer_config: time_state::Config {
                 election_timeout,
@@ -60,6 +65,7 @@ where C: RaftTypeConfig
             purge_batch_size: 256,
             max_payload_entries: 300,
             allow_log_reversion: false,
+            catch_up_delegation_threshold: 0,
             timer_config: time_state::Config::default(),
         }
     }
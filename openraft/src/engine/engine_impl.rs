@@ -21,6 +21,7 @@ use crate::engine::Command;
 use crate::engine::Condition;
 use crate::engine::EngineOutput;
 use crate::engine::Respond;
+use crate::entry::raft_entry_ext::RaftEntryExt;
 use crate::entry::RaftEntry;
 use crate::entry::RaftPayload;
 use crate::error::ForwardToLeader;
@@ -57,6 +58,7 @@ use crate::vote::RaftVote;
 use crate::LogIdOptionExt;
 use crate::Membership;
 use crate::RaftTypeConfig;
+use crate::StorageError;
 
 /// Raft protocol algorithm.
 ///
@@ -95,6 +97,9 @@ where C: RaftTypeConfig
 
     /// Output entry for the runtime.
     pub(crate) output: EngineOutput<C>,
+
+    /// The last error of a received entry that does not match its checksum.
+    pub(crate) last_checksum_error: Option<StorageError<C>>,
 }
 
 impl<C> Engine<C>
@@ -108,6 +113,7 @@ where C: RaftTypeConfig
             leader: None,
             candidate: None,
             output: EngineOutput::new(4096),
+            last_checksum_error: None,
         }
     }
 
@@ -395,11 +401,25 @@ where C: RaftTypeConfig
             func_name!()
         );
 
+        // Entries corrupted in transit are never persisted: only those before the first corrupted
+        // one are accepted, and the Leader resends the rest.
+        let mut entries = entries;
+        let corrupted = entries.iter().enumerate().find_map(|(i, ent)| ent.verify_checksum().err().map(|e| (i, e)));
+        let partial = corrupted.map(|(i, err)| {
+            tracing::error!(error = display(&err), "AppendEntries contains a corrupted entry");
+            self.last_checksum_error = Some(err);
+            entries.truncate(i);
+            entries.last().map(|ent| ent.log_id()).or(prev_log_id.clone())
+        });
+
         let res = self.append_entries(vote, prev_log_id, entries);
         let is_ok = res.is_ok();
 
         if let Some(tx) = tx {
-            let resp: AppendEntriesResponse<C> = res.into();
+            let resp = match (res, partial) {
+                (Ok(()), Some(matching)) => AppendEntriesResponse::PartialSuccess(matching),
+                (res, _) => res.into(),
+            };
 
             let condition = if is_ok {
                 Some(Condition::IOFlushed {
@@ -0,0 +1,103 @@
+use std::sync::Arc;
+use std::time::Duration;
+
+use maplit::btreeset;
+use pretty_assertions::assert_eq;
+
+use crate::engine::testing::log_id;
+use crate::engine::testing::UTConfig;
+use crate::engine::Command;
+use crate::engine::Engine;
+use crate::engine::LogIdList;
+use crate::progress::entry::ProgressEntry;
+use crate::progress::Progress;
+use crate::replication::ReplicationSessionId;
+use crate::type_config::TypeConfigExt;
+use crate::utime::Leased;
+use crate::vote::raft_vote::RaftVoteExt;
+use crate::EffectiveMembership;
+use crate::Membership;
+use crate::MembershipState;
+use crate::Vote;
+
+fn m123() -> Membership<UTConfig> {
+    Membership::<UTConfig>::new_with_defaults(vec![btreeset! {1,2,3}], [])
+}
+
+/// Leader 1 has logs up to 5, all committed.
+///
+/// Node 1 and 2 have all the logs, node 3 has logs up to `matching3`.
+fn eng(matching3: u64) -> Engine<UTConfig> {
+    let mut eng = Engine::testing_default(0);
+    eng.state.enable_validation(false); // Disable validation for incomplete state
+
+    eng.config.id = 1;
+    eng.state.vote = Leased::new(
+        UTConfig::<()>::now(),
+        Duration::from_millis(500),
+        Vote::new_committed(2, 1),
+    );
+    eng.state.log_ids = LogIdList::new([log_id(1, 1, 0), log_id(2, 1, 1), log_id(2, 1, 5)]);
+    eng.state.committed = Some(log_id(2, 1, 5));
+    eng.state.membership_state = MembershipState::new(
+        Arc::new(EffectiveMembership::new(Some(log_id(1, 1, 0)), m123())),
+        Arc::new(EffectiveMembership::new(Some(log_id(1, 1, 0)), m123())),
+    );
+
+    eng.testing_new_leader();
+    eng.output.take_commands();
+
+    let progress = &mut eng.leader.as_mut().unwrap().progress;
+    progress.update(&1, ProgressEntry::new(Some(log_id(2, 1, 5)))).unwrap();
+    progress.update(&2, ProgressEntry::new(Some(log_id(2, 1, 5)))).unwrap();
+    progress.update(&3, ProgressEntry::new(Some(log_id(2, 1, matching3)))).unwrap();
+
+    eng
+}
+
+#[test]
+fn test_hibernate() -> anyhow::Result<()> {
+    let mut eng = eng(5);
+
+    assert!(eng.leader_handler()?.hibernate());
+
+    assert!(eng.leader.as_ref().unwrap().hibernating);
+    assert_eq!(
+        vec![Command::BroadcastHibernate {
+            session_id: ReplicationSessionId::new(Vote::new(2, 1).into_committed(), Some(log_id(1, 1, 0))),
+            last_log_id: Some(log_id(2, 1, 5)),
+        }],
+        eng.output.take_commands()
+    );
+
+    // Already hibernating, nothing to send.
+    assert!(eng.leader_handler()?.hibernate());
+    assert_eq!(0, eng.output.take_commands().len());
+
+    Ok(())
+}
+
+#[test]
+fn test_hibernate_replication_pending() -> anyhow::Result<()> {
+    let mut eng = eng(4);
+
+    assert!(!eng.leader_handler()?.hibernate());
+
+    assert!(!eng.leader.as_ref().unwrap().hibernating);
+    assert_eq!(0, eng.output.take_commands().len());
+
+    Ok(())
+}
+
+#[test]
+fn test_hibernate_not_committed() -> anyhow::Result<()> {
+    let mut eng = eng(5);
+    eng.state.committed = Some(log_id(2, 1, 4));
+
+    assert!(!eng.leader_handler()?.hibernate());
+
+    assert!(!eng.leader.as_ref().unwrap().hibernating);
+    assert_eq!(0, eng.output.take_commands().len());
+
+    Ok(())
+}
@@ -5,6 +5,7 @@ use crate::engine::EngineOutput;
 use crate::entry::raft_entry_ext::RaftEntryExt;
 use crate::entry::RaftEntry;
 use crate::entry::RaftPayload;
+use crate::progress::Progress;
 use crate::proposer::Leader;
 use crate::proposer::LeaderQuorumSet;
 use crate::raft::message::TransferLeaderRequest;
@@ -20,6 +21,8 @@ mod append_entries_test;
 #[cfg(test)]
 mod get_read_log_id_test;
 #[cfg(test)]
+mod hibernate_test;
+#[cfg(test)]
 mod send_heartbeat_test;
 #[cfg(test)]
 mod transfer_leader_test;
@@ -107,6 +110,39 @@ where C: RaftTypeConfig
         });
     }
 
+    /// Stop sending heartbeats and tell other nodes to stop their election timers, because no
+    /// client request is received for a while.
+    ///
+    /// The Leader hibernates only when every log is committed and every other node has all of
+    /// them, so that no replication is pending. It returns whether the Leader hibernated.
+    pub(crate) fn hibernate(&mut self) -> bool {
+        if self.leader.hibernating {
+            return true;
+        }
+
+        let last_log_id = self.leader.last_log_id().cloned();
+
+        if self.state.committed() != last_log_id.as_ref() {
+            return false;
+        }
+
+        if self.leader.progress.iter().any(|(_, p)| p.matching() != last_log_id.as_ref()) {
+            return false;
+        }
+
+        self.leader.hibernating = true;
+
+        let membership_log_id = self.state.membership_state.effective().log_id();
+        let session_id = ReplicationSessionId::new(self.leader.committed_vote.clone(), membership_log_id.clone());
+
+        self.output.push_command(Command::BroadcastHibernate {
+            session_id,
+            last_log_id,
+        });
+
+        true
+    }
+
     /// Get the log id for a linearizable read.
     ///
     /// See: [Read Operation](crate::docs::protocol::read)
@@ -0,0 +1,221 @@
+use std::sync::Arc;
+use std::time::Duration;
+
+use maplit::btreeset;
+use pretty_assertions::assert_eq;
+
+use crate::engine::testing::log_id;
+use crate::engine::testing::UTConfig;
+use crate::engine::Command;
+use crate::engine::Engine;
+use crate::engine::LogIdList;
+use crate::log_id_range::LogIdRange;
+use crate::progress::entry::ProgressEntry;
+use crate::progress::Inflight;
+use crate::progress::Progress;
+use crate::replication::request::Replicate;
+use crate::type_config::TypeConfigExt;
+use crate::utime::Leased;
+use crate::EffectiveMembership;
+use crate::Membership;
+use crate::MembershipState;
+use crate::Vote;
+
+fn m123() -> Membership<UTConfig> {
+    Membership::<UTConfig>::new_with_defaults(vec![btreeset! {1,2,3}], [])
+}
+
+/// Leader 1 has logs up to 50, committed up to 40.
+///
+/// By default, node 2 has all the logs, node 3 has only the first one.
+fn eng() -> Engine<UTConfig> {
+    eng_with(
+        ProgressEntry::new(Some(log_id(2, 1, 50))),
+        ProgressEntry::new(Some(log_id(1, 1, 0))),
+    )
+}
+
+fn eng_with(p2: ProgressEntry<UTConfig>, p3: ProgressEntry<UTConfig>) -> Engine<UTConfig> {
+    let mut eng = Engine::testing_default(0);
+    eng.state.enable_validation(false); // Disable validation for incomplete state
+
+    eng.config.id = 1;
+    eng.config.catch_up_delegation_threshold = 10;
+    eng.state.vote = Leased::new(
+        UTConfig::<()>::now(),
+        Duration::from_millis(500),
+        Vote::new_committed(2, 1),
+    );
+    eng.state.log_ids = LogIdList::new([log_id(1, 1, 0), log_id(2, 1, 1), log_id(2, 1, 50)]);
+    eng.state.committed = Some(log_id(2, 1, 40));
+    eng.state.membership_state = MembershipState::new(
+        Arc::new(EffectiveMembership::new(Some(log_id(1, 1, 0)), m123())),
+        Arc::new(EffectiveMembership::new(Some(log_id(1, 1, 0)), m123())),
+    );
+
+    eng.testing_new_leader();
+    eng.output.take_commands();
+
+    let progress = &mut eng.leader.as_mut().unwrap().progress;
+    progress.update(&2, p2).unwrap();
+    progress.update(&3, p3).unwrap();
+
+    eng
+}
+
+#[test]
+fn test_delegate_catch_up() -> anyhow::Result<()> {
+    let mut eng = eng();
+
+    eng.replication_handler().initiate_replication();
+
+    let range = LogIdRange::new(Some(log_id(1, 1, 0)), Some(log_id(2, 1, 40)));
+    assert_eq!(
+        vec![Command::DelegateReplicate {
+            target: 3,
+            helper: 2,
+            log_id_range: range,
+        }],
+        eng.output.take_commands()
+    );
+
+    // The delegated logs are inflight, the rest are replicated after they are acknowledged.
+    let prog_entry = eng.leader.as_ref().unwrap().progress.get(&3);
+    assert_eq!(Inflight::logs(range.prev, range.last), prog_entry.inflight);
+
+    Ok(())
+}
+
+#[test]
+fn test_delegate_catch_up_disabled() -> anyhow::Result<()> {
+    let mut eng = eng();
+    eng.config.catch_up_delegation_threshold = 0;
+
+    eng.replication_handler().initiate_replication();
+
+    assert_eq!(
+        vec![Command::Replicate {
+            target: 3,
+            req: Replicate::logs(LogIdRange::new(Some(log_id(1, 1, 0)), Some(log_id(2, 1, 50)))),
+        }],
+        eng.output.take_commands()
+    );
+
+    Ok(())
+}
+
+#[test]
+fn test_delegate_catch_up_below_threshold() -> anyhow::Result<()> {
+    let mut eng = eng();
+    eng.config.catch_up_delegation_threshold = 41;
+
+    eng.replication_handler().initiate_replication();
+
+    assert_eq!(
+        vec![Command::Replicate {
+            target: 3,
+            req: Replicate::logs(LogIdRange::new(Some(log_id(1, 1, 0)), Some(log_id(2, 1, 50)))),
+        }],
+        eng.output.take_commands()
+    );
+
+    Ok(())
+}
+
+#[test]
+fn test_delegate_catch_up_matching_unknown() -> anyhow::Result<()> {
+    // The matching log of node 3 is being searched for.
+    let mut eng = eng_with(ProgressEntry::new(Some(log_id(2, 1, 50))), ProgressEntry::empty(51));
+
+    eng.replication_handler().initiate_replication();
+
+    assert_eq!(
+        vec![Command::Replicate {
+            target: 3,
+            req: Replicate::logs(LogIdRange::new(Some(log_id(2, 1, 23)), Some(log_id(2, 1, 50)))),
+        }],
+        eng.output.take_commands()
+    );
+
+    Ok(())
+}
+
+#[test]
+fn test_delegate_catch_up_no_helper() -> anyhow::Result<()> {
+    // Node 2 does not have all the committed logs.
+    let mut eng = eng_with(
+        ProgressEntry::new(Some(log_id(2, 1, 39))),
+        ProgressEntry::new(Some(log_id(1, 1, 0))),
+    );
+
+    eng.replication_handler().initiate_replication();
+
+    assert_eq!(
+        vec![
+            Command::Replicate {
+                target: 2,
+                req: Replicate::logs(LogIdRange::new(Some(log_id(2, 1, 39)), Some(log_id(2, 1, 50)))),
+            },
+            Command::Replicate {
+                target: 3,
+                req: Replicate::logs(LogIdRange::new(Some(log_id(1, 1, 0)), Some(log_id(2, 1, 50)))),
+            }
+        ],
+        eng.output.take_commands()
+    );
+
+    Ok(())
+}
+
+#[test]
+fn test_catch_up_done_partially() -> anyhow::Result<()> {
+    let mut eng = eng();
+
+    eng.replication_handler().initiate_replication();
+    eng.output.take_commands();
+
+    // The helper sent logs up to 30 and failed.
+    eng.replication_handler().catch_up_done(3, Some(log_id(2, 1, 30)));
+
+    let range = LogIdRange::new(Some(log_id(2, 1, 30)), Some(log_id(2, 1, 50)));
+    assert_eq!(
+        vec![Command::Replicate {
+            target: 3,
+            req: Replicate::logs(range),
+        }],
+        eng.output.take_commands()
+    );
+
+    let prog_entry = eng.leader.as_ref().unwrap().progress.get(&3);
+    assert_eq!(Some(&log_id(2, 1, 30)), prog_entry.matching());
+    assert_eq!(Inflight::logs(range.prev, range.last), prog_entry.inflight);
+
+    Ok(())
+}
+
+#[test]
+fn test_catch_up_done_declined() -> anyhow::Result<()> {
+    let mut eng = eng();
+
+    eng.replication_handler().initiate_replication();
+    eng.output.take_commands();
+
+    // The helper declined or the request timed out: the Leader sends all the logs, without
+    // delegating them again.
+    eng.replication_handler().catch_up_done(3, None);
+
+    let range = LogIdRange::new(Some(log_id(1, 1, 0)), Some(log_id(2, 1, 50)));
+    assert_eq!(
+        vec![Command::Replicate {
+            target: 3,
+            req: Replicate::logs(range),
+        }],
+        eng.output.take_commands()
+    );
+
+    let prog_entry = eng.leader.as_ref().unwrap().progress.get(&3);
+    assert_eq!(Some(&log_id(1, 1, 0)), prog_entry.matching());
+    assert_eq!(Inflight::logs(range.prev, range.last), prog_entry.inflight);
+
+    Ok(())
+}
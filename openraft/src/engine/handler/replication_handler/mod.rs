@@ -9,6 +9,7 @@ use crate::engine::EngineOutput;
 use crate::engine::ReplicationProgress;
 use crate::error::NodeNotFound;
 use crate::error::Operation;
+use crate::log_id_range::LogIdRange;
 use crate::progress;
 use crate::progress::entry::ProgressEntry;
 use crate::progress::Inflight;
@@ -31,6 +32,8 @@ use crate::ServerState;
 #[cfg(test)]
 mod append_membership_test;
 #[cfg(test)]
+mod delegate_catch_up_test;
+#[cfg(test)]
 mod update_matching_test;
 
 /// Handle replication operations.
@@ -310,6 +313,13 @@ where C: RaftTypeConfig
     pub(crate) fn initiate_replication(&mut self) {
         tracing::debug!(progress = debug(&self.leader.progress), "{}", func_name!());
 
+        // The matching log of every node, to choose one to delegate a catch-up to.
+        let matchings = if self.config.catch_up_delegation_threshold > 0 {
+            self.leader.progress.iter().map(|(id, p)| (id.clone(), p.matching().cloned())).collect::<Vec<_>>()
+        } else {
+            vec![]
+        };
+
         for (id, prog_entry) in self.leader.progress.iter_mut() {
             // TODO: update matching should be done here for leader
             //       or updating matching should be queued in commands?
@@ -322,7 +332,25 @@ where C: RaftTypeConfig
 
             match t {
                 Ok(inflight) => {
-                    Self::send_to_target(self.output, id, inflight);
+                    let delegate = Self::choose_catch_up_helper(self.config, self.state, id, inflight, &matchings);
+
+                    if let Some((helper, log_id_range)) = delegate {
+                        tracing::info!(
+                            target = display(&*id),
+                            helper = display(&helper),
+                            log_id_range = display(&log_id_range),
+                            "delegate catch-up"
+                        );
+
+                        prog_entry.inflight = Inflight::logs(log_id_range.prev.clone(), log_id_range.last.clone());
+                        self.output.push_command(Command::DelegateReplicate {
+                            target: id.clone(),
+                            helper,
+                            log_id_range,
+                        });
+                    } else {
+                        Self::send_to_target(self.output, id, inflight);
+                    }
                 }
                 Err(e) => {
                     tracing::debug!("no data to replicate for node-{}: current inflight: {:?}", id, e,);
@@ -331,6 +359,85 @@ where C: RaftTypeConfig
         }
     }
 
+    /// Choose a node other than the Leader and `target` to send the logs to `target`, and the
+    /// range of logs to send, if `target` lacks more than
+    /// [`EngineConfig::catch_up_delegation_threshold`] committed logs.
+    ///
+    /// Only the logs after the matching log of `target` are delegated, i.e., when the search for
+    /// the matching log is done. And only committed logs are delegated, because they are never
+    /// truncated and are the same on every node that has them. The helper must have all of them.
+    fn choose_catch_up_helper(
+        config: &EngineConfig<C>,
+        state: &RaftState<C>,
+        target: &C::NodeId,
+        inflight: &Inflight<C>,
+        matchings: &[(C::NodeId, Option<LogIdOf<C>>)],
+    ) -> Option<(C::NodeId, LogIdRange<C>)> {
+        let threshold = config.catch_up_delegation_threshold;
+        if threshold == 0 {
+            return None;
+        }
+
+        let Inflight::Logs { log_id_range } = inflight else {
+            return None;
+        };
+
+        let target_matching = matchings.iter().find(|(id, _)| id == target).map(|(_, m)| m.as_ref())?;
+        if log_id_range.prev.as_ref() != target_matching {
+            return None;
+        }
+
+        let committed = state.committed()?;
+        if committed.index() + 1 < log_id_range.prev.next_index() + threshold {
+            return None;
+        }
+
+        let (helper, _) = matchings
+            .iter()
+            .find(|(id, matching)| id != target && id != &config.id && matching.as_ref() >= Some(committed))?;
+
+        Some((
+            helper.clone(),
+            LogIdRange::new(log_id_range.prev.clone(), Some(committed.clone())),
+        ))
+    }
+
+    /// Update the progress of `target` when a catch-up delegated to another node is done, and send
+    /// the logs the helper did not send.
+    ///
+    /// `matching` is the last log the helper sent to `target`, or `None` if it sent nothing. The
+    /// rest is sent by the Leader itself, to not delegate it again to a helper that failed.
+    #[tracing::instrument(level = "debug", skip_all)]
+    pub(crate) fn catch_up_done(&mut self, target: C::NodeId, matching: Option<LogIdOf<C>>) {
+        tracing::debug!(
+            target = display(&target),
+            matching = display(matching.display()),
+            "{}",
+            func_name!()
+        );
+
+        if matching.is_some() {
+            self.update_matching(target.clone(), matching);
+        }
+
+        let Some(prog_entry) = self.leader.progress.get_mut(&target) else {
+            return;
+        };
+
+        prog_entry.inflight = Inflight::None;
+
+        match prog_entry.next_send(self.state, self.config.max_payload_entries) {
+            Ok(inflight) => {
+                Self::send_to_target(self.output, &target, inflight);
+            }
+            Err(e) => {
+                tracing::debug!("no data to replicate for node-{}: current inflight: {:?}", target, e);
+            }
+        }
+
+        self.try_purge_log();
+    }
+
     #[tracing::instrument(level = "debug", skip_all)]
     pub(crate) fn send_to_target(output: &mut EngineOutput<C>, target: &C::NodeId, inflight: &Inflight<C>) {
         let req = match inflight {
@@ -146,7 +146,8 @@ where C: RaftTypeConfig
         if self.state.is_leader(&self.config.id) {
             self.become_leader();
         } else if self.state.is_leading(&self.config.id) {
-            // candidate, nothing to do
+            // Candidate: a Leader that starts a new election is no longer the Leader of its term.
+            *self.leader = None;
         } else {
             self.become_following();
         }
@@ -114,6 +114,36 @@ fn test_elect_single_node_elect_again() -> anyhow::Result<()> {
     Ok(())
 }
 
+#[test]
+fn test_elect_leader_elect_again() -> anyhow::Result<()> {
+    tracing::info!("--- a leader that elects again quits leading its term");
+    {
+        let mut eng = eng();
+        eng.config.id = 1;
+        eng.state
+            .membership_state
+            .set_effective(Arc::new(EffectiveMembership::new(Some(log_id(0, 1, 1)), m12())));
+
+        eng.state.vote = Leased::new(
+            UTConfig::<()>::now(),
+            Duration::from_millis(500),
+            Vote::new_committed(1, 1),
+        );
+        eng.testing_new_leader();
+        eng.state.server_state = ServerState::Leader;
+
+        eng.elect();
+
+        assert_eq!(Vote::new(2, 1), *eng.state.vote_ref());
+        assert!(eng.leader.is_none());
+        assert!(eng.leader_handler().is_err());
+        assert_eq!(Vote::new(2, 1), *eng.candidate_ref().unwrap().vote_ref());
+
+        assert_eq!(ServerState::Candidate, eng.state.server_state);
+    }
+    Ok(())
+}
+
 #[test]
 fn test_elect_multi_node_enter_candidate() -> anyhow::Result<()> {
     tracing::info!("--- multi nodes: enter candidate state");
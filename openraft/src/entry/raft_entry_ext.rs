@@ -1,6 +1,9 @@
+use anyerror::AnyError;
+
 use crate::entry::RaftEntry;
 use crate::log_id::ref_log_id::RefLogId;
 use crate::RaftTypeConfig;
+use crate::StorageError;
 
 pub(crate) trait RaftEntryExt<C>: RaftEntry<C>
 where C: RaftTypeConfig
@@ -10,6 +13,32 @@ where C: RaftTypeConfig
         let (leader_id, index) = self.log_id_parts();
         RefLogId::new(leader_id, index)
     }
+
+    /// Compute and store the checksum, if this entry type supports it.
+    fn seal_checksum(&mut self) {
+        if let Some(checksum) = self.compute_checksum() {
+            self.set_checksum(checksum);
+        }
+    }
+
+    /// Verify the stored checksum, if there is one, against the content of this entry.
+    fn verify_checksum(&self) -> Result<(), StorageError<C>> {
+        let Some(expected) = self.checksum() else {
+            return Ok(());
+        };
+
+        let actual = self.compute_checksum();
+        if actual == Some(expected) {
+            return Ok(());
+        }
+
+        let err = AnyError::error(format!(
+            "checksum mismatch: stored: {:x}, computed: {:?}",
+            expected,
+            actual.map(|x| format!("{:x}", x))
+        ));
+        Err(StorageError::checksum(self.ref_log_id().into_log_id(), err))
+    }
 }
 
 impl<C, T> RaftEntryExt<C> for T
@@ -18,3 +47,101 @@ where
     T: RaftEntry<C>,
 {
 }
+
+#[cfg(test)]
+mod tests {
+    use std::fmt;
+
+    use crate::engine::testing::log_id;
+    use crate::engine::testing::UTConfig;
+    use crate::entry::raft_entry_ext::RaftEntryExt;
+    use crate::entry::RaftEntry;
+    use crate::entry::RaftPayload;
+    use crate::type_config::alias::CommittedLeaderIdOf;
+    use crate::type_config::alias::LogIdOf;
+    use crate::Entry;
+    use crate::EntryPayload;
+    use crate::ErrorSubject;
+    use crate::Membership;
+
+    /// An entry that carries a checksum of its log index and data.
+    #[derive(Debug, Clone)]
+    #[cfg_attr(feature = "serde", derive(serde::Deserialize, serde::Serialize))]
+    struct ChecksumEntry {
+        log_id: LogIdOf<UTConfig>,
+        data: u64,
+        checksum: Option<u64>,
+    }
+
+    impl fmt::Display for ChecksumEntry {
+        fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+            write!(f, "{}:{}", self.log_id, self.data)
+        }
+    }
+
+    impl RaftPayload<UTConfig> for ChecksumEntry {
+        fn get_membership(&self) -> Option<Membership<UTConfig>> {
+            None
+        }
+    }
+
+    impl RaftEntry<UTConfig> for ChecksumEntry {
+        fn new(log_id: LogIdOf<UTConfig>, _payload: EntryPayload<UTConfig>) -> Self {
+            Self {
+                log_id,
+                data: 0,
+                checksum: None,
+            }
+        }
+
+        fn log_id_parts(&self) -> (&CommittedLeaderIdOf<UTConfig>, u64) {
+            (&self.log_id.leader_id, self.log_id.index)
+        }
+
+        fn set_log_id(&mut self, new: LogIdOf<UTConfig>) {
+            self.log_id = new;
+        }
+
+        fn checksum(&self) -> Option<u64> {
+            self.checksum
+        }
+
+        fn compute_checksum(&self) -> Option<u64> {
+            Some(self.log_id.index.wrapping_mul(31) ^ self.data)
+        }
+
+        fn set_checksum(&mut self, checksum: u64) {
+            self.checksum = Some(checksum);
+        }
+    }
+
+    #[test]
+    fn test_verify_checksum() -> anyhow::Result<()> {
+        let mut ent = ChecksumEntry::new_blank(log_id(1, 1, 5));
+        ent.data = 7;
+
+        // Not sealed yet
+        ent.verify_checksum()?;
+
+        ent.seal_checksum();
+        assert_eq!(Some((5 * 31) ^ 7), ent.checksum);
+        ent.verify_checksum()?;
+
+        ent.data = 8;
+        let err = ent.verify_checksum().unwrap_err();
+        assert_eq!(&ErrorSubject::Checksum(log_id(1, 1, 5)), err.subject());
+
+        Ok(())
+    }
+
+    #[test]
+    fn test_verify_checksum_unsupported() -> anyhow::Result<()> {
+        let mut ent = Entry::<UTConfig>::new_blank(log_id(1, 1, 5));
+
+        ent.seal_checksum();
+        assert_eq!(None, ent.checksum());
+        ent.verify_checksum()?;
+
+        Ok(())
+    }
+}
@@ -47,6 +47,35 @@ where
     #[since(version = "0.10.0", change = "use owned argument log id")]
     fn set_log_id(&mut self, new: LogIdOf<C>);
 
+    /// Returns the checksum stored in this entry, or `None` if it does not carry one.
+    ///
+    /// An entry that carries a checksum is verified against [`Self::compute_checksum`] before it
+    /// is applied or sent to a follower. A mismatch is reported as a [`StorageError`] with
+    /// subject [`ErrorSubject::Checksum`]. A follower also verifies the entries it receives, and
+    /// does not persist a corrupted entry or any entry after it.
+    ///
+    /// By default an entry does not carry a checksum.
+    ///
+    /// [`StorageError`]: crate::StorageError
+    /// [`ErrorSubject::Checksum`]: crate::ErrorSubject::Checksum
+    #[since(version = "0.10.0")]
+    fn checksum(&self) -> Option<u64> {
+        None
+    }
+
+    /// Computes the checksum of the log id and payload of this entry.
+    ///
+    /// The Leader stores it with [`Self::set_checksum`] once a log id is assigned to a proposed
+    /// entry. Returns `None` if this entry type does not support checksums.
+    #[since(version = "0.10.0")]
+    fn compute_checksum(&self) -> Option<u64> {
+        None
+    }
+
+    /// Stores the checksum computed by [`Self::compute_checksum`] in this entry.
+    #[since(version = "0.10.0")]
+    fn set_checksum(&mut self, _checksum: u64) {}
+
     /// Create a new blank log entry.
     #[since(version = "0.10.0", change = "become a default method")]
     fn new_blank(log_id: LogIdOf<C>) -> Self
@@ -1,12 +1,14 @@
 //! Error types exposed by this crate.
 
 mod allow_next_revert_error;
+mod compression_error;
 pub mod decompose;
 pub mod into_ok;
 mod invalid_sm;
 mod membership_error;
 mod node_not_found;
 mod operation;
+mod overloaded;
 mod replication_closed;
 mod streaming_error;
 
@@ -19,10 +21,12 @@ use std::time::Duration;
 use anyerror::AnyError;
 
 pub use self::allow_next_revert_error::AllowNextRevertError;
+pub use self::compression_error::CompressionError;
 pub use self::invalid_sm::InvalidStateMachineType;
 pub use self::membership_error::MembershipError;
 pub use self::node_not_found::NodeNotFound;
 pub use self::operation::Operation;
+pub use self::overloaded::Overloaded;
 pub use self::replication_closed::ReplicationClosed;
 pub use self::streaming_error::StreamingError;
 use crate::network::RPCTypes;
@@ -181,6 +185,7 @@ where C: RaftTypeConfig
 #[derive(Debug, Clone, thiserror::Error, derive_more::TryInto)]
 #[derive(PartialEq, Eq)]
 #[cfg_attr(feature = "serde", derive(serde::Deserialize, serde::Serialize), serde(bound = ""))]
+#[non_exhaustive]
 pub enum ClientWriteError<C>
 where C: RaftTypeConfig
 {
@@ -190,6 +195,10 @@ where C: RaftTypeConfig
     /// When writing a change-membership entry.
     #[error(transparent)]
     ChangeMembershipError(#[from] ChangeMembershipError<C>),
+
+    /// The Leader does not accept more writes for now, the write can be retried later.
+    #[error(transparent)]
+    Overloaded(#[from] Overloaded),
 }
 
 impl<C> TryAsRef<ForwardToLeader<C>> for ClientWriteError<C>
@@ -405,7 +414,8 @@ impl Unreachable {
 /// If the request cannot be divided(contains only one entry), Openraft interprets it as
 /// [`Unreachable`].
 ///
-/// A hint can be provided to help Openraft in splitting the request.
+/// A hint can be provided to help Openraft in splitting the request. The replication to the
+/// target keeps sending no more than the hint, and only occasionally probes for a larger batch.
 ///
 /// The application should also set an appropriate value for [`Config::max_payload_entries`]  to
 /// avoid returning this error if possible.
@@ -457,18 +467,14 @@ impl fmt::Display for PayloadTooLarge {
 
         write!(f, " hint:(")?;
         match self.action {
-            RPCTypes::Vote => {
-                unreachable!("vote rpc should not have payload")
-            }
             RPCTypes::AppendEntries => {
                 write!(f, "entries:{}", self.entries_hint)?;
             }
             RPCTypes::InstallSnapshot => {
                 write!(f, "bytes:{}", self.bytes_hint)?;
             }
-            RPCTypes::TransferLeader => {
-                unreachable!("TransferLeader rpc should not have payload")
-            }
+            // Other RPCs have no payload to shrink.
+            _ => {}
         }
         write!(f, ")")?;
 
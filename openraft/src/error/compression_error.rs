@@ -0,0 +1,30 @@
+use anyerror::AnyError;
+
+use crate::network::compression::Compression;
+
+/// Data can not be compressed or decompressed with a [`Compression`] algorithm.
+///
+/// It is also returned if the algorithm is not enabled by its feature flag.
+#[derive(Debug, Clone, PartialEq, Eq, thiserror::Error)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+#[error("Compression error with {compression}: {source}")]
+pub struct CompressionError {
+    pub compression: Compression,
+    source: AnyError,
+}
+
+impl CompressionError {
+    pub fn new<E: std::error::Error + 'static>(compression: Compression, e: &E) -> Self {
+        Self {
+            compression,
+            source: AnyError::new(e),
+        }
+    }
+
+    pub(crate) fn unsupported(compression: Compression) -> Self {
+        Self {
+            compression,
+            source: AnyError::error(format!("{} is not enabled", compression)),
+        }
+    }
+}
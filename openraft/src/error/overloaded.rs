@@ -0,0 +1,22 @@
+/// A client write is rejected because the Leader is overloaded.
+///
+/// The write is not proposed, and it is safe to retry it later.
+#[derive(Debug, Clone, PartialEq, Eq, thiserror::Error)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+#[error("Overloaded: {reason}")]
+pub struct Overloaded {
+    reason: String,
+}
+
+impl Overloaded {
+    pub fn new(reason: impl ToString) -> Self {
+        Self {
+            reason: reason.to_string(),
+        }
+    }
+
+    /// Why the Leader is overloaded.
+    pub fn reason(&self) -> &str {
+        &self.reason
+    }
+}
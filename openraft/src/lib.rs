@@ -64,8 +64,13 @@ pub mod instant;
 pub mod log_id;
 pub mod membership;
 pub mod metrics;
+pub mod multi_raft;
 pub mod network;
 pub mod raft;
+#[cfg(feature = "sans-io")]
+pub mod sans_io;
+#[cfg(feature = "session")]
+pub mod session;
 pub mod storage;
 pub mod testing;
 pub mod type_config;
@@ -56,3 +56,6 @@ pub(crate) type ReplicationMetrics<C> = BTreeMap<NodeIdOf<C>, Option<LogIdOf<C>>
 /// Heartbeat metrics, a mapping between a node's ID and the time of the last
 /// acknowledged heartbeat or replication to this node.
 pub(crate) type HeartbeatMetrics<C> = BTreeMap<NodeIdOf<C>, Option<SerdeInstantOf<C>>>;
+/// Batch size metrics, a mapping between a node's ID and the number of entries the leader
+/// currently sends to it in one AppendEntries RPC.
+pub(crate) type ReplicationBatchMetrics<C> = BTreeMap<NodeIdOf<C>, u64>;
@@ -1,3 +1,4 @@
+use std::collections::BTreeMap;
 use std::fmt;
 use std::sync::Arc;
 
@@ -6,14 +7,17 @@ use crate::display_ext::DisplayBTreeMapOptValue;
 use crate::display_ext::DisplayOption;
 use crate::error::Fatal;
 use crate::metrics::HeartbeatMetrics;
+use crate::metrics::ReplicationBatchMetrics;
 use crate::metrics::ReplicationMetrics;
 use crate::metrics::SerdeInstant;
+use crate::network::ProtocolVersion;
 use crate::type_config::alias::InstantOf;
 use crate::type_config::alias::LogIdOf;
 use crate::type_config::alias::SerdeInstantOf;
 use crate::type_config::alias::VoteOf;
 use crate::Instant;
 use crate::RaftTypeConfig;
+use crate::StorageError;
 use crate::StoredMembership;
 
 /// A set of metrics describing the current state of a Raft node.
@@ -50,6 +54,24 @@ pub struct RaftMetrics<C: RaftTypeConfig> {
     /// already been deleted.
     pub purged: Option<LogIdOf<C>>,
 
+    /// The number of committed log entries that are not yet applied to the state machine.
+    ///
+    /// See [`Config::max_apply_lag`].
+    ///
+    /// [`Config::max_apply_lag`]: crate::Config::max_apply_lag
+    pub apply_lag: u64,
+
+    /// The total time in milliseconds this node has been throttling client writes because the
+    /// state machine lags behind, since this node started.
+    pub apply_throttled_ms: u64,
+
+    /// The number of client writes submitted to this node that are not yet responded.
+    ///
+    /// See [`Config::max_in_flight_proposals`].
+    ///
+    /// [`Config::max_in_flight_proposals`]: crate::Config::max_in_flight_proposals
+    pub in_flight_proposals: u64,
+
     // ---
     // --- cluster ---
     // ---
@@ -106,11 +128,45 @@ pub struct RaftMetrics<C: RaftTypeConfig> {
     /// higher possibility of that.
     pub heartbeat: Option<HeartbeatMetrics<C>>,
 
+    /// The protocol versions of the cluster members this node has exchanged a handshake with.
+    ///
+    /// A member whose version is not known yet is absent. See [`Self::is_mixed_version()`].
+    pub peer_versions: BTreeMap<C::NodeId, ProtocolVersion>,
+
     // ---
     // --- replication ---
     // ---
     /// The replication states. It is Some() only when this node is leader.
     pub replication: Option<ReplicationMetrics<C>>,
+
+    /// The number of entries sent in one AppendEntries RPC to each follower and learner.
+    /// It is Some() only when this node is leader.
+    ///
+    /// It is adapted to each target: it shrinks when the target rejects a payload or replies
+    /// slowly, and grows back up to [`Config::max_payload_entries`] when it keeps up.
+    ///
+    /// [`Config::max_payload_entries`]: crate::Config::max_payload_entries
+    pub replication_batch_size: Option<ReplicationBatchMetrics<C>>,
+
+    /// The number of times a replication stream read log entries from the Leader's cache of
+    /// recently appended entries, since this node started.
+    ///
+    /// See [`Config::log_cache_size`].
+    ///
+    /// [`Config::log_cache_size`]: crate::Config::log_cache_size
+    pub log_cache_hits: u64,
+
+    /// The number of times a replication stream had to read log entries from the log store,
+    /// because they are not in the Leader's cache, since this node started.
+    pub log_cache_misses: u64,
+
+    /// The last error of a log entry received from the Leader that does not match its checksum.
+    ///
+    /// Its subject is [`ErrorSubject::Checksum`]. Such an entry is corrupted in transit: it is not
+    /// persisted and the Leader sends it again.
+    ///
+    /// [`ErrorSubject::Checksum`]: crate::ErrorSubject::Checksum
+    pub last_checksum_error: Option<StorageError<C>>,
 }
 
 impl<C> fmt::Display for RaftMetrics<C>
@@ -121,13 +177,16 @@ where C: RaftTypeConfig
 
         write!(
             f,
-            "id:{}, {:?}, term:{}, vote:{}, last_log:{}, last_applied:{}, leader:{}",
+            "id:{}, {:?}, term:{}, vote:{}, last_log:{}, last_applied:{}, apply_lag:{}, apply_throttled_ms:{}, in_flight_proposals:{}, leader:{}",
             self.id,
             self.state,
             self.current_term,
             self.vote,
             DisplayOption(&self.last_log_index),
             DisplayOption(&self.last_applied),
+            self.apply_lag,
+            self.apply_throttled_ms,
+            self.in_flight_proposals,
             DisplayOption(&self.current_leader),
         )?;
 
@@ -153,6 +212,36 @@ where C: RaftTypeConfig
             DisplayOption(&self.heartbeat.as_ref().map(DisplayBTreeMapOptValue)),
         )?;
 
+        write!(f, ", peer_versions:{{")?;
+        for (i, (id, version)) in self.peer_versions.iter().enumerate() {
+            if i > 0 {
+                write!(f, ",")?;
+            }
+            write!(f, "{}:{}", id, version)?;
+        }
+        write!(f, "}}")?;
+
+        if let Some(batch_size) = &self.replication_batch_size {
+            write!(f, ", replication_batch_size:{{")?;
+            for (i, (id, n)) in batch_size.iter().enumerate() {
+                if i > 0 {
+                    write!(f, ",")?;
+                }
+                write!(f, "{}:{}", id, n)?;
+            }
+            write!(f, "}}")?;
+        }
+
+        write!(
+            f,
+            ", log_cache:{{hits:{},misses:{}}}",
+            self.log_cache_hits, self.log_cache_misses
+        )?;
+
+        if let Some(err) = &self.last_checksum_error {
+            write!(f, ", last_checksum_error:{}", err)?;
+        }
+
         write!(f, "}}")?;
         Ok(())
     }
@@ -173,6 +262,9 @@ where C: RaftTypeConfig
             last_applied: None,
             snapshot: None,
             purged: None,
+            apply_lag: 0,
+            apply_throttled_ms: 0,
+            in_flight_proposals: 0,
 
             state: ServerState::Follower,
             current_leader: None,
@@ -180,9 +272,21 @@ where C: RaftTypeConfig
             last_quorum_acked: None,
             membership_config: Arc::new(StoredMembership::default()),
             replication: None,
+            replication_batch_size: None,
+            log_cache_hits: 0,
+            log_cache_misses: 0,
+            last_checksum_error: None,
             heartbeat: None,
+            peer_versions: BTreeMap::new(),
         }
     }
+
+    /// Returns true if any known peer speaks a protocol version other than this node's.
+    ///
+    /// It is the case during a rolling upgrade, while some nodes still run an older Openraft.
+    pub fn is_mixed_version(&self) -> bool {
+        self.peer_versions.values().any(|v| *v != ProtocolVersion::CURRENT)
+    }
 }
 
 /// Subset of RaftMetrics, only include data-related metrics
@@ -262,9 +262,17 @@ where C: RaftTypeConfig {
         last_quorum_acked: None,
         membership_config: Arc::new(StoredMembership::new(None, Membership::default())),
         heartbeat: None,
+        peer_versions: Default::default(),
 
         snapshot: None,
         replication: None,
+        replication_batch_size: None,
+        log_cache_hits: 0,
+        log_cache_misses: 0,
+        last_checksum_error: None,
+        apply_lag: 0,
+        apply_throttled_ms: 0,
+        in_flight_proposals: 0,
     };
     let (tx, rx) = C::watch_channel(init.clone());
     let w = Wait {
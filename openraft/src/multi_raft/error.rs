@@ -0,0 +1,38 @@
+use std::fmt::Display;
+
+/// A request is routed to a group that is not running on a [`MultiRaft`] host.
+///
+/// [`MultiRaft`]: crate::multi_raft::MultiRaft
+#[derive(Debug, Clone, PartialEq, Eq, thiserror::Error)]
+#[cfg_attr(feature = "serde", derive(serde::Deserialize, serde::Serialize))]
+#[error("Raft group {group} not found")]
+pub struct GroupNotFound<G>
+where G: Display
+{
+    pub group: G,
+}
+
+impl<G> GroupNotFound<G>
+where G: Display
+{
+    pub fn new(group: G) -> Self {
+        Self { group }
+    }
+}
+
+/// An error returned by a [`MultiRaft`] host when routing a request to a group.
+///
+/// [`MultiRaft`]: crate::multi_raft::MultiRaft
+#[derive(Debug, Clone, PartialEq, Eq, thiserror::Error)]
+#[cfg_attr(feature = "serde", derive(serde::Deserialize, serde::Serialize))]
+pub enum MultiRaftError<G, E>
+where G: Display
+{
+    /// The group is not running on the host.
+    #[error(transparent)]
+    GroupNotFound(#[from] GroupNotFound<G>),
+
+    /// The `Raft` of the group returned an error.
+    #[error(transparent)]
+    Raft(E),
+}
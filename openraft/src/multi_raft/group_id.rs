@@ -0,0 +1,15 @@
+use std::fmt::Debug;
+use std::fmt::Display;
+
+use crate::OptionalSend;
+use crate::OptionalSync;
+
+/// Identifies a Raft group in a [`MultiRaft`](crate::multi_raft::MultiRaft) host.
+///
+/// It is automatically implemented for every type that satisfies the bounds.
+pub trait GroupId
+where Self: Sized + Ord + Clone + Debug + Display + OptionalSend + OptionalSync + 'static
+{
+}
+
+impl<T> GroupId for T where T: Sized + Ord + Clone + Debug + Display + OptionalSend + OptionalSync + 'static {}
@@ -0,0 +1,208 @@
+use std::collections::BTreeMap;
+use std::sync::Arc;
+use std::sync::Mutex;
+use std::time::Duration;
+
+use tracing::Instrument;
+use tracing::Level;
+
+use crate::async_runtime::mutex::Mutex as AsyncMutex;
+use crate::core::SharedTick;
+use crate::error::Fatal;
+use crate::error::RaftError;
+use crate::multi_raft::GroupId;
+use crate::multi_raft::GroupNotFound;
+use crate::multi_raft::MultiRaftError;
+use crate::multi_raft::MultiRaftNetwork;
+use crate::multi_raft::MultiRaftStorage;
+use crate::network::batch::fan_out;
+use crate::network::batch::BatchResponse;
+use crate::raft::AppendEntriesRequest;
+use crate::raft::AppendEntriesResponse;
+use crate::raft::SnapshotResponse;
+use crate::raft::VoteRequest;
+use crate::raft::VoteResponse;
+use crate::type_config::alias::MutexOf;
+use crate::type_config::alias::VoteOf;
+use crate::type_config::TypeConfigExt;
+use crate::Config;
+use crate::Raft;
+use crate::RaftTypeConfig;
+use crate::Snapshot;
+
+/// Hosts the Raft groups of a node.
+///
+/// The groups share one ticker. See the [module docs](crate::multi_raft) for what else can be
+/// shared by the application.
+pub struct MultiRaft<C, G, N, S>
+where
+    C: RaftTypeConfig,
+    G: GroupId,
+    N: MultiRaftNetwork<C, G>,
+    S: MultiRaftStorage<C, G>,
+{
+    id: C::NodeId,
+    network: N,
+    storage: S,
+
+    groups: Mutex<BTreeMap<G, Raft<C>>>,
+
+    /// Serializes adding and removing groups, which await storage IO.
+    group_change: MutexOf<C, ()>,
+
+    tick: SharedTick<C>,
+}
+
+impl<C, G, N, S> MultiRaft<C, G, N, S>
+where
+    C: RaftTypeConfig,
+    G: GroupId,
+    N: MultiRaftNetwork<C, G>,
+    S: MultiRaftStorage<C, G>,
+{
+    /// Create a host for node `id`, with no group.
+    ///
+    /// Every group is ticked each `tick_interval`, regardless of its `heartbeat_interval`. It is
+    /// usually set to the smallest `heartbeat_interval` of the groups.
+    ///
+    /// It must be called in the context of the async runtime, to spawn the tick task.
+    pub fn new(id: C::NodeId, tick_interval: Duration, network: N, storage: S) -> Self {
+        Self {
+            id,
+            network,
+            storage,
+            groups: Mutex::new(BTreeMap::new()),
+            group_change: C::mutex(()),
+            tick: SharedTick::spawn(tick_interval),
+        }
+    }
+
+    /// The id of this node, shared by all the groups.
+    pub fn id(&self) -> &C::NodeId {
+        &self.id
+    }
+
+    /// Open the storage of `group` and start its [`Raft`].
+    ///
+    /// If the group is already running, its `Raft` is returned and `config` is ignored.
+    pub async fn add_group(&self, group: G, config: Arc<Config>) -> Result<Raft<C>, Fatal<C>> {
+        let _guard = self.group_change.lock().await;
+
+        if let Some(raft) = self.group(&group) {
+            return Ok(raft);
+        }
+
+        tracing::info!(group = display(&group), "MultiRaft: add group");
+
+        let (log_store, state_machine) = self.storage.open_group(&group).await?;
+        let network = self.network.group_network(&group);
+
+        let span = tracing::span!(Level::DEBUG, "group", id = display(&group));
+        let raft = Raft::new_with_tick(
+            self.id.clone(),
+            config,
+            network,
+            log_store,
+            state_machine,
+            Some(&self.tick),
+        )
+        .instrument(span)
+        .await?;
+
+        self.groups.lock().unwrap().insert(group, raft.clone());
+        Ok(raft)
+    }
+
+    /// Shut down the [`Raft`] of `group` and destroy its storage.
+    ///
+    /// The storage is destroyed even if the group is not running on this host.
+    pub async fn remove_group(&self, group: &G) -> Result<(), Fatal<C>> {
+        let _guard = self.group_change.lock().await;
+
+        tracing::info!(group = display(group), "MultiRaft: remove group");
+
+        let raft = self.groups.lock().unwrap().remove(group);
+
+        if let Some(raft) = raft {
+            if let Err(e) = raft.shutdown().await {
+                tracing::error!(group = display(group), "error when shutting down group: {}", e);
+            }
+        }
+
+        self.storage.destroy_group(group).await?;
+        Ok(())
+    }
+
+    /// Get the [`Raft`] of `group`, if it is running on this host.
+    pub fn group(&self, group: &G) -> Option<Raft<C>> {
+        self.groups.lock().unwrap().get(group).cloned()
+    }
+
+    /// The ids of all running groups.
+    pub fn groups(&self) -> Vec<G> {
+        self.groups.lock().unwrap().keys().cloned().collect()
+    }
+
+    /// Get the [`Raft`] of `group`, or a [`GroupNotFound`] error if it is not running on this
+    /// host.
+    fn route<E>(&self, group: &G) -> Result<Raft<C>, MultiRaftError<G, E>> {
+        self.group(group).ok_or_else(|| GroupNotFound::new(group.clone()).into())
+    }
+
+    /// Route an AppendEntries RPC to `group`.
+    pub async fn append_entries(
+        &self,
+        group: &G,
+        rpc: AppendEntriesRequest<C>,
+    ) -> Result<AppendEntriesResponse<C>, MultiRaftError<G, RaftError<C>>> {
+        let raft = self.route(group)?;
+        raft.append_entries(rpc).await.map_err(MultiRaftError::Raft)
+    }
+
+    /// Route a Vote RPC to `group`.
+    pub async fn vote(
+        &self,
+        group: &G,
+        rpc: VoteRequest<C>,
+    ) -> Result<VoteResponse<C>, MultiRaftError<G, RaftError<C>>> {
+        let raft = self.route(group)?;
+        raft.vote(rpc).await.map_err(MultiRaftError::Raft)
+    }
+
+    /// Route a snapshot to `group`.
+    pub async fn install_full_snapshot(
+        &self,
+        group: &G,
+        vote: VoteOf<C>,
+        snapshot: Snapshot<C>,
+    ) -> Result<SnapshotResponse<C>, MultiRaftError<G, Fatal<C>>> {
+        let raft = self.route(group)?;
+        raft.install_full_snapshot(vote, snapshot).await.map_err(MultiRaftError::Raft)
+    }
+
+    /// Route every AppendEntries in a batch sent by an
+    /// [`AppendEntriesBatcher`](crate::network::batch::AppendEntriesBatcher) to its group.
+    ///
+    /// A [`BatchResponse`] carries only a [`RaftError`] for each request, thus a request to a group
+    /// that is not running on this host responds with [`Fatal::Stopped`], as [`fan_out()`] does.
+    pub async fn handle_batch(&self, batch: Vec<(G, AppendEntriesRequest<C>)>) -> BatchResponse<C> {
+        fan_out(batch, |group| self.group(group)).await
+    }
+
+    /// Shut down all groups and the shared tick.
+    ///
+    /// The storage of the groups is kept.
+    pub async fn shutdown(&self) {
+        let _guard = self.group_change.lock().await;
+
+        let groups = std::mem::take(&mut *self.groups.lock().unwrap());
+
+        for (group, raft) in groups {
+            if let Err(e) = raft.shutdown().await {
+                tracing::error!(group = display(&group), "error when shutting down group: {}", e);
+            }
+        }
+
+        self.tick.shutdown();
+    }
+}
@@ -0,0 +1,38 @@
+//! Run many Raft groups in one process.
+//!
+//! A [`MultiRaft`] host owns the [`Raft`] instances of the groups on a node, keyed by a group id.
+//! Groups are added and removed at runtime with [`MultiRaft::add_group()`] and
+//! [`MultiRaft::remove_group()`], and inbound RPCs are routed to their group with
+//! [`MultiRaft::append_entries()`], [`MultiRaft::vote()`], [`MultiRaft::handle_batch()`], etc. A
+//! request to a group that is not running on the host fails with [`GroupNotFound`].
+//!
+//! ## Scope
+//!
+//! The only resource the host itself shares among the groups is the ticker: one task emits the
+//! election `Tick` of every group, instead of one task per group. Every group still runs its own
+//! `RaftCore`, state machine worker, heartbeat and replication tasks.
+//!
+//! Openraft does not provide a log engine shared by the groups, nor a network that multiplexes the
+//! groups over one connection, in the same way as it does not provide a storage or a network for
+//! a single group. The host only defines where an application plugs them in:
+//! - A [`MultiRaftNetwork`] builds the [`RaftNetworkFactory`] of each group. It is up to the
+//!   application to tag the RPCs with the group id and to share connections to a remote node, e.g.,
+//!   with an [`AppendEntriesBatcher`](crate::network::batch::AppendEntriesBatcher).
+//! - A [`MultiRaftStorage`] opens the log store and state machine of each group. It is up to the
+//!   application to back them with one underlying storage engine.
+//!
+//! [`Raft`]: crate::Raft
+//! [`RaftNetworkFactory`]: crate::network::RaftNetworkFactory
+
+mod error;
+mod group_id;
+mod host;
+mod network;
+mod storage;
+
+pub use error::GroupNotFound;
+pub use error::MultiRaftError;
+pub use group_id::GroupId;
+pub use host::MultiRaft;
+pub use network::MultiRaftNetwork;
+pub use storage::MultiRaftStorage;
@@ -0,0 +1,21 @@
+use crate::multi_raft::GroupId;
+use crate::OptionalSend;
+use crate::OptionalSync;
+use crate::RaftNetworkFactory;
+use crate::RaftTypeConfig;
+
+/// Builds the network of every group in a [`MultiRaft`](crate::multi_raft::MultiRaft) host.
+///
+/// The returned [`RaftNetworkFactory`] is used by one group only. It must tag every RPC it sends
+/// with `group`, so that the receiving host can route it to the same group.
+pub trait MultiRaftNetwork<C, G>: OptionalSend + OptionalSync + 'static
+where
+    C: RaftTypeConfig,
+    G: GroupId,
+{
+    /// The network of a single group.
+    type Factory: RaftNetworkFactory<C>;
+
+    /// Build the network for `group`.
+    fn group_network(&self, group: &G) -> Self::Factory;
+}
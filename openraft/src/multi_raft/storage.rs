@@ -0,0 +1,35 @@
+use openraft_macros::add_async_trait;
+
+use crate::multi_raft::GroupId;
+use crate::storage::RaftLogStorage;
+use crate::storage::RaftStateMachine;
+use crate::OptionalSend;
+use crate::OptionalSync;
+use crate::RaftTypeConfig;
+use crate::StorageError;
+
+/// Opens the storage of every group in a [`MultiRaft`](crate::multi_raft::MultiRaft) host.
+///
+/// An implementation usually keeps the data of all groups in one storage engine, with keys
+/// prefixed by the group id, and returns a lightweight handle bound to one group.
+#[add_async_trait]
+pub trait MultiRaftStorage<C, G>: OptionalSend + OptionalSync + 'static
+where
+    C: RaftTypeConfig,
+    G: GroupId,
+{
+    /// The log store of a single group.
+    type LogStorage: RaftLogStorage<C>;
+
+    /// The state machine of a single group.
+    type StateMachine: RaftStateMachine<C>;
+
+    /// Open the log store and state machine of `group`, creating them if they do not exist.
+    async fn open_group(&self, group: &G) -> Result<(Self::LogStorage, Self::StateMachine), StorageError<C>>;
+
+    /// Remove all data of `group`.
+    ///
+    /// It is called after the `Raft` of the group is shut down. It should succeed if the group has
+    /// no data.
+    async fn destroy_group(&self, group: &G) -> Result<(), StorageError<C>>;
+}
@@ -0,0 +1,414 @@
+//! Coalescing of the AppendEntries RPCs of many Raft groups running in one process.
+//!
+//! With many [`Raft`] instances, one per group, in a process, every group sends its own heartbeat
+//! to every peer, which is `groups × peers` RPCs each `heartbeat_interval`. An
+//! [`AppendEntriesBatcher`] shared by all the groups collects the AppendEntries RPCs to the same
+//! destination process for a short while, and sends them in one RPC with a [`BatchTransport`]. The
+//! receiving process passes the batch to [`fan_out()`], which dispatches every request to the
+//! `Raft` of its group.
+//!
+//! Heartbeats are AppendEntries without entries, and are sent by a connection built with
+//! [`RaftNetworkFactory::new_heartbeat_client()`], so that an application can batch only them.
+//! Small AppendEntries are batched too if [`BatchConfig::max_entries`] is set.
+//!
+//! Only the messages on the wire are batched: every group still runs its own heartbeat timer and
+//! replication tasks, and builds and handles its own requests and responses. The batcher saves the
+//! RPC round trips, not the per-group work.
+//!
+//! ```ignore
+//! impl RaftNetworkV2<C> for GroupConnection {
+//!     async fn append_entries(&mut self, rpc: AppendEntriesRequest<C>, option: RPCOption)
+//!         -> Result<AppendEntriesResponse<C>, RPCError<C>> {
+//!         if self.batcher.accepts(&rpc) {
+//!             return self.batcher.append_entries(self.target_addr.clone(), self.group_id, rpc, option).await;
+//!         }
+//!         self.inner.append_entries(rpc, option).await
+//!     }
+//!     // ...
+//! }
+//! ```
+//!
+//! [`Raft`]: crate::Raft
+//! [`RaftNetworkFactory::new_heartbeat_client()`]: crate::network::RaftNetworkFactory::new_heartbeat_client
+
+use std::collections::BTreeMap;
+use std::sync::Arc;
+use std::time::Duration;
+
+use anyerror::AnyError;
+use futures::FutureExt;
+use openraft_macros::add_async_trait;
+
+use crate::async_runtime::MpscUnboundedReceiver;
+use crate::async_runtime::MpscUnboundedSender;
+use crate::async_runtime::OneshotSender;
+use crate::error::Fatal;
+use crate::error::NetworkError;
+use crate::error::RPCError;
+use crate::error::RaftError;
+use crate::error::Unreachable;
+use crate::network::RPCOption;
+use crate::raft::AppendEntriesRequest;
+use crate::raft::AppendEntriesResponse;
+use crate::type_config::alias::MpscUnboundedReceiverOf;
+use crate::type_config::alias::MpscUnboundedSenderOf;
+use crate::type_config::alias::OneshotSenderOf;
+use crate::type_config::TypeConfigExt;
+use crate::OptionalSend;
+use crate::OptionalSync;
+use crate::Raft;
+use crate::RaftTypeConfig;
+
+/// The result of every AppendEntries in a batch, in the order of the requests.
+pub type BatchResponse<C> = Vec<Result<AppendEntriesResponse<C>, RaftError<C>>>;
+
+/// Sends a batch of AppendEntries of groups `G` to a destination process `D` in one RPC.
+///
+/// The receiving process should pass the batch to [`fan_out()`] and send back what it returns.
+#[add_async_trait]
+pub trait BatchTransport<C, G, D>: OptionalSend + OptionalSync + 'static
+where C: RaftTypeConfig
+{
+    async fn send_batch(
+        &self,
+        dest: D,
+        batch: Vec<(G, AppendEntriesRequest<C>)>,
+        option: RPCOption,
+    ) -> Result<BatchResponse<C>, RPCError<C>>;
+}
+
+/// When and what an [`AppendEntriesBatcher`] batches.
+#[derive(Debug, Clone)]
+pub struct BatchConfig {
+    /// How long a request waits for other requests to the same destination.
+    pub linger: Duration,
+
+    /// A batch is sent at once when it has this many requests.
+    pub max_batch: usize,
+
+    /// An AppendEntries with no more than this many entries is batched.
+    ///
+    /// With the default `0`, only heartbeats are batched.
+    pub max_entries: usize,
+}
+
+impl Default for BatchConfig {
+    fn default() -> Self {
+        Self {
+            linger: Duration::from_millis(5),
+            max_batch: 1024,
+            max_entries: 0,
+        }
+    }
+}
+
+/// A request waiting in a batch.
+struct Pending<C, G, D>
+where C: RaftTypeConfig
+{
+    dest: D,
+    group: G,
+    rpc: AppendEntriesRequest<C>,
+    option: RPCOption,
+    tx: OneshotSenderOf<C, Result<AppendEntriesResponse<C>, RPCError<C>>>,
+}
+
+/// Batches the AppendEntries RPCs of many groups by destination.
+///
+/// It is cheap to clone and is meant to be shared by the connections of every group in a process.
+/// The background task that sends the batches quits when every clone is dropped.
+pub struct AppendEntriesBatcher<C, G, D>
+where
+    C: RaftTypeConfig,
+    G: OptionalSend + 'static,
+    D: OptionalSend + 'static,
+{
+    config: BatchConfig,
+    tx: MpscUnboundedSenderOf<C, Pending<C, G, D>>,
+}
+
+impl<C, G, D> Clone for AppendEntriesBatcher<C, G, D>
+where
+    C: RaftTypeConfig,
+    G: OptionalSend + 'static,
+    D: OptionalSend + 'static,
+{
+    fn clone(&self) -> Self {
+        Self {
+            config: self.config.clone(),
+            tx: self.tx.clone(),
+        }
+    }
+}
+
+impl<C, G, D> AppendEntriesBatcher<C, G, D>
+where
+    C: RaftTypeConfig,
+    G: OptionalSend + 'static,
+    D: Ord + Clone + OptionalSend + 'static,
+{
+    /// Create a batcher and spawn the task that sends the batches with `transport`.
+    pub fn new<T>(config: BatchConfig, transport: T) -> Self
+    where T: BatchTransport<C, G, D> {
+        let (tx, rx) = C::mpsc_unbounded();
+
+        // False positive lint warning(`non-binding `let` on a future`): https://github.com/rust-lang/rust-clippy/issues/9932
+        #[allow(clippy::let_underscore_future)]
+        let _ = C::spawn(Self::run(config.clone(), Arc::new(transport), rx));
+
+        Self { config, tx }
+    }
+
+    /// Returns true if `rpc` is small enough to be batched.
+    pub fn accepts(&self, rpc: &AppendEntriesRequest<C>) -> bool {
+        rpc.entries.len() <= self.config.max_entries
+    }
+
+    /// Send `rpc` of `group` to `dest` in the next batch, and return its own response.
+    pub async fn append_entries(
+        &self,
+        dest: D,
+        group: G,
+        rpc: AppendEntriesRequest<C>,
+        option: RPCOption,
+    ) -> Result<AppendEntriesResponse<C>, RPCError<C>> {
+        let (tx, rx) = C::oneshot();
+
+        let pending = Pending {
+            dest,
+            group,
+            rpc,
+            option,
+            tx,
+        };
+
+        if self.tx.send(pending).is_err() {
+            return Err(shutdown_error());
+        }
+
+        rx.await.unwrap_or_else(|_| Err(shutdown_error()))
+    }
+
+    async fn run<T>(config: BatchConfig, transport: Arc<T>, mut rx: MpscUnboundedReceiverOf<C, Pending<C, G, D>>)
+    where T: BatchTransport<C, G, D> {
+        loop {
+            let Some(first) = rx.recv().await else {
+                tracing::info!("AppendEntriesBatcher is dropped, quit");
+                return;
+            };
+
+            let mut batches = BTreeMap::<D, Vec<Pending<C, G, D>>>::new();
+            batches.entry(first.dest.clone()).or_default().push(first);
+
+            let deadline = C::now() + config.linger;
+
+            // Collect more requests until the first one has waited long enough.
+            loop {
+                futures::select! {
+                    _ = C::sleep_until(deadline).fuse() => break,
+                    pending = rx.recv().fuse() => {
+                        let Some(pending) = pending else {
+                            break;
+                        };
+
+                        let dest = pending.dest.clone();
+                        let batch = batches.entry(dest.clone()).or_default();
+                        batch.push(pending);
+
+                        if batch.len() >= config.max_batch {
+                            let batch = batches.remove(&dest).unwrap();
+                            Self::spawn_send(&transport, dest, batch);
+                        }
+                    }
+                }
+            }
+
+            for (dest, batch) in batches {
+                Self::spawn_send(&transport, dest, batch);
+            }
+        }
+    }
+
+    /// Send a batch in a spawned task, and send back the response of every request.
+    fn spawn_send<T>(transport: &Arc<T>, dest: D, batch: Vec<Pending<C, G, D>>)
+    where T: BatchTransport<C, G, D> {
+        let transport = transport.clone();
+
+        // Give up the batch when the most impatient request does.
+        let ttl = batch.iter().map(|p| p.option.hard_ttl()).min().unwrap_or_default();
+
+        let (reqs, txs): (Vec<_>, Vec<_>) = batch.into_iter().map(|p| ((p.group, p.rpc), p.tx)).unzip();
+
+        let fut = async move {
+            let n = txs.len();
+            let res = transport.send_batch(dest, reqs, RPCOption::new(ttl)).await;
+
+            let res = match res {
+                Ok(resps) if resps.len() != n => {
+                    let msg = format!("expect {} responses in a batch, but got {}", n, resps.len());
+                    Err(RPCError::Network(NetworkError::new(&AnyError::error(msg))))
+                }
+                x => x,
+            };
+
+            match res {
+                Ok(resps) => {
+                    for (tx, resp) in txs.into_iter().zip(resps) {
+                        let resp = resp.map_err(|e| RPCError::Unreachable(Unreachable::new(&e)));
+                        let _ = tx.send(resp);
+                    }
+                }
+                Err(e) => {
+                    tracing::warn!(error = display(&e), "failed to send a batch of {} AppendEntries", n);
+                    for tx in txs {
+                        let _ = tx.send(Err(e.clone()));
+                    }
+                }
+            }
+        };
+
+        // False positive lint warning(`non-binding `let` on a future`): https://github.com/rust-lang/rust-clippy/issues/9932
+        #[allow(clippy::let_underscore_future)]
+        let _ = C::spawn(fut);
+    }
+}
+
+/// Pass every AppendEntries in a received batch to the [`Raft`] of its group, returned by
+/// `raft_of`, and return the responses in the order of the requests.
+///
+/// A request of a group that is not found responds with [`Fatal::Stopped`].
+pub async fn fan_out<C, G>(
+    batch: Vec<(G, AppendEntriesRequest<C>)>,
+    raft_of: impl Fn(&G) -> Option<Raft<C>>,
+) -> BatchResponse<C>
+where
+    C: RaftTypeConfig,
+{
+    let futs = batch.into_iter().map(|(group, rpc)| {
+        let raft = raft_of(&group);
+        async move {
+            match raft {
+                Some(raft) => raft.append_entries(rpc).await,
+                None => Err(RaftError::Fatal(Fatal::Stopped)),
+            }
+        }
+    });
+
+    futures::future::join_all(futs).await
+}
+
+fn shutdown_error<C>() -> RPCError<C>
+where C: RaftTypeConfig {
+    RPCError::Unreachable(Unreachable::new(&AnyError::error("AppendEntriesBatcher is shut down")))
+}
+
+#[cfg(test)]
+mod tests {
+    use std::sync::Arc;
+    use std::sync::Mutex;
+    use std::time::Duration;
+
+    use super::AppendEntriesBatcher;
+    use super::BatchConfig;
+    use super::BatchResponse;
+    use super::BatchTransport;
+    use crate::engine::testing::UTConfig;
+    use crate::error::Fatal;
+    use crate::error::RPCError;
+    use crate::error::RaftError;
+    use crate::network::RPCOption;
+    use crate::raft::AppendEntriesRequest;
+    use crate::raft::AppendEntriesResponse;
+    use crate::Vote;
+
+    type C = UTConfig;
+
+    /// Records the groups of every batch sent to every destination.
+    ///
+    /// Group 0 is not found on the destination.
+    #[derive(Clone, Default)]
+    struct Recorder {
+        batches: Arc<Mutex<Vec<(u64, Vec<u64>)>>>,
+    }
+
+    impl BatchTransport<C, u64, u64> for Recorder {
+        async fn send_batch(
+            &self,
+            dest: u64,
+            batch: Vec<(u64, AppendEntriesRequest<C>)>,
+            _option: RPCOption,
+        ) -> Result<BatchResponse<C>, RPCError<C>> {
+            let groups = batch.iter().map(|(g, _)| *g).collect::<Vec<_>>();
+            self.batches.lock().unwrap().push((dest, groups.clone()));
+
+            let resps = groups
+                .iter()
+                .map(|g| match g {
+                    0 => Err(RaftError::Fatal(Fatal::Stopped)),
+                    _ => Ok(AppendEntriesResponse::Success),
+                })
+                .collect();
+            Ok(resps)
+        }
+    }
+
+    fn heartbeat() -> AppendEntriesRequest<C> {
+        AppendEntriesRequest {
+            vote: Vote::new_committed(1, 1),
+            prev_log_id: None,
+            leader_commit: None,
+            entries: vec![],
+        }
+    }
+
+    fn option() -> RPCOption {
+        RPCOption::new(Duration::from_millis(100))
+    }
+
+    #[tokio::test]
+    async fn test_batch_by_destination() -> anyhow::Result<()> {
+        let recorder = Recorder::default();
+        let batcher = AppendEntriesBatcher::<C, u64, u64>::new(BatchConfig::default(), recorder.clone());
+
+        let (a, b, c, d) = futures::join!(
+            batcher.append_entries(1, 10, heartbeat(), option()),
+            batcher.append_entries(1, 11, heartbeat(), option()),
+            batcher.append_entries(2, 12, heartbeat(), option()),
+            batcher.append_entries(1, 0, heartbeat(), option()),
+        );
+
+        assert_eq!(Ok(AppendEntriesResponse::Success), a);
+        assert_eq!(Ok(AppendEntriesResponse::Success), b);
+        assert_eq!(Ok(AppendEntriesResponse::Success), c);
+        assert!(matches!(d, Err(RPCError::Unreachable(_))));
+
+        let mut batches = recorder.batches.lock().unwrap().clone();
+        batches.sort();
+        assert_eq!(vec![(1, vec![10, 11, 0]), (2, vec![12])], batches);
+
+        Ok(())
+    }
+
+    #[tokio::test]
+    async fn test_batch_max_size() -> anyhow::Result<()> {
+        let recorder = Recorder::default();
+        let config = BatchConfig {
+            linger: Duration::from_secs(10),
+            max_batch: 2,
+            max_entries: 0,
+        };
+        let batcher = AppendEntriesBatcher::<C, u64, u64>::new(config, recorder.clone());
+
+        // A full batch is sent without waiting for `linger`.
+        let (a, b) = futures::join!(
+            batcher.append_entries(1, 10, heartbeat(), option()),
+            batcher.append_entries(1, 11, heartbeat(), option()),
+        );
+        assert_eq!(Ok(AppendEntriesResponse::Success), a);
+        assert_eq!(Ok(AppendEntriesResponse::Success), b);
+
+        assert_eq!(vec![(1, vec![10, 11])], recorder.batches.lock().unwrap().clone());
+
+        Ok(())
+    }
+}
@@ -0,0 +1,305 @@
+//! Compression of the data sent over the network.
+//!
+//! Algorithms are enabled by feature flags: `compress-zstd` for [`Compression::Zstd`] and
+//! `compress-lz4` for [`Compression::Lz4`]. Because two nodes may be built with different flags,
+//! the algorithm to use is negotiated: each side advertises [`Compression::supported()`], for
+//! example in a connection handshake, and both pick [`Compression::negotiate()`].
+//!
+//! The negotiated algorithm is used by:
+//! - [`snapshot_stream`](crate::network::v2::snapshot_stream) to compress snapshot chunks, if the
+//!   transport returns it from
+//!   [`SnapshotChunkSink::compression()`](crate::network::v2::snapshot_stream::SnapshotChunkSink::compression).
+//! - [`CompressionCodec`], which a network implementation applies to the serialized entries of an
+//!   `AppendEntriesRequest`:
+//!
+//! ```ignore
+//! let codec = CompressionCodec::new(negotiated).with_threshold(4096);
+//!
+//! // Sender:
+//! let frame = codec.encode(&serde_json::to_vec(&rpc.entries)?)?;
+//!
+//! // Receiver:
+//! let entries: Vec<Entry> = serde_json::from_slice(&CompressionCodec::decode(&frame)?)?;
+//! ```
+
+use std::fmt;
+
+use crate::error::CompressionError;
+
+/// A compression algorithm.
+///
+/// All variants exist regardless of feature flags, so that a peer can tell which algorithm a
+/// message is compressed with, but only the enabled ones can compress or decompress.
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, Hash)]
+#[cfg_attr(feature = "serde", derive(serde::Deserialize, serde::Serialize))]
+pub enum Compression {
+    /// Data is sent as is.
+    #[default]
+    None,
+
+    /// Zstandard, enabled by feature `compress-zstd`.
+    Zstd,
+
+    /// LZ4, enabled by feature `compress-lz4`.
+    Lz4,
+}
+
+impl fmt::Display for Compression {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            Compression::None => write!(f, "none"),
+            Compression::Zstd => write!(f, "zstd"),
+            Compression::Lz4 => write!(f, "lz4"),
+        }
+    }
+}
+
+impl Compression {
+    /// The zstd level to compress with, the default of the zstd library.
+    #[cfg(feature = "compress-zstd")]
+    const ZSTD_LEVEL: i32 = 3;
+
+    /// The algorithms enabled in this build, the most preferred first.
+    ///
+    /// [`Compression::None`] is always the last one.
+    pub fn supported() -> &'static [Compression] {
+        &[
+            #[cfg(feature = "compress-zstd")]
+            Compression::Zstd,
+            #[cfg(feature = "compress-lz4")]
+            Compression::Lz4,
+            Compression::None,
+        ]
+    }
+
+    /// Returns true if this algorithm is enabled in this build.
+    pub fn is_supported(&self) -> bool {
+        Self::supported().contains(self)
+    }
+
+    /// Choose the first algorithm in `local`, in the order of local preference, that `remote` also
+    /// supports, or [`Compression::None`] if there is no such one.
+    pub fn negotiate(local: &[Compression], remote: &[Compression]) -> Compression {
+        local.iter().find(|c| remote.contains(c)).copied().unwrap_or_default()
+    }
+
+    /// Compress `data`.
+    pub fn compress(&self, data: &[u8]) -> Result<Vec<u8>, CompressionError> {
+        match self {
+            Compression::None => Ok(data.to_vec()),
+
+            #[cfg(feature = "compress-zstd")]
+            Compression::Zstd => {
+                zstd::bulk::compress(data, Self::ZSTD_LEVEL).map_err(|e| CompressionError::new(*self, &e))
+            }
+
+            #[cfg(feature = "compress-lz4")]
+            Compression::Lz4 => Ok(lz4_flex::compress_prepend_size(data)),
+
+            #[allow(unreachable_patterns)]
+            _ => Err(CompressionError::unsupported(*self)),
+        }
+    }
+
+    /// Decompress `data` that is compressed by [`compress()`](Self::compress) with the same
+    /// algorithm.
+    pub fn decompress(&self, data: &[u8]) -> Result<Vec<u8>, CompressionError> {
+        match self {
+            Compression::None => Ok(data.to_vec()),
+
+            #[cfg(feature = "compress-zstd")]
+            Compression::Zstd => zstd::stream::decode_all(data).map_err(|e| CompressionError::new(*self, &e)),
+
+            #[cfg(feature = "compress-lz4")]
+            Compression::Lz4 => lz4_flex::decompress_size_prepended(data).map_err(|e| CompressionError::new(*self, &e)),
+
+            #[allow(unreachable_patterns)]
+            _ => Err(CompressionError::unsupported(*self)),
+        }
+    }
+
+    /// Compress `data` unless it is shorter than `threshold` or compression does not make it
+    /// smaller, and return the algorithm that is actually used along with the output.
+    pub(crate) fn compress_above(
+        &self,
+        data: Vec<u8>,
+        threshold: usize,
+    ) -> Result<(Compression, Vec<u8>), CompressionError> {
+        if *self == Compression::None || data.len() < threshold {
+            return Ok((Compression::None, data));
+        }
+
+        let compressed = self.compress(&data)?;
+        if compressed.len() >= data.len() {
+            return Ok((Compression::None, data));
+        }
+        Ok((*self, compressed))
+    }
+
+    fn tag(&self) -> u8 {
+        match self {
+            Compression::None => 0,
+            Compression::Zstd => 1,
+            Compression::Lz4 => 2,
+        }
+    }
+
+    fn from_tag(tag: u8) -> Option<Self> {
+        match tag {
+            0 => Some(Compression::None),
+            1 => Some(Compression::Zstd),
+            2 => Some(Compression::Lz4),
+            _ => None,
+        }
+    }
+}
+
+/// Compresses a buffer, such as a serialized batch of entries, into a self-describing frame.
+///
+/// A frame is one byte identifying the [`Compression`] followed by the payload. A buffer shorter
+/// than the threshold, or one that does not get smaller, is framed uncompressed, so that small
+/// heartbeats and incompressible data do not pay the CPU cost.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct CompressionCodec {
+    compression: Compression,
+    threshold: usize,
+}
+
+impl Default for CompressionCodec {
+    fn default() -> Self {
+        Self::new(Compression::None)
+    }
+}
+
+impl CompressionCodec {
+    /// The default threshold in bytes below which data is not compressed.
+    pub const DEFAULT_THRESHOLD: usize = 1024;
+
+    /// Create a codec that compresses with `compression`, usually the negotiated one.
+    pub fn new(compression: Compression) -> Self {
+        Self {
+            compression,
+            threshold: Self::DEFAULT_THRESHOLD,
+        }
+    }
+
+    /// Do not compress data shorter than `threshold` bytes.
+    pub fn with_threshold(mut self, threshold: usize) -> Self {
+        self.threshold = threshold;
+        self
+    }
+
+    pub fn compression(&self) -> Compression {
+        self.compression
+    }
+
+    pub fn threshold(&self) -> usize {
+        self.threshold
+    }
+
+    /// Encode `data` into a frame.
+    pub fn encode(&self, data: &[u8]) -> Result<Vec<u8>, CompressionError> {
+        let (compression, payload) = self.compression.compress_above(data.to_vec(), self.threshold)?;
+
+        let mut frame = Vec::with_capacity(payload.len() + 1);
+        frame.push(compression.tag());
+        frame.extend_from_slice(&payload);
+        Ok(frame)
+    }
+
+    /// Decode a frame built by [`encode()`](Self::encode) of any codec.
+    ///
+    /// It fails if the frame is compressed with an algorithm not enabled in this build.
+    pub fn decode(frame: &[u8]) -> Result<Vec<u8>, CompressionError> {
+        let Some((tag, payload)) = frame.split_first() else {
+            return Err(CompressionError::new(
+                Compression::None,
+                &anyerror::AnyError::error("empty frame"),
+            ));
+        };
+
+        let Some(compression) = Compression::from_tag(*tag) else {
+            let e = anyerror::AnyError::error(format!("unknown compression tag: {}", tag));
+            return Err(CompressionError::new(Compression::None, &e));
+        };
+
+        compression.decompress(payload)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::Compression;
+    use super::CompressionCodec;
+
+    fn json_like(n: usize) -> Vec<u8> {
+        (0..n).flat_map(|i| format!(r#"{{"key":"k{}","value":"v"}}"#, i % 10).into_bytes()).collect()
+    }
+
+    #[test]
+    fn test_negotiate() {
+        use Compression::Lz4;
+        use Compression::Zstd;
+        let none = Compression::None;
+
+        assert_eq!(Zstd, Compression::negotiate(&[Zstd, Lz4, none], &[Lz4, Zstd, none]));
+        assert_eq!(Lz4, Compression::negotiate(&[Zstd, Lz4, none], &[Lz4, none]));
+        assert_eq!(none, Compression::negotiate(&[Zstd], &[Lz4]));
+        assert_eq!(none, Compression::negotiate(&[], &[]));
+
+        assert_eq!(Some(&none), Compression::supported().last());
+        assert!(none.is_supported());
+    }
+
+    #[test]
+    fn test_compress_round_trip() -> anyhow::Result<()> {
+        let data = json_like(100);
+
+        for c in Compression::supported() {
+            let compressed = c.compress(&data)?;
+            if *c != Compression::None {
+                assert!(compressed.len() < data.len(), "{} compresses", c);
+            }
+            assert_eq!(data, c.decompress(&compressed)?);
+        }
+
+        Ok(())
+    }
+
+    #[test]
+    fn test_unsupported() {
+        for c in [Compression::Zstd, Compression::Lz4] {
+            if c.is_supported() {
+                continue;
+            }
+            let err = c.compress(b"foo").unwrap_err();
+            assert_eq!(c, err.compression);
+            let err = c.decompress(b"foo").unwrap_err();
+            assert_eq!(c, err.compression);
+        }
+    }
+
+    #[test]
+    fn test_codec_threshold() -> anyhow::Result<()> {
+        let compression = Compression::supported()[0];
+        let codec = CompressionCodec::new(compression).with_threshold(64);
+
+        // Below the threshold: not compressed.
+        let frame = codec.encode(b"short")?;
+        assert_eq!(b"\0short".to_vec(), frame);
+        assert_eq!(b"short".to_vec(), CompressionCodec::decode(&frame)?);
+
+        // Above the threshold: compressed if an algorithm is enabled.
+        let data = json_like(100);
+        let frame = codec.encode(&data)?;
+        if compression != Compression::None {
+            assert!(frame.len() < data.len());
+        }
+        assert_eq!(data, CompressionCodec::decode(&frame)?);
+
+        assert!(CompressionCodec::decode(&[]).is_err());
+        assert!(CompressionCodec::decode(&[9, 1, 2]).is_err());
+
+        Ok(())
+    }
+}
@@ -1,15 +1,21 @@
 //! The Raft network interface.
 
 mod backoff;
+mod protocol_version;
 mod rpc_option;
 mod rpc_type;
 
+pub mod batch;
+pub mod compression;
+pub mod retry;
 pub mod v1;
 pub mod v2;
 
 pub mod snapshot_transport;
 
 pub use backoff::Backoff;
+pub use protocol_version::ProtocolFeature;
+pub use protocol_version::ProtocolVersion;
 pub use rpc_option::RPCOption;
 pub use rpc_type::RPCTypes;
 pub use v1::RaftNetwork;
@@ -0,0 +1,117 @@
+use std::fmt;
+
+/// The version of the protocol Raft nodes use to talk to each other.
+///
+/// A new version is introduced by a release that adds an RPC or changes the shape of a message.
+/// Nodes exchange their versions with a [`HandshakeRequest`] on first contact, and a node does not
+/// use a feature its peer does not [support](Self::supports). This way a cluster can be upgraded
+/// one node at a time instead of with a full stop.
+///
+/// A node advertises the version its network implements, as returned by
+/// [`RaftNetworkFactory::protocol_version()`]. The version of a peer that has not answered a
+/// handshake yet, or whose network does not implement [`handshake()`], is unknown, and it is
+/// assumed to speak [`ProtocolVersion::V1`].
+///
+/// [`HandshakeRequest`]: crate::raft::HandshakeRequest
+/// [`handshake()`]: crate::network::v2::RaftNetworkV2::handshake
+/// [`RaftNetworkFactory::protocol_version()`]: crate::network::RaftNetworkFactory::protocol_version
+#[derive(Debug, Clone, Copy, PartialEq, Eq, PartialOrd, Ord, Hash)]
+#[cfg_attr(feature = "serde", derive(serde::Deserialize, serde::Serialize), serde(transparent))]
+pub struct ProtocolVersion(u32);
+
+impl fmt::Display for ProtocolVersion {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "v{}", self.0)
+    }
+}
+
+impl ProtocolVersion {
+    /// The protocol before versioning: AppendEntries, RequestVote, InstallSnapshot and
+    /// [`ProtocolFeature::TransferLeader`].
+    ///
+    /// A peer whose version is unknown is assumed to speak it.
+    pub const V1: Self = Self(1);
+
+    /// Openraft 0.10.0: adds the handshake, [`ProtocolFeature::ForwardClientWrite`],
+    /// [`ProtocolFeature::CatchUp`] and [`ProtocolFeature::Hibernate`].
+    pub const V2: Self = Self(2);
+
+    /// The latest version this build of Openraft supports.
+    ///
+    /// A network advertises it only if it implements every RPC of it.
+    pub const CURRENT: Self = Self::V2;
+
+    pub const fn new(version: u32) -> Self {
+        Self(version)
+    }
+
+    pub const fn get(&self) -> u32 {
+        self.0
+    }
+
+    /// Returns true if a node speaking this version understands `feature`.
+    pub fn supports(&self, feature: ProtocolFeature) -> bool {
+        *self >= feature.since()
+    }
+}
+
+/// A protocol feature that not every peer may support.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+#[cfg_attr(feature = "serde", derive(serde::Deserialize, serde::Serialize))]
+pub enum ProtocolFeature {
+    /// The [`transfer_leader`](crate::network::v2::RaftNetworkV2::transfer_leader) RPC.
+    TransferLeader,
+
+    /// The [`forward_client_write`](crate::network::v2::RaftNetworkV2::forward_client_write) RPC.
+    ForwardClientWrite,
+
+    /// The [`catch_up`](crate::network::v2::RaftNetworkV2::catch_up) RPC.
+    CatchUp,
+
+    /// The [`hibernate`](crate::network::v2::RaftNetworkV2::hibernate) RPC.
+    Hibernate,
+}
+
+impl fmt::Display for ProtocolFeature {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "{:?}", self)
+    }
+}
+
+impl ProtocolFeature {
+    /// The first protocol version supporting this feature.
+    pub fn since(&self) -> ProtocolVersion {
+        match self {
+            ProtocolFeature::TransferLeader => ProtocolVersion::V1,
+            ProtocolFeature::ForwardClientWrite => ProtocolVersion::V2,
+            ProtocolFeature::CatchUp => ProtocolVersion::V2,
+            ProtocolFeature::Hibernate => ProtocolVersion::V2,
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::ProtocolFeature;
+    use super::ProtocolVersion;
+
+    #[test]
+    fn test_supports() {
+        let v1 = ProtocolVersion::V1;
+        assert!(v1.supports(ProtocolFeature::TransferLeader));
+        assert!(!v1.supports(ProtocolFeature::ForwardClientWrite));
+        assert!(!v1.supports(ProtocolFeature::CatchUp));
+        assert!(!v1.supports(ProtocolFeature::Hibernate));
+
+        let current = ProtocolVersion::CURRENT;
+        assert!(current.supports(ProtocolFeature::TransferLeader));
+        assert!(current.supports(ProtocolFeature::ForwardClientWrite));
+        assert!(current.supports(ProtocolFeature::CatchUp));
+        assert!(current.supports(ProtocolFeature::Hibernate));
+
+        // A newer peer supports everything this build knows of.
+        assert!(ProtocolVersion::new(100).supports(ProtocolFeature::ForwardClientWrite));
+
+        assert_eq!("v2", ProtocolVersion::V2.to_string());
+    }
+}
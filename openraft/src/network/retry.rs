@@ -0,0 +1,425 @@
+//! Policies that decide what to do when an RPC fails.
+//!
+//! A [`RetryPolicy`] is built by [`RaftNetworkV2::retry_policy()`] for every replication stream,
+//! and is consulted each time an AppendEntries or InstallSnapshot RPC to the target fails. Given
+//! the [`RPCTypes`] and the error, it returns a [`RetryDecision`]: whether to resend the request at
+//! once, how long to wait before the next RPC, and whether to send fewer entries.
+//!
+//! [`DefaultRetryPolicy`] is what Openraft does without a custom policy. The others can be combined
+//! to build one:
+//! - [`RetryByRPCType`] chooses a policy by the RPC type.
+//! - [`JitteredBackoff`] waits a randomized, growing interval after a transient error.
+//! - [`CircuitBreaker`] stops sending to a target for a while after consecutive failures.
+//!
+//! ```ignore
+//! fn retry_policy(&self) -> Box<dyn RetryPolicy<C>> {
+//!     let snapshot = JitteredBackoff::new(Duration::from_millis(100), Duration::from_secs(10));
+//!     let policy = RetryByRPCType::new(DefaultRetryPolicy).with(RPCTypes::InstallSnapshot, snapshot);
+//!     Box::new(CircuitBreaker::new(policy, 10, Duration::from_secs(5)))
+//! }
+//! ```
+//!
+//! [`RaftNetworkV2::retry_policy()`]: crate::network::v2::RaftNetworkV2::retry_policy
+
+use std::collections::HashMap;
+use std::error::Error;
+use std::time::Duration;
+
+use rand::Rng;
+
+use crate::error::RPCError;
+use crate::network::RPCTypes;
+use crate::type_config::AsyncRuntime;
+use crate::OptionalSend;
+use crate::RaftTypeConfig;
+
+/// The kind of an [`RPCError`], regardless of the remote error type.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub enum RPCErrorKind {
+    Timeout,
+    Unreachable,
+    PayloadTooLarge,
+    Network,
+    Remote,
+}
+
+impl RPCErrorKind {
+    pub fn of<C, E>(err: &RPCError<C, E>) -> Self
+    where
+        C: RaftTypeConfig,
+        E: Error,
+    {
+        match err {
+            RPCError::Timeout(_) => RPCErrorKind::Timeout,
+            RPCError::Unreachable(_) => RPCErrorKind::Unreachable,
+            RPCError::PayloadTooLarge(_) => RPCErrorKind::PayloadTooLarge,
+            RPCError::Network(_) => RPCErrorKind::Network,
+            RPCError::RemoteError(_) => RPCErrorKind::Remote,
+        }
+    }
+}
+
+/// How long to wait before sending the next RPC to a target after an error.
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
+pub enum RetryDelay {
+    /// Send the next RPC without waiting.
+    #[default]
+    None,
+
+    /// Wait for the next interval of [`RaftNetworkV2::backoff()`], which keeps being used before
+    /// every RPC until one succeeds.
+    ///
+    /// [`RaftNetworkV2::backoff()`]: crate::network::v2::RaftNetworkV2::backoff
+    Backoff,
+
+    /// Wait for the given duration once.
+    Fixed(Duration),
+}
+
+/// What to do after an RPC fails, returned by [`RetryPolicy::on_error()`].
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
+pub struct RetryDecision {
+    /// Resend the failed request instead of reporting the error to `RaftCore`.
+    ///
+    /// Only a request carrying log entries can be resent. A failed snapshot is always reported.
+    pub retry: bool,
+
+    /// How long to wait before the next RPC.
+    pub delay: RetryDelay,
+
+    /// Send fewer entries in the following AppendEntries RPCs.
+    ///
+    /// The number of entries is the hint in a [`PayloadTooLarge`] error, or otherwise half of the
+    /// entries in the failed request.
+    ///
+    /// [`PayloadTooLarge`]: crate::error::PayloadTooLarge
+    pub shrink_payload: bool,
+}
+
+impl RetryDecision {
+    /// Report the error to `RaftCore`, which sends the request again when it needs to.
+    pub fn fail() -> Self {
+        Self::default()
+    }
+
+    /// Resend the failed request.
+    pub fn retry() -> Self {
+        Self {
+            retry: true,
+            ..Self::default()
+        }
+    }
+
+    pub fn with_delay(mut self, delay: RetryDelay) -> Self {
+        self.delay = delay;
+        self
+    }
+
+    pub fn with_shrink_payload(mut self) -> Self {
+        self.shrink_payload = true;
+        self
+    }
+}
+
+/// Decides what to do when an RPC to a target fails.
+///
+/// An instance is used for a single target and may keep state across RPCs.
+pub trait RetryPolicy<C>: OptionalSend + 'static
+where C: RaftTypeConfig
+{
+    /// Called when an RPC of `rpc_type` fails with `err`.
+    ///
+    /// `attempt` is the number of consecutive failures to this target, starting from 1.
+    fn on_error(&mut self, rpc_type: RPCTypes, err: &RPCError<C>, attempt: u64) -> RetryDecision;
+
+    /// Called when an RPC of `rpc_type` succeeds.
+    fn on_success(&mut self, rpc_type: RPCTypes) {
+        let _ = rpc_type;
+    }
+}
+
+impl<C> RetryPolicy<C> for Box<dyn RetryPolicy<C>>
+where C: RaftTypeConfig
+{
+    fn on_error(&mut self, rpc_type: RPCTypes, err: &RPCError<C>, attempt: u64) -> RetryDecision {
+        self.as_mut().on_error(rpc_type, err, attempt)
+    }
+
+    fn on_success(&mut self, rpc_type: RPCTypes) {
+        self.as_mut().on_success(rpc_type)
+    }
+}
+
+/// The policy Openraft uses unless the network provides another one.
+///
+/// - [`Unreachable`](RPCErrorKind::Unreachable): wait with [`RetryDelay::Backoff`].
+/// - [`PayloadTooLarge`](RPCErrorKind::PayloadTooLarge): shrink the payload and retry at once.
+/// - Any other error is reported without waiting.
+#[derive(Debug, Clone, Copy, Default)]
+pub struct DefaultRetryPolicy;
+
+impl<C> RetryPolicy<C> for DefaultRetryPolicy
+where C: RaftTypeConfig
+{
+    fn on_error(&mut self, _rpc_type: RPCTypes, err: &RPCError<C>, _attempt: u64) -> RetryDecision {
+        match RPCErrorKind::of(err) {
+            RPCErrorKind::Unreachable => RetryDecision::fail().with_delay(RetryDelay::Backoff),
+            RPCErrorKind::PayloadTooLarge => RetryDecision::retry().with_shrink_payload(),
+            RPCErrorKind::Timeout | RPCErrorKind::Network | RPCErrorKind::Remote => RetryDecision::fail(),
+        }
+    }
+}
+
+/// Uses a separate policy for some RPC types, and a default one for the others.
+pub struct RetryByRPCType<C>
+where C: RaftTypeConfig
+{
+    default: Box<dyn RetryPolicy<C>>,
+    by_type: HashMap<RPCTypes, Box<dyn RetryPolicy<C>>>,
+}
+
+impl<C> RetryByRPCType<C>
+where C: RaftTypeConfig
+{
+    pub fn new(default: impl RetryPolicy<C>) -> Self {
+        Self {
+            default: Box::new(default),
+            by_type: HashMap::new(),
+        }
+    }
+
+    /// Use `policy` for RPCs of `rpc_type`.
+    pub fn with(mut self, rpc_type: RPCTypes, policy: impl RetryPolicy<C>) -> Self {
+        self.by_type.insert(rpc_type, Box::new(policy));
+        self
+    }
+
+    fn policy(&mut self, rpc_type: RPCTypes) -> &mut Box<dyn RetryPolicy<C>> {
+        self.by_type.get_mut(&rpc_type).unwrap_or(&mut self.default)
+    }
+}
+
+impl<C> RetryPolicy<C> for RetryByRPCType<C>
+where C: RaftTypeConfig
+{
+    fn on_error(&mut self, rpc_type: RPCTypes, err: &RPCError<C>, attempt: u64) -> RetryDecision {
+        self.policy(rpc_type).on_error(rpc_type, err, attempt)
+    }
+
+    fn on_success(&mut self, rpc_type: RPCTypes) {
+        self.policy(rpc_type).on_success(rpc_type)
+    }
+}
+
+/// Waits a random interval that grows with consecutive transient errors, the "decorrelated
+/// jitter" backoff.
+///
+/// After a timeout, unreachable or network error, the delay is picked uniformly in
+/// `[base, 3 * previous delay]` and capped by `max`, so that leaders retrying the same node spread
+/// out. It restarts from `base` after a successful RPC. `PayloadTooLarge` and remote errors are
+/// handled as in [`DefaultRetryPolicy`].
+#[derive(Debug, Clone)]
+pub struct JitteredBackoff {
+    base: Duration,
+    max: Duration,
+    prev: Duration,
+}
+
+impl JitteredBackoff {
+    pub fn new(base: Duration, max: Duration) -> Self {
+        Self { base, max, prev: base }
+    }
+
+    fn next_delay<C>(&mut self) -> Duration
+    where C: RaftTypeConfig {
+        let upper = std::cmp::max(self.base, self.prev.saturating_mul(3));
+        let delay = C::AsyncRuntime::thread_rng().random_range(self.base..=upper);
+        self.prev = std::cmp::min(delay, self.max);
+        self.prev
+    }
+}
+
+impl<C> RetryPolicy<C> for JitteredBackoff
+where C: RaftTypeConfig
+{
+    fn on_error(&mut self, rpc_type: RPCTypes, err: &RPCError<C>, attempt: u64) -> RetryDecision {
+        match RPCErrorKind::of(err) {
+            RPCErrorKind::Timeout | RPCErrorKind::Unreachable | RPCErrorKind::Network => {
+                RetryDecision::fail().with_delay(RetryDelay::Fixed(self.next_delay::<C>()))
+            }
+            RPCErrorKind::PayloadTooLarge | RPCErrorKind::Remote => DefaultRetryPolicy.on_error(rpc_type, err, attempt),
+        }
+    }
+
+    fn on_success(&mut self, _rpc_type: RPCTypes) {
+        self.prev = self.base;
+    }
+}
+
+/// Stops sending RPCs to a target for a while once it has failed too many times in a row.
+///
+/// Below `threshold` consecutive failures the decision of the inner policy is used. From then on
+/// the circuit is open: the request is not retried, and the next RPC is sent only after
+/// `open_for`, to probe if the target has recovered. A successful RPC closes the circuit.
+#[derive(Debug, Clone)]
+pub struct CircuitBreaker<P> {
+    inner: P,
+    threshold: u64,
+    open_for: Duration,
+}
+
+impl<P> CircuitBreaker<P> {
+    pub fn new(inner: P, threshold: u64, open_for: Duration) -> Self {
+        Self {
+            inner,
+            threshold,
+            open_for,
+        }
+    }
+}
+
+impl<C, P> RetryPolicy<C> for CircuitBreaker<P>
+where
+    C: RaftTypeConfig,
+    P: RetryPolicy<C>,
+{
+    fn on_error(&mut self, rpc_type: RPCTypes, err: &RPCError<C>, attempt: u64) -> RetryDecision {
+        let decision = self.inner.on_error(rpc_type, err, attempt);
+
+        if attempt < self.threshold {
+            return decision;
+        }
+
+        tracing::warn!(
+            "circuit open after {} consecutive failures, pause for {:?}",
+            attempt,
+            self.open_for
+        );
+        RetryDecision {
+            retry: false,
+            delay: RetryDelay::Fixed(self.open_for),
+            shrink_payload: decision.shrink_payload,
+        }
+    }
+
+    fn on_success(&mut self, rpc_type: RPCTypes) {
+        self.inner.on_success(rpc_type)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::time::Duration;
+
+    use anyerror::AnyError;
+
+    use super::CircuitBreaker;
+    use super::DefaultRetryPolicy;
+    use super::JitteredBackoff;
+    use super::RetryByRPCType;
+    use super::RetryDecision;
+    use super::RetryDelay;
+    use super::RetryPolicy;
+    use crate::engine::testing::UTConfig;
+    use crate::error::NetworkError;
+    use crate::error::PayloadTooLarge;
+    use crate::error::RPCError;
+    use crate::error::Unreachable;
+    use crate::network::RPCTypes;
+
+    type C = UTConfig;
+
+    fn unreachable() -> RPCError<C> {
+        Unreachable::new(&AnyError::error("x")).into()
+    }
+
+    fn network() -> RPCError<C> {
+        NetworkError::new(&AnyError::error("x")).into()
+    }
+
+    fn too_large() -> RPCError<C> {
+        PayloadTooLarge::new_entries_hint(1).into()
+    }
+
+    #[test]
+    fn test_default_policy() {
+        let mut p = DefaultRetryPolicy;
+        let ae = RPCTypes::AppendEntries;
+
+        assert_eq!(
+            RetryDecision::fail().with_delay(RetryDelay::Backoff),
+            RetryPolicy::<C>::on_error(&mut p, ae, &unreachable(), 1)
+        );
+        assert_eq!(
+            RetryDecision::retry().with_shrink_payload(),
+            RetryPolicy::<C>::on_error(&mut p, ae, &too_large(), 1)
+        );
+        assert_eq!(
+            RetryDecision::fail(),
+            RetryPolicy::<C>::on_error(&mut p, ae, &network(), 1)
+        );
+    }
+
+    #[test]
+    fn test_by_rpc_type() {
+        let mut p = RetryByRPCType::<C>::new(DefaultRetryPolicy).with(
+            RPCTypes::InstallSnapshot,
+            CircuitBreaker::new(DefaultRetryPolicy, 1, Duration::from_secs(1)),
+        );
+
+        let d = p.on_error(RPCTypes::AppendEntries, &network(), 1);
+        assert_eq!(RetryDecision::fail(), d);
+
+        let d = p.on_error(RPCTypes::InstallSnapshot, &network(), 1);
+        assert_eq!(RetryDelay::Fixed(Duration::from_secs(1)), d.delay);
+    }
+
+    #[test]
+    fn test_jittered_backoff() {
+        let base = Duration::from_millis(10);
+        let max = Duration::from_millis(100);
+        let mut p = JitteredBackoff::new(base, max);
+
+        let delay =
+            |p: &mut JitteredBackoff| match RetryPolicy::<C>::on_error(p, RPCTypes::AppendEntries, &network(), 1) {
+                RetryDecision {
+                    retry: false,
+                    delay: RetryDelay::Fixed(d),
+                    shrink_payload: false,
+                } => d,
+                d => panic!("unexpected decision: {:?}", d),
+            };
+
+        for _ in 0..100 {
+            let d = delay(&mut p);
+            assert!(d >= base && d <= max, "delay: {:?}", d);
+        }
+
+        RetryPolicy::<C>::on_success(&mut p, RPCTypes::AppendEntries);
+        let d = delay(&mut p);
+        assert!(d <= base * 3, "restart from base, delay: {:?}", d);
+
+        // PayloadTooLarge is not delayed.
+        let d = RetryPolicy::<C>::on_error(&mut p, RPCTypes::AppendEntries, &too_large(), 1);
+        assert_eq!(RetryDecision::retry().with_shrink_payload(), d);
+    }
+
+    #[test]
+    fn test_circuit_breaker() {
+        let open_for = Duration::from_secs(3);
+        let mut p = CircuitBreaker::new(DefaultRetryPolicy, 3, open_for);
+        let ae = RPCTypes::AppendEntries;
+
+        for attempt in 1..3 {
+            let d = RetryPolicy::<C>::on_error(&mut p, ae, &too_large(), attempt);
+            assert_eq!(RetryDecision::retry().with_shrink_payload(), d, "closed");
+        }
+
+        let d = RetryPolicy::<C>::on_error(&mut p, ae, &too_large(), 3);
+        assert_eq!(
+            RetryDecision::fail().with_delay(RetryDelay::Fixed(open_for)).with_shrink_payload(),
+            d,
+            "open: no retry"
+        );
+    }
+}
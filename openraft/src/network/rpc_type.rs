@@ -4,11 +4,16 @@ use std::fmt;
 #[derive(PartialEq, Eq)]
 #[derive(Hash)]
 #[cfg_attr(feature = "serde", derive(serde::Deserialize, serde::Serialize))]
+#[non_exhaustive]
 pub enum RPCTypes {
     Vote,
     AppendEntries,
     InstallSnapshot,
     TransferLeader,
+    ClientWrite,
+    Handshake,
+    CatchUp,
+    Hibernate,
 }
 
 impl fmt::Display for RPCTypes {
@@ -1,6 +1,8 @@
 use openraft_macros::add_async_trait;
+use openraft_macros::since;
 
 use crate::network::v2::RaftNetworkV2;
+use crate::network::ProtocolVersion;
 use crate::OptionalSend;
 use crate::OptionalSync;
 use crate::RaftTypeConfig;
@@ -30,4 +32,38 @@ where C: RaftTypeConfig
     /// The method is intentionally async to give the implementation a chance to use asynchronous
     /// sync primitives to serialize access to the common internal object, if needed.
     async fn new_client(&mut self, target: C::NodeId, node: &C::Node) -> Self::Network;
+
+    /// Create a new network instance sending heartbeats to the target node.
+    ///
+    /// Heartbeats are AppendEntries without entries, sent by a dedicated task to every follower.
+    /// An application running many Raft groups in one process can return a connection that
+    /// coalesces the heartbeat RPCs of every group to the same destination, e.g., with
+    /// [`AppendEntriesBatcher`]. Only the RPCs are coalesced: every group still sends heartbeats
+    /// by its own timer.
+    ///
+    /// By default, it is the same as [`new_client()`](Self::new_client).
+    ///
+    /// [`AppendEntriesBatcher`]: crate::network::batch::AppendEntriesBatcher
+    #[since(version = "0.10.0")]
+    async fn new_heartbeat_client(&mut self, target: C::NodeId, node: &C::Node) -> Self::Network {
+        self.new_client(target, node).await
+    }
+
+    /// The protocol version this node advertises to its peers in handshakes.
+    ///
+    /// It is the highest [`ProtocolVersion`] whose RPCs are all implemented by [`Self::Network`]
+    /// and served by the application, which is not necessarily the latest version Openraft
+    /// supports: e.g., a network that implements [`handshake()`] but not
+    /// [`forward_client_write()`] must not claim [`ProtocolVersion::V2`], otherwise its peers would
+    /// forward client writes to it. A feature is used with a peer only if both this version and
+    /// the peer's support it.
+    ///
+    /// By default, it is [`ProtocolVersion::V1`].
+    ///
+    /// [`handshake()`]: RaftNetworkV2::handshake
+    /// [`forward_client_write()`]: RaftNetworkV2::forward_client_write
+    #[since(version = "0.10.0")]
+    fn protocol_version(&self) -> ProtocolVersion {
+        ProtocolVersion::V1
+    }
 }
@@ -1,5 +1,7 @@
 #[cfg(feature = "tokio-rt")]
 mod adapt_v1;
 mod network;
+#[cfg(feature = "tokio-rt")]
+pub mod snapshot_stream;
 
 pub use network::RaftNetworkV2;
@@ -5,15 +5,25 @@ use anyerror::AnyError;
 use openraft_macros::add_async_trait;
 use openraft_macros::since;
 
+use crate::error::ClientWriteError;
 use crate::error::RPCError;
+use crate::error::RaftError;
 use crate::error::ReplicationClosed;
 use crate::error::StreamingError;
 use crate::error::Unreachable;
+use crate::network::retry::DefaultRetryPolicy;
+use crate::network::retry::RetryPolicy;
 use crate::network::Backoff;
 use crate::network::RPCOption;
 use crate::raft::message::TransferLeaderRequest;
 use crate::raft::AppendEntriesRequest;
 use crate::raft::AppendEntriesResponse;
+use crate::raft::CatchUpRequest;
+use crate::raft::CatchUpResponse;
+use crate::raft::ClientWriteResponse;
+use crate::raft::HandshakeRequest;
+use crate::raft::HandshakeResponse;
+use crate::raft::HibernateRequest;
 use crate::raft::SnapshotResponse;
 use crate::raft::VoteRequest;
 use crate::raft::VoteResponse;
@@ -72,7 +82,12 @@ where C: RaftTypeConfig
     ///
     /// `cancel` get `Ready` when the caller decides to cancel this snapshot transmission.
     ///
+    /// If the snapshot data implements `AsyncRead`, [`snapshot_stream::send_snapshot()`] can be
+    /// used to send it in chunks with flow control, and [`SnapshotAssembler`] to receive it.
+    ///
     /// [`Raft::install_full_snapshot()`]: crate::raft::Raft::install_full_snapshot
+    /// [`snapshot_stream::send_snapshot()`]: crate::network::v2::snapshot_stream::send_snapshot
+    /// [`SnapshotAssembler`]: crate::network::v2::snapshot_stream::SnapshotAssembler
     async fn full_snapshot(
         &mut self,
         vote: VoteOf<C>,
@@ -97,6 +112,92 @@ where C: RaftTypeConfig
         ))))
     }
 
+    /// Forward a client write to the target node, which is believed to be the Leader.
+    ///
+    /// The node received this message should pass it to
+    /// [`Raft::handle_forwarded_client_write()`], and send back its result, with an error wrapped
+    /// in [`RemoteError`](crate::error::RemoteError). [`Raft::client_write_forwarded()`] calls this
+    /// method on a non-Leader node.
+    ///
+    /// This method provide a default implementation that just return [`Unreachable`] error, in
+    /// which case a forwarded write fails when its deadline is reached.
+    ///
+    /// [`Raft::handle_forwarded_client_write()`]: crate::raft::Raft::handle_forwarded_client_write
+    /// [`Raft::client_write_forwarded()`]: crate::raft::Raft::client_write_forwarded
+    #[since(version = "0.10.0")]
+    async fn forward_client_write(
+        &mut self,
+        _app_data: C::D,
+        _option: RPCOption,
+    ) -> Result<ClientWriteResponse<C>, RPCError<C, RaftError<C, ClientWriteError<C>>>> {
+        Err(RPCError::Unreachable(Unreachable::new(&AnyError::error(
+            "forward_client_write not implemented",
+        ))))
+    }
+
+    /// Exchange protocol versions with the target node.
+    ///
+    /// It is sent on first contact with a peer, and retried with backoff until it succeeds. The
+    /// node received this message should pass it to [`Raft::handle_handshake()`] and send back the
+    /// response wrapped in `Some`. Openraft then refrains from using the features the peer's
+    /// [`ProtocolVersion`] does not support, which allows a rolling upgrade of a cluster.
+    ///
+    /// This method provide a default implementation that returns `Ok(None)`, meaning handshake is
+    /// not supported by this network. The peer is then taken as speaking
+    /// [`ProtocolVersion::V1`] and the handshake is not retried.
+    ///
+    /// [`Raft::handle_handshake()`]: crate::raft::Raft::handle_handshake
+    /// [`ProtocolVersion`]: crate::network::ProtocolVersion
+    /// [`ProtocolVersion::V1`]: crate::network::ProtocolVersion::V1
+    #[since(version = "0.10.0")]
+    async fn handshake(
+        &mut self,
+        _req: HandshakeRequest<C>,
+        _option: RPCOption,
+    ) -> Result<Option<HandshakeResponse>, RPCError<C>> {
+        Ok(None)
+    }
+
+    /// Ask an up-to-date follower to bring another node up to date in place of the Leader.
+    ///
+    /// When [`Config::catch_up_delegation_threshold`] is set, a Leader sends it to a follower that
+    /// has every committed log, for a target lagging behind by more than the threshold. The
+    /// implementation on the remote node should call [`Raft::handle_catch_up()`] and send back
+    /// the response.
+    ///
+    /// By default it returns an [`Unreachable`] error, and the Leader replicates to the target
+    /// itself.
+    ///
+    /// [`Config::catch_up_delegation_threshold`]: crate::Config::catch_up_delegation_threshold
+    /// [`Raft::handle_catch_up()`]: crate::raft::Raft::handle_catch_up
+    #[since(version = "0.10.0")]
+    async fn catch_up(
+        &mut self,
+        _rpc: CatchUpRequest<C>,
+        _option: RPCOption,
+    ) -> Result<CatchUpResponse<C>, RPCError<C>> {
+        Err(RPCError::Unreachable(Unreachable::new(&AnyError::error(
+            "catch_up not implemented",
+        ))))
+    }
+
+    /// Tell a follower that the Leader stops sending heartbeats because the group is idle.
+    ///
+    /// When [`Config::hibernate_timeout`] is set, a Leader that has received no client request for
+    /// that long sends it to every follower and learner, then stops sending heartbeats. The
+    /// implementation on the remote node should call [`Raft::handle_hibernate()`].
+    ///
+    /// By default it returns an [`Unreachable`] error, and the Leader keeps sending heartbeats.
+    ///
+    /// [`Config::hibernate_timeout`]: crate::Config::hibernate_timeout
+    /// [`Raft::handle_hibernate()`]: crate::raft::Raft::handle_hibernate
+    #[since(version = "0.10.0")]
+    async fn hibernate(&mut self, _rpc: HibernateRequest<C>, _option: RPCOption) -> Result<(), RPCError<C>> {
+        Err(RPCError::Unreachable(Unreachable::new(&AnyError::error(
+            "hibernate not implemented",
+        ))))
+    }
+
     /// Build a backoff instance if the target node is temporarily(or permanently) unreachable.
     ///
     /// When a [`Unreachable`](`crate::error::Unreachable`) error is returned from the `Network`
@@ -111,4 +212,21 @@ where C: RaftTypeConfig
     fn backoff(&self) -> Backoff {
         Backoff::new(std::iter::repeat(Duration::from_millis(500)))
     }
+
+    /// Build the policy that decides what to do when an RPC to the target fails.
+    ///
+    /// A replication stream calls it once when it starts, and consults the returned
+    /// [`RetryPolicy`] upon every failed AppendEntries or InstallSnapshot RPC, to decide whether
+    /// to resend, how long to wait and whether to send fewer entries. See [`retry`] for the
+    /// policies provided by Openraft.
+    ///
+    /// By default it returns [`DefaultRetryPolicy`]: it waits with [`backoff()`](Self::backoff)
+    /// after an [`Unreachable`] error, and retries with fewer entries after a
+    /// [`PayloadTooLarge`](crate::error::PayloadTooLarge) error.
+    ///
+    /// [`retry`]: crate::network::retry
+    #[since(version = "0.10.0")]
+    fn retry_policy(&self) -> Box<dyn RetryPolicy<C>> {
+        Box::new(DefaultRetryPolicy)
+    }
 }
@@ -0,0 +1,635 @@
+//! Stream a snapshot in chunks, for a [`RaftNetworkV2::full_snapshot()`] implementation.
+//!
+//! Unlike [`Chunked`], which sends chunks with the v1 `install_snapshot()` RPC and requires the
+//! snapshot data to be seekable, this module only reads the snapshot data with [`AsyncRead`] on
+//! the sending side and writes it with [`AsyncWrite`] on the receiving side. How chunks are sent is
+//! left to the transport:
+//!
+//! - On the Leader, [`send_snapshot()`] reads chunks from the snapshot and passes them to a
+//!   [`SnapshotChunkSink`] provided by the transport, e.g., a client stream of a gRPC call. At most
+//!   `window` chunks are sent but not yet acknowledged.
+//! - On the receiving node, [`SnapshotAssembler`] writes the chunks into the snapshot data returned
+//!   by [`Raft::begin_receiving_snapshot()`], and installs it with
+//!   [`Raft::install_full_snapshot()`] when the last chunk is received. Every chunk is acknowledged
+//!   with the returned [`SnapshotResponse`].
+//!
+//! ```ignore
+//! // Leader side:
+//! impl RaftNetworkV2<C> for MyConnection {
+//!     async fn full_snapshot(&mut self, vote, snapshot, cancel, option) -> Result<_, StreamingError<C>> {
+//!         let mut sink = self.open_snapshot_stream().await?;
+//!         send_snapshot(&mut sink, vote, snapshot, cancel, &option, 4).await
+//!     }
+//! }
+//!
+//! // Receiving side, for every snapshot stream:
+//! let mut assembler = SnapshotAssembler::new(raft.clone());
+//! while let Some(chunk) = stream.next().await {
+//!     let ack = assembler.receive(chunk).await?;
+//!     stream.send_ack(ack).await?;
+//! }
+//! ```
+//!
+//! [`RaftNetworkV2::full_snapshot()`]: crate::network::v2::RaftNetworkV2::full_snapshot
+//! [`Chunked`]: crate::network::snapshot_transport::Chunked
+//! [`Raft::begin_receiving_snapshot()`]: crate::Raft::begin_receiving_snapshot
+//! [`Raft::install_full_snapshot()`]: crate::Raft::install_full_snapshot
+
+use std::fmt;
+use std::future::Future;
+use std::pin::pin;
+use std::pin::Pin;
+
+use futures::future::select;
+use futures::future::Either;
+use openraft_macros::add_async_trait;
+use tokio::io::AsyncRead;
+use tokio::io::AsyncReadExt;
+use tokio::io::AsyncWrite;
+use tokio::io::AsyncWriteExt;
+
+use crate::async_runtime::watch::WatchReceiver;
+use crate::error::CompressionError;
+use crate::error::NetworkError;
+use crate::error::RaftError;
+use crate::error::ReplicationClosed;
+use crate::error::SnapshotMismatch;
+use crate::error::StreamingError;
+use crate::network::compression::Compression;
+use crate::network::RPCOption;
+use crate::raft::SnapshotResponse;
+use crate::storage::Snapshot;
+use crate::storage::SnapshotMeta;
+use crate::type_config::alias::VoteOf;
+use crate::vote::raft_vote::RaftVoteExt;
+use crate::ErrorSubject;
+use crate::ErrorVerb;
+use crate::OptionalSend;
+use crate::Raft;
+use crate::RaftTypeConfig;
+use crate::SnapshotSegmentId;
+use crate::StorageError;
+use crate::ToStorageResult;
+
+/// The chunk size used if [`RPCOption::snapshot_chunk_size()`] is not set, the same as the default
+/// [`Config::snapshot_max_chunk_size`](crate::Config::snapshot_max_chunk_size).
+const DEFAULT_CHUNK_SIZE: usize = 3 * 1024 * 1024;
+
+/// A message of a snapshot stream.
+///
+/// A stream consists of a `Start`, `Data` chunks in the order of their offsets, and a `Finish`.
+#[derive(Debug, Clone, PartialEq, Eq)]
+#[cfg_attr(feature = "serde", derive(serde::Deserialize, serde::Serialize), serde(bound = ""))]
+pub enum SnapshotChunk<C>
+where C: RaftTypeConfig
+{
+    /// Start receiving a snapshot sent by the Leader with `vote`.
+    Start { vote: VoteOf<C>, meta: SnapshotMeta<C> },
+
+    /// The snapshot data at `offset`, compressed with `compression`.
+    ///
+    /// `offset` is the position in the uncompressed snapshot data.
+    Data {
+        offset: u64,
+        compression: Compression,
+        data: Vec<u8>,
+    },
+
+    /// All of the `size` bytes of snapshot data are sent.
+    Finish { size: u64 },
+}
+
+impl<C> fmt::Display for SnapshotChunk<C>
+where C: RaftTypeConfig
+{
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            SnapshotChunk::Start { vote, meta } => write!(f, "Start{{vote: {}, meta: {}}}", vote, meta),
+            SnapshotChunk::Data {
+                offset,
+                compression,
+                data,
+            } => write!(f, "Data{{offset: {}, {}, len: {}}}", offset, compression, data.len()),
+            SnapshotChunk::Finish { size } => write!(f, "Finish{{size: {}}}", size),
+        }
+    }
+}
+
+/// The sending half of a snapshot stream to a single target, provided by the transport.
+///
+/// The receiver acknowledges every [`SnapshotChunk`], in order, with the [`SnapshotResponse`]
+/// returned by [`SnapshotAssembler::receive()`].
+#[add_async_trait]
+pub trait SnapshotChunkSink<C>: OptionalSend
+where C: RaftTypeConfig
+{
+    /// Send a chunk without waiting for it to be acknowledged.
+    async fn send_chunk(&mut self, chunk: SnapshotChunk<C>) -> Result<(), StreamingError<C>>;
+
+    /// Wait for the acknowledgement of the earliest chunk that is sent but not yet acknowledged.
+    ///
+    /// An implementation should return a [`Timeout`](crate::error::Timeout) error if the
+    /// acknowledgement is not received within [`RPCOption::hard_ttl()`].
+    async fn recv_ack(&mut self) -> Result<SnapshotResponse<C>, StreamingError<C>>;
+
+    /// The compression negotiated with the receiver, to compress `Data` chunks with.
+    ///
+    /// The default is no compression. See [`Compression::negotiate()`].
+    fn compression(&self) -> Compression {
+        Compression::None
+    }
+}
+
+/// Stream a snapshot to `sink` in chunks of [`RPCOption::snapshot_chunk_size()`], and return the
+/// response to the last chunk.
+///
+/// At most `window` chunks are sent before their acknowledgements are received. It returns early
+/// if an acknowledgement contains a vote greater than `vote`; the caller then finds the higher
+/// vote in the returned response, as with any `full_snapshot()` response.
+///
+/// `Data` chunks are compressed with [`SnapshotChunkSink::compression()`], except those that do
+/// not get smaller.
+///
+/// The snapshot data is read from its current position to the end.
+pub async fn send_snapshot<C, S>(
+    sink: &mut S,
+    vote: VoteOf<C>,
+    snapshot: Snapshot<C>,
+    cancel: impl Future<Output = ReplicationClosed> + OptionalSend + 'static,
+    option: &RPCOption,
+    window: usize,
+) -> Result<SnapshotResponse<C>, StreamingError<C>>
+where
+    C: RaftTypeConfig,
+    C::SnapshotData: AsyncRead + Unpin,
+    S: SnapshotChunkSink<C> + ?Sized,
+{
+    let chunk_size = option.snapshot_chunk_size().unwrap_or(DEFAULT_CHUNK_SIZE).max(1);
+    let Snapshot {
+        meta,
+        snapshot: mut data,
+    } = snapshot;
+
+    let subject_verb = || (ErrorSubject::Snapshot(Some(meta.signature())), ErrorVerb::Read);
+
+    let compression = sink.compression();
+
+    let mut sender = WindowedSender {
+        sink,
+        vote: vote.clone(),
+        window: window.max(1),
+        in_flight: 0,
+        cancel: pin!(cancel),
+    };
+
+    if let Some(resp) = sender
+        .send(SnapshotChunk::Start {
+            vote,
+            meta: meta.clone(),
+        })
+        .await?
+    {
+        return Ok(resp);
+    }
+
+    let mut offset = 0;
+    loop {
+        let mut buf = Vec::with_capacity(chunk_size);
+        (&mut *data).take(chunk_size as u64).read_to_end(&mut buf).await.sto_res(subject_verb)?;
+
+        if buf.is_empty() {
+            break;
+        }
+
+        let n = buf.len() as u64;
+        let (compression, buf) = compression.compress_above(buf, 0).map_err(|e| NetworkError::new(&e))?;
+
+        tracing::debug!(offset, len = n, compressed_len = buf.len(), "sending snapshot chunk");
+
+        let chunk = SnapshotChunk::Data {
+            offset,
+            compression,
+            data: buf,
+        };
+        if let Some(resp) = sender.send(chunk).await? {
+            return Ok(resp);
+        }
+        offset += n;
+    }
+
+    if let Some(resp) = sender.send(SnapshotChunk::Finish { size: offset }).await? {
+        return Ok(resp);
+    }
+
+    // The last acknowledgement is the response to `Finish`.
+    loop {
+        let resp = sender.recv_ack().await?;
+        if sender.in_flight == 0 {
+            return Ok(resp);
+        }
+        if sender.is_rejected(&resp) {
+            return Ok(resp);
+        }
+    }
+}
+
+/// Sends chunks to a sink, and keeps at most `window` of them unacknowledged.
+struct WindowedSender<'a, C, S, F>
+where
+    C: RaftTypeConfig,
+    S: ?Sized,
+{
+    sink: &'a mut S,
+    vote: VoteOf<C>,
+    window: usize,
+    in_flight: usize,
+    cancel: Pin<&'a mut F>,
+}
+
+impl<C, S, F> WindowedSender<'_, C, S, F>
+where
+    C: RaftTypeConfig,
+    S: SnapshotChunkSink<C> + ?Sized,
+    F: Future<Output = ReplicationClosed>,
+{
+    /// Send a chunk after the window has room for it.
+    ///
+    /// It returns the acknowledgement with a higher vote if one is received while waiting.
+    async fn send(&mut self, chunk: SnapshotChunk<C>) -> Result<Option<SnapshotResponse<C>>, StreamingError<C>> {
+        while self.in_flight >= self.window {
+            let resp = self.recv_ack().await?;
+            if self.is_rejected(&resp) {
+                return Ok(Some(resp));
+            }
+        }
+
+        let closed = match select(self.cancel.as_mut(), pin!(self.sink.send_chunk(chunk))).await {
+            Either::Left((closed, _)) => closed,
+            Either::Right((res, _)) => {
+                res?;
+                self.in_flight += 1;
+                return Ok(None);
+            }
+        };
+        Err(closed.into())
+    }
+
+    async fn recv_ack(&mut self) -> Result<SnapshotResponse<C>, StreamingError<C>> {
+        let closed = match select(self.cancel.as_mut(), pin!(self.sink.recv_ack())).await {
+            Either::Left((closed, _)) => closed,
+            Either::Right((res, _)) => {
+                let resp = res?;
+                self.in_flight -= 1;
+                return Ok(resp);
+            }
+        };
+        Err(closed.into())
+    }
+
+    fn is_rejected(&self, resp: &SnapshotResponse<C>) -> bool {
+        resp.vote.as_ref_vote() > self.vote.as_ref_vote()
+    }
+}
+
+/// A chunk of a snapshot stream that [`SnapshotAssembler::receive()`] can not accept.
+#[derive(Debug, Clone, thiserror::Error, derive_more::TryInto)]
+#[derive(PartialEq, Eq)]
+#[cfg_attr(feature = "serde", derive(serde::Deserialize, serde::Serialize))]
+pub enum ReceiveSnapshotError {
+    /// The chunk does not continue the stream, the Leader has to restart from `Start`.
+    #[error(transparent)]
+    SnapshotMismatch(#[from] SnapshotMismatch),
+
+    /// A `Data` chunk can not be decompressed.
+    #[error(transparent)]
+    Compression(#[from] CompressionError),
+}
+
+/// Assembles the chunks of a snapshot stream into the snapshot data of a local [`Raft`], and
+/// installs the snapshot when the stream finishes.
+///
+/// An assembler is used for one stream at a time. A `Start` chunk discards the snapshot being
+/// received, if there is one, and starts a new one.
+pub struct SnapshotAssembler<C>
+where C: RaftTypeConfig
+{
+    raft: Raft<C>,
+    receiving: Option<Receiving<C>>,
+}
+
+/// The snapshot that is being received.
+struct Receiving<C>
+where C: RaftTypeConfig
+{
+    vote: VoteOf<C>,
+
+    /// The vote of the local node when the stream started, sent back in acknowledgements.
+    local_vote: VoteOf<C>,
+
+    meta: SnapshotMeta<C>,
+    data: Box<C::SnapshotData>,
+
+    /// The number of bytes written.
+    offset: u64,
+}
+
+impl<C> SnapshotAssembler<C>
+where
+    C: RaftTypeConfig,
+    C::SnapshotData: AsyncWrite + Unpin,
+{
+    pub fn new(raft: Raft<C>) -> Self {
+        Self { raft, receiving: None }
+    }
+
+    /// Handle a chunk and return the acknowledgement to send back to the Leader.
+    ///
+    /// The acknowledgement of `Finish` is the response of [`Raft::install_full_snapshot()`]. A
+    /// chunk that does not continue the stream, such as a `Data` at an unexpected offset, is
+    /// rejected with [`SnapshotMismatch`], and the Leader has to restart from `Start`. A `Data`
+    /// compressed with an algorithm that is not enabled is rejected with a [`CompressionError`].
+    pub async fn receive(
+        &mut self,
+        chunk: SnapshotChunk<C>,
+    ) -> Result<SnapshotResponse<C>, RaftError<C, ReceiveSnapshotError>> {
+        tracing::debug!(chunk = display(&chunk), "{}", func_name!());
+
+        match chunk {
+            SnapshotChunk::Start { vote, meta } => {
+                // Safe unwrap: `RaftError<Infallible>` is always a Fatal.
+                let data = self
+                    .raft
+                    .begin_receiving_snapshot()
+                    .await
+                    .map_err(|e| RaftError::Fatal(e.into_fatal().unwrap()))?;
+
+                let local_vote = self.raft.metrics().borrow_watched().vote.clone();
+                let resp = SnapshotResponse::new(local_vote.clone());
+
+                self.receiving = Some(Receiving {
+                    vote,
+                    local_vote,
+                    meta,
+                    data,
+                    offset: 0,
+                });
+                Ok(resp)
+            }
+
+            SnapshotChunk::Data {
+                offset,
+                compression,
+                data,
+            } => {
+                let r = self.expect_offset(offset)?;
+                let data = compression.decompress(&data).map_err(|e| RaftError::APIError(e.into()))?;
+
+                r.data
+                    .write_all(&data)
+                    .await
+                    .map_err(|e| StorageError::write_snapshot(Some(r.meta.signature()), &e))?;
+                r.offset += data.len() as u64;
+
+                Ok(SnapshotResponse::new(r.local_vote.clone()))
+            }
+
+            SnapshotChunk::Finish { size } => {
+                self.expect_offset(size)?;
+
+                // Safe unwrap: `expect_offset()` returns error if it is None.
+                let mut r = self.receiving.take().unwrap();
+
+                r.data.shutdown().await.map_err(|e| StorageError::write_snapshot(Some(r.meta.signature()), &e))?;
+
+                tracing::info!("finished receiving snapshot: {}", r.meta);
+
+                let resp = self.raft.install_full_snapshot(r.vote, Snapshot::new(r.meta, r.data)).await?;
+                Ok(resp)
+            }
+        }
+    }
+
+    /// Return the snapshot being received if its data ends at `offset`.
+    fn expect_offset(&mut self, offset: u64) -> Result<&mut Receiving<C>, RaftError<C, ReceiveSnapshotError>> {
+        let (expect, got) = match &mut self.receiving {
+            Some(r) if r.offset == offset => return Ok(self.receiving.as_mut().unwrap()),
+            Some(r) => {
+                let id = r.meta.snapshot_id.clone();
+                (
+                    SnapshotSegmentId::from((&id, r.offset)),
+                    SnapshotSegmentId::from((&id, offset)),
+                )
+            }
+            // Not started, expect a new stream.
+            None => (SnapshotSegmentId::default(), SnapshotSegmentId::from(("", offset))),
+        };
+
+        let mismatch = SnapshotMismatch { expect, got };
+        Err(RaftError::APIError(ReceiveSnapshotError::SnapshotMismatch(mismatch)))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::collections::VecDeque;
+    use std::io::Cursor;
+    use std::time::Duration;
+
+    use super::send_snapshot;
+    use super::SnapshotChunk;
+    use super::SnapshotChunkSink;
+    use crate::engine::testing::UTConfig;
+    use crate::error::StreamingError;
+    use crate::network::compression::Compression;
+    use crate::network::RPCOption;
+    use crate::raft::SnapshotResponse;
+    use crate::storage::Snapshot;
+    use crate::storage::SnapshotMeta;
+    use crate::Vote;
+
+    /// A sink that acknowledges chunks with the votes in `acks`, and records the max number of
+    /// chunks in flight.
+    #[derive(Default)]
+    struct Sink {
+        sent: Vec<SnapshotChunk<UTConfig>>,
+        acks: VecDeque<Vote<UTConfig>>,
+        in_flight: usize,
+        max_in_flight: usize,
+        compression: Compression,
+    }
+
+    impl SnapshotChunkSink<UTConfig> for Sink {
+        async fn send_chunk(&mut self, chunk: SnapshotChunk<UTConfig>) -> Result<(), StreamingError<UTConfig>> {
+            self.sent.push(chunk);
+            self.in_flight += 1;
+            self.max_in_flight = self.max_in_flight.max(self.in_flight);
+            Ok(())
+        }
+
+        async fn recv_ack(&mut self) -> Result<SnapshotResponse<UTConfig>, StreamingError<UTConfig>> {
+            self.in_flight -= 1;
+            let vote = self.acks.pop_front().unwrap_or(Vote::new_committed(1, 0));
+            Ok(SnapshotResponse::new(vote))
+        }
+
+        fn compression(&self) -> Compression {
+            self.compression
+        }
+    }
+
+    fn snapshot(data: Vec<u8>) -> Snapshot<UTConfig> {
+        Snapshot::new(
+            SnapshotMeta {
+                last_log_id: None,
+                last_membership: Default::default(),
+                snapshot_id: "s1".to_string(),
+            },
+            Box::new(Cursor::new(data)),
+        )
+    }
+
+    fn option(chunk_size: usize) -> RPCOption {
+        let mut opt = RPCOption::new(Duration::from_millis(100));
+        opt.snapshot_chunk_size = Some(chunk_size);
+        opt
+    }
+
+    #[tokio::test]
+    async fn test_send_snapshot_in_window() -> anyhow::Result<()> {
+        let mut sink = Sink::default();
+        let cancel = futures::future::pending();
+
+        let resp = send_snapshot(
+            &mut sink,
+            Vote::new_committed(1, 0),
+            snapshot(vec![1, 2, 3, 4, 5]),
+            cancel,
+            &option(2),
+            2,
+        )
+        .await?;
+
+        assert_eq!(Vote::new_committed(1, 0), resp.vote);
+        assert_eq!(
+            vec![
+                SnapshotChunk::Start {
+                    vote: Vote::new_committed(1, 0),
+                    meta: snapshot(vec![]).meta,
+                },
+                SnapshotChunk::Data {
+                    offset: 0,
+                    compression: Compression::None,
+                    data: vec![1, 2]
+                },
+                SnapshotChunk::Data {
+                    offset: 2,
+                    compression: Compression::None,
+                    data: vec![3, 4]
+                },
+                SnapshotChunk::Data {
+                    offset: 4,
+                    compression: Compression::None,
+                    data: vec![5]
+                },
+                SnapshotChunk::Finish { size: 5 },
+            ],
+            sink.sent
+        );
+        assert_eq!(2, sink.max_in_flight);
+        assert_eq!(0, sink.in_flight);
+
+        Ok(())
+    }
+
+    /// Stop sending once the receiver acknowledges with a higher vote.
+    #[tokio::test]
+    async fn test_send_snapshot_stop_at_higher_vote() -> anyhow::Result<()> {
+        let mut sink = Sink {
+            acks: VecDeque::from([Vote::new(2, 1)]),
+            ..Default::default()
+        };
+        let cancel = futures::future::pending();
+
+        let resp = send_snapshot(
+            &mut sink,
+            Vote::new_committed(1, 0),
+            snapshot(vec![1, 2, 3, 4, 5]),
+            cancel,
+            &option(1),
+            1,
+        )
+        .await?;
+
+        assert_eq!(Vote::new(2, 1), resp.vote);
+        assert_eq!(1, sink.sent.len(), "only Start is sent");
+
+        Ok(())
+    }
+
+    #[tokio::test]
+    async fn test_send_snapshot_cancel() -> anyhow::Result<()> {
+        let mut sink = Sink::default();
+        let cancel = futures::future::ready(crate::error::ReplicationClosed::new("test"));
+
+        let res = send_snapshot(
+            &mut sink,
+            Vote::new_committed(1, 0),
+            snapshot(vec![1, 2, 3]),
+            cancel,
+            &option(1),
+            1,
+        )
+        .await;
+
+        assert!(matches!(res, Err(StreamingError::Closed(_))), "got: {:?}", res);
+
+        Ok(())
+    }
+
+    /// `Data` chunks are compressed with the compression of the sink, unless they do not get
+    /// smaller.
+    #[tokio::test]
+    async fn test_send_snapshot_compressed() -> anyhow::Result<()> {
+        let compression = Compression::supported()[0];
+        let data = b"abcd".repeat(256);
+
+        let mut sink = Sink {
+            compression,
+            ..Default::default()
+        };
+
+        send_snapshot(
+            &mut sink,
+            Vote::new_committed(1, 0),
+            snapshot([data.clone(), vec![7]].concat()),
+            futures::future::pending(),
+            &option(1024),
+            2,
+        )
+        .await?;
+
+        let SnapshotChunk::Data {
+            offset: 0,
+            compression: c,
+            data: compressed,
+        } = &sink.sent[1]
+        else {
+            panic!("expect the first Data chunk, got: {}", sink.sent[1]);
+        };
+        assert_eq!(compression, *c);
+        assert_eq!(data, c.decompress(compressed)?);
+
+        assert_eq!(
+            SnapshotChunk::Data {
+                offset: 1024,
+                compression: Compression::None,
+                data: vec![7],
+            },
+            sink.sent[2],
+            "a single byte does not get smaller"
+        );
+        assert_eq!(SnapshotChunk::Finish { size: 1025 }, sink.sent[3]);
+
+        Ok(())
+    }
+}
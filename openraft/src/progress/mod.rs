@@ -296,7 +296,8 @@ where
     ///
     /// This way it gets rid of a portion of unnecessary re-calculation of committed,
     /// and avoids unnecessary sorting: progresses are kept in order and only values greater than
-    /// committed need to sort.
+    /// committed need to sort. A value greater than committed is still moved up when it increases,
+    /// otherwise a later re-calculation would see an unsorted prefix.
     ///
     /// E.g., given 3 ids with value `1,3,5`, as shown in the figure below:
     ///
@@ -354,29 +355,35 @@ where
             return Ok(&self.granted);
         }
 
-        // Sort and find the greatest value granted by a quorum set.
+        if !new_gt_granted {
+            return Ok(&self.granted);
+        }
+
+        // Keep values greater than `granted` sorted, even if the granted value can not change.
+        let new_index = self.move_up(index);
 
-        if prev_le_granted && new_gt_granted {
-            let new_index = self.move_up(index);
+        // A value that was already greater than `granted` does not make up a new quorum.
+        if !prev_le_granted {
+            return Ok(&self.granted);
+        }
 
-            // From high to low, find the max value that has constituted a quorum.
-            for i in new_index..self.voter_count {
-                let prog = self.vector[i].1.borrow();
+        // From high to low, find the max value that has constituted a quorum.
+        for i in new_index..self.voter_count {
+            let prog = self.vector[i].1.borrow();
 
-                // No need to re-calculate already committed value.
-                if prog <= &self.granted {
-                    break;
-                }
+            // No need to re-calculate already committed value.
+            if prog <= &self.granted {
+                break;
+            }
 
-                // Ids of the target that has value GE `vector[i]`
-                let it = self.vector[0..=i].iter().map(|x| &x.0);
+            // Ids of the target that has value GE `vector[i]`
+            let it = self.vector[0..=i].iter().map(|x| &x.0);
 
-                self.stat.is_quorum_count += 1;
+            self.stat.is_quorum_count += 1;
 
-                if self.quorum_set.is_quorum(it) {
-                    self.granted = prog.clone();
-                    break;
-                }
+            if self.quorum_set.is_quorum(it) {
+                self.granted = prog.clone();
+                break;
             }
         }
 
@@ -574,6 +581,26 @@ mod t {
         Ok(())
     }
 
+    #[test]
+    fn vec_progress_update_keeps_values_above_granted_sorted() -> anyhow::Result<()> {
+        let quorum_set: Vec<u64> = vec![0, 1, 2, 3, 4];
+        let mut progress = VecProgress::<u64, u64, u64, _>::new(quorum_set, [], || 0);
+
+        let cases = [
+            ((0, 10), Ok(&0)),  // 10,0,0,0,0
+            ((1, 8), Ok(&0)),   // 10,8,0,0,0
+            ((2, 3), Ok(&3)),   // 10,8,3,0,0
+            ((1, 12), Ok(&3)),  // 10,12,3,0,0 // 1 is above granted and has to be moved up
+            ((2, 11), Ok(&10)), // 10,12,11,0,0 // only 1 and 2 reach 11
+        ];
+
+        for (ith, ((id, v), want_committed)) in cases.iter().enumerate() {
+            let got = progress.update(id, *v);
+            assert_eq!(want_committed.clone(), got, "{}-th case: id:{}, v:{}", ith, id, v);
+        }
+        Ok(())
+    }
+
     /// Progress entry for testing
     #[derive(Clone, Copy, Debug, PartialEq, Eq)]
     struct ProgressEntry {
@@ -52,6 +52,16 @@ where C: RaftTypeConfig
     /// The time to send next heartbeat.
     pub(crate) next_heartbeat: InstantOf<C>,
 
+    /// The last time this Leader received a client request.
+    ///
+    /// The Leader hibernates when it is idle for [`Config::hibernate_timeout`].
+    ///
+    /// [`Config::hibernate_timeout`]: crate::Config::hibernate_timeout
+    pub(crate) last_active: InstantOf<C>,
+
+    /// Whether this Leader has stopped sending heartbeats because it is idle.
+    pub(crate) hibernating: bool,
+
     last_log_id: Option<LogIdOf<C>>,
 
     /// The log id of the first log entry proposed by this leader,
@@ -125,6 +135,8 @@ where
             transfer_to: None,
             committed_vote: vote,
             next_heartbeat: C::now(),
+            last_active: C::now(),
+            hibernating: false,
             last_log_id: last_log_id.clone(),
             noop_log_id,
             progress: VecProgress::new(quorum_set.clone(), learner_ids.iter().cloned(), || {
@@ -183,6 +195,7 @@ where
 
         for entry in it {
             entry.set_log_id(LogIdOf::<C>::new(committed_leader_id.clone(), index));
+            entry.seal_checksum();
             tracing::debug!("assign log id: {}", entry.ref_log_id());
             index += 1;
         }
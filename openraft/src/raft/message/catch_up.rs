@@ -0,0 +1,90 @@
+use std::fmt;
+
+use crate::display_ext::DisplayOptionExt;
+use crate::type_config::alias::LogIdOf;
+use crate::type_config::alias::VoteOf;
+use crate::RaftTypeConfig;
+
+/// Sent by the Leader to an up-to-date follower, to ask it to bring `target` up to
+/// `last_log_id` in place of the Leader.
+///
+/// The follower sends its log entries, or its latest snapshot if the entries are purged, to the
+/// target with the Leader's `vote`. Only committed log entries are delegated, so that they are the
+/// same on every node that has them.
+#[derive(Clone, Debug)]
+#[derive(PartialEq, Eq)]
+#[cfg_attr(feature = "serde", derive(serde::Deserialize, serde::Serialize), serde(bound = ""))]
+pub struct CatchUpRequest<C>
+where C: RaftTypeConfig
+{
+    /// The vote of the Leader that delegates the catch-up.
+    pub vote: VoteOf<C>,
+
+    /// The node to bring up to date.
+    pub target: C::NodeId,
+
+    /// The address of the node to bring up to date.
+    pub target_node: C::Node,
+
+    /// The last log id the target is known to have.
+    pub prev_log_id: Option<LogIdOf<C>>,
+
+    /// The last log id to send to the target.
+    pub last_log_id: LogIdOf<C>,
+
+    /// The Leader's committed log id.
+    pub leader_commit: Option<LogIdOf<C>>,
+}
+
+impl<C> fmt::Display for CatchUpRequest<C>
+where C: RaftTypeConfig
+{
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(
+            f,
+            "vote={}, target={}, logs:({}, {}], leader_commit={}",
+            self.vote,
+            self.target,
+            self.prev_log_id.display(),
+            self.last_log_id,
+            self.leader_commit.display()
+        )
+    }
+}
+
+/// The result of a [`CatchUpRequest`].
+#[derive(Clone, Debug)]
+#[derive(PartialEq, Eq)]
+#[cfg_attr(feature = "serde", derive(serde::Deserialize, serde::Serialize), serde(bound = ""))]
+pub enum CatchUpResponse<C>
+where C: RaftTypeConfig
+{
+    /// The target's log matches the Leader's up to this log id.
+    ///
+    /// It may be less than the requested `last_log_id` if the catch-up stopped midway, in which
+    /// case the Leader replicates the rest itself.
+    Success(Option<LogIdOf<C>>),
+
+    /// The target does not have the requested `prev_log_id`.
+    Conflict,
+
+    /// The follower or the target has seen a vote higher than the Leader's.
+    HigherVote(VoteOf<C>),
+
+    /// The follower did not send anything, e.g., because it does not have the logs or can not
+    /// reach the target. The Leader replicates to the target itself.
+    Declined(String),
+}
+
+impl<C> fmt::Display for CatchUpResponse<C>
+where C: RaftTypeConfig
+{
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            CatchUpResponse::Success(m) => write!(f, "Success({})", m.display()),
+            CatchUpResponse::Conflict => write!(f, "Conflict"),
+            CatchUpResponse::HigherVote(v) => write!(f, "HigherVote({})", v),
+            CatchUpResponse::Declined(reason) => write!(f, "Declined({})", reason),
+        }
+    }
+}
@@ -0,0 +1,55 @@
+use std::fmt;
+
+use crate::network::ProtocolVersion;
+use crate::RaftTypeConfig;
+
+/// Sent on first contact with a peer to tell it the protocol version of the sender.
+#[derive(Clone, Debug)]
+#[derive(PartialEq, Eq)]
+#[cfg_attr(feature = "serde", derive(serde::Deserialize, serde::Serialize), serde(bound = ""))]
+pub struct HandshakeRequest<C>
+where C: RaftTypeConfig
+{
+    /// The ID of the node sending the handshake.
+    pub from: C::NodeId,
+
+    /// The protocol version of the sender.
+    pub version: ProtocolVersion,
+}
+
+impl<C> HandshakeRequest<C>
+where C: RaftTypeConfig
+{
+    pub fn new(from: C::NodeId, version: ProtocolVersion) -> Self {
+        Self { from, version }
+    }
+}
+
+impl<C> fmt::Display for HandshakeRequest<C>
+where C: RaftTypeConfig
+{
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "{{from:{}, version:{}}}", self.from, self.version)
+    }
+}
+
+/// The response to a [`HandshakeRequest`], carrying the protocol version of the receiver.
+#[derive(Clone, Debug)]
+#[derive(PartialEq, Eq)]
+#[cfg_attr(feature = "serde", derive(serde::Deserialize, serde::Serialize))]
+pub struct HandshakeResponse {
+    /// The protocol version of the receiver.
+    pub version: ProtocolVersion,
+}
+
+impl HandshakeResponse {
+    pub fn new(version: ProtocolVersion) -> Self {
+        Self { version }
+    }
+}
+
+impl fmt::Display for HandshakeResponse {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "{{version:{}}}", self.version)
+    }
+}
@@ -0,0 +1,35 @@
+use std::fmt;
+
+use crate::display_ext::DisplayOptionExt;
+use crate::type_config::alias::LogIdOf;
+use crate::type_config::alias::VoteOf;
+use crate::RaftTypeConfig;
+
+/// Sent by an idle Leader to tell a follower that it stops sending heartbeats.
+///
+/// The follower suspends its election timer until it receives another message from a Leader or a
+/// Candidate, or until the application wakes it up with [`Trigger::wake_up()`].
+///
+/// [`Trigger::wake_up()`]: crate::raft::trigger::Trigger::wake_up
+#[derive(Clone, Debug)]
+#[derive(PartialEq, Eq)]
+#[cfg_attr(feature = "serde", derive(serde::Deserialize, serde::Serialize), serde(bound = ""))]
+pub struct HibernateRequest<C>
+where C: RaftTypeConfig
+{
+    /// The vote of the Leader that hibernates.
+    pub vote: VoteOf<C>,
+
+    /// The last log id of the Leader.
+    ///
+    /// It is committed and every node has it when the Leader hibernates.
+    pub last_log_id: Option<LogIdOf<C>>,
+}
+
+impl<C> fmt::Display for HibernateRequest<C>
+where C: RaftTypeConfig
+{
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "vote={}, last_log_id={}", self.vote, self.last_log_id.display())
+    }
+}
@@ -4,6 +4,9 @@
 //! and are also used by network layer to talk to other Raft nodes.
 
 mod append_entries;
+mod catch_up;
+mod handshake;
+mod hibernate;
 mod install_snapshot;
 mod transfer_leader;
 mod vote;
@@ -12,8 +15,13 @@ mod client_write;
 
 pub use append_entries::AppendEntriesRequest;
 pub use append_entries::AppendEntriesResponse;
+pub use catch_up::CatchUpRequest;
+pub use catch_up::CatchUpResponse;
 pub use client_write::ClientWriteResponse;
 pub use client_write::ClientWriteResult;
+pub use handshake::HandshakeRequest;
+pub use handshake::HandshakeResponse;
+pub use hibernate::HibernateRequest;
 pub use install_snapshot::InstallSnapshotRequest;
 pub use install_snapshot::InstallSnapshotResponse;
 pub use install_snapshot::SnapshotResponse;
@@ -30,8 +30,13 @@ use std::time::Duration;
 use core_state::CoreState;
 pub use message::AppendEntriesRequest;
 pub use message::AppendEntriesResponse;
+pub use message::CatchUpRequest;
+pub use message::CatchUpResponse;
 pub use message::ClientWriteResponse;
 pub use message::ClientWriteResult;
+pub use message::HandshakeRequest;
+pub use message::HandshakeResponse;
+pub use message::HibernateRequest;
 pub use message::InstallSnapshotRequest;
 pub use message::InstallSnapshotResponse;
 pub use message::SnapshotResponse;
@@ -51,13 +56,17 @@ use crate::base::BoxFuture;
 use crate::base::BoxOnce;
 use crate::config::Config;
 use crate::config::RuntimeConfig;
+use crate::core::admission::Admission;
+use crate::core::admission::ProposalPermit;
 use crate::core::heartbeat::handle::HeartbeatWorkersHandle;
 use crate::core::raft_msg::external_command::ExternalCommand;
 use crate::core::raft_msg::RaftMsg;
 use crate::core::replication_lag;
 use crate::core::sm;
 use crate::core::sm::worker;
+use crate::core::ApplyThrottle;
 use crate::core::RaftCore;
+use crate::core::SharedTick;
 use crate::core::Tick;
 use crate::display_ext::DisplayOptionExt;
 use crate::engine::Engine;
@@ -68,17 +77,21 @@ use crate::error::Fatal;
 use crate::error::Infallible;
 use crate::error::InitializeError;
 use crate::error::InvalidStateMachineType;
+use crate::error::RPCError;
 use crate::error::RaftError;
+use crate::error::RemoteError;
 use crate::membership::IntoNodes;
 use crate::metrics::RaftDataMetrics;
 use crate::metrics::RaftMetrics;
 use crate::metrics::RaftServerMetrics;
 use crate::metrics::Wait;
 use crate::metrics::WaitError;
+use crate::network::RPCOption;
 use crate::raft::raft_inner::RaftInner;
 use crate::raft::responder::Responder;
 pub use crate::raft::runtime_config_handle::RuntimeConfigHandle;
 use crate::raft::trigger::Trigger;
+use crate::replication::log_cache::LogCache;
 use crate::storage::RaftLogStorage;
 use crate::storage::RaftStateMachine;
 use crate::storage::Snapshot;
@@ -91,6 +104,7 @@ use crate::type_config::alias::VoteOf;
 use crate::type_config::alias::WatchReceiverOf;
 use crate::type_config::TypeConfigExt;
 use crate::vote::raft_vote::RaftVoteExt;
+use crate::Instant;
 use crate::LogIdOptionExt;
 use crate::LogIndexOptionExt;
 use crate::OptionalSend;
@@ -241,11 +255,30 @@ where C: RaftTypeConfig
     /// used by Raft for data storage.
     #[tracing::instrument(level="debug", skip_all, fields(cluster=%config.cluster_name))]
     pub async fn new<LS, N, SM>(
+        id: C::NodeId,
+        config: Arc<Config>,
+        network: N,
+        log_store: LS,
+        state_machine: SM,
+    ) -> Result<Self, Fatal<C>>
+    where
+        N: RaftNetworkFactory<C>,
+        LS: RaftLogStorage<C>,
+        SM: RaftStateMachine<C>,
+    {
+        Self::new_with_tick(id, config, network, log_store, state_machine, None).await
+    }
+
+    /// Create and spawn a new Raft task, same as [`Raft::new()`].
+    ///
+    /// If `shared_tick` is given, this Raft subscribes to it instead of spawning its own tick task.
+    pub(crate) async fn new_with_tick<LS, N, SM>(
         id: C::NodeId,
         config: Arc<Config>,
         network: N,
         mut log_store: LS,
         mut state_machine: SM,
+        shared_tick: Option<&SharedTick<C>>,
     ) -> Result<Self, Fatal<C>>
     where
         N: RaftNetworkFactory<C>,
@@ -259,13 +292,19 @@ where C: RaftTypeConfig
         let (tx_server_metrics, rx_server_metrics) = C::watch_channel(RaftServerMetrics::default());
         let (tx_shutdown, rx_shutdown) = C::oneshot();
 
-        let tick_handle = Tick::spawn(
-            Duration::from_millis(config.heartbeat_interval * 3 / 2),
-            tx_notify.clone(),
-            config.enable_tick,
-        );
+        let tick_handle = match shared_tick {
+            Some(shared_tick) => shared_tick.subscribe(tx_notify.clone(), config.enable_tick),
+            None => Tick::spawn(
+                Duration::from_millis(config.heartbeat_interval * 3 / 2),
+                tx_notify.clone(),
+                config.enable_tick,
+            ),
+        };
 
         let runtime_config = Arc::new(RuntimeConfig::new(&config));
+        let log_cache = Arc::new(LogCache::new(config.log_cache_size));
+        let admission = Arc::new(Admission::new(config.max_in_flight_proposals));
+        let protocol_version = network.protocol_version();
 
         let core_span = tracing::span!(
             parent: tracing::Span::current(),
@@ -299,15 +338,22 @@ where C: RaftTypeConfig
             runtime_config: runtime_config.clone(),
             network_factory: network,
             log_store,
+            log_cache: log_cache.clone(),
             sm_handle,
 
             engine,
 
             client_resp_channels: BTreeMap::new(),
+            proposal_permits: BTreeMap::new(),
+            admission: admission.clone(),
+            apply_throttle: ApplyThrottle::default(),
 
             replications: Default::default(),
 
             heartbeat_handle: HeartbeatWorkersHandle::new(id.clone(), config.clone()),
+            peer_versions: Default::default(),
+            forward_client: None,
+            hibernating: None,
             tx_api: tx_api.clone(),
             rx_api,
 
@@ -327,6 +373,9 @@ where C: RaftTypeConfig
             id,
             config,
             runtime_config,
+            log_cache,
+            admission,
+            protocol_version,
             tick_handle,
             tx_api,
             rx_metrics,
@@ -356,6 +405,24 @@ where C: RaftTypeConfig
         RuntimeConfigHandle::new(self.inner.as_ref())
     }
 
+    /// Enable the Leader's cache of recently appended log entries.
+    ///
+    /// Once enabled, a Leader keeps up to [`Config::log_cache_size`] of the entries it appends in
+    /// memory, and replication streams read them from the cache instead of from the log store.
+    /// It requires log entries to be `Clone`, thus it is not enabled by default. It has no effect
+    /// if [`Config::log_cache_size`] is `0`, which is the default.
+    ///
+    /// Example:
+    /// ```ignore
+    /// let raft = Raft::new(...).await?;
+    /// raft.enable_log_cache();
+    /// ```
+    #[since(version = "0.10.0")]
+    pub fn enable_log_cache(&self)
+    where C::Entry: Clone {
+        self.inner.log_cache.enable();
+    }
+
     /// Return the config of this Raft node.
     pub fn config(&self) -> &Arc<Config> {
         &self.inner.config
@@ -614,7 +681,14 @@ where C: RaftTypeConfig
     /// this.
     ///
     /// These are application specific requirements, and must be implemented by the application
-    /// which is being built on top of Raft.
+    /// which is being built on top of Raft. With feature flag `session`, wrapping the state
+    /// machine in a `session::SessionStateMachine` implements them.
+    ///
+    /// If the state machine lags behind more than [`Config::max_apply_lag`] entries, the request
+    /// waits until it catches up, or fails with [`ClientWriteError::Overloaded`].
+    ///
+    /// If there are already [`Config::max_in_flight_proposals`] client writes not yet responded,
+    /// it waits for one of them to finish. Use [`Raft::try_client_write`] to fail fast instead.
     #[tracing::instrument(level = "debug", skip(self, app_data))]
     pub async fn client_write<E>(
         &self,
@@ -624,12 +698,8 @@ where C: RaftTypeConfig
         ResponderReceiverOf<C>: Future<Output = Result<ClientWriteResult<C>, E>>,
         E: Error + OptionalSend,
     {
-        let rx = self.client_write_ff(app_data).await?;
-
-        let res: ClientWriteResult<C> = self.inner.recv_msg(rx).await?;
-
-        let client_write_response = res.map_err(|e| RaftError::APIError(e))?;
-        Ok(client_write_response)
+        let permit = self.inner.admission.acquire().await;
+        self.client_write_with_permit(app_data, permit).await
     }
 
     /// Submit a mutating client request to Raft to update the state machine, returns an application
@@ -640,13 +710,169 @@ where C: RaftTypeConfig
     /// It is same as [`Raft::client_write`] but does not wait for the response.
     #[tracing::instrument(level = "debug", skip(self, app_data))]
     pub async fn client_write_ff(&self, app_data: C::D) -> Result<ResponderReceiverOf<C>, Fatal<C>> {
+        let permit = self.inner.admission.acquire().await;
+        self.send_client_write(app_data, permit).await
+    }
+
+    /// Submit a mutating client request, or fail immediately if the node is overloaded.
+    ///
+    /// It is the same as [`Raft::client_write`], except that if there are already
+    /// [`Config::max_in_flight_proposals`] client writes not yet responded, it returns a
+    /// [`ClientWriteError::Overloaded`] error at once instead of waiting. The request is not
+    /// proposed and can be retried later.
+    #[since(version = "0.10.0")]
+    #[tracing::instrument(level = "debug", skip(self, app_data))]
+    pub async fn try_client_write<E>(
+        &self,
+        app_data: C::D,
+    ) -> Result<ClientWriteResponse<C>, RaftError<C, ClientWriteError<C>>>
+    where
+        ResponderReceiverOf<C>: Future<Output = Result<ClientWriteResult<C>, E>>,
+        E: Error + OptionalSend,
+    {
+        let permit = self
+            .inner
+            .admission
+            .try_acquire()
+            .map_err(|e| RaftError::APIError(ClientWriteError::Overloaded(e)))?;
+
+        self.client_write_with_permit(app_data, permit).await
+    }
+
+    /// Submit a client write that has been admitted with `permit`, and wait for the response.
+    async fn client_write_with_permit<E>(
+        &self,
+        app_data: C::D,
+        permit: ProposalPermit<C>,
+    ) -> Result<ClientWriteResponse<C>, RaftError<C, ClientWriteError<C>>>
+    where
+        ResponderReceiverOf<C>: Future<Output = Result<ClientWriteResult<C>, E>>,
+        E: Error + OptionalSend,
+    {
+        let rx = self.send_client_write(app_data, permit).await?;
+
+        let res: ClientWriteResult<C> = self.inner.recv_msg(rx).await?;
+
+        let client_write_response = res.map_err(|e| RaftError::APIError(e))?;
+        Ok(client_write_response)
+    }
+
+    /// Send a client write that has been admitted with `permit` to `RaftCore`, without waiting
+    /// for the response.
+    async fn send_client_write(
+        &self,
+        app_data: C::D,
+        permit: ProposalPermit<C>,
+    ) -> Result<ResponderReceiverOf<C>, Fatal<C>> {
         let (app_data, tx, rx) = ResponderOf::<C>::from_app_data(app_data);
 
-        self.inner.send_msg(RaftMsg::ClientWriteRequest { app_data, tx }).await?;
+        self.inner.send_msg(RaftMsg::ClientWriteRequest { app_data, tx, permit }).await?;
 
         Ok(rx)
     }
 
+    /// Submit a mutating client request, and forward it to the Leader if this node is not the
+    /// Leader.
+    ///
+    /// On the Leader it is the same as [`Raft::client_write`]. On other nodes, instead of returning
+    /// a [`ForwardToLeader`] error, it sends `app_data` to the Leader with
+    /// [`RaftNetworkV2::forward_client_write`] and returns the Leader's response.
+    ///
+    /// If the Leader is unknown, unreachable, or is no longer the Leader, it retries every
+    /// heartbeat interval, with the Leader this node knows of at that time, until `timeout`
+    /// elapses. Then the last [`ForwardToLeader`] error is returned.
+    ///
+    /// A forwarded write that fails with a network error or a timeout may already have been
+    /// applied by the Leader, and retrying applies it again. The state machine should deduplicate
+    /// requests, as explained in [`Raft::client_write`].
+    ///
+    /// [`ForwardToLeader`]: crate::error::ForwardToLeader
+    /// [`RaftNetworkV2::forward_client_write`]: crate::network::v2::RaftNetworkV2::forward_client_write
+    #[since(version = "0.10.0")]
+    #[tracing::instrument(level = "debug", skip(self, app_data))]
+    pub async fn client_write_forwarded<E>(
+        &self,
+        app_data: C::D,
+        timeout: Duration,
+    ) -> Result<ClientWriteResponse<C>, RaftError<C, ClientWriteError<C>>>
+    where
+        C::D: Clone,
+        ResponderReceiverOf<C>: Future<Output = Result<ClientWriteResult<C>, E>>,
+        E: Error + OptionalSend,
+    {
+        let deadline = C::now() + timeout;
+
+        loop {
+            let forward = match self.client_write(app_data.clone()).await {
+                Err(RaftError::APIError(ClientWriteError::ForwardToLeader(forward))) => forward,
+                res => return res,
+            };
+
+            if let (Some(leader_id), Some(leader_node)) = (&forward.leader_id, &forward.leader_node) {
+                let ttl = deadline.saturating_duration_since(C::now());
+
+                let (tx, rx) = C::oneshot();
+                let msg = RaftMsg::ForwardClientWrite {
+                    target: leader_id.clone(),
+                    target_node: leader_node.clone(),
+                    app_data: app_data.clone(),
+                    option: RPCOption::new(ttl),
+                    tx,
+                };
+
+                let rpc_err = match self.inner.call_core(msg, rx).await {
+                    Ok(resp) => return Ok(resp),
+                    Err(RaftError::Fatal(fatal)) => return Err(RaftError::Fatal(fatal)),
+                    Err(RaftError::APIError(rpc_err)) => rpc_err,
+                };
+
+                // Retry unless the Leader rejects the write for other reason than it is not the
+                // Leader.
+                if let RPCError::RemoteError(RemoteError {
+                    source: RaftError::APIError(e),
+                    ..
+                }) = rpc_err
+                {
+                    if !matches!(e, ClientWriteError::ForwardToLeader(_)) {
+                        return Err(RaftError::APIError(e));
+                    }
+                } else {
+                    tracing::info!("failed to forward client write to {}, retry: {}", leader_id, rpc_err);
+                }
+            }
+
+            let now = C::now();
+            if now >= deadline {
+                return Err(RaftError::APIError(ClientWriteError::ForwardToLeader(forward)));
+            }
+
+            let interval = Duration::from_millis(self.inner.config.heartbeat_interval);
+            C::sleep(std::cmp::min(interval, deadline - now)).await;
+        }
+    }
+
+    /// Handle a client write forwarded from another node with
+    /// [`RaftNetworkV2::forward_client_write`].
+    ///
+    /// It is the same as [`Raft::client_write`]. If this node is not the Leader, the write is not
+    /// forwarded again, so that it never loops between nodes: the [`ForwardToLeader`] error is
+    /// sent back, and the node that forwarded it retries with a newer Leader.
+    ///
+    /// [`ForwardToLeader`]: crate::error::ForwardToLeader
+    /// [`RaftNetworkV2::forward_client_write`]: crate::network::v2::RaftNetworkV2::forward_client_write
+    #[since(version = "0.10.0")]
+    #[tracing::instrument(level = "debug", skip(self, app_data))]
+    pub async fn handle_forwarded_client_write<E>(
+        &self,
+        app_data: C::D,
+    ) -> Result<ClientWriteResponse<C>, RaftError<C, ClientWriteError<C>>>
+    where
+        ResponderReceiverOf<C>: Future<Output = Result<ClientWriteResult<C>, E>>,
+        E: Error + OptionalSend,
+    {
+        self.client_write(app_data).await
+    }
+
     /// Handle the LeaderTransfer request from a Leader node.
     ///
     /// If this node is the `to` node, it resets the Leader lease and triggers an election when the
@@ -678,6 +904,58 @@ where C: RaftTypeConfig
         Ok(())
     }
 
+    /// Handle a handshake from another node, sent with [`RaftNetworkV2::handshake`].
+    ///
+    /// It records the protocol version of the sender, so that this node does not use the features
+    /// the sender does not support, and responds with the protocol version of this node, i.e.,
+    /// [`RaftNetworkFactory::protocol_version()`].
+    ///
+    /// [`RaftNetworkV2::handshake`]: crate::network::v2::RaftNetworkV2::handshake
+    #[since(version = "0.10.0")]
+    #[tracing::instrument(level = "debug", skip(self))]
+    pub async fn handle_handshake(&self, req: HandshakeRequest<C>) -> Result<HandshakeResponse, Fatal<C>> {
+        let raft_msg = RaftMsg::HandleHandshake {
+            from: req.from,
+            version: req.version,
+        };
+
+        self.inner.send_msg(raft_msg).await?;
+
+        Ok(HandshakeResponse::new(self.inner.protocol_version))
+    }
+
+    /// Handle a message from the Leader telling it stops sending heartbeats, sent with
+    /// [`RaftNetworkV2::hibernate`].
+    ///
+    /// If this node follows the Leader and has all of its logs, it suspends its election timer
+    /// until it receives another message from a Leader or a Candidate.
+    ///
+    /// [`RaftNetworkV2::hibernate`]: crate::network::v2::RaftNetworkV2::hibernate
+    #[since(version = "0.10.0")]
+    #[tracing::instrument(level = "debug", skip(self))]
+    pub async fn handle_hibernate(&self, rpc: HibernateRequest<C>) -> Result<(), Fatal<C>> {
+        tracing::info!(rpc = display(&rpc), "Raft::handle_hibernate()");
+
+        self.inner.send_msg(RaftMsg::HandleHibernate { rpc }).await
+    }
+
+    /// Handle a request from the Leader to bring another node up to date, sent with
+    /// [`RaftNetworkV2::catch_up`].
+    ///
+    /// This node sends its log entries, or its latest snapshot if the entries are purged, to the
+    /// target in place of the Leader, and responds when it is done. It declines if it does not
+    /// follow the Leader or does not have the requested logs.
+    ///
+    /// [`RaftNetworkV2::catch_up`]: crate::network::v2::RaftNetworkV2::catch_up
+    #[since(version = "0.10.0")]
+    #[tracing::instrument(level = "debug", skip(self, rpc))]
+    pub async fn handle_catch_up(&self, rpc: CatchUpRequest<C>) -> Result<CatchUpResponse<C>, RaftError<C>> {
+        tracing::info!(rpc = display(&rpc), "Raft::handle_catch_up()");
+
+        let (tx, rx) = C::oneshot();
+        self.inner.call_core(RaftMsg::CatchUp { rpc, tx }, rx).await
+    }
+
     /// Wait for the log to be flushed to make sure the RequestVote.last_log_id is upto date, then
     /// TransferLeader will be able to proceed.
     async fn ensure_log_flushed_for_transfer_leader(&self, req: &TransferLeaderRequest<C>) -> Result<(), Fatal<C>> {
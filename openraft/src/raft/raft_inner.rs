@@ -9,6 +9,7 @@ use crate::async_runtime::watch::WatchReceiver;
 use crate::async_runtime::watch::WatchSender;
 use crate::async_runtime::MpscUnboundedSender;
 use crate::config::RuntimeConfig;
+use crate::core::admission::Admission;
 use crate::core::raft_msg::external_command::ExternalCommand;
 use crate::core::raft_msg::RaftMsg;
 use crate::core::TickHandle;
@@ -16,7 +17,9 @@ use crate::error::Fatal;
 use crate::error::RaftError;
 use crate::metrics::RaftDataMetrics;
 use crate::metrics::RaftServerMetrics;
+use crate::network::ProtocolVersion;
 use crate::raft::core_state::CoreState;
+use crate::replication::log_cache::LogCache;
 use crate::type_config::alias::AsyncRuntimeOf;
 use crate::type_config::alias::MpscUnboundedSenderOf;
 use crate::type_config::alias::MutexOf;
@@ -38,6 +41,11 @@ where C: RaftTypeConfig
     pub(in crate::raft) id: C::NodeId,
     pub(in crate::raft) config: Arc<Config>,
     pub(in crate::raft) runtime_config: Arc<RuntimeConfig>,
+    pub(in crate::raft) log_cache: Arc<LogCache<C>>,
+    pub(in crate::raft) admission: Arc<Admission<C>>,
+
+    /// The protocol version this node advertises in handshakes.
+    pub(in crate::raft) protocol_version: ProtocolVersion,
     pub(in crate::raft) tick_handle: TickHandle<C>,
     pub(in crate::raft) tx_api: MpscUnboundedSenderOf<C, RaftMsg<C>>,
     pub(in crate::raft) rx_metrics: WatchReceiverOf<C, RaftMetrics<C>>,
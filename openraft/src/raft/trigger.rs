@@ -1,5 +1,7 @@
 //! Trigger an action to RaftCore by external caller.
 
+use openraft_macros::since;
+
 use crate::core::raft_msg::external_command::ExternalCommand;
 use crate::error::AllowNextRevertError;
 use crate::error::Fatal;
@@ -89,6 +91,22 @@ where C: RaftTypeConfig
             .await
     }
 
+    /// Wake up this node if it is hibernating.
+    ///
+    /// A hibernating follower resumes its election timer. Since the Leader is not sending
+    /// heartbeats, it starts an election unless it hears from a Leader within an election timeout.
+    /// Call it when an external liveness check finds the Leader down.
+    ///
+    /// A hibernating Leader resumes sending heartbeats, which also wakes up its followers.
+    ///
+    /// See [`Config::hibernate_timeout`].
+    ///
+    /// [`Config::hibernate_timeout`]: crate::Config::hibernate_timeout
+    #[since(version = "0.10.0")]
+    pub async fn wake_up(&self) -> Result<(), Fatal<C>> {
+        self.raft_inner.send_external_command(ExternalCommand::WakeUp, "wake_up").await
+    }
+
     /// Request the RaftCore to allow to reset replication for a specific node when log revert is
     /// detected.
     ///
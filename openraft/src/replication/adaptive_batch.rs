@@ -0,0 +1,235 @@
+//! Adaptive number of entries to send in an AppendEntries RPC.
+
+use std::cmp::max;
+use std::cmp::min;
+use std::sync::atomic::AtomicU64;
+use std::sync::atomic::Ordering;
+use std::sync::Arc;
+use std::time::Duration;
+
+/// After this many full batches are sent at the ceiling, the ceiling is raised by one step to probe
+/// whether the follower accepts a larger payload again.
+const PROBE_INTERVAL: u64 = 32;
+
+/// Per-follower batch size, adjusted in the AIMD(additive increase, multiplicative decrease) way.
+///
+/// - A full batch replicated fast enough grows the size by a step, up to the ceiling.
+/// - A slow or timed-out batch halves the size. A batch is slow if its RTT approaches the timeout
+///   and is at least twice the latency of the link, i.e., the RTT of the smallest batch. Thus a
+///   link with a long but steady latency keeps sending large batches.
+/// - A rejected batch halves the size and lowers the ceiling below the rejected size; a
+///   `PayloadTooLarge` hint sets both to the hint.
+///
+/// The size never exceeds [`Config::max_payload_entries`].
+///
+/// [`Config::max_payload_entries`]: crate::Config::max_payload_entries
+#[derive(Debug)]
+pub(crate) struct AdaptiveBatch {
+    /// The number of entries to send in the next AppendEntries RPC.
+    ///
+    /// It is shared with `RaftCore` to report it in metrics.
+    size: Arc<AtomicU64>,
+
+    /// The size grows no larger than this, until a probe raises it.
+    ceiling: u64,
+
+    /// The upper bound of the size and the ceiling.
+    max: u64,
+
+    /// The amount the size grows by.
+    step: u64,
+
+    /// The number of full batches sent at the ceiling since the last probe.
+    at_ceiling: u64,
+
+    /// The least RTT observed, as the latency of the link. A single-entry batch resets it.
+    min_rtt: Option<Duration>,
+}
+
+impl AdaptiveBatch {
+    pub(crate) fn new(max_payload_entries: u64) -> Self {
+        let m = max(max_payload_entries, 1);
+        Self {
+            size: Arc::new(AtomicU64::new(m)),
+            ceiling: m,
+            max: m,
+            step: max(m / 16, 1),
+            at_ceiling: 0,
+            min_rtt: None,
+        }
+    }
+
+    /// The number of entries to send in the next AppendEntries RPC.
+    pub(crate) fn size(&self) -> u64 {
+        self.size.load(Ordering::Relaxed)
+    }
+
+    /// Returns a handle to read the current size from another task.
+    pub(crate) fn shared_size(&self) -> Arc<AtomicU64> {
+        self.size.clone()
+    }
+
+    /// A batch of `sent` entries is replicated in `rtt`.
+    ///
+    /// `rtt` reaching `slow` while it is dominated by the entries rather than the latency of the
+    /// link means the link can not carry the batch in time, and the size is halved. Otherwise, the
+    /// size grows only if the batch is full: a partial batch tells nothing about whether a larger
+    /// one would be carried.
+    pub(crate) fn on_success(&mut self, sent: u64, rtt: Duration, slow: Duration) {
+        // A heartbeat carries no entries.
+        if sent == 0 {
+            return;
+        }
+
+        let latency = match self.min_rtt {
+            Some(m) if sent > 1 => min(m, rtt),
+            _ => rtt,
+        };
+        self.min_rtt = Some(latency);
+
+        if rtt >= slow && rtt >= latency * 2 {
+            self.on_slow(sent);
+            return;
+        }
+
+        let size = self.size();
+        if sent < size {
+            return;
+        }
+
+        if size < self.ceiling {
+            self.set_size(min(size + self.step, self.ceiling));
+            return;
+        }
+
+        if self.ceiling < self.max {
+            self.at_ceiling += 1;
+            if self.at_ceiling >= PROBE_INTERVAL {
+                self.at_ceiling = 0;
+                self.ceiling = min(self.ceiling + self.step, self.max);
+                self.set_size(self.ceiling);
+            }
+        }
+    }
+
+    /// A batch of `sent` entries is replicated too slowly or timed out: halve the size.
+    pub(crate) fn on_slow(&mut self, sent: u64) {
+        if sent == 0 {
+            return;
+        }
+        self.at_ceiling = 0;
+        self.set_size(max(min(self.size(), sent) / 2, 1));
+    }
+
+    /// A batch of `sent` entries failed: halve the size and do not grow back to `sent` until a
+    /// probe.
+    pub(crate) fn on_error(&mut self, sent: u64) {
+        if sent == 0 {
+            return;
+        }
+        self.ceiling = max(min(self.ceiling, sent - 1), 1);
+        self.on_slow(sent);
+    }
+
+    /// The follower tells the number of entries it accepts in one RPC.
+    pub(crate) fn on_too_large(&mut self, entries_hint: u64) {
+        self.ceiling = min(max(entries_hint, 1), self.max);
+        self.at_ceiling = 0;
+        self.set_size(self.ceiling);
+    }
+
+    fn set_size(&mut self, size: u64) {
+        self.size.store(size, Ordering::Relaxed);
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::time::Duration;
+
+    use super::AdaptiveBatch;
+    use super::PROBE_INTERVAL;
+
+    const FAST: Duration = Duration::from_millis(1);
+    const SLOW: Duration = Duration::from_millis(100);
+
+    #[test]
+    fn test_adaptive_batch_aimd() {
+        let mut b = AdaptiveBatch::new(160);
+        assert_eq!(160, b.size());
+
+        // A single entry measures the latency of the link, a partial batch does not change size.
+        b.on_success(1, FAST, SLOW);
+        assert_eq!(160, b.size());
+
+        // Slow: halve
+        b.on_success(160, SLOW, SLOW);
+        assert_eq!(80, b.size());
+
+        // A partial batch does not grow
+        b.on_success(10, FAST, SLOW);
+        assert_eq!(80, b.size());
+
+        // A full batch grows by a step: 160/16
+        b.on_success(80, FAST, SLOW);
+        assert_eq!(90, b.size());
+
+        // Heartbeat changes nothing
+        b.on_slow(0);
+        b.on_error(0);
+        assert_eq!(90, b.size());
+
+        // Timeout: halve
+        b.on_slow(90);
+        assert_eq!(45, b.size());
+
+        // Grows back to max
+        for _ in 0..100 {
+            let n = b.size();
+            b.on_success(n, FAST, SLOW);
+        }
+        assert_eq!(160, b.size());
+    }
+
+    #[test]
+    fn test_adaptive_batch_long_latency() {
+        let mut b = AdaptiveBatch::new(160);
+
+        // The latency of the link is SLOW
+        b.on_success(1, SLOW, SLOW);
+
+        // The batch takes no longer than a single entry: the link is not saturated.
+        b.on_success(160, SLOW, SLOW);
+        assert_eq!(160, b.size());
+
+        b.on_success(160, SLOW * 2, SLOW);
+        assert_eq!(80, b.size());
+    }
+
+    #[test]
+    fn test_adaptive_batch_ceiling() {
+        let mut b = AdaptiveBatch::new(160);
+
+        b.on_too_large(3);
+        assert_eq!(3, b.size());
+
+        // Stays at the ceiling until a probe
+        for _ in 0..PROBE_INTERVAL - 1 {
+            b.on_success(3, FAST, SLOW);
+            assert_eq!(3, b.size());
+        }
+        b.on_success(3, FAST, SLOW);
+        assert_eq!(13, b.size());
+
+        // Error lowers the ceiling below the failed size
+        b.on_error(13);
+        assert_eq!(6, b.size());
+        b.on_success(6, FAST, SLOW);
+        b.on_success(12, FAST, SLOW);
+        assert_eq!(12, b.size());
+
+        // Hint is bounded by max
+        b.on_too_large(1000);
+        assert_eq!(160, b.size());
+    }
+}
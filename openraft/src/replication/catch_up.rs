@@ -0,0 +1,208 @@
+//! Catch-up of a lagging node, run by a follower on behalf of the Leader.
+
+use std::sync::Arc;
+use std::time::Duration;
+
+use crate::config::Config;
+use crate::core::sm::handle::SnapshotReader;
+use crate::display_ext::DisplayOptionExt;
+use crate::entry::raft_entry_ext::RaftEntryExt;
+use crate::entry::RaftEntry;
+use crate::error::ReplicationClosed;
+use crate::log_id::LogIdOptionExt;
+use crate::network::v2::RaftNetworkV2;
+use crate::network::RPCOption;
+use crate::raft::AppendEntriesRequest;
+use crate::raft::AppendEntriesResponse;
+use crate::raft::CatchUpRequest;
+use crate::raft::CatchUpResponse;
+use crate::storage::RaftLogReader;
+use crate::storage::RaftLogStorage;
+use crate::type_config::alias::LogIdOf;
+use crate::type_config::TypeConfigExt;
+use crate::vote::raft_vote::RaftVoteExt;
+use crate::RaftNetworkFactory;
+use crate::RaftTypeConfig;
+
+/// Sends the logs requested by a [`CatchUpRequest`] to its target, with the Leader's vote.
+///
+/// If the first logs to send are already purged, the latest snapshot is sent instead, then the
+/// logs after it.
+pub(crate) struct CatchUp<C, N, LS>
+where
+    C: RaftTypeConfig,
+    N: RaftNetworkFactory<C>,
+    LS: RaftLogStorage<C>,
+{
+    rpc: CatchUpRequest<C>,
+
+    /// The connection to the target.
+    network: N::Network,
+
+    log_reader: LS::LogReader,
+
+    snapshot_reader: SnapshotReader<C>,
+
+    /// The last purged log id of this node when the request is received.
+    purged: Option<LogIdOf<C>>,
+
+    config: Arc<Config>,
+}
+
+impl<C, N, LS> CatchUp<C, N, LS>
+where
+    C: RaftTypeConfig,
+    N: RaftNetworkFactory<C>,
+    LS: RaftLogStorage<C>,
+{
+    pub(crate) fn new(
+        rpc: CatchUpRequest<C>,
+        network: N::Network,
+        log_reader: LS::LogReader,
+        snapshot_reader: SnapshotReader<C>,
+        purged: Option<LogIdOf<C>>,
+        config: Arc<Config>,
+    ) -> Self {
+        Self {
+            rpc,
+            network,
+            log_reader,
+            snapshot_reader,
+            purged,
+            config,
+        }
+    }
+
+    /// Send logs to the target until it has `last_log_id`, or it fails.
+    ///
+    /// If some logs are sent before a failure, it returns the progress made so far, and the Leader
+    /// sends the rest.
+    pub(crate) async fn run(mut self) -> CatchUpResponse<C> {
+        let prev = self.rpc.prev_log_id.clone();
+        let mut matching = prev.clone();
+
+        if prev.next_index() < self.purged.next_index() {
+            match self.send_snapshot().await {
+                Ok(m) => matching = m,
+                Err(resp) => return resp,
+            }
+        }
+
+        let end = self.rpc.last_log_id.index() + 1;
+
+        while matching.next_index() < end {
+            match self.send_log_entries(matching.clone(), end).await {
+                Ok(m) => matching = m,
+                Err(CatchUpResponse::Declined(reason)) if matching > prev => {
+                    tracing::info!(
+                        reason = display(&reason),
+                        matching = display(matching.display()),
+                        "catch-up stopped midway"
+                    );
+                    break;
+                }
+                Err(resp) => return resp,
+            }
+        }
+
+        if matching > prev {
+            CatchUpResponse::Success(std::cmp::min(matching, Some(self.rpc.last_log_id.clone())))
+        } else {
+            CatchUpResponse::Declined("no log is sent".to_string())
+        }
+    }
+
+    /// Send the latest snapshot and return the last log id in it.
+    async fn send_snapshot(&mut self) -> Result<Option<LogIdOf<C>>, CatchUpResponse<C>> {
+        let snapshot =
+            self.snapshot_reader.get_snapshot().await.map_err(|e| CatchUpResponse::Declined(e.to_string()))?;
+
+        let Some(snapshot) = snapshot else {
+            return Err(CatchUpResponse::Declined("snapshot not found".to_string()));
+        };
+
+        let last = snapshot.meta.last_log_id.clone();
+        tracing::info!(snapshot = display(&snapshot.meta), "catch-up with snapshot");
+
+        let ttl = self.config.install_snapshot_timeout();
+        let mut option = RPCOption::new(ttl);
+        option.snapshot_chunk_size = Some(self.config.snapshot_max_chunk_size as usize);
+
+        // Not cancelled: `ttl` bounds it.
+        let cancel = futures::future::pending::<ReplicationClosed>();
+
+        let fu = self.network.full_snapshot(self.rpc.vote.clone(), snapshot, cancel, option);
+        let resp = match C::timeout(ttl, fu).await {
+            Ok(Ok(resp)) => resp,
+            Ok(Err(e)) => return Err(CatchUpResponse::Declined(e.to_string())),
+            Err(timeout) => return Err(CatchUpResponse::Declined(timeout.to_string())),
+        };
+
+        if resp.vote.as_ref_vote() > self.rpc.vote.as_ref_vote() {
+            return Err(CatchUpResponse::HigherVote(resp.vote));
+        }
+
+        Ok(last)
+    }
+
+    /// Send one batch of logs after `prev`, before index `end`, and return the new matching log id.
+    async fn send_log_entries(
+        &mut self,
+        prev: Option<LogIdOf<C>>,
+        end: u64,
+    ) -> Result<Option<LogIdOf<C>>, CatchUpResponse<C>> {
+        let start = prev.next_index();
+        let end = std::cmp::min(end, start + self.config.max_payload_entries);
+
+        let entries = self
+            .log_reader
+            .limited_get_log_entries(start, end)
+            .await
+            .map_err(|e| CatchUpResponse::Declined(e.to_string()))?;
+
+        for ent in entries.iter() {
+            ent.verify_checksum().map_err(|e| CatchUpResponse::Declined(e.to_string()))?;
+        }
+
+        let Some(last) = entries.last().map(|ent| ent.log_id()) else {
+            return Err(CatchUpResponse::Declined(format!("no log at index {}", start)));
+        };
+
+        let req = AppendEntriesRequest {
+            vote: self.rpc.vote.clone(),
+            prev_log_id: prev.clone(),
+            leader_commit: self.rpc.leader_commit.clone(),
+            entries,
+        };
+
+        let ttl = Duration::from_millis(self.config.heartbeat_interval);
+        let option = RPCOption::new(ttl);
+
+        let resp = match C::timeout(ttl, self.network.append_entries(req, option)).await {
+            Ok(Ok(resp)) => resp,
+            Ok(Err(e)) => return Err(CatchUpResponse::Declined(e.to_string())),
+            Err(timeout) => return Err(CatchUpResponse::Declined(timeout.to_string())),
+        };
+
+        match resp {
+            AppendEntriesResponse::Success => Ok(Some(last)),
+            AppendEntriesResponse::PartialSuccess(matching) => {
+                if matching > prev {
+                    Ok(matching)
+                } else {
+                    Err(CatchUpResponse::Declined("no log is accepted".to_string()))
+                }
+            }
+            AppendEntriesResponse::HigherVote(vote) => Err(CatchUpResponse::HigherVote(vote)),
+            AppendEntriesResponse::Conflict => {
+                // Conflict at a log this catch-up has sent can not happen, because the logs are
+                // committed.
+                if prev == self.rpc.prev_log_id {
+                    Err(CatchUpResponse::Conflict)
+                } else {
+                    Err(CatchUpResponse::Declined(format!("conflict at {}", prev.display())))
+                }
+            }
+        }
+    }
+}
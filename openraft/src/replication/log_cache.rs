@@ -0,0 +1,252 @@
+//! In-memory cache of recently appended log entries, shared by the replication streams.
+
+use std::collections::VecDeque;
+use std::sync::atomic::AtomicU64;
+use std::sync::atomic::Ordering;
+use std::sync::Mutex;
+
+use crate::entry::RaftEntry;
+use crate::RaftTypeConfig;
+
+/// A bounded cache of the latest log entries a Leader appended, so that replication streams do not
+/// each read them back from the log store.
+///
+/// The cached entries are consecutive and are the same as the entries in the log store:
+/// - An append that does not follow the last cached entry replaces the entries it overlaps, or
+///   clears the cache if there is a gap.
+/// - A truncate removes the truncated entries.
+///
+/// Purged entries may stay in the cache until they are evicted, a replication stream never reads
+/// them, because it sends a snapshot instead.
+///
+/// The cache stays empty until [`LogCache::enable()`] is called, because entries can be cloned
+/// only if `C::Entry: Clone`.
+pub(crate) struct LogCache<C>
+where C: RaftTypeConfig
+{
+    /// The maximum number of entries to keep.
+    capacity: usize,
+
+    inner: Mutex<Inner<C>>,
+
+    hits: AtomicU64,
+    misses: AtomicU64,
+}
+
+struct Inner<C>
+where C: RaftTypeConfig
+{
+    /// Clones an entry. It is `None` until the cache is enabled.
+    clone_entry: Option<fn(&C::Entry) -> C::Entry>,
+
+    /// Consecutive entries, the oldest first.
+    entries: VecDeque<C::Entry>,
+}
+
+impl<C> LogCache<C>
+where C: RaftTypeConfig
+{
+    pub(crate) fn new(capacity: u64) -> Self {
+        Self {
+            capacity: capacity as usize,
+            inner: Mutex::new(Inner {
+                clone_entry: None,
+                entries: VecDeque::new(),
+            }),
+            hits: AtomicU64::new(0),
+            misses: AtomicU64::new(0),
+        }
+    }
+
+    /// Start caching entries appended from now on.
+    pub(crate) fn enable(&self)
+    where C::Entry: Clone {
+        let mut inner = self.inner.lock().unwrap();
+        inner.clone_entry = Some(C::Entry::clone);
+    }
+
+    /// Cache a copy of the entries being appended to the log store.
+    pub(crate) fn append(&self, entries: &[C::Entry]) {
+        let Some(first) = entries.first() else {
+            return;
+        };
+
+        let mut inner = self.inner.lock().unwrap();
+        let Some(clone_entry) = inner.clone_entry else {
+            return;
+        };
+
+        if self.capacity == 0 {
+            return;
+        }
+
+        let index = first.index();
+        let keep = match inner.first_index() {
+            Some(cached) if cached <= index && index <= cached + inner.entries.len() as u64 => {
+                (index - cached) as usize
+            }
+            _ => 0,
+        };
+        inner.entries.truncate(keep);
+
+        // Only the last `capacity` entries would be kept.
+        let skip = entries.len().saturating_sub(self.capacity);
+        inner.entries.extend(entries[skip..].iter().map(clone_entry));
+
+        let evict = inner.entries.len().saturating_sub(self.capacity);
+        inner.entries.drain(..evict);
+    }
+
+    /// Remove cached entries since log index `since`, inclusive.
+    pub(crate) fn truncate(&self, since: u64) {
+        let mut inner = self.inner.lock().unwrap();
+        let Some(cached) = inner.first_index() else {
+            return;
+        };
+
+        let keep = since.saturating_sub(cached) as usize;
+        inner.entries.truncate(keep);
+    }
+
+    /// Get cached entries in the range `[start, end)`.
+    ///
+    /// It returns `None` if the entry at `start` is not cached; otherwise it returns the cached
+    /// entries from `start`, which may be fewer than requested, like
+    /// [`RaftLogReader::limited_get_log_entries()`].
+    ///
+    /// [`RaftLogReader::limited_get_log_entries()`]: crate::storage::RaftLogReader::limited_get_log_entries
+    pub(crate) fn get(&self, start: u64, end: u64) -> Option<Vec<C::Entry>> {
+        let res = self.try_get(start, end);
+
+        if res.is_some() {
+            self.hits.fetch_add(1, Ordering::Relaxed);
+        } else {
+            self.misses.fetch_add(1, Ordering::Relaxed);
+        }
+        res
+    }
+
+    fn try_get(&self, start: u64, end: u64) -> Option<Vec<C::Entry>> {
+        let inner = self.inner.lock().unwrap();
+        let clone_entry = inner.clone_entry?;
+        let cached = inner.first_index()?;
+
+        if start < cached || start >= end {
+            return None;
+        }
+
+        let from = (start - cached) as usize;
+        let to = std::cmp::min((end - cached) as usize, inner.entries.len());
+        if from >= to {
+            return None;
+        }
+
+        Some(inner.entries.range(from..to).map(clone_entry).collect())
+    }
+
+    /// The number of reads served by the cache.
+    pub(crate) fn hits(&self) -> u64 {
+        self.hits.load(Ordering::Relaxed)
+    }
+
+    /// The number of reads not served by the cache.
+    pub(crate) fn misses(&self) -> u64 {
+        self.misses.load(Ordering::Relaxed)
+    }
+}
+
+impl<C> Inner<C>
+where C: RaftTypeConfig
+{
+    fn first_index(&self) -> Option<u64> {
+        self.entries.front().map(|e| e.index())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::LogCache;
+    use crate::engine::testing::UTConfig;
+    use crate::entry::RaftEntry;
+    use crate::testing::blank_ent;
+    use crate::Entry;
+
+    fn ents(term: u64, indexes: impl IntoIterator<Item = u64>) -> Vec<Entry<UTConfig>> {
+        indexes.into_iter().map(|i| blank_ent::<UTConfig>(term, 1, i)).collect()
+    }
+
+    fn indexes(entries: Option<Vec<Entry<UTConfig>>>) -> Option<Vec<(u64, u64)>> {
+        entries.map(|v| v.iter().map(|e| (e.log_id.leader_id.term, e.index())).collect())
+    }
+
+    #[test]
+    fn test_log_cache_disabled() {
+        let c = LogCache::<UTConfig>::new(10);
+        c.append(&ents(1, 1..4));
+        assert_eq!(None, indexes(c.get(1, 4)));
+        assert_eq!((0, 1), (c.hits(), c.misses()));
+
+        let c = LogCache::<UTConfig>::new(0);
+        c.enable();
+        c.append(&ents(1, 1..4));
+        assert_eq!(None, indexes(c.get(1, 4)));
+    }
+
+    #[test]
+    fn test_log_cache_get() {
+        let c = LogCache::<UTConfig>::new(10);
+        c.enable();
+        c.append(&ents(1, 3..6));
+
+        assert_eq!(Some(vec![(1, 3), (1, 4)]), indexes(c.get(3, 5)));
+        assert_eq!(
+            Some(vec![(1, 4), (1, 5)]),
+            indexes(c.get(4, 100)),
+            "fewer than requested"
+        );
+        assert_eq!(None, indexes(c.get(2, 5)), "start is not cached");
+        assert_eq!(None, indexes(c.get(6, 8)), "start is not cached");
+        assert_eq!(None, indexes(c.get(4, 4)), "empty range");
+
+        assert_eq!((2, 3), (c.hits(), c.misses()));
+    }
+
+    #[test]
+    fn test_log_cache_evict() {
+        let c = LogCache::<UTConfig>::new(3);
+        c.enable();
+
+        c.append(&ents(1, 1..3));
+        c.append(&ents(1, 3..5));
+        assert_eq!(None, indexes(c.get(1, 5)));
+        assert_eq!(Some(vec![(1, 2), (1, 3), (1, 4)]), indexes(c.get(2, 5)));
+
+        c.append(&ents(1, 5..10));
+        assert_eq!(None, indexes(c.get(6, 10)));
+        assert_eq!(Some(vec![(1, 7), (1, 8), (1, 9)]), indexes(c.get(7, 10)));
+    }
+
+    #[test]
+    fn test_log_cache_overwrite_and_truncate() {
+        let c = LogCache::<UTConfig>::new(10);
+        c.enable();
+        c.append(&ents(1, 1..6));
+
+        // Overlapping entries replace the cached ones.
+        c.append(&ents(2, 4..5));
+        assert_eq!(Some(vec![(1, 3), (2, 4)]), indexes(c.get(3, 10)));
+
+        c.truncate(4);
+        assert_eq!(Some(vec![(1, 3)]), indexes(c.get(3, 10)));
+        assert_eq!(None, indexes(c.get(4, 10)));
+
+        c.truncate(0);
+        assert_eq!(None, indexes(c.get(1, 10)));
+
+        // A gap clears the cache.
+        c.append(&ents(2, 1..3));
+        c.append(&ents(2, 5..6));
+        assert_eq!(None, indexes(c.get(1, 10)));
+        assert_eq!(Some(vec![(2, 5)]), indexes(c.get(5, 10)));
+    }
+}
@@ -1,11 +1,15 @@
 //! Replication stream.
 
+pub(crate) mod adaptive_batch;
 pub(crate) mod callbacks;
-pub(crate) mod hint;
+pub(crate) mod catch_up;
+pub(crate) mod log_cache;
 mod replication_session_id;
 pub(crate) mod request;
 pub(crate) mod response;
 
+use std::sync::atomic::AtomicU64;
+use std::sync::atomic::Ordering;
 use std::sync::Arc;
 use std::time::Duration;
 
@@ -34,16 +38,21 @@ use crate::error::RPCError;
 use crate::error::ReplicationClosed;
 use crate::error::ReplicationError;
 use crate::error::Timeout;
+use crate::error::Unreachable;
+use crate::instant::Instant;
 use crate::log_id::LogIdOptionExt;
 use crate::log_id_range::LogIdRange;
+use crate::network::retry::RetryDelay;
+use crate::network::retry::RetryPolicy;
 use crate::network::v2::RaftNetworkV2;
 use crate::network::Backoff;
 use crate::network::RPCOption;
 use crate::network::RPCTypes;
 use crate::raft::AppendEntriesRequest;
 use crate::raft::AppendEntriesResponse;
+use crate::replication::adaptive_batch::AdaptiveBatch;
 use crate::replication::callbacks::SnapshotCallback;
-use crate::replication::hint::ReplicationHint;
+use crate::replication::log_cache::LogCache;
 use crate::storage::RaftLogReader;
 use crate::storage::RaftLogStorage;
 use crate::storage::Snapshot;
@@ -73,6 +82,18 @@ where C: RaftTypeConfig
 
     /// The channel used for communicating with the replication task.
     pub(crate) tx_repl: MpscUnboundedSenderOf<C, Replicate<C>>,
+
+    /// The number of entries the replication task currently sends in one AppendEntries RPC.
+    batch_size: Arc<AtomicU64>,
+}
+
+impl<C> ReplicationHandle<C>
+where C: RaftTypeConfig
+{
+    /// Returns the number of entries currently sent in one AppendEntries RPC.
+    pub(crate) fn batch_size(&self) -> u64 {
+        self.batch_size.load(Ordering::Relaxed)
+    }
 }
 
 /// A task responsible for sending replication events to a target follower in the Raft cluster.
@@ -121,13 +142,25 @@ where
     /// to quit.
     snapshot_state: Option<(OneshotSenderOf<C, ()>, JoinHandleOf<C, ()>)>,
 
-    /// The backoff policy if an [`Unreachable`](`crate::error::Unreachable`) error is returned.
+    /// Decides what to do when an RPC fails.
+    retry_policy: Box<dyn RetryPolicy<C>>,
+
+    /// The number of consecutive failed RPCs, reset when an RPC succeeds.
+    failures: u64,
+
+    /// The backoff if the retry policy decides to wait with [`RetryDelay::Backoff`].
     /// It will be reset to `None` when an successful response is received.
     backoff: Option<Backoff>,
 
+    /// A delay to wait once before the next RPC, decided by the retry policy.
+    delay: Option<Duration>,
+
     /// The [`RaftLogStorage::LogReader`] interface.
     log_reader: LS::LogReader,
 
+    /// Recently appended entries, read before reading from `log_reader`.
+    log_cache: Arc<LogCache<C>>,
+
     /// The handle to get a snapshot directly from state machine.
     snapshot_reader: SnapshotReader<C>,
 
@@ -143,9 +176,12 @@ where
     /// Next replication action to run.
     next_action: Option<Data<C>>,
 
-    /// Appropriate number of entries to send.
+    /// Appropriate number of entries to send, adapted to the link to the target.
     /// This is only used by AppendEntries RPC.
-    entries_hint: ReplicationHint,
+    batch: AdaptiveBatch,
+
+    /// The number of entries in the last AppendEntries RPC.
+    entries_sent: u64,
 }
 
 impl<C, N, LS> ReplicationCore<C, N, LS>
@@ -167,6 +203,7 @@ where
         network: N::Network,
         snapshot_network: N::Network,
         log_reader: LS::LogReader,
+        log_cache: Arc<LogCache<C>>,
         snapshot_reader: SnapshotReader<C>,
         tx_raft_core: MpscUnboundedSenderOf<C, Notification<C>>,
         span: tracing::Span,
@@ -182,14 +219,23 @@ where
         // other component to ReplicationStream
         let (tx_event, rx_event) = C::mpsc_unbounded();
 
+        let retry_policy = network.retry_policy();
+
+        let batch = AdaptiveBatch::new(config.max_payload_entries);
+        let batch_size = batch.shared_size();
+
         let this = Self {
             target,
             session_id,
             network,
             snapshot_network: Arc::new(C::mutex(snapshot_network)),
             snapshot_state: None,
+            retry_policy,
+            failures: 0,
             backoff: None,
+            delay: None,
             log_reader,
+            log_cache,
             snapshot_reader,
             config,
             committed,
@@ -198,7 +244,8 @@ where
             rx_event,
             weak_tx_event: tx_event.downgrade(),
             next_action: None,
-            entries_hint: Default::default(),
+            batch,
+            entries_sent: 0,
         };
 
         let join_handle = C::spawn(this.main().instrument(span));
@@ -206,6 +253,7 @@ where
         ReplicationHandle {
             join_handle,
             tx_repl: tx_event,
+            batch_size,
         }
     }
 
@@ -227,6 +275,11 @@ where
             // If an RPC response is expected by RaftCore
             let need_notify = d.has_payload();
 
+            let rpc_type = match &d {
+                Data::Committed | Data::Logs(_) => RPCTypes::AppendEntries,
+                Data::Snapshot(_) | Data::SnapshotCallback(_) => RPCTypes::InstallSnapshot,
+            };
+
             let res = match d {
                 Data::Committed => {
                     let m = &self.matching;
@@ -249,6 +302,8 @@ where
                 Ok(next) => {
                     // reset backoff at once if replication succeeds
                     self.backoff = None;
+                    self.failures = 0;
+                    self.retry_policy.on_success(rpc_type);
 
                     // If the RPC was successful but not finished, continue.
                     if let Some(next) = next {
@@ -279,26 +334,38 @@ where
                         ReplicationError::RPCError(err) => {
                             tracing::error!(err = display(&err), "RPCError");
 
-                            let retry = match &err {
-                                RPCError::Timeout(_) => false,
-                                RPCError::Unreachable(_unreachable) => {
-                                    // If there is an [`Unreachable`] error, we will backoff for a
-                                    // period of time. Backoff will be reset if there is a
-                                    // successful RPC is sent.
+                            self.failures += 1;
+                            let decision = self.retry_policy.on_error(rpc_type, &err, self.failures);
+                            tracing::debug!(decision = debug(&decision), "retry policy decision");
+
+                            match decision.delay {
+                                RetryDelay::None => {}
+                                RetryDelay::Backoff => {
+                                    // Backoff for a period of time before every RPC.
+                                    // Backoff will be reset if there is a successful RPC is sent.
                                     if self.backoff.is_none() {
                                         self.backoff = Some(self.network.backoff());
                                     }
-                                    false
                                 }
-                                RPCError::PayloadTooLarge(too_large) => {
-                                    self.update_hint(too_large);
+                                RetryDelay::Fixed(d) => self.delay = Some(d),
+                            }
 
-                                    // PayloadTooLarge is a retryable error: retry at once.
-                                    self.next_action = Some(Data::Logs(log_data.unwrap()));
+                            // Shrink the batch at most once for a failure: `shrink_payload()` already
+                            // halves it, otherwise a timed-out AppendEntries implies the link is too
+                            // slow for the batch.
+                            if decision.shrink_payload {
+                                self.shrink_payload(&err, log_data.as_ref());
+                            } else if rpc_type == RPCTypes::AppendEntries && matches!(err, RPCError::Timeout(_)) {
+                                self.batch.on_slow(self.entries_sent);
+                            }
+
+                            // Only a request of logs can be resent.
+                            let retry = match log_data {
+                                Some(log_data) if decision.retry => {
+                                    self.next_action = Some(Data::Logs(log_data));
                                     true
                                 }
-                                RPCError::Network(_) => false,
-                                RPCError::RemoteError(_) => false,
+                                _ => false,
                             };
 
                             if retry {
@@ -321,12 +388,18 @@ where
     }
 
     async fn drain_events_with_backoff(&mut self) -> Result<(), ReplicationClosed> {
-        if let Some(b) = &mut self.backoff {
-            let duration = b.next().unwrap_or_else(|| {
-                tracing::warn!("backoff exhausted, using default");
-                Duration::from_millis(500)
-            });
+        let duration = if let Some(d) = self.delay.take() {
+            Some(d)
+        } else {
+            self.backoff.as_mut().map(|b| {
+                b.next().unwrap_or_else(|| {
+                    tracing::warn!("backoff exhausted, using default");
+                    Duration::from_millis(500)
+                })
+            })
+        };
 
+        if let Some(duration) = duration {
             self.backoff_drain_events(C::now() + duration).await?;
         }
 
@@ -334,24 +407,37 @@ where
         Ok(())
     }
 
-    /// When a [`PayloadTooLarge`] error is received, update the hint for the next several RPC.
-    fn update_hint(&mut self, too_large: &PayloadTooLarge) {
-        const DEFAULT_ENTRIES_HINT_TTL: u64 = 10;
+    /// Send fewer entries in the next several RPC, as decided by the retry policy.
+    ///
+    /// The hint of a [`PayloadTooLarge`] error is used if there is one, otherwise the batch size is
+    /// halved.
+    fn shrink_payload(&mut self, err: &RPCError<C>, log_data: Option<&LogIdRange<C>>) {
+        if let RPCError::PayloadTooLarge(too_large) = err {
+            self.update_hint(too_large);
+            return;
+        }
+
+        if log_data.is_none() {
+            return;
+        }
+
+        self.batch.on_error(self.entries_sent);
+        tracing::debug!(batch = debug(&self.batch), "shrink payload");
+    }
 
+    /// When a [`PayloadTooLarge`] error is received, limit the batch size to the hint.
+    fn update_hint(&mut self, too_large: &PayloadTooLarge) {
         match too_large.action() {
-            RPCTypes::Vote => {
-                unreachable!("Vote RPC should not be too large")
-            }
             RPCTypes::AppendEntries => {
-                self.entries_hint = ReplicationHint::new(too_large.entries_hint(), DEFAULT_ENTRIES_HINT_TTL);
-                tracing::debug!(entries_hint = debug(&self.entries_hint), "updated entries hint");
+                self.batch.on_too_large(too_large.entries_hint());
+                tracing::debug!(batch = debug(&self.batch), "updated batch size by entries hint");
             }
             RPCTypes::InstallSnapshot => {
                 // TODO: handle too large
                 tracing::error!("InstallSnapshot RPC is too large, but it is not supported yet");
             }
-            RPCTypes::TransferLeader => {
-                unreachable!("TransferLeader RPC should not be too large")
+            action => {
+                tracing::warn!("{} RPC is not sent by replication, ignore: {}", action, too_large);
             }
         }
     }
@@ -382,12 +468,7 @@ where
                 let start = rng.prev.next_index();
                 let end = rng.last.next_index();
 
-                if let Some(hint) = self.entries_hint.get() {
-                    let hint_end = start + hint;
-                    (start, std::cmp::min(end, hint_end))
-                } else {
-                    (start, end)
-                }
+                (start, std::cmp::min(end, start + self.batch.size()))
             };
 
             if start == end {
@@ -395,8 +476,17 @@ where
                 let r = LogIdRange::new(rng.prev.clone(), rng.prev.clone());
                 (vec![], r)
             } else {
-                // limited_get_log_entries will return logs smaller than the range [start, end).
-                let logs = self.log_reader.limited_get_log_entries(start, end).await?;
+                // Both return logs smaller than the range [start, end).
+                let logs = match self.log_cache.get(start, end) {
+                    Some(logs) => logs,
+                    None => {
+                        let logs = self.log_reader.limited_get_log_entries(start, end).await?;
+                        for ent in logs.iter() {
+                            ent.verify_checksum()?;
+                        }
+                        logs
+                    }
+                };
 
                 let first = logs.first().map(|ent| ent.ref_log_id()).unwrap();
                 let last = logs.last().map(|ent| ent.log_id()).unwrap();
@@ -416,6 +506,9 @@ where
             }
         };
 
+        self.entries_sent = logs.len() as u64;
+        let first_sent = logs.first().map(|ent| ent.log_id());
+
         let leader_time = C::now();
 
         // Build the heartbeat frame to be sent to the follower.
@@ -452,6 +545,14 @@ where
 
         let append_resp = append_res?;
 
+        if matches!(
+            append_resp,
+            AppendEntriesResponse::Success | AppendEntriesResponse::PartialSuccess(_)
+        ) {
+            // A batch taking more than half of the timeout is too large for the link.
+            self.batch.on_success(self.entries_sent, leader_time.elapsed(), the_timeout / 2);
+        }
+
         tracing::debug!(
             req = display(&sending_range),
             resp = display(&append_resp),
@@ -475,6 +576,14 @@ where
 
                 self.notify_heartbeat_progress(leader_time);
 
+                // No entry is accepted because the first one is corrupted in transit. Resending
+                // at once may fail the same way: the target is unreachable for these entries, and
+                // the retry policy decides when to resend.
+                if let Some(first) = first_sent.filter(|_| matching == sending_range.prev) {
+                    let err = StorageError::checksum(first, AnyError::error("rejected by the target"));
+                    return Err(RPCError::Unreachable(Unreachable::new(&err)).into());
+                }
+
                 if has_payload {
                     self.notify_progress(ReplicationResult(Ok(matching.clone())));
                     Ok(self.next_action_to_send(matching.clone(), log_ids))
@@ -0,0 +1,302 @@
+use std::fmt;
+
+use crate::display_ext::DisplayOptionExt;
+use crate::display_ext::DisplaySliceExt;
+use crate::raft::message::TransferLeaderRequest;
+use crate::raft::AppendEntriesResponse;
+use crate::raft::SnapshotResponse;
+use crate::raft::VoteRequest;
+use crate::raft::VoteResponse;
+use crate::raft_state::IOId;
+use crate::replication::ReplicationSessionId;
+use crate::storage::Snapshot;
+use crate::type_config::alias::InstantOf;
+use crate::type_config::alias::LogIdOf;
+use crate::type_config::alias::VoteOf;
+use crate::RaftTypeConfig;
+
+/// Identifies an IO submitted to storage by a [`Command`].
+///
+/// The driver passes it back to [`RaftEngine::io_flushed()`] once the IO is persisted.
+///
+/// [`RaftEngine::io_flushed()`]: crate::sans_io::RaftEngine::io_flushed
+#[derive(Debug, Clone)]
+#[derive(PartialEq, Eq)]
+pub struct IoId<C>(pub(crate) IOId<C>)
+where C: RaftTypeConfig;
+
+impl<C> fmt::Display for IoId<C>
+where C: RaftTypeConfig
+{
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "{}", self.0)
+    }
+}
+
+/// Identifies the leader and the membership a replication command is issued for.
+///
+/// A response reported with a session that is no longer current is ignored, so that a delayed
+/// response to a former leader does not update the progress of the current one.
+#[derive(Debug, Clone)]
+#[derive(PartialEq, Eq)]
+pub struct Session<C>(pub(crate) ReplicationSessionId<C>)
+where C: RaftTypeConfig;
+
+impl<C> Session<C>
+where C: RaftTypeConfig
+{
+    /// The vote of the leader, to be sent in every replication request of this session.
+    pub fn vote(&self) -> VoteOf<C> {
+        self.0.vote()
+    }
+}
+
+impl<C> fmt::Display for Session<C>
+where C: RaftTypeConfig
+{
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "{}", self.0)
+    }
+}
+
+/// Identifies an incoming request whose reply is delivered later by [`Command::Reply`].
+#[derive(Debug, Clone, Copy)]
+#[derive(PartialEq, Eq, PartialOrd, Ord, Hash)]
+pub struct RequestId(pub(crate) u64);
+
+impl fmt::Display for RequestId {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "req-{}", self.0)
+    }
+}
+
+/// What to send to a follower or learner.
+#[derive(Debug, Clone)]
+#[derive(PartialEq, Eq)]
+pub enum Replicate<C>
+where C: RaftTypeConfig
+{
+    /// Send an `AppendEntries` without entries to inform the target of the committed log id.
+    ///
+    /// `prev_log_id` is the last log id known to be replicated to the target. No progress needs to
+    /// be reported for it.
+    Committed {
+        prev_log_id: Option<LogIdOf<C>>,
+        committed: Option<LogIdOf<C>>,
+    },
+
+    /// Send the log entries in the range `(prev_log_id, last_log_id]`.
+    ///
+    /// The response is reported with [`RaftEngine::handle_append_entries_response()`].
+    ///
+    /// [`RaftEngine::handle_append_entries_response()`]: crate::sans_io::RaftEngine::handle_append_entries_response
+    Logs {
+        prev_log_id: Option<LogIdOf<C>>,
+        last_log_id: Option<LogIdOf<C>>,
+    },
+
+    /// Send the current snapshot, because the logs the target needs are purged.
+    ///
+    /// The response is reported with [`RaftEngine::handle_snapshot_response()`].
+    ///
+    /// [`RaftEngine::handle_snapshot_response()`]: crate::sans_io::RaftEngine::handle_snapshot_response
+    Snapshot,
+}
+
+impl<C> fmt::Display for Replicate<C>
+where C: RaftTypeConfig
+{
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            Replicate::Committed { prev_log_id, committed } => {
+                write!(
+                    f,
+                    "Committed(prev: {}, committed: {})",
+                    prev_log_id.display(),
+                    committed.display()
+                )
+            }
+            Replicate::Logs {
+                prev_log_id,
+                last_log_id,
+            } => write!(f, "Logs({}, {}]", prev_log_id.display(), last_log_id.display()),
+            Replicate::Snapshot => write!(f, "Snapshot"),
+        }
+    }
+}
+
+/// The reply to an incoming request.
+#[derive(Debug)]
+#[derive(PartialEq, Eq)]
+pub enum Reply<C>
+where C: RaftTypeConfig
+{
+    Vote(VoteResponse<C>),
+    AppendEntries(AppendEntriesResponse<C>),
+    InstallSnapshot(SnapshotResponse<C>),
+}
+
+impl<C> fmt::Display for Reply<C>
+where C: RaftTypeConfig
+{
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            Reply::Vote(r) => write!(f, "Vote({})", r),
+            Reply::AppendEntries(r) => write!(f, "AppendEntries({})", r),
+            Reply::InstallSnapshot(r) => write!(f, "InstallSnapshot({})", r),
+        }
+    }
+}
+
+/// A command a driver of [`RaftEngine`] has to execute.
+///
+/// Commands are returned in the order they have to be executed in. Storage commands must be
+/// submitted in order; each of them may complete later, as long as completions are reported in the
+/// same order.
+///
+/// [`RaftEngine`]: crate::sans_io::RaftEngine
+pub enum Command<C>
+where C: RaftTypeConfig
+{
+    /// Append entries to the log store.
+    ///
+    /// Call [`RaftEngine::io_flushed()`] with `io` once the entries are persisted.
+    ///
+    /// [`RaftEngine::io_flushed()`]: crate::sans_io::RaftEngine::io_flushed
+    AppendEntries { io: IoId<C>, entries: Vec<C::Entry> },
+
+    /// Save the vote to the log store.
+    ///
+    /// Call [`RaftEngine::io_flushed()`] with `io` once the vote is persisted.
+    ///
+    /// [`RaftEngine::io_flushed()`]: crate::sans_io::RaftEngine::io_flushed
+    SaveVote { io: IoId<C>, vote: VoteOf<C> },
+
+    /// Save the committed log id to the log store. It is optional to persist it.
+    SaveCommitted { committed: LogIdOf<C> },
+
+    /// Delete logs since `since`, inclusive, which conflict with the leader.
+    ///
+    /// Pending client writes at these indexes will never be applied.
+    TruncateLog { since: LogIdOf<C> },
+
+    /// Delete logs up to `upto`, inclusive, which are already included in a snapshot.
+    PurgeLog { upto: LogIdOf<C> },
+
+    /// Apply committed logs in `[first, last]` to the state machine.
+    ///
+    /// Call [`RaftEngine::applied()`] once they are applied.
+    ///
+    /// [`RaftEngine::applied()`]: crate::sans_io::RaftEngine::applied
+    Apply { first: LogIdOf<C>, last: LogIdOf<C> },
+
+    /// Build a snapshot of the state machine.
+    ///
+    /// Call [`RaftEngine::snapshot_built()`] once it is built.
+    ///
+    /// [`RaftEngine::snapshot_built()`]: crate::sans_io::RaftEngine::snapshot_built
+    BuildSnapshot,
+
+    /// Install a snapshot received from the leader into the state machine.
+    ///
+    /// Call [`RaftEngine::snapshot_installed()`] with `io` once it is installed.
+    ///
+    /// [`RaftEngine::snapshot_installed()`]: crate::sans_io::RaftEngine::snapshot_installed
+    InstallSnapshot { io: IoId<C>, snapshot: Snapshot<C> },
+
+    /// Send a vote request to every other voter.
+    ///
+    /// Each response is reported with [`RaftEngine::handle_vote_response()`].
+    ///
+    /// [`RaftEngine::handle_vote_response()`]: crate::sans_io::RaftEngine::handle_vote_response
+    SendVote { req: VoteRequest<C> },
+
+    /// Replicate logs, a snapshot or the committed log id to `target`.
+    Replicate {
+        session: Session<C>,
+        target: C::NodeId,
+        req: Replicate<C>,
+    },
+
+    /// Send a heartbeat, an `AppendEntries` without `prev_log_id` and entries, to `target`.
+    ///
+    /// Once it is acknowledged, call [`RaftEngine::handle_heartbeat_response()`] with `sent_at`.
+    ///
+    /// [`RaftEngine::handle_heartbeat_response()`]: crate::sans_io::RaftEngine::handle_heartbeat_response
+    Heartbeat {
+        session: Session<C>,
+        target: C::NodeId,
+        committed: Option<LogIdOf<C>>,
+        sent_at: InstantOf<C>,
+    },
+
+    /// The set of replication targets changed: discard requests in flight to any target and
+    /// replicate to `targets` from now on, each with the last log id known to be replicated to it.
+    RebuildReplication {
+        session: Session<C>,
+        targets: Vec<(C::NodeId, Option<LogIdOf<C>>)>,
+    },
+
+    /// Send a transfer-leader request to every other node.
+    BroadcastTransferLeader { req: TransferLeaderRequest<C> },
+
+    /// Send the reply to the incoming request `id`.
+    Reply { id: RequestId, reply: Reply<C> },
+}
+
+impl<C> fmt::Display for Command<C>
+where C: RaftTypeConfig
+{
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            Command::AppendEntries { io, entries } => {
+                write!(f, "AppendEntries: io: {}, entries: {}", io, entries.display())
+            }
+            Command::SaveVote { io, vote } => write!(f, "SaveVote: io: {}, vote: {}", io, vote),
+            Command::SaveCommitted { committed } => write!(f, "SaveCommitted: {}", committed),
+            Command::TruncateLog { since } => write!(f, "TruncateLog: since: {}", since),
+            Command::PurgeLog { upto } => write!(f, "PurgeLog: upto: {}", upto),
+            Command::Apply { first, last } => write!(f, "Apply: [{}, {}]", first, last),
+            Command::BuildSnapshot => write!(f, "BuildSnapshot"),
+            Command::InstallSnapshot { io, snapshot } => {
+                write!(f, "InstallSnapshot: io: {}, snapshot: {}", io, snapshot)
+            }
+            Command::SendVote { req } => write!(f, "SendVote: {}", req),
+            Command::Replicate { session, target, req } => {
+                write!(f, "Replicate: session: {}, target: {}, req: {}", session, target, req)
+            }
+            Command::Heartbeat {
+                session,
+                target,
+                committed,
+                ..
+            } => write!(
+                f,
+                "Heartbeat: session: {}, target: {}, committed: {}",
+                session,
+                target,
+                committed.display()
+            ),
+            Command::RebuildReplication { session, targets } => {
+                write!(f, "RebuildReplication: session: {}, targets: [", session)?;
+                for (i, (target, matching)) in targets.iter().enumerate() {
+                    if i > 0 {
+                        write!(f, ", ")?;
+                    }
+                    write!(f, "{}: {}", target, matching.display())?;
+                }
+                write!(f, "]")
+            }
+            Command::BroadcastTransferLeader { req } => write!(f, "BroadcastTransferLeader: {}", req),
+            Command::Reply { id, reply } => write!(f, "Reply: {}: {}", id, reply),
+        }
+    }
+}
+
+impl<C> fmt::Debug for Command<C>
+where C: RaftTypeConfig
+{
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        fmt::Display::fmt(self, f)
+    }
+}
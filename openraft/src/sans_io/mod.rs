@@ -0,0 +1,65 @@
+//! A Raft state machine without IO, for applications that run their own event loop.
+//!
+//! [`Raft`] runs the protocol with its own tasks, storage and network adaptors. [`RaftEngine`]
+//! exposes the same protocol implementation as a plain, synchronous state machine instead: the
+//! driver feeds it events and executes the [`Command`]s it returns. This is useful for
+//! deterministic simulation, for embedding into an existing event loop, or for hosting many Raft
+//! groups on a few threads.
+//!
+//! This module is enabled by feature flag `sans-io`.
+//!
+//! ```text
+//!                  Driver                    RaftEngine
+//!                  |                         |
+//!  Timer --------> + ----------------------> + tick()
+//!  Client -------> + ----------------------> + client_write(), change_membership()
+//!  Network ------> + ----------------------> + handle_vote_request(), handle_append_entries(), ...
+//!  Storage ------> + ----------------------> + io_flushed(), applied(), snapshot_built(), ...
+//!                  |                         |
+//!                  + <---------------------- + next_command()
+//!                  |
+//!                  +--> Storage: AppendEntries, SaveVote, TruncateLog, PurgeLog, Apply, ...
+//!                  +--> Network: SendVote, Replicate, Heartbeat, Reply, ...
+//! ```
+//!
+//! A driver loop looks like:
+//!
+//! ```ignore
+//! loop {
+//!     match next_event().await {
+//!         Event::Tick => engine.tick(),
+//!         Event::Vote(req, reply_to) => pending.insert(engine.handle_vote_request(req), reply_to),
+//!         Event::Flushed(io) => engine.io_flushed(io),
+//!         // ...
+//!     }
+//!
+//!     while let Some(cmd) = engine.next_command() {
+//!         execute(cmd);
+//!     }
+//! }
+//! ```
+//!
+//! Storage commands are submitted in the order they are returned and their completion is reported
+//! in the same order. A command that waits for an IO to complete, such as a reply that can only be
+//! sent after the vote it grants is persisted, is held back: [`RaftEngine::next_command()`] returns
+//! `None` until the IO is reported.
+//!
+//! [`Raft`]: crate::Raft
+
+mod command;
+mod raft_engine;
+
+pub use command::Command;
+pub use command::IoId;
+pub use command::Replicate;
+pub use command::Reply;
+pub use command::RequestId;
+pub use command::Session;
+pub use raft_engine::RaftEngine;
+
+#[cfg(test)]
+mod tests {
+    mod cluster_test;
+    mod model;
+    mod model_test;
+}
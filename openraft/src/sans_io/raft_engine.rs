@@ -0,0 +1,763 @@
+use std::collections::VecDeque;
+use std::time::Duration;
+
+use crate::core::sm;
+use crate::core::ServerState;
+use crate::engine::Command as EngineCommand;
+use crate::engine::Condition;
+use crate::engine::Engine;
+use crate::engine::EngineConfig;
+use crate::engine::ReplicationProgress;
+use crate::engine::Respond;
+use crate::entry::RaftEntry;
+use crate::error::ClientWriteError;
+use crate::error::ForwardToLeader;
+use crate::error::Infallible;
+use crate::error::InitializeError;
+use crate::progress::Progress;
+use crate::raft::AppendEntriesRequest;
+use crate::raft::AppendEntriesResponse;
+use crate::raft::SnapshotResponse;
+use crate::raft::VoteRequest;
+use crate::raft::VoteResponse;
+use crate::raft_state::IOId;
+use crate::raft_state::LogStateReader;
+use crate::replication::request::Data;
+use crate::replication::request::Replicate as EngineReplicate;
+use crate::replication::response::ReplicationResult;
+use crate::replication::ReplicationSessionId;
+use crate::sans_io::Command;
+use crate::sans_io::IoId;
+use crate::sans_io::Replicate;
+use crate::sans_io::Reply;
+use crate::sans_io::RequestId;
+use crate::sans_io::Session;
+use crate::storage::Snapshot;
+use crate::storage::SnapshotMeta;
+use crate::type_config::alias::InstantOf;
+use crate::type_config::alias::LogIdOf;
+use crate::type_config::alias::VoteOf;
+use crate::type_config::TypeConfigExt;
+use crate::vote::raft_vote::RaftVoteExt;
+use crate::vote::RaftLeaderId;
+use crate::vote::RaftVote;
+use crate::ChangeMembers;
+use crate::Config;
+use crate::LogIdOptionExt;
+use crate::Membership;
+use crate::RaftState;
+use crate::RaftTypeConfig;
+
+fn into_ok<T>(res: Result<T, Infallible>) -> T {
+    match res {
+        Ok(t) => t,
+        Err(e) => match e {},
+    }
+}
+
+/// The Raft protocol as a state machine without IO, for applications that drive it with their own
+/// event loop, storage and network.
+///
+/// The driver feeds events into it: incoming RPCs, ticks, client writes and the completion of the
+/// IO it was asked to do. After every event, it takes the resulting [`Command`]s with
+/// [`next_command()`](Self::next_command) until it returns `None`, and executes them.
+///
+/// All timing is based on [`RaftTypeConfig::AsyncRuntime`]'s clock, thus a driver running in
+/// virtual time has to provide a runtime whose `Instant` follows it.
+pub struct RaftEngine<C>
+where C: RaftTypeConfig
+{
+    config: Config,
+    engine: Engine<C>,
+
+    /// Commands that are translated but not yet taken by the driver.
+    ready: VecDeque<Command<C>>,
+
+    next_request_id: u64,
+
+    /// Requests whose reply is not yet emitted, in the order they are received.
+    ///
+    /// The engine queues exactly one `Respond` command for every request and its output is taken
+    /// strictly in order, thus the next `Respond` always answers the front of this queue.
+    pending_replies: VecDeque<RequestId>,
+
+    /// The session of the last [`Command::RebuildReplication`] taken by the driver.
+    ///
+    /// The engine state moves to a new session as soon as a membership log is appended, but a
+    /// replication queued before it still belongs to the previous one, as a replication stream of
+    /// [`Raft`](crate::Raft) does until it is rebuilt. Thus its reply is discarded instead of
+    /// being taken as progress of the new session.
+    replication_session: Option<Session<C>>,
+}
+
+impl<C> RaftEngine<C>
+where C: RaftTypeConfig
+{
+    /// Create an engine for node `id` from the state loaded from storage.
+    ///
+    /// The state is usually built with [`StorageHelper::get_initial_state()`], which also
+    /// re-applies committed logs to the state machine.
+    ///
+    /// [`StorageHelper::get_initial_state()`]: crate::StorageHelper::get_initial_state
+    pub fn new(id: C::NodeId, state: RaftState<C>, config: &Config) -> Self {
+        let engine_config = EngineConfig::new(id, config);
+        let mut engine = Engine::new(state, engine_config);
+        engine.startup();
+
+        Self {
+            config: config.clone(),
+            engine,
+            ready: VecDeque::new(),
+            next_request_id: 0,
+            pending_replies: VecDeque::new(),
+            replication_session: None,
+        }
+    }
+
+    pub fn id(&self) -> &C::NodeId {
+        &self.engine.config.id
+    }
+
+    /// The in-memory state of this node, which is ahead of or equal to the persisted state.
+    pub fn state(&self) -> &RaftState<C> {
+        &self.engine.state
+    }
+
+    pub fn server_state(&self) -> ServerState {
+        self.engine.state.server_state
+    }
+
+    pub fn vote(&self) -> &VoteOf<C> {
+        self.engine.state.vote_ref()
+    }
+
+    pub fn committed(&self) -> Option<&LogIdOf<C>> {
+        self.engine.state.committed()
+    }
+
+    pub fn last_log_id(&self) -> Option<&LogIdOf<C>> {
+        self.engine.state.last_log_id()
+    }
+
+    /// The id of the leader this node knows of, if it is a voter.
+    pub fn current_leader(&self) -> Option<C::NodeId> {
+        let vote = self.engine.state.vote_ref();
+        if !vote.is_committed() {
+            return None;
+        }
+
+        let id = vote.to_leader_id().node_id().cloned()?;
+        if self.engine.state.membership_state.effective().is_voter(&id) {
+            Some(id)
+        } else {
+            None
+        }
+    }
+
+    /// Initialize a cluster with this node as a member, as [`Raft::initialize()`] does.
+    ///
+    /// The initial membership log is only durable once the [`Command::AppendEntries`] emitted for
+    /// it is flushed.
+    ///
+    /// [`Raft::initialize()`]: crate::Raft::initialize
+    pub fn initialize(&mut self, membership: Membership<C>) -> Result<(), InitializeError<C>> {
+        let entry = C::Entry::new_membership(LogIdOf::<C>::default(), membership);
+        self.engine.initialize(entry)
+    }
+
+    /// Start an election at once, regardless of the election timeout.
+    pub fn elect(&mut self) {
+        self.engine.elect();
+    }
+
+    /// Check timers: start an election when the election timeout has passed, send heartbeats when
+    /// it is a leader, and step down when a membership without this leader is applied.
+    ///
+    /// It should be called periodically, e.g., every `heartbeat_interval / 2`.
+    pub fn tick(&mut self) {
+        let now = C::now();
+
+        self.tick_election(now);
+
+        let heartbeat_at = self.engine.leader_ref().map(|l| l.next_heartbeat);
+        if let Some(t) = heartbeat_at {
+            if now >= t {
+                if self.config.enable_heartbeat {
+                    self.send_heartbeat();
+                }
+
+                if let Some(l) = self.engine.leader_mut() {
+                    l.next_heartbeat = C::now() + Duration::from_millis(self.config.heartbeat_interval);
+                }
+            }
+        }
+
+        if self.engine.state.io_applied() >= self.engine.state.membership_state.effective().log_id().as_ref() {
+            self.engine.leader_step_down();
+        }
+    }
+
+    fn tick_election(&mut self, now: InstantOf<C>) {
+        if self.engine.state.server_state == ServerState::Leader {
+            return;
+        }
+
+        let id = self.engine.config.id.clone();
+        let effective = self.engine.state.membership_state.effective();
+
+        if !effective.is_voter(&id) || !self.config.enable_elect {
+            return;
+        }
+
+        if effective.voter_ids().count() > 1 {
+            let timer_config = &self.engine.config.timer_config;
+
+            let mut election_timeout = timer_config.election_timeout;
+            if self.engine.is_there_greater_log() {
+                election_timeout += timer_config.smaller_log_timeout;
+            }
+
+            if !self.engine.state.vote.is_expired(now, election_timeout) {
+                return;
+            }
+        }
+
+        self.engine.reset_greater_log();
+
+        tracing::info!("election timeout passed, about to elect");
+        self.engine.elect();
+    }
+
+    fn send_heartbeat(&mut self) {
+        let Ok(mut lh) = self.engine.leader_handler() else {
+            return;
+        };
+
+        if lh.leader.get_transfer_to().is_some() {
+            return;
+        }
+
+        lh.send_heartbeat();
+    }
+
+    /// Propose `data` as a new log entry and return its log id.
+    ///
+    /// The result of the write is the result of applying the entry, which is known once the driver
+    /// applies it. If the entry is truncated by [`Command::TruncateLog`] before that, the write
+    /// failed.
+    pub fn client_write(&mut self, data: C::D) -> Result<LogIdOf<C>, ClientWriteError<C>> {
+        let entry = C::Entry::new_normal(LogIdOf::<C>::default(), data);
+        self.write_entry(entry)
+    }
+
+    /// Propose a membership change and return the log id of the new membership entry.
+    ///
+    /// See [`Raft::change_membership()`] for the meaning of `retain`.
+    ///
+    /// [`Raft::change_membership()`]: crate::Raft::change_membership
+    pub fn change_membership(
+        &mut self,
+        changes: ChangeMembers<C>,
+        retain: bool,
+    ) -> Result<LogIdOf<C>, ClientWriteError<C>> {
+        let res = self.engine.state.membership_state.change_handler().apply(changes, retain);
+        let membership = res.map_err(ClientWriteError::ChangeMembershipError)?;
+
+        let entry = C::Entry::new_membership(LogIdOf::<C>::default(), membership);
+        self.write_entry(entry)
+    }
+
+    fn write_entry(&mut self, entry: C::Entry) -> Result<LogIdOf<C>, ClientWriteError<C>> {
+        let mut lh = self.engine.leader_handler()?;
+
+        if let Some(to) = lh.leader.get_transfer_to() {
+            let err = lh.state.new_forward_to_leader(to.clone());
+            return Err(ClientWriteError::ForwardToLeader(err));
+        }
+
+        lh.leader_append_entries(vec![entry]);
+        Ok(lh.state.last_log_id().cloned().unwrap())
+    }
+
+    /// Ask the leader to transfer leadership to `to`.
+    pub fn transfer_leader(&mut self, to: C::NodeId) -> Result<(), ForwardToLeader<C>> {
+        self.engine.leader_handler()?;
+        self.engine.trigger_transfer_leader(to);
+        Ok(())
+    }
+
+    /// Build a snapshot if none is being built.
+    pub fn trigger_snapshot(&mut self) {
+        self.engine.snapshot_handler().trigger_snapshot();
+    }
+
+    /// Handle a vote request from a candidate.
+    ///
+    /// The reply is emitted as [`Command::Reply`] once the vote it grants is persisted.
+    pub fn handle_vote_request(&mut self, req: VoteRequest<C>) -> RequestId {
+        let resp = self.engine.handle_vote_req(req);
+        let condition = Some(Condition::IOFlushed {
+            io_id: IOId::new(self.engine.state.vote_ref()),
+        });
+
+        let (tx, _rx) = C::oneshot();
+        self.engine.output.push_command(EngineCommand::Respond {
+            when: condition,
+            resp: Respond::new(Ok(resp), tx),
+        });
+        self.add_pending_reply()
+    }
+
+    /// Handle an append-entries request from a leader.
+    ///
+    /// The reply is emitted as [`Command::Reply`] once the accepted entries are persisted.
+    pub fn handle_append_entries(&mut self, req: AppendEntriesRequest<C>) -> RequestId {
+        let (tx, _rx) = C::oneshot();
+        let is_ok = self.engine.handle_append_entries(&req.vote, req.prev_log_id, req.entries, Some(tx));
+
+        if is_ok {
+            self.engine.handle_commit_entries(req.leader_commit);
+        }
+        self.add_pending_reply()
+    }
+
+    /// Handle a complete snapshot received from the leader with `vote`.
+    ///
+    /// The reply is emitted as [`Command::Reply`] once the snapshot is installed.
+    pub fn handle_install_snapshot(&mut self, vote: VoteOf<C>, snapshot: Snapshot<C>) -> RequestId {
+        let (tx, _rx) = C::oneshot();
+        self.engine.handle_install_full_snapshot(vote, snapshot, tx);
+        self.add_pending_reply()
+    }
+
+    fn add_pending_reply(&mut self) -> RequestId {
+        self.next_request_id += 1;
+        let id = RequestId(self.next_request_id);
+        self.pending_replies.push_back(id);
+        id
+    }
+
+    /// Handle the response to a [`Command::SendVote`] request with vote `sent` from `target`.
+    pub fn handle_vote_response(&mut self, target: C::NodeId, sent: &VoteOf<C>, resp: VoteResponse<C>) {
+        let Some(candidate) = self.engine.candidate_ref() else {
+            return;
+        };
+
+        if candidate.vote_ref().leader_id() != sent.leader_id() {
+            tracing::warn!("ignore vote response to a former election: {}", sent);
+            return;
+        }
+
+        self.engine.handle_vote_resp(target, resp);
+    }
+
+    /// Handle the response to a [`Replicate::Logs`] request of `session` to `target`.
+    pub fn handle_append_entries_response(
+        &mut self,
+        session: &Session<C>,
+        target: C::NodeId,
+        prev_log_id: Option<LogIdOf<C>>,
+        last_log_id: Option<LogIdOf<C>>,
+        resp: AppendEntriesResponse<C>,
+    ) {
+        let result = match resp {
+            AppendEntriesResponse::Success => Ok(last_log_id),
+            AppendEntriesResponse::PartialSuccess(matching) => {
+                // No entry is accepted because the first one is corrupted in transit.
+                if matching == prev_log_id && last_log_id != prev_log_id {
+                    self.replication_failed(session, target, "no entry is accepted: checksum mismatch");
+                    return;
+                }
+                Ok(matching)
+            }
+            AppendEntriesResponse::Conflict => {
+                let Some(conflict) = prev_log_id else {
+                    tracing::warn!("prev_log_id=None never conflicts, ignore the response");
+                    return;
+                };
+                Err(conflict)
+            }
+            AppendEntriesResponse::HigherVote(vote) => {
+                self.handle_higher_vote(session, vote);
+                return;
+            }
+        };
+
+        self.update_progress(session, target, Ok(ReplicationResult(result)));
+    }
+
+    /// Handle the response to a [`Replicate::Snapshot`] request of `session` to `target`, which
+    /// sent the snapshot described by `meta`.
+    pub fn handle_snapshot_response(
+        &mut self,
+        session: &Session<C>,
+        target: C::NodeId,
+        meta: &SnapshotMeta<C>,
+        resp: SnapshotResponse<C>,
+    ) {
+        if resp.vote.as_ref_vote() > session.vote().as_ref_vote() {
+            self.handle_higher_vote(session, resp.vote);
+            return;
+        }
+
+        let result = ReplicationResult(Ok(meta.last_log_id.clone()));
+        self.update_progress(session, target, Ok(result));
+    }
+
+    /// Report that a [`Command::Replicate`] request of `session` to `target` failed, e.g., because
+    /// of a network error.
+    ///
+    /// The request will be sent again by a later [`Command::Replicate`].
+    pub fn replication_failed(&mut self, session: &Session<C>, target: C::NodeId, reason: impl ToString) {
+        self.update_progress(session, target, Err(reason.to_string()));
+    }
+
+    fn update_progress(&mut self, session: &Session<C>, target: C::NodeId, res: Result<ReplicationResult<C>, String>) {
+        if self.does_session_match(session) {
+            self.engine.replication_handler().update_progress(target, res);
+        }
+    }
+
+    /// Handle an acknowledged [`Command::Heartbeat`].
+    pub fn handle_heartbeat_response(
+        &mut self,
+        session: &Session<C>,
+        target: C::NodeId,
+        sent_at: InstantOf<C>,
+        resp: AppendEntriesResponse<C>,
+    ) {
+        if let AppendEntriesResponse::HigherVote(vote) = resp {
+            self.handle_higher_vote(session, vote);
+            return;
+        }
+
+        if self.does_session_match(session) {
+            self.engine.replication_handler().update_leader_clock(target, sent_at);
+        }
+    }
+
+    /// A replication or heartbeat request of `session` is rejected by a greater vote.
+    pub fn handle_higher_vote(&mut self, session: &Session<C>, higher: VoteOf<C>) {
+        if self.does_leader_vote_match(session) {
+            // Rejected vote change is ok.
+            let _ = self.engine.vote_handler().update_vote(&higher);
+        }
+    }
+
+    fn does_leader_vote_match(&self, session: &Session<C>) -> bool {
+        let Some(leader) = self.engine.leader.as_ref() else {
+            return false;
+        };
+        leader.committed_vote == session.0.committed_vote()
+    }
+
+    fn does_session_match(&self, session: &Session<C>) -> bool {
+        self.does_leader_vote_match(session)
+            && &session.0.membership_log_id == self.engine.state.membership_state.effective().log_id()
+    }
+
+    /// Report that the IO `io` of a [`Command::AppendEntries`] or [`Command::SaveVote`] is
+    /// persisted.
+    pub fn io_flushed(&mut self, io: IoId<C>) {
+        let io_id = io.0;
+        self.engine.state.io_state.io_progress.flush(io_id.clone());
+
+        match io_id {
+            IOId::Log(log_io_id) => {
+                let is_my_leader = self.engine.leader.as_ref().map(|l| &l.committed_vote);
+                if is_my_leader == Some(&log_io_id.committed_vote) {
+                    self.engine.replication_handler().update_local_progress(log_io_id.log_id);
+                }
+            }
+            IOId::Vote(non_committed) => {
+                // The local node grants its own vote once the vote is persisted.
+                let vote = non_committed.into_vote();
+                if self.engine.candidate_ref().map(|c| c.vote_ref()) == Some(&vote) {
+                    let resp = VoteResponse::new(vote, None, true);
+                    let id = self.engine.config.id.clone();
+                    self.engine.handle_vote_resp(id, resp);
+                }
+            }
+        }
+    }
+
+    /// Report that logs up to `last` are applied to the state machine.
+    pub fn applied(&mut self, last: LogIdOf<C>) {
+        self.engine.state.io_state_mut().update_applied(Some(last));
+    }
+
+    /// Report that the snapshot requested by [`Command::BuildSnapshot`] is built.
+    pub fn snapshot_built(&mut self, meta: SnapshotMeta<C>) {
+        let last_log_id = meta.last_log_id.clone();
+        self.engine.finish_building_snapshot(meta);
+        self.engine.state.io_state_mut().update_snapshot(last_log_id);
+    }
+
+    /// Report that the snapshot of a [`Command::InstallSnapshot`] is installed, with `meta` being
+    /// the meta of the installed snapshot, or `None` if it is skipped because it is not newer than
+    /// the state machine.
+    pub fn snapshot_installed(&mut self, io: IoId<C>, meta: Option<SnapshotMeta<C>>) {
+        let st = self.engine.state.io_state_mut();
+        st.io_progress.flush(io.0);
+
+        if let Some(meta) = meta {
+            st.update_applied(meta.last_log_id.clone());
+            st.update_snapshot(meta.last_log_id);
+        }
+    }
+
+    /// Return the next command to execute, or `None` if there is none, or the next one waits for
+    /// an IO to complete.
+    pub fn next_command(&mut self) -> Option<Command<C>> {
+        loop {
+            if let Some(cmd) = self.ready.pop_front() {
+                return Some(cmd);
+            }
+
+            let Some(cmd) = self.engine.output.pop_command() else {
+                // Keep replicating to a target if there is nothing in flight to it.
+                if let Ok(mut lh) = self.engine.leader_handler() {
+                    lh.replication_handler().initiate_replication();
+                }
+
+                let cmd = self.engine.output.pop_command()?;
+                self.engine.output.postpone_command(cmd);
+                continue;
+            };
+
+            if !self.is_condition_satisfied(cmd.condition()) {
+                tracing::debug!("postpone command: {}", cmd);
+                self.engine.output.postpone_command(cmd);
+                return None;
+            }
+
+            self.translate(cmd);
+        }
+    }
+
+    fn is_condition_satisfied(&self, condition: Option<Condition<C>>) -> bool {
+        let Some(condition) = condition else {
+            return true;
+        };
+
+        let io_state = self.engine.state.io_state();
+        match condition {
+            Condition::IOFlushed { io_id } => io_state.io_progress.flushed() >= Some(&io_id),
+            Condition::LogFlushed { log_id } => {
+                let flushed = io_state.io_progress.flushed().and_then(|x| x.last_log_id());
+                flushed >= log_id.as_ref()
+            }
+            Condition::Applied { log_id } => self.engine.state.io_applied() >= log_id.as_ref(),
+            Condition::Snapshot { log_id } => io_state.snapshot() >= log_id.as_ref(),
+        }
+    }
+
+    /// Translate an engine command into zero or more commands for the driver.
+    fn translate(&mut self, cmd: EngineCommand<C>) {
+        tracing::debug!("translate command: {}", cmd);
+
+        match cmd {
+            EngineCommand::UpdateIOProgress { io_id, .. } => {
+                // No IO is needed: it is flushed as soon as it is submitted.
+                self.engine.state.io_state.io_progress.submit(io_id.clone());
+                self.io_flushed(IoId(io_id));
+            }
+            EngineCommand::AppendInputEntries {
+                committed_vote,
+                entries,
+            } => {
+                let last_log_id = entries.last().unwrap().log_id();
+                let io_id = IOId::new_log_io(committed_vote, Some(last_log_id));
+                self.engine.state.io_state.io_progress.submit(io_id.clone());
+
+                self.ready.push_back(Command::AppendEntries {
+                    io: IoId(io_id),
+                    entries,
+                });
+            }
+            EngineCommand::SaveVote { vote } => {
+                let io_id = IOId::new(&vote);
+                self.engine.state.io_state.io_progress.submit(io_id.clone());
+
+                self.ready.push_back(Command::SaveVote { io: IoId(io_id), vote });
+            }
+            EngineCommand::SaveCommitted { committed } => {
+                self.ready.push_back(Command::SaveCommitted { committed });
+            }
+            EngineCommand::TruncateLog { since } => {
+                self.ready.push_back(Command::TruncateLog { since });
+            }
+            EngineCommand::PurgeLog { upto } => {
+                self.engine.state.io_state_mut().update_purged(Some(upto.clone()));
+                self.ready.push_back(Command::PurgeLog { upto });
+            }
+            EngineCommand::Apply {
+                already_committed,
+                upto,
+            } => {
+                let first = self.engine.state.get_log_id(already_committed.next_index()).unwrap();
+                self.ready.push_back(Command::Apply { first, last: upto });
+            }
+            EngineCommand::SendVote { vote_req } => {
+                self.ready.push_back(Command::SendVote { req: vote_req });
+            }
+            EngineCommand::ReplicateCommitted { committed } => {
+                let Some(session) = self.replication_session() else {
+                    return;
+                };
+
+                for (target, matching) in self.replication_targets() {
+                    self.ready.push_back(Command::Replicate {
+                        session: session.clone(),
+                        target,
+                        req: Replicate::Committed {
+                            prev_log_id: matching,
+                            committed: committed.clone(),
+                        },
+                    });
+                }
+            }
+            EngineCommand::BroadcastHeartbeat { session_id, committed } => {
+                let session = Session(session_id);
+                let sent_at = C::now();
+
+                for (target, _) in self.replication_targets() {
+                    self.ready.push_back(Command::Heartbeat {
+                        session: session.clone(),
+                        target,
+                        committed: committed.clone(),
+                        sent_at,
+                    });
+                }
+            }
+            EngineCommand::Replicate { target, req } => {
+                let Some(session) = self.replication_session() else {
+                    return;
+                };
+
+                let req = match req {
+                    EngineReplicate::Committed(committed) => Replicate::Committed {
+                        prev_log_id: self.matching(&target),
+                        committed,
+                    },
+                    EngineReplicate::Data(Data::Committed) => Replicate::Committed {
+                        prev_log_id: self.matching(&target),
+                        committed: self.engine.state.committed().cloned(),
+                    },
+                    EngineReplicate::Data(Data::Logs(range)) => Replicate::Logs {
+                        prev_log_id: range.prev,
+                        last_log_id: range.last,
+                    },
+                    EngineReplicate::Data(Data::Snapshot(_)) => Replicate::Snapshot,
+                    EngineReplicate::Data(Data::SnapshotCallback(_)) => {
+                        unreachable!("SnapshotCallback is only built by a replication stream")
+                    }
+                };
+
+                self.ready.push_back(Command::Replicate { session, target, req });
+            }
+            EngineCommand::DelegateReplicate {
+                target, log_id_range, ..
+            } => {
+                // There is no network to ask another node with: replicate as usual.
+                let Some(session) = self.replication_session() else {
+                    return;
+                };
+
+                let req = Replicate::Logs {
+                    prev_log_id: log_id_range.prev,
+                    last_log_id: log_id_range.last,
+                };
+                self.ready.push_back(Command::Replicate { session, target, req });
+            }
+            EngineCommand::RebuildReplicationStreams { targets } => {
+                let Some(session) = self.session() else {
+                    return;
+                };
+
+                let targets =
+                    targets.into_iter().map(|ReplicationProgress(target, p)| (target, p.matching().cloned())).collect();
+
+                self.replication_session = Some(session.clone());
+                self.ready.push_back(Command::RebuildReplication { session, targets });
+            }
+            EngineCommand::BroadcastTransferLeader { req } => {
+                self.ready.push_back(Command::BroadcastTransferLeader { req });
+            }
+            EngineCommand::BroadcastHibernate { .. } => {
+                // Hibernation is decided by `RaftCore` on tick, which this engine does not run.
+            }
+            EngineCommand::StateMachine { command } => {
+                if let Some(io_id) = command.get_submit_io() {
+                    self.engine.state.io_state.io_progress.submit(io_id);
+                }
+
+                match command {
+                    sm::Command::BuildSnapshot => self.ready.push_back(Command::BuildSnapshot),
+                    sm::Command::InstallFullSnapshot { io_id, snapshot } => {
+                        self.ready.push_back(Command::InstallSnapshot {
+                            io: IoId(io_id),
+                            snapshot,
+                        });
+                    }
+                    sm::Command::Apply { first, last } => self.ready.push_back(Command::Apply { first, last }),
+                    sm::Command::GetSnapshot { .. }
+                    | sm::Command::BeginReceivingSnapshot { .. }
+                    | sm::Command::Func { .. } => {
+                        unreachable!("{} is not issued by RaftEngine", command)
+                    }
+                }
+            }
+            EngineCommand::Respond { resp, .. } => {
+                // The value is taken from the command instead of being sent through the channel:
+                // polling a receiver without yielding may spuriously return `Pending`, e.g., when
+                // tokio's cooperative budget of the current task is used up.
+                let reply = match resp {
+                    Respond::Vote(vs) => Reply::Vote(into_ok(vs.into_value())),
+                    Respond::AppendEntries(vs) => Reply::AppendEntries(into_ok(vs.into_value())),
+                    Respond::InstallFullSnapshot(vs) => Reply::InstallSnapshot(into_ok(vs.into_value())),
+                    Respond::ReceiveSnapshotChunk(_) | Respond::InstallSnapshot(_) | Respond::Initialize(_) => {
+                        unreachable!("{} is not issued by RaftEngine", resp)
+                    }
+                };
+                let id = self.pending_replies.pop_front().expect("every Respond answers a pending request");
+                self.ready.push_back(Command::Reply { id, reply });
+            }
+        }
+    }
+
+    /// The current replication session if this node is a leader.
+    fn session(&self) -> Option<Session<C>> {
+        let leader = self.engine.leader.as_ref()?;
+        let membership_log_id = self.engine.state.membership_state.effective().log_id().clone();
+        Some(Session(ReplicationSessionId::new(
+            leader.committed_vote.clone(),
+            membership_log_id,
+        )))
+    }
+
+    /// The session a replication command is sent in, if this node is still a leader.
+    fn replication_session(&self) -> Option<Session<C>> {
+        self.engine.leader.as_ref()?;
+        self.replication_session.clone()
+    }
+
+    fn matching(&self, target: &C::NodeId) -> Option<LogIdOf<C>> {
+        let leader = self.engine.leader.as_ref()?;
+        leader.progress.get(target).matching().cloned()
+    }
+
+    /// Replication targets other than this node and the last log id replicated to each of them.
+    fn replication_targets(&self) -> Vec<(C::NodeId, Option<LogIdOf<C>>)> {
+        let Some(leader) = self.engine.leader.as_ref() else {
+            return vec![];
+        };
+
+        leader
+            .progress
+            .iter()
+            .filter(|(id, _)| id != &self.engine.config.id)
+            .map(|(id, p)| (id.clone(), p.matching().cloned()))
+            .collect()
+    }
+}
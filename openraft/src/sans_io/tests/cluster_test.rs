@@ -0,0 +1,351 @@
+use std::collections::BTreeMap;
+use std::collections::VecDeque;
+
+use maplit::btreeset;
+use pretty_assertions::assert_eq;
+
+use crate::engine::testing::UTConfig;
+use crate::entry::RaftEntry;
+use crate::error::ClientWriteError;
+use crate::raft::AppendEntriesRequest;
+use crate::raft::AppendEntriesResponse;
+use crate::raft::VoteRequest;
+use crate::raft::VoteResponse;
+use crate::sans_io::Command;
+use crate::sans_io::RaftEngine;
+use crate::sans_io::Replicate;
+use crate::sans_io::Reply;
+use crate::sans_io::RequestId;
+use crate::sans_io::Session;
+use crate::type_config::alias::EntryOf;
+use crate::type_config::alias::InstantOf;
+use crate::type_config::alias::LogIdOf;
+use crate::type_config::alias::VoteOf;
+use crate::ChangeMembers;
+use crate::Config;
+use crate::Membership;
+use crate::RaftState;
+use crate::ServerState;
+use crate::Vote;
+
+type C = UTConfig;
+
+/// What a leader sent, to interpret the reply with.
+enum Sent {
+    Vote(VoteOf<C>),
+    Logs {
+        session: Session<C>,
+        prev_log_id: Option<LogIdOf<C>>,
+        last_log_id: Option<LogIdOf<C>>,
+    },
+    Committed(Session<C>),
+    Heartbeat(Session<C>, InstantOf<C>),
+}
+
+enum Message {
+    Vote(VoteRequest<C>, Sent),
+    AppendEntries(AppendEntriesRequest<C>, Sent),
+    Reply(Reply<C>, Sent),
+}
+
+/// A node with in-memory storage that completes every IO at once.
+struct Node {
+    engine: RaftEngine<C>,
+    log: BTreeMap<u64, EntryOf<C>>,
+    applied: Vec<LogIdOf<C>>,
+
+    /// Where to send the reply of an incoming request: the requester and what it sent.
+    replies_to: BTreeMap<RequestId, (u64, Sent)>,
+}
+
+/// Nodes connected by a network that delivers messages in order, without loss.
+struct Cluster {
+    nodes: BTreeMap<u64, Node>,
+    network: VecDeque<(u64, u64, Message)>,
+}
+
+impl Cluster {
+    fn new(ids: impl IntoIterator<Item = u64>) -> Self {
+        let config = Config::default().validate().unwrap();
+
+        let nodes = ids
+            .into_iter()
+            .map(|id| {
+                let node = Node {
+                    engine: RaftEngine::new(id, RaftState::default(), &config),
+                    log: BTreeMap::new(),
+                    applied: vec![],
+                    replies_to: BTreeMap::new(),
+                };
+                (id, node)
+            })
+            .collect();
+
+        Self {
+            nodes,
+            network: VecDeque::new(),
+        }
+    }
+
+    fn engine(&mut self, id: u64) -> &mut RaftEngine<C> {
+        &mut self.nodes.get_mut(&id).unwrap().engine
+    }
+
+    /// Execute the commands of every node and deliver messages until nothing is left to do.
+    fn run(&mut self) {
+        loop {
+            let ids = self.nodes.keys().copied().collect::<Vec<_>>();
+            for id in ids {
+                self.run_commands(id);
+            }
+
+            let Some((from, to, msg)) = self.network.pop_front() else {
+                return;
+            };
+            self.deliver(from, to, msg);
+        }
+    }
+
+    fn run_commands(&mut self, id: u64) {
+        let peers = self.peers(id);
+        let node = self.nodes.get_mut(&id).unwrap();
+
+        while let Some(cmd) = node.engine.next_command() {
+            match cmd {
+                Command::AppendEntries { io, entries } => {
+                    for ent in entries {
+                        node.log.insert(ent.index(), ent);
+                    }
+                    node.engine.io_flushed(io);
+                }
+                Command::SaveVote { io, .. } => node.engine.io_flushed(io),
+                Command::SaveCommitted { .. } => {}
+                Command::TruncateLog { since } => {
+                    node.log.split_off(&since.index);
+                }
+                Command::PurgeLog { upto } => {
+                    node.log = node.log.split_off(&(upto.index + 1));
+                }
+                Command::Apply { first, last } => {
+                    for index in first.index..=last.index {
+                        node.applied.push(node.log[&index].log_id());
+                    }
+                    node.engine.applied(last);
+                }
+                Command::BuildSnapshot | Command::InstallSnapshot { .. } => {
+                    unreachable!("no snapshot is built in this test")
+                }
+                Command::SendVote { req } => {
+                    for target in peers.iter().copied() {
+                        let msg = Message::Vote(req.clone(), Sent::Vote(req.vote));
+                        self.network.push_back((id, target, msg));
+                    }
+                }
+                Command::Replicate { session, target, req } => {
+                    let committed = node.engine.committed().cloned();
+
+                    let (prev_log_id, entries, sent) = match req {
+                        Replicate::Committed { prev_log_id, .. } => {
+                            (prev_log_id, vec![], Sent::Committed(session.clone()))
+                        }
+                        Replicate::Logs {
+                            prev_log_id,
+                            last_log_id,
+                        } => {
+                            let start = prev_log_id.as_ref().map_or(0, |x| x.index + 1);
+                            let end = last_log_id.as_ref().map_or(0, |x| x.index + 1);
+                            let entries = node.log.range(start..end).map(|(_, e)| e.clone()).collect();
+                            let sent = Sent::Logs {
+                                session: session.clone(),
+                                prev_log_id,
+                                last_log_id,
+                            };
+                            (prev_log_id, entries, sent)
+                        }
+                        Replicate::Snapshot => unreachable!("no log is purged in this test"),
+                    };
+
+                    let req = AppendEntriesRequest {
+                        vote: session.vote(),
+                        prev_log_id,
+                        leader_commit: committed,
+                        entries,
+                    };
+                    self.network.push_back((id, target, Message::AppendEntries(req, sent)));
+                }
+                Command::Heartbeat {
+                    session,
+                    target,
+                    committed,
+                    sent_at,
+                } => {
+                    let req = AppendEntriesRequest {
+                        vote: session.vote(),
+                        prev_log_id: None,
+                        leader_commit: committed,
+                        entries: vec![],
+                    };
+                    let sent = Sent::Heartbeat(session, sent_at);
+                    self.network.push_back((id, target, Message::AppendEntries(req, sent)));
+                }
+                Command::RebuildReplication { .. } => {}
+                Command::BroadcastTransferLeader { .. } => {}
+                Command::Reply { id: request_id, reply } => {
+                    let (requester, sent) = node.replies_to.remove(&request_id).unwrap();
+                    self.network.push_back((id, requester, Message::Reply(reply, sent)));
+                }
+            }
+        }
+    }
+
+    fn deliver(&mut self, from: u64, to: u64, msg: Message) {
+        let node = self.nodes.get_mut(&to).unwrap();
+
+        match msg {
+            Message::Vote(req, sent) => {
+                let request_id = node.engine.handle_vote_request(req);
+                node.replies_to.insert(request_id, (from, sent));
+            }
+            Message::AppendEntries(req, sent) => {
+                let request_id = node.engine.handle_append_entries(req);
+                node.replies_to.insert(request_id, (from, sent));
+            }
+            Message::Reply(Reply::Vote(resp), Sent::Vote(vote)) => {
+                node.engine.handle_vote_response(from, &vote, resp);
+            }
+            Message::Reply(Reply::AppendEntries(resp), sent) => match sent {
+                Sent::Logs {
+                    session,
+                    prev_log_id,
+                    last_log_id,
+                } => node.engine.handle_append_entries_response(&session, from, prev_log_id, last_log_id, resp),
+                Sent::Committed(session) => {
+                    if let AppendEntriesResponse::HigherVote(vote) = resp {
+                        node.engine.handle_higher_vote(&session, vote);
+                    }
+                }
+                Sent::Heartbeat(session, sent_at) => {
+                    node.engine.handle_heartbeat_response(&session, from, sent_at, resp);
+                }
+                Sent::Vote(_) => unreachable!(),
+            },
+            Message::Reply(_, _) => unreachable!("unexpected reply"),
+        }
+    }
+
+    fn peers(&self, id: u64) -> Vec<u64> {
+        self.nodes.keys().copied().filter(|x| *x != id).collect()
+    }
+}
+
+#[test]
+fn test_three_nodes_elect_and_replicate() -> anyhow::Result<()> {
+    let mut cluster = Cluster::new([1, 2, 3]);
+
+    tracing::info!("--- initialize node 1, which elects itself");
+    {
+        cluster.engine(1).initialize(Membership::new_with_defaults(vec![btreeset! {1,2,3}], []))?;
+        cluster.run();
+
+        assert_eq!(ServerState::Leader, cluster.engine(1).server_state());
+        assert_eq!(ServerState::Follower, cluster.engine(2).server_state());
+        assert_eq!(ServerState::Follower, cluster.engine(3).server_state());
+        assert_eq!(Some(1), cluster.engine(2).current_leader());
+    }
+
+    tracing::info!("--- write to the leader, the writes are applied on every node");
+    {
+        let mut last = None;
+        for _ in 0..3 {
+            last = Some(cluster.engine(1).client_write(())?);
+        }
+        cluster.run();
+
+        let leader_log_ids = cluster.nodes[&1].applied.clone();
+        assert_eq!(last.as_ref(), leader_log_ids.last());
+        assert_eq!(5, leader_log_ids.len(), "membership, blank, and 3 writes");
+
+        for id in [1, 2, 3] {
+            let node = &cluster.nodes[&id];
+            assert_eq!(leader_log_ids, node.applied, "applied on node {}", id);
+            assert_eq!(last.as_ref(), node.engine.committed());
+        }
+    }
+
+    tracing::info!("--- a follower rejects writes with the leader to forward to");
+    {
+        let res = cluster.engine(2).client_write(());
+        let Err(ClientWriteError::ForwardToLeader(fwd)) = res else {
+            panic!("expect ForwardToLeader, got: {:?}", res);
+        };
+        assert_eq!(Some(1), fwd.leader_id);
+    }
+
+    Ok(())
+}
+
+#[test]
+fn test_vote_reply_waits_for_vote_to_flush() -> anyhow::Result<()> {
+    let config = Config::default().validate()?;
+    let mut eng = RaftEngine::<C>::new(2, RaftState::default(), &config);
+
+    let request_id = eng.handle_vote_request(VoteRequest::new(Vote::new(1, 1), None));
+
+    let Some(Command::SaveVote { io, vote }) = eng.next_command() else {
+        panic!("expect SaveVote");
+    };
+    assert_eq!(Vote::new(1, 1), vote);
+    assert!(
+        eng.next_command().is_none(),
+        "the reply waits for the vote to be persisted"
+    );
+
+    eng.io_flushed(io);
+
+    let Some(Command::Reply { id, reply }) = eng.next_command() else {
+        panic!("expect Reply");
+    };
+    assert_eq!(request_id, id);
+    assert_eq!(Reply::Vote(VoteResponse::new(Vote::new(1, 1), None, true)), reply);
+    assert!(eng.next_command().is_none());
+
+    Ok(())
+}
+
+#[test]
+fn test_replicate_queued_before_membership_change_keeps_session() -> anyhow::Result<()> {
+    let mut cluster = Cluster::new([1, 2, 3]);
+    cluster.engine(1).initialize(Membership::new_with_defaults(vec![btreeset! {1,2,3}], []))?;
+    cluster.run();
+
+    let eng = cluster.engine(1);
+    let membership_log_id = *eng.state().membership_state.effective().log_id();
+
+    eng.client_write(())?;
+    let new_membership_log_id = eng.change_membership(ChangeMembers::RemoveVoters(btreeset! {3}), true)?;
+
+    let mut sessions = vec![];
+    while let Some(cmd) = eng.next_command() {
+        match cmd {
+            Command::AppendEntries { io, .. } => eng.io_flushed(io),
+            Command::Replicate { session, .. } => sessions.push(("replicate", session.0.membership_log_id)),
+            Command::RebuildReplication { session, .. } => sessions.push(("rebuild", session.0.membership_log_id)),
+            _ => {}
+        }
+    }
+
+    // The replication of the write is queued before the membership entry is appended: its reply
+    // must not be taken as progress of the rebuilt replication.
+    assert_eq!(
+        vec![
+            ("replicate", membership_log_id),
+            ("replicate", membership_log_id),
+            ("rebuild", Some(new_membership_log_id)),
+            ("replicate", Some(new_membership_log_id)),
+            ("replicate", Some(new_membership_log_id)),
+        ],
+        sessions
+    );
+
+    Ok(())
+}
@@ -0,0 +1,764 @@
+//! A model-based test harness for the Raft protocol implemented by [`RaftEngine`].
+//!
+//! A [`Trace`] is a sequence of [`Step`]s, each of which is an input to one node of a simulated
+//! cluster: an election, a timer tick, a client write, a membership change, the delivery, loss or
+//! duplication of a message, or the completion of a storage IO. [`Sim`] runs a trace and checks the
+//! safety properties of Raft after every step:
+//!
+//! - Election safety: at most one leader is elected in a term.
+//! - Log matching: if two logs contain an entry with the same log id, they are identical up to it.
+//! - Leader completeness: a leader contains every entry committed in its term or an earlier one.
+//! - State machine safety: no two nodes apply different entries at the same index.
+//!
+//! A trace is printed one step per line and can be parsed back, so that a failing trace can be
+//! saved and replayed.
+
+use std::collections::BTreeMap;
+use std::collections::VecDeque;
+use std::fmt;
+use std::panic::AssertUnwindSafe;
+use std::str::FromStr;
+use std::time::Duration;
+
+use futures::FutureExt;
+use maplit::btreemap;
+use maplit::btreeset;
+use rand::rngs::StdRng;
+use rand::Rng;
+use rand::SeedableRng;
+
+use crate::entry::RaftEntry;
+use crate::raft::AppendEntriesRequest;
+use crate::raft::AppendEntriesResponse;
+use crate::raft::VoteRequest;
+use crate::sans_io::Command;
+use crate::sans_io::IoId;
+use crate::sans_io::RaftEngine;
+use crate::sans_io::Replicate;
+use crate::sans_io::Reply;
+use crate::sans_io::RequestId;
+use crate::sans_io::Session;
+use crate::type_config::alias::EntryOf;
+use crate::type_config::alias::InstantOf;
+use crate::type_config::alias::LogIdOf;
+use crate::type_config::alias::VoteOf;
+use crate::ChangeMembers;
+use crate::Config;
+use crate::Membership;
+use crate::RaftState;
+use crate::ServerState;
+use crate::SnapshotPolicy;
+
+crate::declare_raft_types!(
+    /// Standard Raft: at most one leader can be elected in a term.
+    pub(crate) ModelConfig:
+        D = u64,
+        R = (),
+        Node = (),
+        LeaderId = crate::impls::leader_id_std::LeaderId<ModelConfig>,
+);
+
+type C = ModelConfig;
+
+/// Ids of the simulated nodes. Only the first three are voters initially.
+pub(crate) const NODES: [u64; 5] = [1, 2, 3, 4, 5];
+
+/// An input to the simulated cluster.
+///
+/// A step that refers to a message in flight by position wraps around the number of messages, and
+/// does nothing if there is none. A step that is not applicable, e.g., a write to a follower, is
+/// not an error: its effect is up to the engine.
+#[derive(Debug, Clone, Copy)]
+#[derive(PartialEq, Eq)]
+pub(crate) enum Step {
+    /// Start an election on a node at once.
+    Elect(u64),
+
+    /// Advance the clock by some milliseconds, then tick a node.
+    Tick { node: u64, ms: u64 },
+
+    /// Propose a value on a node.
+    Write { node: u64, value: u64 },
+
+    /// Propose on a node to add a voter.
+    AddVoter { node: u64, voter: u64 },
+
+    /// Propose on a node to remove a voter.
+    RemoveVoter { node: u64, voter: u64 },
+
+    /// Deliver the message at a position in the network.
+    Deliver(usize),
+
+    /// Lose the message at a position in the network.
+    Drop(usize),
+
+    /// Duplicate the message at a position in the network.
+    Duplicate(usize),
+
+    /// Complete the oldest pending storage IO of a node.
+    Flush(u64),
+}
+
+impl Step {
+    /// Generate a random step. The kinds of steps are weighted so that a cluster makes progress.
+    pub(crate) fn random(rng: &mut impl Rng) -> Self {
+        let node = NODES[rng.random_range(0..NODES.len())];
+        let msg = rng.random_range(0..64);
+
+        match rng.random_range(0..100) {
+            0..1 => Step::Elect(node),
+            1..11 => Step::Tick {
+                node,
+                ms: rng.random_range(0..50),
+            },
+            11..23 => Step::Write {
+                node,
+                value: rng.random_range(0..1000),
+            },
+            23..29 => Step::AddVoter {
+                node,
+                voter: rng.random_range(4..=5),
+            },
+            29..31 => Step::RemoveVoter {
+                node,
+                voter: NODES[rng.random_range(0..NODES.len())],
+            },
+            31..66 => Step::Deliver(msg),
+            66..69 => Step::Drop(msg),
+            69..72 => Step::Duplicate(msg),
+            _ => Step::Flush(node),
+        }
+    }
+}
+
+impl fmt::Display for Step {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            Step::Elect(node) => write!(f, "elect {}", node),
+            Step::Tick { node, ms } => write!(f, "tick {} {}", node, ms),
+            Step::Write { node, value } => write!(f, "write {} {}", node, value),
+            Step::AddVoter { node, voter } => write!(f, "add-voter {} {}", node, voter),
+            Step::RemoveVoter { node, voter } => write!(f, "remove-voter {} {}", node, voter),
+            Step::Deliver(i) => write!(f, "deliver {}", i),
+            Step::Drop(i) => write!(f, "drop {}", i),
+            Step::Duplicate(i) => write!(f, "dup {}", i),
+            Step::Flush(node) => write!(f, "flush {}", node),
+        }
+    }
+}
+
+impl FromStr for Step {
+    type Err = String;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        let words = s.split_whitespace().collect::<Vec<_>>();
+
+        let arg = |i: usize| -> Result<u64, String> {
+            let w = words.get(i).ok_or_else(|| format!("missing argument {} in: {:?}", i, s))?;
+            w.parse::<u64>().map_err(|e| format!("invalid argument {:?} in: {:?}: {}", w, s, e))
+        };
+
+        let step = match words.first().copied() {
+            Some("elect") => Step::Elect(arg(1)?),
+            Some("tick") => Step::Tick {
+                node: arg(1)?,
+                ms: arg(2)?,
+            },
+            Some("write") => Step::Write {
+                node: arg(1)?,
+                value: arg(2)?,
+            },
+            Some("add-voter") => Step::AddVoter {
+                node: arg(1)?,
+                voter: arg(2)?,
+            },
+            Some("remove-voter") => Step::RemoveVoter {
+                node: arg(1)?,
+                voter: arg(2)?,
+            },
+            Some("deliver") => Step::Deliver(arg(1)? as usize),
+            Some("drop") => Step::Drop(arg(1)? as usize),
+            Some("dup") => Step::Duplicate(arg(1)? as usize),
+            Some("flush") => Step::Flush(arg(1)?),
+            _ => return Err(format!("unknown step: {:?}", s)),
+        };
+
+        Ok(step)
+    }
+}
+
+/// A sequence of steps, printed one step per line.
+///
+/// Empty lines and lines starting with `#` are ignored when parsing.
+#[derive(Debug, Clone, Default)]
+#[derive(PartialEq, Eq)]
+pub(crate) struct Trace {
+    pub(crate) steps: Vec<Step>,
+}
+
+impl Trace {
+    /// Generate a trace of `n` random steps from `seed`.
+    pub(crate) fn random(seed: u64, n: usize) -> Self {
+        let mut rng = StdRng::seed_from_u64(seed);
+        let steps = (0..n).map(|_| Step::random(&mut rng)).collect();
+        Self { steps }
+    }
+
+    /// Find a shorter trace that still fails, by removing one step at a time.
+    pub(crate) async fn shrink(&self) -> Trace {
+        let mut trace = self.clone();
+
+        for _ in 0..3 {
+            let len = trace.steps.len();
+
+            for i in (0..trace.steps.len()).rev() {
+                let mut t = trace.clone();
+                t.steps.remove(i);
+
+                if Sim::run(&t).await.is_err() {
+                    trace = t;
+                }
+            }
+
+            if trace.steps.len() == len {
+                break;
+            }
+        }
+
+        trace
+    }
+}
+
+impl fmt::Display for Trace {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        for step in self.steps.iter() {
+            writeln!(f, "{}", step)?;
+        }
+        Ok(())
+    }
+}
+
+impl FromStr for Trace {
+    type Err = String;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        let steps = s
+            .lines()
+            .map(|l| l.trim())
+            .filter(|l| !l.is_empty() && !l.starts_with('#'))
+            .map(Step::from_str)
+            .collect::<Result<Vec<_>, _>>()?;
+
+        Ok(Self { steps })
+    }
+}
+
+/// A safety property that does not hold after a step.
+#[derive(Debug, Clone)]
+#[derive(PartialEq, Eq)]
+pub(crate) struct Violation {
+    /// The position of the step in the trace.
+    pub(crate) step: usize,
+    pub(crate) message: String,
+}
+
+impl fmt::Display for Violation {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "after step {}: {}", self.step, self.message)
+    }
+}
+
+/// What happened in a trace that passed, to tell whether it covered anything.
+#[derive(Debug, Clone, Default)]
+pub(crate) struct Stats {
+    /// Number of terms in which a leader is elected.
+    pub(crate) leaders: usize,
+
+    /// Number of entries known to be committed.
+    pub(crate) committed: usize,
+}
+
+/// What a leader or candidate sent, to interpret the reply with.
+#[derive(Clone)]
+enum Sent {
+    Vote(VoteOf<C>),
+    Logs {
+        /// Identifies the request, so that only the first reply of a duplicated request is
+        /// reported.
+        rpc: u64,
+        session: Session<C>,
+        prev_log_id: Option<LogIdOf<C>>,
+        last_log_id: Option<LogIdOf<C>>,
+    },
+    Committed(Session<C>),
+    Heartbeat(Session<C>, InstantOf<C>),
+}
+
+enum Message {
+    Vote(VoteRequest<C>, Sent),
+    AppendEntries(AppendEntriesRequest<C>, Sent),
+    Reply(Reply<C>, Sent),
+}
+
+impl Message {
+    fn duplicate(&self) -> Self {
+        match self {
+            Message::Vote(req, sent) => Message::Vote(req.clone(), sent.clone()),
+            Message::AppendEntries(req, sent) => Message::AppendEntries(req.clone(), sent.clone()),
+            Message::Reply(reply, sent) => {
+                let reply = match reply {
+                    Reply::Vote(r) => Reply::Vote(r.clone()),
+                    Reply::AppendEntries(r) => Reply::AppendEntries(match r {
+                        AppendEntriesResponse::Success => AppendEntriesResponse::Success,
+                        AppendEntriesResponse::PartialSuccess(x) => AppendEntriesResponse::PartialSuccess(*x),
+                        AppendEntriesResponse::Conflict => AppendEntriesResponse::Conflict,
+                        AppendEntriesResponse::HigherVote(v) => AppendEntriesResponse::HigherVote(*v),
+                    }),
+                    Reply::InstallSnapshot(_) => unreachable!("no snapshot is sent"),
+                };
+                Message::Reply(reply, sent.clone())
+            }
+        }
+    }
+}
+
+/// A storage IO that is submitted but not yet reported as completed.
+enum Pending {
+    Io(IoId<C>),
+    Applied(LogIdOf<C>),
+}
+
+/// A simulated node, with an in-memory log store and state machine.
+///
+/// Storage commands take effect at once, but their completion is reported only by a
+/// [`Step::Flush`].
+struct Node {
+    engine: RaftEngine<C>,
+    log: BTreeMap<u64, EntryOf<C>>,
+    pending: VecDeque<Pending>,
+
+    /// Log ids of the applied entries, in the order they are applied.
+    applied: Vec<LogIdOf<C>>,
+
+    /// Where to send the reply of an incoming request: the requester and what it sent.
+    replies_to: BTreeMap<RequestId, (u64, Sent)>,
+}
+
+/// A cluster of simulated nodes connected by a network that may reorder, lose or duplicate
+/// messages.
+pub(crate) struct Sim {
+    nodes: BTreeMap<u64, Node>,
+    network: Vec<(u64, u64, Message)>,
+
+    next_rpc: u64,
+
+    /// Replication requests that are replied to or failed. A reply to them is ignored.
+    rpc_done: std::collections::BTreeSet<u64>,
+
+    /// The leader elected in each term.
+    leaders: BTreeMap<u64, u64>,
+
+    /// The committed entries by index: the log id and the term at which it is known committed.
+    committed: BTreeMap<u64, (LogIdOf<C>, u64)>,
+}
+
+impl Sim {
+    /// Run a trace and check safety after every step.
+    ///
+    /// A panic in the engine is reported as a violation, too.
+    pub(crate) async fn run(trace: &Trace) -> Result<Stats, Violation> {
+        let mut sim = Sim::new();
+
+        for (i, step) in trace.steps.iter().enumerate() {
+            let res = AssertUnwindSafe(sim.step(step)).catch_unwind().await;
+
+            let res = match res {
+                Ok(res) => res.and_then(|_| sim.check()),
+                Err(panic) => {
+                    let message = panic
+                        .downcast_ref::<String>()
+                        .cloned()
+                        .or_else(|| panic.downcast_ref::<&str>().map(|s| s.to_string()))
+                        .unwrap_or_default();
+                    Err(format!("panic: {}", message))
+                }
+            };
+
+            res.map_err(|message| Violation { step: i, message })?;
+        }
+
+        Ok(Stats {
+            leaders: sim.leaders.len(),
+            committed: sim.committed.len(),
+        })
+    }
+
+    fn new() -> Self {
+        let config = Config {
+            heartbeat_interval: 50,
+            // A single-value range keeps elections deterministic.
+            election_timeout_min: 150,
+            election_timeout_max: 151,
+            snapshot_policy: SnapshotPolicy::Never,
+            ..Default::default()
+        };
+        let config = config.validate().unwrap();
+
+        let nodes = NODES
+            .iter()
+            .map(|id| {
+                let node = Node {
+                    engine: RaftEngine::new(*id, RaftState::default(), &config),
+                    log: BTreeMap::new(),
+                    pending: VecDeque::new(),
+                    applied: vec![],
+                    replies_to: BTreeMap::new(),
+                };
+                (*id, node)
+            })
+            .collect();
+
+        let mut sim = Self {
+            nodes,
+            network: vec![],
+            next_rpc: 0,
+            rpc_done: Default::default(),
+            leaders: BTreeMap::new(),
+            committed: BTreeMap::new(),
+        };
+
+        let membership = Membership::new_with_defaults(vec![btreeset! {1,2,3}], []);
+        sim.node(1).engine.initialize(membership).unwrap();
+        sim.run_commands().unwrap();
+
+        sim
+    }
+
+    fn node(&mut self, id: u64) -> &mut Node {
+        self.nodes.get_mut(&id).unwrap()
+    }
+
+    async fn step(&mut self, step: &Step) -> Result<(), String> {
+        match *step {
+            Step::Elect(id) => {
+                let node = self.node(id);
+                if node.engine.state().membership_state.effective().is_voter(&id) {
+                    node.engine.elect();
+                }
+            }
+            Step::Tick { node, ms } => {
+                tokio::time::advance(Duration::from_millis(ms)).await;
+                self.node(node).engine.tick();
+            }
+            Step::Write { node, value } => {
+                let _ = self.node(node).engine.client_write(value);
+            }
+            Step::AddVoter { node, voter } => {
+                let changes = ChangeMembers::AddVoters(btreemap! {voter => ()});
+                let _ = self.node(node).engine.change_membership(changes, true);
+            }
+            Step::RemoveVoter { node, voter } => {
+                let changes = ChangeMembers::RemoveVoters(btreeset! {voter});
+                let _ = self.node(node).engine.change_membership(changes, true);
+            }
+            Step::Deliver(i) => {
+                if !self.network.is_empty() {
+                    let (from, to, msg) = self.network.remove(i % self.network.len());
+                    self.deliver(from, to, msg);
+                }
+            }
+            Step::Drop(i) => {
+                if !self.network.is_empty() {
+                    let (from, to, msg) = self.network.remove(i % self.network.len());
+                    self.lose(from, to, msg);
+                }
+            }
+            Step::Duplicate(i) => {
+                if !self.network.is_empty() {
+                    let (from, to, msg) = &self.network[i % self.network.len()];
+                    let dup = (*from, *to, msg.duplicate());
+                    self.network.push(dup);
+                }
+            }
+            Step::Flush(id) => {
+                let node = self.node(id);
+                match node.pending.pop_front() {
+                    Some(Pending::Io(io)) => node.engine.io_flushed(io),
+                    Some(Pending::Applied(last)) => node.engine.applied(last),
+                    None => {}
+                }
+            }
+        }
+
+        self.run_commands()
+    }
+
+    /// Execute the commands of every node.
+    ///
+    /// No command completes at once, so no more command is emitted after one pass.
+    fn run_commands(&mut self) -> Result<(), String> {
+        for id in NODES {
+            self.run_node_commands(id)?;
+        }
+        Ok(())
+    }
+
+    fn run_node_commands(&mut self, id: u64) -> Result<(), String> {
+        let node = self.nodes.get_mut(&id).unwrap();
+
+        while let Some(cmd) = node.engine.next_command() {
+            match cmd {
+                Command::AppendEntries { io, entries } => {
+                    for ent in entries {
+                        node.log.insert(ent.index(), ent);
+                    }
+                    node.pending.push_back(Pending::Io(io));
+                }
+                Command::SaveVote { io, .. } => node.pending.push_back(Pending::Io(io)),
+                Command::SaveCommitted { .. } => {}
+                Command::TruncateLog { since } => {
+                    node.log.split_off(&since.index);
+                }
+                Command::PurgeLog { .. } => return Err(format!("node {} purges logs without a snapshot", id)),
+                Command::Apply { first, last } => {
+                    let term = node.engine.vote().leader_id().term;
+
+                    for index in first.index..=last.index {
+                        let Some(ent) = node.log.get(&index) else {
+                            return Err(format!("node {} applies {}, which is not in its log", id, index));
+                        };
+                        let log_id = ent.log_id();
+
+                        let expected_index = node.applied.last().map_or(0, |x| x.index + 1);
+                        if index != expected_index {
+                            return Err(format!(
+                                "node {} applies {} after {:?}",
+                                id,
+                                log_id,
+                                node.applied.last()
+                            ));
+                        }
+                        node.applied.push(log_id);
+
+                        let (committed, _) = self.committed.entry(index).or_insert((log_id, term));
+                        if *committed != log_id {
+                            return Err(format!(
+                                "state machine safety: node {} applies {}, but {} is applied at the same index",
+                                id, log_id, committed
+                            ));
+                        }
+                    }
+
+                    if node.applied.last() != Some(&last) {
+                        return Err(format!(
+                            "node {} is asked to apply up to {}, which is not in its log",
+                            id, last
+                        ));
+                    }
+                    node.pending.push_back(Pending::Applied(last));
+                }
+                Command::BuildSnapshot | Command::InstallSnapshot { .. } => {
+                    return Err(format!("node {} handles a snapshot, which is disabled", id));
+                }
+                Command::SendVote { req } => {
+                    let voters = node.engine.state().membership_state.effective().voter_ids().collect::<Vec<_>>();
+                    for target in voters.into_iter().filter(|x| *x != id) {
+                        let msg = Message::Vote(req.clone(), Sent::Vote(req.vote));
+                        self.network.push((id, target, msg));
+                    }
+                }
+                Command::Replicate { session, target, req } => {
+                    let committed = node.engine.committed().cloned();
+
+                    let (prev_log_id, entries, sent) = match req {
+                        Replicate::Committed { prev_log_id, .. } => {
+                            (prev_log_id, vec![], Sent::Committed(session.clone()))
+                        }
+                        Replicate::Logs {
+                            prev_log_id,
+                            last_log_id,
+                        } => {
+                            let start = prev_log_id.as_ref().map_or(0, |x| x.index + 1);
+                            let end = last_log_id.as_ref().map_or(0, |x| x.index + 1);
+                            let entries = node.log.range(start..end).map(|(_, e)| e.clone()).collect();
+
+                            self.next_rpc += 1;
+                            let sent = Sent::Logs {
+                                rpc: self.next_rpc,
+                                session: session.clone(),
+                                prev_log_id,
+                                last_log_id,
+                            };
+                            (prev_log_id, entries, sent)
+                        }
+                        Replicate::Snapshot => {
+                            return Err(format!("node {} replicates a snapshot, but no log is purged", id));
+                        }
+                    };
+
+                    let req = AppendEntriesRequest {
+                        vote: session.vote(),
+                        prev_log_id,
+                        leader_commit: committed,
+                        entries,
+                    };
+                    self.network.push((id, target, Message::AppendEntries(req, sent)));
+                }
+                Command::Heartbeat {
+                    session,
+                    target,
+                    committed,
+                    sent_at,
+                } => {
+                    let req = AppendEntriesRequest {
+                        vote: session.vote(),
+                        prev_log_id: None,
+                        leader_commit: committed,
+                        entries: vec![],
+                    };
+                    let sent = Sent::Heartbeat(session, sent_at);
+                    self.network.push((id, target, Message::AppendEntries(req, sent)));
+                }
+                Command::RebuildReplication { .. } => {}
+                Command::BroadcastTransferLeader { .. } => {}
+                Command::Reply { id: request_id, reply } => {
+                    let (requester, sent) = node.replies_to.remove(&request_id).unwrap();
+                    self.network.push((id, requester, Message::Reply(reply, sent)));
+                }
+            }
+        }
+
+        Ok(())
+    }
+
+    fn deliver(&mut self, from: u64, to: u64, msg: Message) {
+        if let Message::Reply(_, Sent::Logs { rpc, .. }) = &msg {
+            // The reply to a duplicated request
+            if !self.rpc_done.insert(*rpc) {
+                return;
+            }
+        }
+
+        let node = self.node(to);
+
+        match msg {
+            Message::Vote(req, sent) => {
+                let request_id = node.engine.handle_vote_request(req);
+                node.replies_to.insert(request_id, (from, sent));
+            }
+            Message::AppendEntries(req, sent) => {
+                let request_id = node.engine.handle_append_entries(req);
+                node.replies_to.insert(request_id, (from, sent));
+            }
+            Message::Reply(Reply::Vote(resp), Sent::Vote(vote)) => {
+                node.engine.handle_vote_response(from, &vote, resp);
+            }
+            Message::Reply(Reply::AppendEntries(resp), sent) => match sent {
+                Sent::Logs {
+                    session,
+                    prev_log_id,
+                    last_log_id,
+                    ..
+                } => node.engine.handle_append_entries_response(&session, from, prev_log_id, last_log_id, resp),
+                Sent::Committed(session) => {
+                    if let AppendEntriesResponse::HigherVote(vote) = resp {
+                        node.engine.handle_higher_vote(&session, vote);
+                    }
+                }
+                Sent::Heartbeat(session, sent_at) => {
+                    node.engine.handle_heartbeat_response(&session, from, sent_at, resp);
+                }
+                Sent::Vote(_) => unreachable!("a vote request is replied with a vote response"),
+            },
+            Message::Reply(_, _) => unreachable!("no snapshot is sent"),
+        }
+    }
+
+    /// Lose a message. If it is a replication request or its reply, the leader is told the
+    /// replication failed, as a network error does.
+    fn lose(&mut self, from: u64, to: u64, msg: Message) {
+        let (leader, target, sent) = match msg {
+            Message::AppendEntries(_, sent) => (from, to, sent),
+            Message::Reply(_, sent) => (to, from, sent),
+            Message::Vote(_, _) => return,
+        };
+
+        if let Sent::Logs { rpc, session, .. } = sent {
+            if self.rpc_done.insert(rpc) {
+                self.node(leader).engine.replication_failed(&session, target, "message lost");
+            }
+        }
+    }
+
+    /// Check the safety properties.
+    fn check(&mut self) -> Result<(), String> {
+        for (id, node) in self.nodes.iter() {
+            if node.engine.server_state() != ServerState::Leader {
+                continue;
+            }
+
+            let term = node.engine.vote().leader_id().term;
+            let leader = self.leaders.entry(term).or_insert(*id);
+            if leader != id {
+                return Err(format!(
+                    "election safety: node {} and node {} are both leader in term {}",
+                    leader, id, term
+                ));
+            }
+        }
+
+        let logs = self.nodes.iter().map(|(id, n)| (*id, &n.log)).collect::<Vec<_>>();
+        check_log_matching(&logs)?;
+
+        for (id, node) in self.nodes.iter() {
+            if node.engine.server_state() != ServerState::Leader {
+                continue;
+            }
+
+            let term = node.engine.vote().leader_id().term;
+            for (index, (log_id, committed_at)) in self.committed.iter() {
+                if *committed_at > term {
+                    continue;
+                }
+                // The log the leader replicates, including entries not yet submitted to storage.
+                let got = node.engine.state().log_ids.get(*index);
+                if got.as_ref() != Some(log_id) {
+                    return Err(format!(
+                        "leader completeness: leader {} of term {} has {:?} at {}, but {} is committed",
+                        id, term, got, index, log_id
+                    ));
+                }
+            }
+        }
+
+        Ok(())
+    }
+}
+
+/// Check that if two logs contain an entry with the same log id, all the preceding entries are
+/// identical.
+pub(crate) fn check_log_matching(logs: &[(u64, &BTreeMap<u64, EntryOf<C>>)]) -> Result<(), String> {
+    for (i, (a_id, a)) in logs.iter().enumerate() {
+        for (b_id, b) in logs.iter().skip(i + 1) {
+            let mut matched: Option<LogIdOf<C>> = None;
+
+            for (index, a_ent) in a.iter().rev() {
+                let a_log_id = a_ent.log_id();
+                let b_log_id = b.get(index).map(|e| e.log_id());
+
+                if let Some(m) = &matched {
+                    if b_log_id.as_ref() != Some(&a_log_id) {
+                        return Err(format!(
+                            "log matching: node {} and node {} both have {}, but differ at {}: {} vs {:?}",
+                            a_id, b_id, m, index, a_log_id, b_log_id
+                        ));
+                    }
+                } else if b_log_id.as_ref() == Some(&a_log_id) {
+                    matched = Some(a_log_id);
+                }
+            }
+        }
+    }
+
+    Ok(())
+}
@@ -0,0 +1,201 @@
+use std::collections::BTreeMap;
+use std::path::PathBuf;
+use std::str::FromStr;
+
+use pretty_assertions::assert_eq;
+
+use crate::entry::RaftEntry;
+use crate::sans_io::tests::model::check_log_matching;
+use crate::sans_io::tests::model::ModelConfig;
+use crate::sans_io::tests::model::Sim;
+use crate::sans_io::tests::model::Step;
+use crate::sans_io::tests::model::Trace;
+use crate::type_config::alias::EntryOf;
+use crate::type_config::alias::LeaderIdOf;
+use crate::type_config::alias::LogIdOf;
+use crate::vote::RaftLeaderIdExt;
+
+type C = ModelConfig;
+
+/// Number of random traces to run. Override with env `OPENRAFT_MODEL_SEEDS`.
+const SEEDS: u64 = 64;
+
+/// Number of steps in a random trace.
+const STEPS: usize = 400;
+
+/// Run random traces from a range of seeds and check safety after every step.
+///
+/// A failing trace is shrunk and saved to `_log/engine-model-<seed>.trace`. To replay it:
+///
+/// ```text
+/// OPENRAFT_MODEL_TRACE=_log/engine-model-<seed>.trace cargo test --features sans-io test_model_random
+/// ```
+#[tokio::test(start_paused = true)]
+async fn test_model_random() -> anyhow::Result<()> {
+    if let Ok(path) = std::env::var("OPENRAFT_MODEL_TRACE") {
+        let trace = Trace::from_str(&std::fs::read_to_string(&path)?).map_err(anyhow::Error::msg)?;
+        if let Err(v) = Sim::run(&trace).await {
+            panic!("trace {} fails {}", path, v);
+        }
+        return Ok(());
+    }
+
+    let seeds = match std::env::var("OPENRAFT_MODEL_SEEDS") {
+        Ok(n) => n.parse::<u64>()?,
+        Err(_) => SEEDS,
+    };
+
+    let mut leaders = 0;
+    let mut committed = 0;
+
+    for seed in 0..seeds {
+        let trace = Trace::random(seed, STEPS);
+
+        match Sim::run(&trace).await {
+            Ok(stats) => {
+                leaders += stats.leaders;
+                committed += stats.committed;
+            }
+            Err(_) => {
+                let trace = trace.shrink().await;
+                let violation = Sim::run(&trace).await.unwrap_err();
+
+                let text = format!("# seed: {}\n# {}\n{}", seed, violation, trace);
+                let path = save_trace(seed, &text)?;
+
+                panic!(
+                    "seed {} fails {}; trace saved to {}:\n{}",
+                    seed,
+                    violation,
+                    path.display(),
+                    text
+                );
+            }
+        }
+    }
+
+    assert!(
+        leaders > seeds as usize,
+        "elected {} leaders in {} traces",
+        leaders,
+        seeds
+    );
+    assert!(
+        committed > seeds as usize,
+        "committed {} entries in {} traces",
+        committed,
+        seeds
+    );
+
+    Ok(())
+}
+
+fn save_trace(seed: u64, text: &str) -> anyhow::Result<PathBuf> {
+    let dir = PathBuf::from("_log");
+    std::fs::create_dir_all(&dir)?;
+
+    let path = dir.join(format!("engine-model-{}.trace", seed));
+    std::fs::write(&path, text)?;
+    Ok(path)
+}
+
+/// Replay a hand-written trace: node 1 is elected and commits a write with node 2, then node 2
+/// times out, is elected by node 3 and commits the write in term 2, and node 1 steps down.
+#[tokio::test(start_paused = true)]
+async fn test_model_replay_trace() -> anyhow::Result<()> {
+    let trace = r#"
+        # node 1 is elected by node 2; the vote request to node 3 is lost
+        flush 1
+        flush 1
+        deliver 0
+        flush 2
+        deliver 1
+        drop 0
+
+        # a write is replicated to node 2 and committed by node 1
+        write 1 7
+        flush 1
+        flush 1
+        deliver 0
+        flush 2
+        flush 1
+        flush 2
+        deliver 1
+        flush 2
+        deliver 1
+        flush 2
+        deliver 2
+        flush 2
+        deliver 3
+
+        # node 2 no longer hears from node 1 and is elected by node 3 in term 2
+        tick 2 400
+        flush 2
+        deliver 6
+        flush 3
+        deliver 6
+
+        # node 2 replicates to node 3 and commits its blank entry, with the write before it
+        deliver 7
+        flush 2
+        flush 2
+        flush 3
+        flush 3
+        deliver 7
+
+        # node 1 learns the higher vote from node 2's reply and steps down
+        deliver 3
+        flush 2
+        deliver 8
+    "#;
+    let trace = Trace::from_str(trace).map_err(anyhow::Error::msg)?;
+
+    let stats = Sim::run(&trace).await.map_err(|v| anyhow::anyhow!("{}", v))?;
+    assert_eq!(2, stats.leaders, "node 1 in term 1 and node 2 in term 2");
+    assert_eq!(4, stats.committed, "membership, blank, the write, blank");
+
+    Ok(())
+}
+
+#[test]
+fn test_trace_display_and_parse() -> anyhow::Result<()> {
+    let trace = Trace::random(3, 200);
+
+    let text = trace.to_string();
+    assert_eq!(trace, Trace::from_str(&text).map_err(anyhow::Error::msg)?);
+
+    assert_eq!(Ok(Step::Tick { node: 2, ms: 120 }), Step::from_str("  tick 2   120"));
+    assert_eq!(
+        Ok(Step::AddVoter { node: 1, voter: 4 }),
+        Step::from_str("add-voter 1 4")
+    );
+    assert!(Step::from_str("tick 2").is_err());
+    assert!(Step::from_str("foo 1").is_err());
+
+    Ok(())
+}
+
+#[test]
+fn test_check_log_matching() -> anyhow::Result<()> {
+    fn log(log_ids: &[(u64, u64)]) -> BTreeMap<u64, EntryOf<C>> {
+        log_ids
+            .iter()
+            .enumerate()
+            .map(|(index, (term, node))| {
+                let log_id = LogIdOf::<C>::new(LeaderIdOf::<C>::new_committed(*term, *node), index as u64);
+                (index as u64, EntryOf::<C>::new_blank(log_id))
+            })
+            .collect()
+    }
+
+    let a = log(&[(1, 1), (1, 1), (2, 2)]);
+    let b = log(&[(1, 1), (1, 1), (3, 3), (3, 3)]);
+    let c = log(&[(1, 1)]);
+    assert_eq!(Ok(()), check_log_matching(&[(1, &a), (2, &b), (3, &c)]));
+
+    let d = log(&[(1, 1), (2, 2), (2, 2)]);
+    let res = check_log_matching(&[(1, &a), (4, &d)]);
+    assert!(res.is_err(), "same log id at index 2 but differ at index 1: {:?}", res);
+
+    Ok(())
+}
@@ -0,0 +1,40 @@
+//! Exactly-once client sessions on top of an application state machine.
+//!
+//! If a Leader crashes after committing a client request but before responding, the client retries
+//! the request with the new Leader and the request is appended to the log a second time. To apply
+//! every request at most once, each client numbers its requests, and the state machine remembers
+//! the last sequence number applied for each client together with its response (§6.3).
+//!
+//! [`SessionStateMachine`] implements this as a wrapper of an application [`RaftStateMachine`]:
+//!
+//! - The application data `C::D` carries a client id and a sequence number, by implementing
+//!   [`SessionData`], e.g., by wrapping the application request in a [`SessionRequest`].
+//! - An entry whose sequence number equals the last one applied for the client is not applied
+//!   again. The response cached for the client is returned instead.
+//! - An entry whose sequence number is less than the last one applied is stale and is not applied
+//!   either. It is responded with [`SessionResponse::stale()`], which `C::R` implements.
+//! - A session is expired when no request of the client is seen in a number of log entries. The log
+//!   index is used instead of a wall clock time, which a client may skew, so that every replica
+//!   expires the same sessions at the same log position.
+//! - The session table is stored in the snapshots built by the wrapper, and is restored when a
+//!   snapshot is installed. It is otherwise kept only in memory, thus the wrapped state machine
+//!   must not be persistent either, see [`SessionStateMachine`].
+//!
+//! This module is enabled by feature flag `session`.
+//!
+//! [`RaftStateMachine`]: crate::storage::RaftStateMachine
+
+mod session_request;
+mod session_response;
+mod session_state_machine;
+mod session_table;
+
+#[cfg(test)]
+mod session_state_machine_test;
+
+pub use session_request::SessionData;
+pub use session_request::SessionInfo;
+pub use session_request::SessionRequest;
+pub use session_response::SessionResponse;
+pub use session_state_machine::SessionSnapshotBuilder;
+pub use session_state_machine::SessionStateMachine;
@@ -0,0 +1,65 @@
+use std::fmt;
+
+/// Identifies a client request in a session.
+#[derive(Debug, Clone, Copy)]
+#[derive(PartialEq, Eq)]
+pub struct SessionInfo {
+    /// The id of the client that proposed the request.
+    pub client_id: u64,
+
+    /// The sequence number of the request, increasing by the client for every new request.
+    ///
+    /// A retry of a request uses the same sequence number.
+    pub sequence: u64,
+}
+
+/// Application data that can be deduplicated by a [`SessionStateMachine`].
+///
+/// [`SessionStateMachine`]: crate::session::SessionStateMachine
+pub trait SessionData {
+    /// Returns the session info of this request.
+    ///
+    /// A request that returns `None` is applied without deduplication.
+    fn session_info(&self) -> Option<SessionInfo>;
+}
+
+/// Application data `D` with the session info of the client that proposed it.
+///
+/// It can be used as `C::D` of a [`SessionStateMachine`].
+///
+/// [`SessionStateMachine`]: crate::session::SessionStateMachine
+#[derive(Debug, Clone)]
+#[derive(PartialEq, Eq)]
+#[derive(serde::Deserialize, serde::Serialize)]
+pub struct SessionRequest<D> {
+    pub client_id: u64,
+    pub sequence: u64,
+    pub data: D,
+}
+
+impl<D> SessionRequest<D> {
+    pub fn new(client_id: u64, sequence: u64, data: D) -> Self {
+        Self {
+            client_id,
+            sequence,
+            data,
+        }
+    }
+}
+
+impl<D> SessionData for SessionRequest<D> {
+    fn session_info(&self) -> Option<SessionInfo> {
+        Some(SessionInfo {
+            client_id: self.client_id,
+            sequence: self.sequence,
+        })
+    }
+}
+
+impl<D> fmt::Display for SessionRequest<D>
+where D: fmt::Display
+{
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "{}@{}:{}", self.client_id, self.sequence, self.data)
+    }
+}
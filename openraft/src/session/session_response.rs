@@ -0,0 +1,14 @@
+use crate::session::SessionInfo;
+
+/// Application response that a [`SessionStateMachine`] can return for a stale request.
+///
+/// [`SessionStateMachine`]: crate::session::SessionStateMachine
+pub trait SessionResponse {
+    /// Build the response to a request older than the last applied request of the same client.
+    ///
+    /// Only the response of the last applied request is cached, thus a stale request, such as a
+    /// delayed retry of a request the client has already seen responded, can not be answered with
+    /// its own response. `last_sequence` is the sequence number of the last applied request of the
+    /// client.
+    fn stale(info: &SessionInfo, last_sequence: u64) -> Self;
+}
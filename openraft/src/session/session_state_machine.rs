@@ -0,0 +1,362 @@
+use std::collections::BTreeSet;
+use std::io::Cursor;
+use std::sync::Arc;
+use std::sync::Mutex;
+
+use anyerror::AnyError;
+
+use crate::entry::RaftEntry;
+use crate::session::session_table::Applied;
+use crate::session::session_table::SessionTable;
+use crate::session::SessionData;
+use crate::session::SessionInfo;
+use crate::session::SessionResponse;
+use crate::storage::RaftStateMachine;
+use crate::storage::Snapshot;
+use crate::storage::SnapshotMeta;
+use crate::type_config::alias::LogIdOf;
+use crate::Entry;
+use crate::EntryPayload;
+use crate::RaftSnapshotBuilder;
+use crate::RaftTypeConfig;
+use crate::StorageError;
+use crate::StoredMembership;
+
+/// A [`RaftStateMachine`] wrapper that applies every client request at most once.
+///
+/// An entry whose [`SessionInfo::sequence`] equals the last one applied for the same client is a
+/// retry: it is passed to the inner state machine as a blank entry, so that the last applied log id
+/// still advances, and the response of the last applied request of the client is returned. A client
+/// must therefore not send a new request before the previous one is responded. An entry with a
+/// smaller sequence number is stale: it is not applied either, and is responded with
+/// [`SessionResponse::stale()`].
+///
+/// A session expires if no request of the client is seen in the next `session_ttl` log entries. A
+/// retry after its session expired is applied again, thus the TTL must cover the entries the
+/// cluster may append while a client keeps retrying a request.
+///
+/// A snapshot of this state machine is the session table followed by the snapshot data of the inner
+/// state machine. The snapshot builder of the inner state machine must build a snapshot of the
+/// state when [`RaftStateMachine::get_snapshot_builder()`] is called, otherwise building a snapshot
+/// fails.
+///
+/// # Persistence
+///
+/// **The session table is kept only in memory and is not persisted.** The inner state machine must
+/// therefore be volatile as well: it has to start empty after a restart, and be rebuilt by
+/// re-applying logs or by installing a snapshot, as the session table is. A persistent inner state
+/// machine would come back with applied requests whose sessions are lost, and retries of them
+/// would be applied a second time. To prevent this,
+/// [`applied_state()`](RaftStateMachine::applied_state) returns an error if the inner state
+/// machine has applied logs the session table does not cover.
+///
+/// Entries are applied with [`RaftStateMachine::apply()`] of the inner state machine in batches.
+/// A batch is split before a second request of the same client.
+pub struct SessionStateMachine<C, SM>
+where
+    C: RaftTypeConfig,
+    SM: RaftStateMachine<C>,
+{
+    inner: SM,
+
+    /// The number of log entries after which a session without requests expires.
+    session_ttl: u64,
+
+    sessions: SessionTable<C::R>,
+
+    /// The last log id applied to the inner state machine, by this wrapper.
+    last_applied: Option<LogIdOf<C>>,
+
+    /// The last built or installed snapshot, including the session table.
+    current_snapshot: Arc<Mutex<Option<(SnapshotMeta<C>, Vec<u8>)>>>,
+}
+
+/// What to respond for an entry in a batch to apply.
+enum Slot<R> {
+    /// Respond with the response of the inner state machine.
+    Apply,
+
+    /// Respond with the response of the inner state machine, and record it in the session at the
+    /// log index.
+    Record(SessionInfo, u64),
+
+    /// A retry of an applied request or a stale request, respond with the given response.
+    Respond(R),
+}
+
+impl<C, SM> SessionStateMachine<C, SM>
+where
+    C: RaftTypeConfig,
+    SM: RaftStateMachine<C>,
+{
+    /// Create a session state machine wrapping an empty inner state machine.
+    ///
+    /// A session expires if no request of the client is seen in the next `session_ttl` log
+    /// entries.
+    pub fn new(inner: SM, session_ttl: u64) -> Self {
+        Self {
+            inner,
+            session_ttl,
+            sessions: SessionTable::default(),
+            last_applied: None,
+            current_snapshot: Arc::new(Mutex::new(None)),
+        }
+    }
+
+    /// Returns a reference to the inner state machine.
+    pub fn inner(&self) -> &SM {
+        &self.inner
+    }
+
+    /// Returns the number of sessions that are not removed yet.
+    pub fn session_count(&self) -> usize {
+        self.sessions.sessions.len()
+    }
+}
+
+impl<C, SM> SessionStateMachine<C, SM>
+where
+    C: RaftTypeConfig<Entry = Entry<C>, SnapshotData = Cursor<Vec<u8>>>,
+    C::D: SessionData,
+    C::R: SessionResponse + Clone,
+    SM: RaftStateMachine<C>,
+{
+    /// Apply a batch to the inner state machine and push the responses to `results`.
+    async fn apply_batch(
+        &mut self,
+        batch: &mut Vec<Entry<C>>,
+        slots: &mut Vec<Slot<C::R>>,
+        clients: &mut BTreeSet<u64>,
+        results: &mut Vec<C::R>,
+    ) -> Result<(), StorageError<C>> {
+        let Some(last) = batch.last().map(|e| e.log_id.clone()) else {
+            return Ok(());
+        };
+
+        let responses = self.inner.apply(std::mem::take(batch)).await?;
+        let last_index = last.index();
+        self.last_applied = Some(last);
+
+        for (slot, resp) in slots.drain(..).zip(responses) {
+            match slot {
+                Slot::Apply => results.push(resp),
+                Slot::Record(info, index) => {
+                    self.sessions.insert(&info, index, resp.clone());
+                    results.push(resp);
+                }
+                Slot::Respond(resp) => results.push(resp),
+            }
+        }
+
+        clients.clear();
+        self.sessions.remove_expired(last_index, self.session_ttl);
+        Ok(())
+    }
+}
+
+impl<C, SM> RaftStateMachine<C> for SessionStateMachine<C, SM>
+where
+    C: RaftTypeConfig<Entry = Entry<C>, SnapshotData = Cursor<Vec<u8>>>,
+    C::D: SessionData,
+    C::R: SessionResponse + Clone,
+    SM: RaftStateMachine<C>,
+{
+    type SnapshotBuilder = SessionSnapshotBuilder<C, SM::SnapshotBuilder>;
+
+    async fn applied_state(&mut self) -> Result<(Option<LogIdOf<C>>, StoredMembership<C>), StorageError<C>> {
+        let (last_applied, membership) = self.inner.applied_state().await?;
+
+        if last_applied != self.last_applied {
+            return Err(StorageError::read_state_machine(AnyError::error(format!(
+                "the inner state machine has applied logs up to {:?}, but the session table only covers up to {:?}; \
+                 the session table is not persisted, the inner state machine must start empty",
+                last_applied, self.last_applied
+            ))));
+        }
+
+        Ok((last_applied, membership))
+    }
+
+    async fn apply<I>(&mut self, entries: I) -> Result<Vec<C::R>, StorageError<C>>
+    where
+        I: IntoIterator<Item = C::Entry> + crate::OptionalSend,
+        I::IntoIter: crate::OptionalSend,
+    {
+        let mut results = vec![];
+
+        let mut batch = vec![];
+        let mut slots = vec![];
+        // Clients that have a request in `batch`.
+        let mut clients = BTreeSet::new();
+
+        for entry in entries {
+            let info = match &entry.payload {
+                EntryPayload::Normal(data) => data.session_info(),
+                _ => None,
+            };
+
+            let Some(info) = info else {
+                batch.push(entry);
+                slots.push(Slot::Apply);
+                continue;
+            };
+
+            // The response of the previous request of this client is not known until it is applied.
+            if clients.contains(&info.client_id) {
+                self.apply_batch(&mut batch, &mut slots, &mut clients, &mut results).await?;
+            }
+
+            let index = entry.log_id.index();
+
+            let resp = match self.sessions.check_applied(&info, index, self.session_ttl) {
+                Applied::No => None,
+                Applied::Duplicate(resp) => {
+                    tracing::debug!(
+                        "skip applied request: client: {}, sequence: {}, log_id: {}",
+                        info.client_id,
+                        info.sequence,
+                        entry.log_id
+                    );
+                    Some(resp)
+                }
+                Applied::Stale(last_sequence) => {
+                    tracing::debug!(
+                        "skip stale request: client: {}, sequence: {}, last applied sequence: {}, log_id: {}",
+                        info.client_id,
+                        info.sequence,
+                        last_sequence,
+                        entry.log_id
+                    );
+                    Some(C::R::stale(&info, last_sequence))
+                }
+            };
+
+            if let Some(resp) = resp {
+                batch.push(Entry::new_blank(entry.log_id));
+                slots.push(Slot::Respond(resp));
+                continue;
+            }
+
+            clients.insert(info.client_id);
+            batch.push(entry);
+            slots.push(Slot::Record(info, index));
+        }
+
+        self.apply_batch(&mut batch, &mut slots, &mut clients, &mut results).await?;
+
+        Ok(results)
+    }
+
+    async fn get_snapshot_builder(&mut self) -> Self::SnapshotBuilder {
+        SessionSnapshotBuilder {
+            inner: self.inner.get_snapshot_builder().await,
+            sessions: self.sessions.clone(),
+            last_applied: self.last_applied.clone(),
+            current_snapshot: self.current_snapshot.clone(),
+        }
+    }
+
+    async fn begin_receiving_snapshot(&mut self) -> Result<Box<C::SnapshotData>, StorageError<C>> {
+        Ok(Box::new(Cursor::new(Vec::new())))
+    }
+
+    async fn install_snapshot(
+        &mut self,
+        meta: &SnapshotMeta<C>,
+        snapshot: Box<C::SnapshotData>,
+    ) -> Result<(), StorageError<C>> {
+        let data = snapshot.into_inner();
+
+        let (sessions, inner_data) = decode_snapshot::<C::R>(&data)
+            .map_err(|e| StorageError::read_snapshot(Some(meta.signature()), AnyError::new(&e)))?;
+
+        self.inner.install_snapshot(meta, Box::new(Cursor::new(inner_data.to_vec()))).await?;
+
+        self.sessions = sessions;
+        self.last_applied = meta.last_log_id.clone();
+        *self.current_snapshot.lock().unwrap() = Some((meta.clone(), data));
+
+        Ok(())
+    }
+
+    async fn get_current_snapshot(&mut self) -> Result<Option<Snapshot<C>>, StorageError<C>> {
+        let current = self.current_snapshot.lock().unwrap().clone();
+
+        Ok(current.map(|(meta, data)| Snapshot {
+            meta,
+            snapshot: Box::new(Cursor::new(data)),
+        }))
+    }
+}
+
+/// Builds a snapshot of a [`SessionStateMachine`]: the session table followed by the snapshot of
+/// the inner state machine.
+pub struct SessionSnapshotBuilder<C, B>
+where C: RaftTypeConfig
+{
+    inner: B,
+    sessions: SessionTable<C::R>,
+    last_applied: Option<LogIdOf<C>>,
+    current_snapshot: Arc<Mutex<Option<(SnapshotMeta<C>, Vec<u8>)>>>,
+}
+
+impl<C, B> RaftSnapshotBuilder<C> for SessionSnapshotBuilder<C, B>
+where
+    C: RaftTypeConfig<Entry = Entry<C>, SnapshotData = Cursor<Vec<u8>>>,
+    C::R: Clone,
+    B: RaftSnapshotBuilder<C>,
+{
+    async fn build_snapshot(&mut self) -> Result<Snapshot<C>, StorageError<C>> {
+        let snapshot = self.inner.build_snapshot().await?;
+        let meta = snapshot.meta;
+
+        if meta.last_log_id != self.last_applied {
+            return Err(StorageError::write_snapshot(
+                Some(meta.signature()),
+                AnyError::error(format!(
+                    "inner snapshot is built at {:?}, but the session table is at {:?}",
+                    meta.last_log_id, self.last_applied
+                )),
+            ));
+        }
+
+        let data = encode_snapshot(&self.sessions, snapshot.snapshot.get_ref())
+            .map_err(|e| StorageError::write_snapshot(Some(meta.signature()), AnyError::new(&e)))?;
+
+        *self.current_snapshot.lock().unwrap() = Some((meta.clone(), data.clone()));
+
+        Ok(Snapshot {
+            meta,
+            snapshot: Box::new(Cursor::new(data)),
+        })
+    }
+}
+
+/// Encode a session table and the inner snapshot data as: the length of the encoded session table
+/// in 8 bytes big endian, the session table in JSON, then the inner snapshot data.
+fn encode_snapshot<R>(sessions: &SessionTable<R>, inner: &[u8]) -> Result<Vec<u8>, serde_json::Error>
+where R: serde::Serialize {
+    let table = serde_json::to_vec(sessions)?;
+
+    let mut data = Vec::with_capacity(8 + table.len() + inner.len());
+    data.extend_from_slice(&(table.len() as u64).to_be_bytes());
+    data.extend_from_slice(&table);
+    data.extend_from_slice(inner);
+    Ok(data)
+}
+
+fn decode_snapshot<R>(data: &[u8]) -> Result<(SessionTable<R>, &[u8]), std::io::Error>
+where R: for<'a> serde::Deserialize<'a> {
+    let truncated = || std::io::Error::new(std::io::ErrorKind::UnexpectedEof, "truncated session snapshot");
+
+    let (len, rest) = data.split_first_chunk::<8>().ok_or_else(truncated)?;
+    let len = u64::from_be_bytes(*len) as usize;
+
+    if rest.len() < len {
+        return Err(truncated());
+    }
+    let (table, inner) = rest.split_at(len);
+
+    let sessions = serde_json::from_slice(table)?;
+    Ok((sessions, inner))
+}
@@ -0,0 +1,202 @@
+use std::io::Cursor;
+
+use pretty_assertions::assert_eq;
+use Resp::Stale;
+use Resp::Sum;
+
+use crate::entry::RaftEntry;
+use crate::session::SessionInfo;
+use crate::session::SessionRequest;
+use crate::session::SessionResponse;
+use crate::session::SessionStateMachine;
+use crate::storage::RaftStateMachine;
+use crate::storage::Snapshot;
+use crate::storage::SnapshotMeta;
+use crate::testing::blank_ent;
+use crate::testing::log_id;
+use crate::type_config::alias::LogIdOf;
+use crate::Entry;
+use crate::EntryPayload;
+use crate::RaftSnapshotBuilder;
+use crate::StorageError;
+use crate::StoredMembership;
+
+crate::declare_raft_types!(
+    pub(crate) SessionConfig:
+        D = SessionRequest<u64>,
+        R = Resp,
+        Node = (),
+);
+
+type C = SessionConfig;
+
+const TTL: u64 = 3;
+
+/// The sum after a request is applied, or the last applied sequence number for a stale request.
+#[derive(Debug, Clone)]
+#[derive(PartialEq, Eq)]
+#[derive(serde::Deserialize, serde::Serialize)]
+pub(crate) enum Resp {
+    Sum(u64),
+    Stale(u64),
+}
+
+impl SessionResponse for Resp {
+    fn stale(_info: &SessionInfo, last_sequence: u64) -> Self {
+        Stale(last_sequence)
+    }
+}
+
+/// Adds up the applied values and responds with the sum.
+#[derive(Default, Clone)]
+struct SumStateMachine {
+    last_applied: Option<LogIdOf<C>>,
+    sum: u64,
+}
+
+impl RaftSnapshotBuilder<C> for SumStateMachine {
+    async fn build_snapshot(&mut self) -> Result<Snapshot<C>, StorageError<C>> {
+        Ok(Snapshot {
+            meta: SnapshotMeta {
+                last_log_id: self.last_applied,
+                last_membership: StoredMembership::default(),
+                snapshot_id: "sum".to_string(),
+            },
+            snapshot: Box::new(Cursor::new(self.sum.to_be_bytes().to_vec())),
+        })
+    }
+}
+
+impl RaftStateMachine<C> for SumStateMachine {
+    type SnapshotBuilder = Self;
+
+    async fn applied_state(&mut self) -> Result<(Option<LogIdOf<C>>, StoredMembership<C>), StorageError<C>> {
+        Ok((self.last_applied, StoredMembership::default()))
+    }
+
+    async fn apply<I>(&mut self, entries: I) -> Result<Vec<Resp>, StorageError<C>>
+    where I: IntoIterator<Item = Entry<C>> {
+        let mut res = vec![];
+        for entry in entries {
+            self.last_applied = Some(entry.log_id);
+            if let EntryPayload::Normal(req) = entry.payload {
+                self.sum += req.data;
+            }
+            res.push(Sum(self.sum));
+        }
+        Ok(res)
+    }
+
+    async fn get_snapshot_builder(&mut self) -> Self::SnapshotBuilder {
+        self.clone()
+    }
+
+    async fn begin_receiving_snapshot(&mut self) -> Result<Box<Cursor<Vec<u8>>>, StorageError<C>> {
+        Ok(Box::new(Cursor::new(vec![])))
+    }
+
+    async fn install_snapshot(
+        &mut self,
+        meta: &SnapshotMeta<C>,
+        snapshot: Box<Cursor<Vec<u8>>>,
+    ) -> Result<(), StorageError<C>> {
+        self.last_applied = meta.last_log_id;
+        self.sum = u64::from_be_bytes(snapshot.into_inner().try_into().unwrap());
+        Ok(())
+    }
+
+    async fn get_current_snapshot(&mut self) -> Result<Option<Snapshot<C>>, StorageError<C>> {
+        Ok(None)
+    }
+}
+
+fn req(index: u64, client_id: u64, sequence: u64, data: u64) -> Entry<C> {
+    Entry::new_normal(log_id::<C>(1, 0, index), SessionRequest::new(client_id, sequence, data))
+}
+
+#[tokio::test]
+async fn test_session_retry_returns_cached_response() -> anyhow::Result<()> {
+    let mut sm = SessionStateMachine::new(SumStateMachine::default(), TTL);
+
+    let res = sm.apply([req(1, 7, 1, 10), req(2, 8, 1, 5)]).await?;
+    assert_eq!(vec![Sum(10), Sum(15)], res);
+
+    // Retry in another batch
+    let res = sm.apply([req(3, 7, 1, 10), req(4, 8, 2, 1)]).await?;
+    assert_eq!(vec![Sum(10), Sum(16)], res);
+    assert_eq!(16, sm.inner().sum);
+    assert_eq!(Some(log_id::<C>(1, 0, 4)), sm.applied_state().await?.0);
+
+    // Retry in the same batch: the retry waits for the first one to be applied
+    let res = sm.apply([req(5, 7, 2, 100), blank_ent::<C>(1, 0, 6), req(7, 7, 2, 100)]).await?;
+    assert_eq!(vec![Sum(116), Sum(116), Sum(116)], res);
+    assert_eq!(116, sm.inner().sum);
+    assert_eq!(Some(log_id::<C>(1, 0, 7)), sm.applied_state().await?.0);
+
+    // A stale retry of an older request is not applied either
+    let res = sm.apply([req(8, 7, 1, 10)]).await?;
+    assert_eq!(vec![Stale(2)], res);
+    assert_eq!(116, sm.inner().sum);
+
+    Ok(())
+}
+
+#[tokio::test]
+async fn test_session_expire_by_log_index() -> anyhow::Result<()> {
+    let mut sm = SessionStateMachine::new(SumStateMachine::default(), TTL);
+
+    sm.apply([req(1, 7, 1, 10), req(2, 8, 1, 5)]).await?;
+    assert_eq!(2, sm.session_count());
+
+    // Client 8 keeps its session alive.
+    sm.apply([req(3, 8, 2, 1), req(4, 8, 3, 1)]).await?;
+    assert_eq!(2, sm.session_count());
+
+    sm.apply([req(5, 8, 4, 1)]).await?;
+    assert_eq!(1, sm.session_count());
+
+    // Client 7 retries after its session expired, it is applied again.
+    let res = sm.apply([req(6, 7, 1, 10)]).await?;
+    assert_eq!(vec![Sum(28)], res);
+
+    Ok(())
+}
+
+#[tokio::test]
+async fn test_session_reject_persistent_inner() -> anyhow::Result<()> {
+    // The inner state machine comes back with applied logs, but the session table is lost.
+    let mut inner = SumStateMachine::default();
+    inner.apply([req(1, 7, 1, 10)]).await?;
+
+    let mut sm = SessionStateMachine::new(inner, TTL);
+
+    let res = sm.applied_state().await;
+    assert!(res.is_err());
+
+    Ok(())
+}
+
+#[tokio::test]
+async fn test_session_snapshot() -> anyhow::Result<()> {
+    let mut sm = SessionStateMachine::new(SumStateMachine::default(), TTL);
+
+    sm.apply([req(1, 7, 1, 10), req(2, 8, 1, 5)]).await?;
+
+    let mut builder = sm.get_snapshot_builder().await;
+    let snapshot = builder.build_snapshot().await?;
+    assert_eq!(Some(log_id::<C>(1, 0, 2)), snapshot.meta.last_log_id);
+
+    let current = sm.get_current_snapshot().await?.unwrap();
+    assert_eq!(snapshot.snapshot.get_ref(), current.snapshot.get_ref());
+
+    let mut sm2 = SessionStateMachine::new(SumStateMachine::default(), TTL);
+    sm2.install_snapshot(&snapshot.meta, snapshot.snapshot).await?;
+    assert_eq!(15, sm2.inner().sum);
+    assert_eq!(2, sm2.session_count());
+    assert_eq!(Some(log_id::<C>(1, 0, 2)), sm2.applied_state().await?.0);
+
+    let res = sm2.apply([req(3, 7, 1, 10), req(4, 8, 2, 1)]).await?;
+    assert_eq!(vec![Sum(10), Sum(16)], res);
+
+    Ok(())
+}
@@ -0,0 +1,91 @@
+use std::cmp::Ordering;
+use std::collections::BTreeMap;
+
+use crate::session::SessionInfo;
+
+/// The last applied request of a client.
+#[derive(Debug, Clone)]
+#[derive(serde::Deserialize, serde::Serialize)]
+#[serde(bound(serialize = "R: serde::Serialize", deserialize = "R: serde::Deserialize<'de>"))]
+pub(crate) struct Session<R> {
+    pub(crate) sequence: u64,
+
+    /// The response of the request `sequence`.
+    pub(crate) response: R,
+
+    /// The log index when a request of this client was last seen.
+    pub(crate) last_active_index: u64,
+}
+
+/// Whether a request is applied already, according to the session of its client.
+pub(crate) enum Applied<R> {
+    /// The request is not applied yet.
+    No,
+
+    /// The request is the last applied one of the client, with its cached response.
+    Duplicate(R),
+
+    /// The request is older than the last applied one of the client, whose sequence number is
+    /// given.
+    Stale(u64),
+}
+
+/// Sessions of all clients, keyed by client id.
+#[derive(Debug, Clone)]
+#[derive(serde::Deserialize, serde::Serialize)]
+#[serde(bound(serialize = "R: serde::Serialize", deserialize = "R: serde::Deserialize<'de>"))]
+pub(crate) struct SessionTable<R> {
+    pub(crate) sessions: BTreeMap<u64, Session<R>>,
+}
+
+impl<R> Default for SessionTable<R> {
+    fn default() -> Self {
+        Self {
+            sessions: BTreeMap::new(),
+        }
+    }
+}
+
+impl<R> SessionTable<R>
+where R: Clone
+{
+    /// Check if the request seen at log `index` is already applied.
+    ///
+    /// An expired session is treated as absent, whether or not it is removed yet, so that the
+    /// result does not depend on when [`Self::remove_expired`] is called.
+    pub(crate) fn check_applied(&mut self, info: &SessionInfo, index: u64, ttl: u64) -> Applied<R> {
+        let Some(session) = self.sessions.get_mut(&info.client_id) else {
+            return Applied::No;
+        };
+
+        if Self::is_expired(session, index, ttl) {
+            return Applied::No;
+        }
+
+        session.last_active_index = index;
+
+        match info.sequence.cmp(&session.sequence) {
+            Ordering::Greater => Applied::No,
+            Ordering::Equal => Applied::Duplicate(session.response.clone()),
+            Ordering::Less => Applied::Stale(session.sequence),
+        }
+    }
+
+    /// Record the response of an applied request, applied at log `index`.
+    pub(crate) fn insert(&mut self, info: &SessionInfo, index: u64, response: R) {
+        self.sessions.insert(info.client_id, Session {
+            sequence: info.sequence,
+            response,
+            last_active_index: index,
+        });
+    }
+
+    /// Remove the sessions expired at log `index`.
+    pub(crate) fn remove_expired(&mut self, index: u64, ttl: u64) {
+        self.sessions.retain(|_, s| !Self::is_expired(s, index, ttl));
+    }
+
+    fn is_expired(session: &Session<R>, index: u64, ttl: u64) -> bool {
+        session.last_active_index.saturating_add(ttl) < index
+    }
+}
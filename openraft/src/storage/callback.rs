@@ -88,7 +88,10 @@ where C: RaftTypeConfig
             Notification::HigherVote { .. }
             | Notification::StorageError { .. }
             | Notification::ReplicationProgress { .. }
+            | Notification::CatchUpDone { .. }
             | Notification::HeartbeatProgress { .. }
+            | Notification::HibernateFailed { .. }
+            | Notification::Handshake { .. }
             | Notification::StateMachine { .. }
             | Notification::Tick { .. } => {
                 unreachable!("Unexpected notification: {}", self.notification)
@@ -1,5 +1,7 @@
 use openraft_macros::add_async_trait;
+use openraft_macros::since;
 
+use crate::base::BoxFuture;
 use crate::storage::Snapshot;
 use crate::storage::SnapshotMeta;
 use crate::type_config::alias::LogIdOf;
@@ -68,6 +70,35 @@ where C: RaftTypeConfig
         I: IntoIterator<Item = C::Entry> + OptionalSend,
         I::IntoIter: OptionalSend;
 
+    /// Try to apply an entry in parallel with other entries.
+    ///
+    /// Returns the conflict key of the entry and a task that applies it, or gives the entry back
+    /// to be applied with [`apply()`](Self::apply). By default every entry is given back.
+    ///
+    /// Two entries with different conflict keys do not affect each other, e.g., they write
+    /// different keys of a key-value store, and can be applied in any order. A conflict key
+    /// collision only reduces parallelism, thus a hash of the written key is a good choice.
+    ///
+    /// Consecutive committed entries that are accepted are applied in waves: the tasks in a wave
+    /// have distinct conflict keys and are spawned to run in parallel. A task is not run until all
+    /// the entries before it in the log are applied, and it must not access the state machine
+    /// before it is run. An entry that is given back is applied with [`apply()`](Self::apply) after
+    /// all the preceding entries are done. A membership entry is always applied with
+    /// [`apply()`](Self::apply).
+    ///
+    /// The tasks in a wave may complete in any order. Responses are still sent to clients in log
+    /// order, and the last applied log id reported by Openraft advances only after all the entries
+    /// before it are applied. An implementation must do the same with the last applied log id it
+    /// returns from [`applied_state()`](Self::applied_state).
+    #[since(version = "0.10.0")]
+    #[allow(clippy::type_complexity)]
+    fn try_apply_parallel(
+        &self,
+        entry: C::Entry,
+    ) -> Result<(u64, BoxFuture<'static, Result<C::R, StorageError<C>>>), C::Entry> {
+        Err(entry)
+    }
+
     /// Get the snapshot builder for the state machine.
     ///
     /// Usually it returns a snapshot view of the state machine(i.e., subsequent changes to the
@@ -56,6 +56,9 @@ where C: RaftTypeConfig
     /// Error happened when applying a log entry
     Apply(LogIdOf<C>),
 
+    /// A log entry does not match its checksum, i.e., it is corrupted in the store.
+    Checksum(LogIdOf<C>),
+
     /// Error happened when operating state machine.
     StateMachine,
 
@@ -137,6 +140,11 @@ where C: RaftTypeConfig
         }
     }
 
+    /// What the error is about, e.g., [`ErrorSubject::Checksum`] for a corrupted log entry.
+    pub fn subject(&self) -> &ErrorSubject<C> {
+        &self.subject
+    }
+
     pub fn write_log_entry(log_id: LogIdOf<C>, source: impl Into<AnyError>) -> Self {
         Self::new(ErrorSubject::Log(log_id), ErrorVerb::Write, source)
     }
@@ -149,6 +157,10 @@ where C: RaftTypeConfig
         Self::new(ErrorSubject::Log(log_id), ErrorVerb::Read, source)
     }
 
+    pub fn checksum(log_id: LogIdOf<C>, source: impl Into<AnyError>) -> Self {
+        Self::new(ErrorSubject::Checksum(log_id), ErrorVerb::Read, source)
+    }
+
     pub fn write_logs(source: impl Into<AnyError>) -> Self {
         Self::new(ErrorSubject::Logs, ErrorVerb::Write, source)
     }
@@ -0,0 +1,487 @@
+use std::collections::BTreeMap;
+use std::collections::BTreeSet;
+use std::fmt::Debug;
+use std::marker::PhantomData;
+
+use openraft_macros::add_async_trait;
+
+use crate::async_runtime::MpscUnboundedReceiver;
+use crate::async_runtime::MpscUnboundedSender;
+use crate::core::notification::Notification;
+use crate::entry::RaftEntry;
+use crate::raft_state::io_state::io_id::IOId;
+use crate::raft_state::LogStateReader;
+use crate::storage::IOFlushed;
+use crate::storage::RaftLogStorage;
+use crate::storage::RaftStateMachine;
+use crate::storage::StorageHelper;
+use crate::testing::log::StoreBuilder;
+use crate::type_config::alias::LogIdOf;
+use crate::type_config::alias::MpscUnboundedReceiverOf;
+use crate::type_config::alias::MpscUnboundedSenderOf;
+use crate::type_config::alias::VoteOf;
+use crate::type_config::TypeConfigExt;
+use crate::vote::raft_vote::RaftVoteExt;
+use crate::vote::RaftLeaderIdExt;
+use crate::LogIdOptionExt;
+use crate::RaftLogReader;
+use crate::RaftTypeConfig;
+use crate::StorageError;
+
+/// What is left of the writes that are not synced to disk when a store crashes.
+#[derive(Debug, Clone, Copy)]
+#[derive(PartialEq, Eq)]
+pub enum CrashMode {
+    /// Every write that is not synced to disk is lost.
+    LoseUnsynced,
+
+    /// Every write that is not synced to disk is lost, except a part of the bytes appended to a
+    /// file, as if the crash tore the write.
+    TornWrite,
+}
+
+/// A [`StoreBuilder`] that can inject crashes into the storage it builds, and restart the store
+/// from what is left on it.
+#[add_async_trait]
+pub trait CrashStoreBuilder<C, LS, SM, G = ()>: StoreBuilder<C, LS, SM, G>
+where
+    C: RaftTypeConfig,
+    LS: RaftLogStorage<C>,
+    SM: RaftStateMachine<C>,
+{
+    /// Make the storage held by `guard` crash at its `n`-th IO operation from now on, counting
+    /// from 1, as if the machine stops in the middle of a write.
+    ///
+    /// The operation and every one after it fail without changing the storage, until
+    /// [`Self::restart()`].
+    fn crash_at(&self, guard: &G, n: u64);
+
+    /// Returns true if the crash set by [`Self::crash_at()`] happened.
+    fn crashed(&self, guard: &G) -> bool;
+
+    /// Crash `log_store` and `sm`, then open them again from the storage held by `guard`.
+    ///
+    /// Every write that is not synced to disk when the crash happens must be discarded, or partly
+    /// discarded according to `mode`: an `append()` whose [`IOFlushed`] callback is not called is
+    /// not guaranteed to survive a crash.
+    async fn restart(&self, guard: &G, log_store: LS, sm: SM, mode: CrashMode) -> Result<(LS, SM), StorageError<C>>;
+}
+
+/// Test suite to ensure a [`RaftLogStorage`] impl recovers a valid state after a crash.
+///
+/// It runs a sequence of writes and crashes the store:
+///
+/// - after each of the writes, either before or after the pending appends are flushed;
+/// - at each IO operation the writes make, e.g., in the middle of a `truncate()`.
+///
+/// Every crash is tried with each [`CrashMode`]. The restarted store is checked that:
+///
+/// - [`StorageHelper::get_initial_state`] succeeds.
+/// - The logs have no hole and contain only entries that were appended.
+/// - No flushed entry, unless removed by a later `truncate()` or `purge()`, is lost.
+/// - The vote is not older than the last vote that `save_vote()` returned.
+pub struct CrashSuite<C, LS, SM, B, G>
+where
+    C: RaftTypeConfig,
+    LS: RaftLogStorage<C>,
+    SM: RaftStateMachine<C>,
+    B: CrashStoreBuilder<C, LS, SM, G>,
+    G: Send + Sync,
+{
+    _p: PhantomData<(C, LS, SM, B, G)>,
+}
+
+impl<C, LS, SM, B, G> CrashSuite<C, LS, SM, B, G>
+where
+    C: RaftTypeConfig,
+    C::D: Debug,
+    C::R: Debug,
+    C::Term: From<u64>,
+    C::NodeId: From<u64>,
+    LS: RaftLogStorage<C>,
+    SM: RaftStateMachine<C>,
+    B: CrashStoreBuilder<C, LS, SM, G>,
+    G: Send + Sync,
+{
+    pub async fn test_all(builder: B) -> Result<(), StorageError<C>> {
+        let cases: [(&str, fn() -> Vec<Write<C>>); 3] = [
+            ("append", Self::append),
+            ("truncate", Self::truncate),
+            ("purge", Self::purge),
+        ];
+
+        for (name, writes) in cases {
+            Self::crash_after_writes(&builder, name, writes).await?;
+            Self::crash_in_writes(&builder, name, writes).await?;
+        }
+        Ok(())
+    }
+
+    fn append() -> Vec<Write<C>> {
+        vec![
+            Write::Vote(vote::<C>(1, 0)),
+            Write::Append(vec![blank_ent::<C>(1, 0), blank_ent::<C>(1, 1), blank_ent::<C>(1, 2)]),
+            Write::Append(vec![blank_ent::<C>(1, 3)]),
+            Write::Vote(vote::<C>(2, 1)),
+            Write::Append(vec![blank_ent::<C>(2, 4), blank_ent::<C>(2, 5)]),
+            Write::Vote(vote::<C>(3, 0)),
+        ]
+    }
+
+    fn truncate() -> Vec<Write<C>> {
+        vec![
+            Write::Vote(vote::<C>(1, 0)),
+            Write::Append(vec![
+                blank_ent::<C>(1, 0),
+                blank_ent::<C>(1, 1),
+                blank_ent::<C>(1, 2),
+                blank_ent::<C>(1, 3),
+            ]),
+            Write::Append(vec![blank_ent::<C>(1, 4), blank_ent::<C>(1, 5)]),
+            Write::Vote(vote::<C>(2, 1)),
+            Write::Truncate(log_id::<C>(1, 2)),
+            Write::Append(vec![blank_ent::<C>(2, 2), blank_ent::<C>(2, 3)]),
+            Write::Truncate(log_id::<C>(2, 3)),
+            Write::Append(vec![blank_ent::<C>(2, 3)]),
+        ]
+    }
+
+    fn purge() -> Vec<Write<C>> {
+        vec![
+            Write::Vote(vote::<C>(1, 0)),
+            Write::Append(vec![
+                blank_ent::<C>(1, 0),
+                blank_ent::<C>(1, 1),
+                blank_ent::<C>(1, 2),
+                blank_ent::<C>(1, 3),
+            ]),
+            Write::Purge(log_id::<C>(1, 1)),
+            Write::Append(vec![blank_ent::<C>(1, 4)]),
+            Write::Purge(log_id::<C>(1, 4)),
+            Write::Append(vec![blank_ent::<C>(1, 5), blank_ent::<C>(1, 6)]),
+            Write::Purge(log_id::<C>(1, 10)),
+            Write::Append(vec![blank_ent::<C>(1, 11)]),
+        ]
+    }
+
+    /// Crash after every prefix of the writes built by `writes`, and check the restarted store.
+    async fn crash_after_writes(builder: &B, name: &str, writes: fn() -> Vec<Write<C>>) -> Result<(), StorageError<C>> {
+        let n = writes().len();
+
+        for crash_at in 0..=n {
+            for flush in [false, true] {
+                for mode in [CrashMode::LoseUnsynced, CrashMode::TornWrite] {
+                    let point = format!(
+                        "{}: crash after {} writes, flushed: {}, {:?}",
+                        name, crash_at, flush, mode
+                    );
+                    tracing::info!("--- {}", point);
+
+                    let (g, mut log_store, sm) = builder.build().await?;
+
+                    let mut model = Model::default();
+                    for w in writes().into_iter().take(crash_at) {
+                        model.write(&mut log_store, w).await?;
+                    }
+
+                    if flush {
+                        model.wait_flushed().await?;
+                    } else {
+                        model.poll_flushed(false)?;
+                    }
+
+                    let (mut log_store, mut sm) = builder.restart(&g, log_store, sm, mode).await?;
+                    model.check(&point, &mut log_store, &mut sm).await?;
+                }
+            }
+        }
+
+        Ok(())
+    }
+
+    /// Crash at every IO operation made by the writes built by `writes`, and check the restarted
+    /// store.
+    ///
+    /// The writes stop at the crash. It ends when the writes are done before the crash point.
+    async fn crash_in_writes(builder: &B, name: &str, writes: fn() -> Vec<Write<C>>) -> Result<(), StorageError<C>> {
+        for op in 1.. {
+            let mut done = false;
+
+            for mode in [CrashMode::LoseUnsynced, CrashMode::TornWrite] {
+                let point = format!("{}: crash at IO operation {}, {:?}", name, op, mode);
+                tracing::info!("--- {}", point);
+
+                let (g, mut log_store, sm) = builder.build().await?;
+                builder.crash_at(&g, op);
+
+                let mut model = Model::default();
+                for w in writes() {
+                    let res = model.write(&mut log_store, w).await;
+                    if builder.crashed(&g) {
+                        break;
+                    }
+                    res?;
+                }
+
+                let crashed = builder.crashed(&g);
+                done |= !crashed;
+
+                model.poll_flushed(crashed)?;
+
+                let (mut log_store, mut sm) = builder.restart(&g, log_store, sm, mode).await?;
+                model.check(&point, &mut log_store, &mut sm).await?;
+            }
+
+            if done {
+                break;
+            }
+        }
+
+        Ok(())
+    }
+}
+
+/// A write to a [`RaftLogStorage`].
+enum Write<C>
+where C: RaftTypeConfig
+{
+    Append(Vec<C::Entry>),
+    Vote(VoteOf<C>),
+    Truncate(LogIdOf<C>),
+    Purge(LogIdOf<C>),
+}
+
+/// An append whose [`IOFlushed`] callback is not received yet.
+struct PendingAppend<C>
+where C: RaftTypeConfig
+{
+    log_ids: Vec<LogIdOf<C>>,
+
+    // Keep the sender so that the callback can be delivered.
+    _tx: MpscUnboundedSenderOf<C, Notification<C>>,
+    rx: MpscUnboundedReceiverOf<C, Notification<C>>,
+}
+
+/// Tracks what a store has acknowledged, i.e., what must survive a crash.
+struct Model<C>
+where C: RaftTypeConfig
+{
+    /// Every saved vote, in order.
+    votes: Vec<VoteOf<C>>,
+
+    /// The number of votes that `save_vote()` returned for.
+    votes_saved: usize,
+
+    /// Every appended log id.
+    appended: BTreeSet<LogIdOf<C>>,
+
+    pending: Vec<PendingAppend<C>>,
+
+    /// Flushed entries that are not truncated since.
+    flushed: BTreeMap<u64, LogIdOf<C>>,
+
+    /// Every purged log id.
+    purged: BTreeSet<LogIdOf<C>>,
+}
+
+impl<C> Default for Model<C>
+where C: RaftTypeConfig
+{
+    fn default() -> Self {
+        Self {
+            votes: vec![],
+            votes_saved: 0,
+            appended: BTreeSet::new(),
+            pending: vec![],
+            flushed: BTreeMap::new(),
+            purged: BTreeSet::new(),
+        }
+    }
+}
+
+impl<C> Model<C>
+where C: RaftTypeConfig
+{
+    /// Apply a write to the store and record what it must keep after a crash.
+    ///
+    /// The record is updated before the write is done, because a crash may stop it in the middle.
+    async fn write<LS>(&mut self, log_store: &mut LS, w: Write<C>) -> Result<(), StorageError<C>>
+    where LS: RaftLogStorage<C> {
+        self.poll_flushed(false)?;
+
+        match w {
+            Write::Append(entries) => {
+                let log_ids = entries.iter().map(|e| e.log_id()).collect::<Vec<_>>();
+                self.appended.extend(log_ids.iter().cloned());
+
+                let (tx, rx) = C::mpsc_unbounded();
+                let io_id = IOId::<C>::new_log_io(VoteOf::<C>::default().into_committed(), log_ids.last().cloned());
+                let callback = IOFlushed::new(Notification::LocalIO { io_id }, tx.downgrade());
+
+                log_store.append(entries, callback).await?;
+
+                self.pending.push(PendingAppend { log_ids, _tx: tx, rx });
+            }
+            Write::Vote(vote) => {
+                self.votes.push(vote.clone());
+                log_store.save_vote(&vote).await?;
+                self.votes_saved = self.votes.len();
+            }
+            Write::Truncate(log_id) => {
+                // Entries flushed after this must not resurrect the removed ones.
+                self.flushed.split_off(&log_id.index());
+                for p in self.pending.iter_mut() {
+                    p.log_ids.retain(|x| x.index() < log_id.index());
+                }
+
+                log_store.truncate(log_id).await?;
+            }
+            Write::Purge(log_id) => {
+                self.purged.insert(log_id.clone());
+                log_store.purge(log_id).await?;
+            }
+        }
+
+        Ok(())
+    }
+
+    /// Collect the appends that are reported as flushed.
+    ///
+    /// If the store `crashed`, an append reported as failed is not flushed, instead of an error.
+    fn poll_flushed(&mut self, crashed: bool) -> Result<(), StorageError<C>> {
+        let mut still_pending = vec![];
+
+        for mut p in self.pending.drain(..) {
+            match p.rx.try_recv() {
+                Ok(notification) => Self::flushed(&mut self.flushed, notification, p.log_ids, crashed)?,
+                Err(_) => still_pending.push(p),
+            }
+        }
+
+        self.pending = still_pending;
+        Ok(())
+    }
+
+    /// Wait for every pending append to be flushed.
+    async fn wait_flushed(&mut self) -> Result<(), StorageError<C>> {
+        for mut p in self.pending.drain(..) {
+            let notification = p.rx.recv().await.unwrap();
+            Self::flushed(&mut self.flushed, notification, p.log_ids, false)?;
+        }
+        Ok(())
+    }
+
+    fn flushed(
+        flushed: &mut BTreeMap<u64, LogIdOf<C>>,
+        notification: Notification<C>,
+        log_ids: Vec<LogIdOf<C>>,
+        crashed: bool,
+    ) -> Result<(), StorageError<C>> {
+        if let Notification::StorageError { error } = notification {
+            if crashed {
+                return Ok(());
+            }
+            return Err(error);
+        }
+
+        flushed.extend(log_ids.into_iter().map(|x| (x.index(), x)));
+        Ok(())
+    }
+
+    /// Check the state of a restarted store against what it acknowledged before the crash.
+    async fn check<LS, SM>(&self, point: &str, log_store: &mut LS, sm: &mut SM) -> Result<(), StorageError<C>>
+    where
+        LS: RaftLogStorage<C>,
+        SM: RaftStateMachine<C>,
+    {
+        let state = StorageHelper::new(log_store, sm).get_initial_state().await?;
+
+        // Vote
+
+        let vote = log_store.get_log_reader().await.read_vote().await?;
+        let acceptable = &self.votes[self.votes_saved.saturating_sub(1)..];
+        match &vote {
+            None => assert_eq!(0, self.votes_saved, "{}: saved vote is lost", point),
+            Some(v) => assert!(
+                acceptable.contains(v),
+                "{}: vote {} is not one of {:?}",
+                point,
+                v,
+                acceptable
+            ),
+        }
+        assert_eq!(vote.unwrap_or_default(), *state.vote_ref(), "{}: initial vote", point);
+
+        // Logs
+
+        let last_purged = state.last_purged_log_id().cloned();
+        if let Some(p) = &last_purged {
+            assert!(self.purged.contains(p), "{}: {} is never purged", point, p);
+        }
+
+        let start = last_purged.next_index();
+        let end = state.last_log_id().next_index();
+        let entries = log_store.get_log_reader().await.try_get_log_entries(start..end).await?;
+
+        assert_eq!(
+            (start..end).collect::<Vec<_>>(),
+            entries.iter().map(|e| e.index()).collect::<Vec<_>>(),
+            "{}: logs have no hole",
+            point
+        );
+
+        let present = entries.iter().map(|e| (e.index(), e.log_id())).collect::<BTreeMap<_, _>>();
+        for log_id in present.values() {
+            assert!(
+                self.appended.contains(log_id),
+                "{}: {} is never appended",
+                point,
+                log_id
+            );
+        }
+
+        let log_ids = present.values().collect::<Vec<_>>();
+        for w in log_ids.windows(2) {
+            assert!(w[0] < w[1], "{}: log ids are not increasing: {}, {}", point, w[0], w[1]);
+        }
+
+        for (index, log_id) in self.flushed.range(start..) {
+            assert_eq!(
+                Some(log_id),
+                present.get(index),
+                "{}: flushed entry at {} is lost",
+                point,
+                index
+            );
+        }
+
+        Ok(())
+    }
+}
+
+fn vote<C>(term: u64, node_id: u64) -> VoteOf<C>
+where
+    C: RaftTypeConfig,
+    C::Term: From<u64>,
+    C::NodeId: From<u64>,
+{
+    VoteOf::<C>::from_term_node_id(term.into(), node_id.into())
+}
+
+fn log_id<C>(term: u64, index: u64) -> LogIdOf<C>
+where
+    C: RaftTypeConfig,
+    C::Term: From<u64>,
+    C::NodeId: From<u64>,
+{
+    LogIdOf::<C>::new(C::LeaderId::new_committed(term.into(), 0.into()), index)
+}
+
+fn blank_ent<C>(term: u64, index: u64) -> C::Entry
+where
+    C: RaftTypeConfig,
+    C::Term: From<u64>,
+    C::NodeId: From<u64>,
+{
+    C::Entry::new_blank(log_id::<C>(term, index))
+}
@@ -1,10 +1,16 @@
 //! Suite for testing implementations of [`RaftLogStorage`] and [`RaftStateMachine`].
 //!
+//! [`CrashSuite`] additionally tests if a [`RaftLogStorage`] recovers from a crash.
+//!
 //! [`RaftLogStorage`]: crate::storage::RaftLogStorage
 //! [`RaftStateMachine`]: crate::storage::RaftStateMachine
 
+mod crash_suite;
 mod store_builder;
 mod suite;
 
+pub use crash_suite::CrashMode;
+pub use crash_suite::CrashStoreBuilder;
+pub use crash_suite::CrashSuite;
 pub use store_builder::StoreBuilder;
 pub use suite::Suite;
@@ -79,7 +79,8 @@ impl<Rt: AsyncRuntime> Suite<Rt> {
     }
 
     pub async fn test_sleep() {
-        let start_time = std::time::Instant::now();
+        // Measure with the runtime clock: a simulated runtime does not follow the wall clock.
+        let start_time = Rt::Instant::now();
         let dur_10ms = std::time::Duration::from_millis(10);
         Rt::sleep(dur_10ms).await;
         let elapsed = start_time.elapsed();
@@ -0,0 +1,539 @@
+//! A deterministic single-threaded executor driven by a virtual clock.
+
+use std::cell::RefCell;
+use std::collections::BTreeMap;
+use std::collections::BTreeSet;
+use std::fmt;
+use std::future::Future;
+use std::panic::AssertUnwindSafe;
+use std::pin::pin;
+use std::pin::Pin;
+use std::sync::Arc;
+use std::sync::Mutex;
+use std::sync::MutexGuard;
+use std::sync::Weak;
+use std::task::Context;
+use std::task::Poll;
+use std::task::Waker;
+use std::time::Duration;
+
+use futures::future::BoxFuture;
+use futures::task::waker;
+use futures::task::waker_ref;
+use futures::task::ArcWake;
+use futures::FutureExt;
+use rand::rngs::StdRng;
+use rand::Rng;
+use rand::SeedableRng;
+
+use crate::time::SimInstant;
+
+type TaskId = u64;
+
+/// The id of the future passed to [`Sim::block_on()`].
+const MAIN_TASK: TaskId = 0;
+
+/// A deterministic simulation of an asynchronous runtime.
+///
+/// All tasks are polled on the thread that calls [`Sim::block_on()`]. When more than one task is
+/// ready, the next one to poll is chosen with the seeded RNG, so that different seeds explore
+/// different interleavings while the same seed always reproduces the same run.
+///
+/// Time is virtual: it does not move while any task is ready to run. Once every task is idle, the
+/// clock jumps to the earliest pending timer and wakes it up. Thus a simulated election timeout of
+/// seconds costs no wall clock time at all.
+///
+/// ```ignore
+/// let sim = Sim::new(42);
+/// sim.block_on(async {
+///     SimRuntime::sleep(Duration::from_secs(3600)).await;
+/// });
+/// assert_eq!(sim.elapsed(), Duration::from_secs(3600));
+/// ```
+pub struct Sim {
+    handle: Handle,
+}
+
+impl Sim {
+    /// Create a simulation whose scheduling and randomness are derived from `seed`.
+    pub fn new(seed: u64) -> Self {
+        let state = State {
+            seed,
+            now: Duration::ZERO,
+            rng: StdRng::seed_from_u64(seed),
+            next_task_id: MAIN_TASK + 1,
+            tasks: BTreeMap::new(),
+            ready: Vec::new(),
+            queued: BTreeSet::new(),
+            next_timer_id: 0,
+            timers: BTreeMap::new(),
+            polls: 0,
+        };
+
+        Self {
+            handle: Handle {
+                shared: Arc::new(Shared {
+                    state: Mutex::new(state),
+                }),
+            },
+        }
+    }
+
+    /// The seed this simulation is created with.
+    pub fn seed(&self) -> u64 {
+        self.handle.state().seed
+    }
+
+    /// The current virtual time.
+    pub fn now(&self) -> SimInstant {
+        SimInstant::from_elapsed(self.elapsed())
+    }
+
+    /// The virtual time elapsed since the simulation is created.
+    pub fn elapsed(&self) -> Duration {
+        self.handle.state().now
+    }
+
+    /// The number of times a task has been polled, including the `block_on()` future.
+    ///
+    /// Two runs with the same seed poll the same number of times, which makes it a cheap
+    /// fingerprint for checking that a simulation is reproducible.
+    pub fn polls(&self) -> u64 {
+        self.handle.state().polls
+    }
+
+    /// Number of spawned tasks that have not yet finished.
+    pub fn alive_tasks(&self) -> usize {
+        self.handle.state().tasks.len()
+    }
+
+    /// Run a future to completion, driving all spawned tasks and the virtual clock.
+    ///
+    /// Tasks spawned by the future keep existing after it returns and continue running in the next
+    /// call to `block_on()`. They are dropped when the `Sim` is dropped.
+    ///
+    /// # Panics
+    ///
+    /// Panics if called from within another `block_on()` on the same thread, or if every task is
+    /// blocked while no timer is pending, i.e., the simulation is deadlocked.
+    pub fn block_on<F: Future>(&self, fut: F) -> F::Output {
+        assert!(
+            !context::is_entered(),
+            "can not call `Sim::block_on()` within a running Sim"
+        );
+        let _guard = context::enter(self.handle.clone());
+
+        let mut fut = pin!(fut);
+
+        let main = Arc::new(Task {
+            id: MAIN_TASK,
+            future: Mutex::new(None),
+            shared: Arc::downgrade(&self.handle.shared),
+        });
+        let main_waker = waker(main);
+
+        self.handle.schedule(MAIN_TASK);
+
+        loop {
+            let id = self.handle.next_runnable();
+
+            if id == MAIN_TASK {
+                let mut cx = Context::from_waker(&main_waker);
+                if let Poll::Ready(output) = fut.as_mut().poll(&mut cx) {
+                    return output;
+                }
+                continue;
+            }
+
+            let task = self.handle.state().tasks.get(&id).cloned();
+            if let Some(task) = task {
+                if task.run() {
+                    self.handle.state().tasks.remove(&id);
+                }
+            }
+        }
+    }
+}
+
+impl Drop for Sim {
+    fn drop(&mut self) {
+        // Tasks hold a `Handle` and the `Handle` holds the tasks: break the cycle.
+        // Futures are dropped outside the lock because dropping them may touch the timer wheel.
+        let _guard = context::enter(self.handle.clone());
+
+        let tasks = {
+            let mut state = self.handle.state();
+            state.ready.clear();
+            state.queued.clear();
+            std::mem::take(&mut state.tasks)
+        };
+
+        for task in tasks.into_values() {
+            let fut = task.future.lock().unwrap().take();
+            drop(fut);
+        }
+
+        self.handle.state().timers.clear();
+    }
+}
+
+impl fmt::Debug for Sim {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        let state = self.handle.state();
+        f.debug_struct("Sim")
+            .field("seed", &state.seed)
+            .field("now", &state.now)
+            .field("tasks", &state.tasks.len())
+            .field("timers", &state.timers.len())
+            .finish()
+    }
+}
+
+/// A reference to a running [`Sim`], used by [`SimRuntime`](crate::SimRuntime) to reach the
+/// simulation that the current task belongs to.
+#[derive(Clone)]
+pub(crate) struct Handle {
+    shared: Arc<Shared>,
+}
+
+struct Shared {
+    state: Mutex<State>,
+}
+
+struct State {
+    seed: u64,
+
+    /// The virtual time elapsed since the simulation started.
+    now: Duration,
+
+    rng: StdRng,
+
+    next_task_id: TaskId,
+
+    tasks: BTreeMap<TaskId, Arc<Task>>,
+
+    /// Tasks that are woken and waiting to be polled.
+    ready: Vec<TaskId>,
+
+    /// The same set as `ready`, to deduplicate wakeups.
+    queued: BTreeSet<TaskId>,
+
+    next_timer_id: u64,
+
+    /// Pending timers ordered by `(deadline, registration order)`.
+    timers: BTreeMap<(Duration, u64), Waker>,
+
+    polls: u64,
+}
+
+impl Handle {
+    fn state(&self) -> MutexGuard<'_, State> {
+        self.shared.state()
+    }
+
+    fn schedule(&self, id: TaskId) {
+        self.shared.schedule(id)
+    }
+
+    pub(crate) fn now(&self) -> Duration {
+        self.state().now
+    }
+
+    pub(crate) fn with_rng<T>(&self, f: impl FnOnce(&mut StdRng) -> T) -> T {
+        f(&mut self.state().rng)
+    }
+
+    /// Register a timer firing at `deadline`, or update the waker of an already registered one.
+    ///
+    /// Returns `None` if the deadline has already been reached.
+    pub(crate) fn register_timer(
+        &self,
+        deadline: Duration,
+        key: Option<(Duration, u64)>,
+        waker: &Waker,
+    ) -> Option<(Duration, u64)> {
+        let mut state = self.state();
+
+        if state.now >= deadline {
+            if let Some(k) = key {
+                state.timers.remove(&k);
+            }
+            return None;
+        }
+
+        let key = match key {
+            Some(k) => k,
+            None => {
+                state.next_timer_id += 1;
+                (deadline, state.next_timer_id)
+            }
+        };
+
+        state.timers.insert(key, waker.clone());
+        Some(key)
+    }
+
+    pub(crate) fn cancel_timer(&self, key: (Duration, u64)) {
+        self.state().timers.remove(&key);
+    }
+
+    pub(crate) fn spawn<F>(&self, fut: F) -> JoinHandle<F::Output>
+    where
+        F: Future + Send + 'static,
+        F::Output: Send + 'static,
+    {
+        let slot = Arc::new(Mutex::new(JoinSlot {
+            output: None,
+            waker: None,
+        }));
+
+        let completion = Completion { slot: slot.clone() };
+
+        let wrapped = async move {
+            let res = AssertUnwindSafe(fut).catch_unwind().await;
+            completion.complete(res.map_err(JoinError::panic));
+        };
+
+        let id = {
+            let mut state = self.state();
+            let id = state.next_task_id;
+            state.next_task_id += 1;
+
+            let task = Arc::new(Task {
+                id,
+                future: Mutex::new(Some(wrapped.boxed())),
+                shared: Arc::downgrade(&self.shared),
+            });
+            state.tasks.insert(id, task);
+            id
+        };
+
+        self.schedule(id);
+
+        JoinHandle { slot }
+    }
+
+    /// Pick the next task to poll.
+    ///
+    /// If no task is ready, advance the virtual clock to the earliest timer and fire all timers
+    /// that are due. If there is no timer either, nothing can ever make progress.
+    fn next_runnable(&self) -> TaskId {
+        let mut state = self.state();
+
+        loop {
+            if !state.ready.is_empty() {
+                let n = state.ready.len();
+                let i = state.rng.random_range(0..n);
+                let id = state.ready.swap_remove(i);
+                state.queued.remove(&id);
+                state.polls += 1;
+                return id;
+            }
+
+            let first_deadline = state.timers.first_key_value().map(|((deadline, _), _)| *deadline);
+
+            if let Some(deadline) = first_deadline {
+                state.now = std::cmp::max(state.now, deadline);
+
+                let now = state.now;
+                let mut due = vec![];
+                while let Some(entry) = state.timers.first_entry() {
+                    if entry.key().0 > now {
+                        break;
+                    }
+                    due.push(entry.remove());
+                }
+
+                drop(state);
+                for w in due {
+                    w.wake();
+                }
+                state = self.state();
+                continue;
+            }
+
+            panic!(
+                "Sim(seed={}) deadlocked at {:?}: no task is ready and no timer is pending",
+                state.seed, state.now
+            );
+        }
+    }
+}
+
+impl Shared {
+    fn state(&self) -> MutexGuard<'_, State> {
+        self.state.lock().unwrap()
+    }
+
+    fn schedule(&self, id: TaskId) {
+        let mut state = self.state();
+        if state.queued.insert(id) {
+            state.ready.push(id);
+        }
+    }
+}
+
+struct Task {
+    id: TaskId,
+
+    /// `None` for the `block_on()` future, which is polled by the caller, and for finished tasks.
+    future: Mutex<Option<BoxFuture<'static, ()>>>,
+
+    shared: Weak<Shared>,
+}
+
+impl Task {
+    /// Poll the task once, returns `true` if it is finished.
+    fn run(self: &Arc<Self>) -> bool {
+        let waker = waker_ref(self);
+        let mut cx = Context::from_waker(&waker);
+
+        let mut fut = self.future.lock().unwrap();
+        let Some(f) = fut.as_mut() else {
+            return true;
+        };
+
+        if f.as_mut().poll(&mut cx).is_ready() {
+            *fut = None;
+            true
+        } else {
+            false
+        }
+    }
+}
+
+impl ArcWake for Task {
+    fn wake_by_ref(arc_self: &Arc<Self>) {
+        if let Some(shared) = arc_self.shared.upgrade() {
+            shared.schedule(arc_self.id);
+        }
+    }
+}
+
+/// Error returned by awaiting a [`JoinHandle`].
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum JoinError {
+    /// The task panicked, with the panic message.
+    Panic(String),
+
+    /// The task is dropped before finishing, because the [`Sim`] is dropped.
+    Cancelled,
+}
+
+impl JoinError {
+    fn panic(payload: Box<dyn std::any::Any + Send>) -> Self {
+        let msg = if let Some(s) = payload.downcast_ref::<&str>() {
+            s.to_string()
+        } else if let Some(s) = payload.downcast_ref::<String>() {
+            s.clone()
+        } else {
+            "<non-string panic payload>".to_string()
+        };
+        JoinError::Panic(msg)
+    }
+}
+
+impl fmt::Display for JoinError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            JoinError::Panic(msg) => write!(f, "task panicked: {}", msg),
+            JoinError::Cancelled => write!(f, "task cancelled"),
+        }
+    }
+}
+
+impl std::error::Error for JoinError {}
+
+struct JoinSlot<T> {
+    output: Option<Result<T, JoinError>>,
+    waker: Option<Waker>,
+}
+
+/// Fills the [`JoinSlot`] when the task finishes, or with [`JoinError::Cancelled`] if the task is
+/// dropped before that.
+struct Completion<T> {
+    slot: Arc<Mutex<JoinSlot<T>>>,
+}
+
+impl<T> Completion<T> {
+    fn complete(&self, res: Result<T, JoinError>) {
+        let waker = {
+            let mut slot = self.slot.lock().unwrap();
+            slot.output = Some(res);
+            slot.waker.take()
+        };
+        if let Some(w) = waker {
+            w.wake();
+        }
+    }
+}
+
+impl<T> Drop for Completion<T> {
+    fn drop(&mut self) {
+        let finished = self.slot.lock().unwrap().output.is_some();
+        if !finished {
+            self.complete(Err(JoinError::Cancelled));
+        }
+    }
+}
+
+/// An owned permission to join on a task spawned in a [`Sim`].
+///
+/// Dropping the handle detaches the task.
+pub struct JoinHandle<T> {
+    slot: Arc<Mutex<JoinSlot<T>>>,
+}
+
+impl<T> Future for JoinHandle<T> {
+    type Output = Result<T, JoinError>;
+
+    fn poll(self: Pin<&mut Self>, cx: &mut Context<'_>) -> Poll<Self::Output> {
+        let mut slot = self.slot.lock().unwrap();
+        match slot.output.take() {
+            Some(output) => Poll::Ready(output),
+            None => {
+                slot.waker = Some(cx.waker().clone());
+                Poll::Pending
+            }
+        }
+    }
+}
+
+pub(crate) mod context {
+    //! Tracks the [`Sim`] that is running on the current thread.
+
+    use super::*;
+
+    thread_local! {
+        static CURRENT: RefCell<Option<Handle>> = const { RefCell::new(None) };
+    }
+
+    pub(crate) struct EnterGuard {
+        prev: Option<Handle>,
+    }
+
+    impl Drop for EnterGuard {
+        fn drop(&mut self) {
+            let prev = self.prev.take();
+            CURRENT.with(|c| *c.borrow_mut() = prev);
+        }
+    }
+
+    pub(crate) fn enter(handle: Handle) -> EnterGuard {
+        let prev = CURRENT.with(|c| c.borrow_mut().replace(handle));
+        EnterGuard { prev }
+    }
+
+    pub(crate) fn is_entered() -> bool {
+        CURRENT.with(|c| c.borrow().is_some())
+    }
+
+    /// Return the handle to the simulation running on this thread.
+    ///
+    /// # Panics
+    ///
+    /// Panics if called outside of [`Sim::block_on()`].
+    pub(crate) fn current() -> Handle {
+        CURRENT.with(|c| c.borrow().clone()).expect("SimRuntime must be used within `Sim::block_on()`")
+    }
+}
@@ -0,0 +1,255 @@
+//! This crate provides a [`SimRuntime`] type, an [`AsyncRuntime`] that runs Openraft in a
+//! deterministic simulation with a virtual clock.
+//!
+//! ```ignore
+//! pub struct TypeConfig {}
+//!
+//! impl openraft::RaftTypeConfig for TypeConfig {
+//!     // Other type are omitted
+//!
+//!     type AsyncRuntime = openraft_rt_sim::SimRuntime;
+//! }
+//!
+//! openraft_rt_sim::impl_sim_network!(TypeConfig);
+//!
+//! let sim = openraft_rt_sim::Sim::new(seed);
+//! sim.block_on(async {
+//!     let raft = Raft::<TypeConfig>::new(/* omitted */).await?;
+//!     // ...
+//! });
+//! ```
+//!
+//! A [`Sim`] polls every task on the calling thread, picks the next task to poll with a seeded
+//! RNG, and advances the virtual clock only when every task is idle. Together with
+//! [`SimNetwork`], an in-process network with seeded latency, message loss and partitions, a whole
+//! multi-node cluster runs as fast as the CPU allows and every run is reproducible from its seed.
+//!
+//! # NOTE
+//!
+//! 1. Everything that is time or randomness dependent must go through [`SimRuntime`], e.g.,
+//!    [`SimRuntime::sleep`] instead of `tokio::time::sleep`, otherwise the simulation is no longer
+//!    deterministic.
+//! 2. The `singlethreaded` feature of Openraft is not supported: tasks must be [`Send`].
+//! 3. Channels and the mutex are Tokio's runtime-independent primitives from `tokio::sync`, the
+//!    same ones [`TokioRuntime`](openraft::TokioRuntime) uses, except for the watch channel:
+//!    [`SimWatch`] wakes its receivers in a deterministic order.
+
+mod executor;
+mod network;
+mod rng;
+mod time;
+mod watch;
+
+use std::future::Future;
+use std::time::Duration;
+
+pub use executor::JoinError;
+pub use executor::JoinHandle;
+pub use executor::Sim;
+pub use network::SimConnection;
+pub use network::SimNetwork;
+pub use network::SimNetworkFactory;
+use openraft::instant::Instant;
+use openraft::AsyncRuntime;
+use openraft::OptionalSend;
+use openraft::TokioRuntime;
+pub use rng::SimRng;
+pub use time::Elapsed;
+pub use time::SimInstant;
+pub use time::Sleep;
+pub use time::Timeout;
+pub use watch::SimWatch;
+pub use watch::SimWatchReceiver;
+pub use watch::SimWatchSender;
+
+use crate::executor::context;
+
+#[doc(hidden)]
+pub mod __private {
+    //! Re-exports used by [`impl_sim_network!`](crate::impl_sim_network).
+
+    pub use openraft::alias::VoteOf;
+    pub use openraft::error::RPCError;
+    pub use openraft::error::ReplicationClosed;
+    pub use openraft::error::StreamingError;
+    pub use openraft::network::v2::RaftNetworkV2;
+    pub use openraft::network::RPCOption;
+    pub use openraft::raft::AppendEntriesRequest;
+    pub use openraft::raft::AppendEntriesResponse;
+    pub use openraft::raft::SnapshotResponse;
+    pub use openraft::raft::TransferLeaderRequest;
+    pub use openraft::raft::VoteRequest;
+    pub use openraft::raft::VoteResponse;
+    pub use openraft::OptionalSend;
+    pub use openraft::Snapshot;
+}
+
+/// [`AsyncRuntime`] implementation that runs tasks in the [`Sim`] of the current thread.
+///
+/// All the methods panic if called outside [`Sim::block_on()`].
+#[derive(Debug, Default, PartialEq, Eq)]
+pub struct SimRuntime;
+
+impl AsyncRuntime for SimRuntime {
+    type JoinError = JoinError;
+    type JoinHandle<T: OptionalSend + 'static> = JoinHandle<T>;
+    type Sleep = Sleep;
+    type Instant = SimInstant;
+    type TimeoutError = Elapsed;
+    type Timeout<R, T: Future<Output = R> + OptionalSend> = Timeout<R, T>;
+    type ThreadLocalRng = SimRng;
+
+    #[inline]
+    fn spawn<T>(future: T) -> Self::JoinHandle<T::Output>
+    where
+        T: Future + OptionalSend + 'static,
+        T::Output: OptionalSend + 'static,
+    {
+        context::current().spawn(future)
+    }
+
+    #[inline]
+    fn sleep(duration: Duration) -> Self::Sleep {
+        Sleep::until(SimInstant::now() + duration)
+    }
+
+    #[inline]
+    fn sleep_until(deadline: Self::Instant) -> Self::Sleep {
+        Sleep::until(deadline)
+    }
+
+    #[inline]
+    fn timeout<R, F: Future<Output = R> + OptionalSend>(duration: Duration, future: F) -> Self::Timeout<R, F> {
+        Timeout::new(SimInstant::now() + duration, future)
+    }
+
+    #[inline]
+    fn timeout_at<R, F: Future<Output = R> + OptionalSend>(deadline: Self::Instant, future: F) -> Self::Timeout<R, F> {
+        Timeout::new(deadline, future)
+    }
+
+    #[inline]
+    fn is_panic(join_error: &Self::JoinError) -> bool {
+        matches!(join_error, JoinError::Panic(_))
+    }
+
+    #[inline]
+    fn thread_rng() -> Self::ThreadLocalRng {
+        SimRng
+    }
+
+    type Mpsc = <TokioRuntime as AsyncRuntime>::Mpsc;
+    type MpscUnbounded = <TokioRuntime as AsyncRuntime>::MpscUnbounded;
+    type Watch = SimWatch;
+    type Oneshot = <TokioRuntime as AsyncRuntime>::Oneshot;
+    type Mutex<T: OptionalSend + 'static> = <TokioRuntime as AsyncRuntime>::Mutex<T>;
+}
+
+#[cfg(test)]
+mod tests {
+    use std::time::Duration;
+
+    use openraft::testing::runtime::Suite;
+    use openraft::AsyncRuntime;
+
+    use super::*;
+
+    #[test]
+    fn test_sim_rt() {
+        let sim = Sim::new(0);
+        sim.block_on(Suite::<SimRuntime>::test_all());
+    }
+
+    #[test]
+    fn test_virtual_time_does_not_wait() {
+        let sim = Sim::new(0);
+        let start = std::time::Instant::now();
+
+        sim.block_on(async {
+            SimRuntime::sleep(Duration::from_secs(3600)).await;
+        });
+
+        assert_eq!(sim.elapsed(), Duration::from_secs(3600));
+        assert!(start.elapsed() < Duration::from_secs(60));
+    }
+
+    #[test]
+    fn test_same_seed_same_schedule() {
+        fn run(seed: u64) -> Vec<u64> {
+            let sim = Sim::new(seed);
+            sim.block_on(async {
+                let (tx, mut rx) = tokio::sync::mpsc::unbounded_channel();
+                for i in 0..20 {
+                    let tx = tx.clone();
+                    SimRuntime::spawn(async move {
+                        tx.send(i).unwrap();
+                    });
+                }
+                drop(tx);
+
+                let mut got = vec![];
+                while let Some(i) = rx.recv().await {
+                    got.push(i);
+                }
+                got
+            })
+        }
+
+        assert_eq!(run(1), run(1));
+        assert_ne!(run(1), run(2));
+    }
+
+    #[test]
+    fn test_watch_wakes_receivers_in_order() {
+        use openraft::async_runtime::watch::Watch;
+        use openraft::async_runtime::watch::WatchReceiver;
+        use openraft::async_runtime::watch::WatchSender;
+
+        fn run(seed: u64) -> Vec<u64> {
+            let sim = Sim::new(seed);
+            sim.block_on(async {
+                let (tx, rx) = SimWatch::channel(0u64);
+                let (tx_got, mut rx_got) = tokio::sync::mpsc::unbounded_channel();
+
+                for i in 0..10 {
+                    let mut rx = rx.clone();
+                    let tx_got = tx_got.clone();
+                    SimRuntime::spawn(async move {
+                        while rx.changed().await.is_ok() {
+                            tx_got.send(i).unwrap();
+                        }
+                    });
+                }
+                drop(tx_got);
+
+                SimRuntime::sleep(Duration::from_millis(1)).await;
+                tx.send(1).unwrap();
+                SimRuntime::sleep(Duration::from_millis(1)).await;
+                drop(tx);
+
+                let mut got = vec![];
+                while let Some(i) = rx_got.recv().await {
+                    got.push(i);
+                }
+                got
+            })
+        }
+
+        let got = run(1);
+        assert_eq!(10, got.len());
+        for _ in 0..10 {
+            assert_eq!(got, run(1));
+        }
+    }
+
+    #[test]
+    fn test_join_panicked_task() {
+        let sim = Sim::new(0);
+        sim.block_on(async {
+            let h = SimRuntime::spawn(async { panic!("foo") });
+            let err = h.await.unwrap_err();
+            assert!(SimRuntime::is_panic(&err));
+            assert_eq!("task panicked: foo", err.to_string());
+        });
+    }
+}
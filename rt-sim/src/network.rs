@@ -0,0 +1,352 @@
+//! An in-process `RaftNetworkV2` whose latency and faults are driven by the seeded RNG of the
+//! running [`Sim`](crate::Sim).
+
+use std::collections::BTreeMap;
+use std::collections::BTreeSet;
+use std::future::Future;
+use std::sync::Arc;
+use std::sync::Mutex;
+use std::time::Duration;
+
+use openraft::alias::VoteOf;
+use openraft::error::NetworkError;
+use openraft::error::RPCError;
+use openraft::error::ReplicationClosed;
+use openraft::error::StreamingError;
+use openraft::error::Unreachable;
+use openraft::network::RPCOption;
+use openraft::network::RaftNetworkFactory;
+use openraft::raft::AppendEntriesRequest;
+use openraft::raft::AppendEntriesResponse;
+use openraft::raft::SnapshotResponse;
+use openraft::raft::TransferLeaderRequest;
+use openraft::raft::VoteRequest;
+use openraft::raft::VoteResponse;
+use openraft::AnyError;
+use openraft::AsyncRuntime;
+use openraft::OptionalSend;
+use openraft::Raft;
+use openraft::RaftTypeConfig;
+use openraft::Snapshot;
+use rand::Rng;
+
+use crate::SimRuntime;
+
+/// A simulated network that connects [`Raft`] nodes running in the same [`Sim`](crate::Sim).
+///
+/// Every message, a request or a response, is delayed by a random latency in
+/// `[min_latency, max_latency]` and is lost with probability `loss_rate`. A lost message is
+/// reported to the sender as a [`NetworkError`]. Links can be cut to build network partitions;
+/// sending over a cut link returns [`Unreachable`].
+///
+/// All randomness is drawn from the simulation RNG, thus the same seed delivers the same
+/// messages in the same order.
+///
+/// Each node gets its own [`RaftNetworkFactory`] from [`SimNetwork::factory()`], so that the
+/// network knows the source of every RPC. [`impl_sim_network!`](crate::impl_sim_network) must be
+/// invoked once for the type config to make [`SimConnection`] a `RaftNetworkV2`.
+pub struct SimNetwork<C>
+where C: RaftTypeConfig<AsyncRuntime = SimRuntime>
+{
+    inner: Arc<Mutex<NetworkState<C>>>,
+}
+
+impl<C> Clone for SimNetwork<C>
+where C: RaftTypeConfig<AsyncRuntime = SimRuntime>
+{
+    fn clone(&self) -> Self {
+        Self {
+            inner: self.inner.clone(),
+        }
+    }
+}
+
+struct NetworkState<C>
+where C: RaftTypeConfig<AsyncRuntime = SimRuntime>
+{
+    nodes: BTreeMap<C::NodeId, Raft<C>>,
+
+    min_latency: Duration,
+    max_latency: Duration,
+
+    /// Probability in `[0, 1]` that a message is lost.
+    loss_rate: f64,
+
+    /// Directed links `(from, to)` that are cut.
+    cut: BTreeSet<(C::NodeId, C::NodeId)>,
+}
+
+impl<C> Default for SimNetwork<C>
+where C: RaftTypeConfig<AsyncRuntime = SimRuntime>
+{
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+impl<C> SimNetwork<C>
+where C: RaftTypeConfig<AsyncRuntime = SimRuntime>
+{
+    /// Create a network with 1-5 ms latency and no message loss.
+    pub fn new() -> Self {
+        Self {
+            inner: Arc::new(Mutex::new(NetworkState {
+                nodes: BTreeMap::new(),
+                min_latency: Duration::from_millis(1),
+                max_latency: Duration::from_millis(5),
+                loss_rate: 0.0,
+                cut: BTreeSet::new(),
+            })),
+        }
+    }
+
+    /// Set the range of the latency of every message.
+    pub fn set_latency(&self, min: Duration, max: Duration) {
+        assert!(
+            min <= max,
+            "min latency {:?} must not be greater than max {:?}",
+            min,
+            max
+        );
+
+        let mut inner = self.inner.lock().unwrap();
+        inner.min_latency = min;
+        inner.max_latency = max;
+    }
+
+    /// Set the probability in `[0, 1]` that a message is lost.
+    pub fn set_loss_rate(&self, rate: f64) {
+        assert!((0.0..=1.0).contains(&rate), "loss rate must be in [0, 1], got {}", rate);
+        self.inner.lock().unwrap().loss_rate = rate;
+    }
+
+    /// Register a [`Raft`] node so that it can receive RPCs.
+    pub fn add_node(&self, id: C::NodeId, raft: Raft<C>) {
+        self.inner.lock().unwrap().nodes.insert(id, raft);
+    }
+
+    /// Unregister a node, e.g., to simulate a crash. RPCs to it return [`Unreachable`].
+    pub fn remove_node(&self, id: &C::NodeId) -> Option<Raft<C>> {
+        self.inner.lock().unwrap().nodes.remove(id)
+    }
+
+    /// Build a [`RaftNetworkFactory`] for the node `source`.
+    pub fn factory(&self, source: C::NodeId) -> SimNetworkFactory<C> {
+        SimNetworkFactory {
+            source,
+            network: self.clone(),
+        }
+    }
+
+    /// Cut both directions of the link between `a` and `b`.
+    pub fn cut(&self, a: C::NodeId, b: C::NodeId) {
+        let mut inner = self.inner.lock().unwrap();
+        inner.cut.insert((a.clone(), b.clone()));
+        inner.cut.insert((b, a));
+    }
+
+    /// Cut every link between a node in `a` and a node in `b`.
+    pub fn partition(&self, a: &[C::NodeId], b: &[C::NodeId]) {
+        for x in a {
+            for y in b {
+                self.cut(x.clone(), y.clone());
+            }
+        }
+    }
+
+    /// Cut every link from and to `id`.
+    pub fn isolate(&self, id: C::NodeId) {
+        let others = {
+            let inner = self.inner.lock().unwrap();
+            inner.nodes.keys().filter(|x| **x != id).cloned().collect::<Vec<_>>()
+        };
+        self.partition(&[id], &others);
+    }
+
+    /// Restore every cut link.
+    pub fn heal(&self) {
+        self.inner.lock().unwrap().cut.clear();
+    }
+
+    /// Simulate sending one message from `from` to `to`.
+    ///
+    /// It waits for the message latency and then returns the target node, or an error if the
+    /// message is lost.
+    async fn transmit(&self, from: &C::NodeId, to: &C::NodeId) -> Result<Raft<C>, RPCError<C>> {
+        let latency = {
+            let inner = self.inner.lock().unwrap();
+            let mut rng = SimRuntime::thread_rng();
+
+            if inner.max_latency > inner.min_latency {
+                rng.random_range(inner.min_latency..=inner.max_latency)
+            } else {
+                inner.min_latency
+            }
+        };
+
+        SimRuntime::sleep(latency).await;
+
+        let inner = self.inner.lock().unwrap();
+
+        if inner.cut.contains(&(from.clone(), to.clone())) {
+            let msg = format!("link {} -> {} is cut", from, to);
+            return Err(Unreachable::new(&AnyError::error(msg)).into());
+        }
+
+        if inner.loss_rate > 0.0 && SimRuntime::thread_rng().random_bool(inner.loss_rate) {
+            let msg = format!("message {} -> {} is lost", from, to);
+            return Err(NetworkError::new(&AnyError::error(msg)).into());
+        }
+
+        let Some(raft) = inner.nodes.get(to) else {
+            let msg = format!("node {} is not in the network", to);
+            return Err(Unreachable::new(&AnyError::error(msg)).into());
+        };
+
+        Ok(raft.clone())
+    }
+}
+
+/// The [`RaftNetworkFactory`] of a single node in a [`SimNetwork`].
+pub struct SimNetworkFactory<C>
+where C: RaftTypeConfig<AsyncRuntime = SimRuntime>
+{
+    source: C::NodeId,
+    network: SimNetwork<C>,
+}
+
+impl<C> RaftNetworkFactory<C> for SimNetworkFactory<C>
+where
+    C: RaftTypeConfig<AsyncRuntime = SimRuntime>,
+    SimConnection<C>: openraft::network::v2::RaftNetworkV2<C>,
+{
+    type Network = SimConnection<C>;
+
+    async fn new_client(&mut self, target: C::NodeId, _node: &C::Node) -> Self::Network {
+        SimConnection {
+            source: self.source.clone(),
+            target,
+            network: self.network.clone(),
+        }
+    }
+}
+
+/// A connection from one node to another in a [`SimNetwork`].
+pub struct SimConnection<C>
+where C: RaftTypeConfig<AsyncRuntime = SimRuntime>
+{
+    source: C::NodeId,
+    target: C::NodeId,
+    network: SimNetwork<C>,
+}
+
+impl<C> SimConnection<C>
+where C: RaftTypeConfig<AsyncRuntime = SimRuntime>
+{
+    /// Deliver a request to the target, call it, and deliver the response back.
+    async fn call<Fu, Resp, E>(&self, f: impl FnOnce(Raft<C>) -> Fu) -> Result<Resp, RPCError<C>>
+    where
+        Fu: Future<Output = Result<Resp, E>>,
+        E: std::error::Error + 'static,
+    {
+        let raft = self.network.transmit(&self.source, &self.target).await?;
+
+        let resp = f(raft).await.map_err(|e| Unreachable::new(&e))?;
+
+        // The response may be lost after the request has been handled by the target.
+        self.network.transmit(&self.target, &self.source).await?;
+
+        Ok(resp)
+    }
+
+    /// Send an AppendEntries RPC through the simulated network.
+    pub async fn send_append_entries(
+        &mut self,
+        rpc: AppendEntriesRequest<C>,
+        _option: RPCOption,
+    ) -> Result<AppendEntriesResponse<C>, RPCError<C>> {
+        self.call(|raft| async move { raft.append_entries(rpc).await }).await
+    }
+
+    /// Send a RequestVote RPC through the simulated network.
+    pub async fn send_vote(&mut self, rpc: VoteRequest<C>, _option: RPCOption) -> Result<VoteResponse<C>, RPCError<C>> {
+        self.call(|raft| async move { raft.vote(rpc).await }).await
+    }
+
+    /// Send a complete snapshot through the simulated network.
+    ///
+    /// The snapshot data is moved to the target as is, without serialization.
+    pub async fn send_full_snapshot(
+        &mut self,
+        vote: VoteOf<C>,
+        snapshot: Snapshot<C>,
+        _cancel: impl Future<Output = ReplicationClosed> + OptionalSend + 'static,
+        _option: RPCOption,
+    ) -> Result<SnapshotResponse<C>, StreamingError<C>> {
+        let resp = self.call(|raft| async move { raft.install_full_snapshot(vote, snapshot).await }).await?;
+        Ok(resp)
+    }
+
+    /// Send a TransferLeader message through the simulated network.
+    pub async fn send_transfer_leader(
+        &mut self,
+        req: TransferLeaderRequest<C>,
+        _option: RPCOption,
+    ) -> Result<(), RPCError<C>> {
+        self.call(|raft| async move { raft.handle_transfer_leader(req).await }).await
+    }
+}
+
+/// Implement `RaftNetworkV2` for [`SimConnection`] of a type config.
+///
+/// A generic `impl<C> RaftNetworkV2<C> for SimConnection<C>` conflicts with the blanket impl that
+/// adapts a v1 [`RaftNetwork`](openraft::RaftNetwork) to `RaftNetworkV2` when the `tokio-rt`
+/// feature of Openraft is enabled. Thus the impl has to be generated in the crate that defines the
+/// type config:
+///
+/// ```ignore
+/// openraft::declare_raft_types!(pub TypeConfig: AsyncRuntime = openraft_rt_sim::SimRuntime);
+/// openraft_rt_sim::impl_sim_network!(TypeConfig);
+/// ```
+#[macro_export]
+macro_rules! impl_sim_network {
+    ($C:ty) => {
+        impl $crate::__private::RaftNetworkV2<$C> for $crate::SimConnection<$C> {
+            async fn append_entries(
+                &mut self,
+                rpc: $crate::__private::AppendEntriesRequest<$C>,
+                option: $crate::__private::RPCOption,
+            ) -> Result<$crate::__private::AppendEntriesResponse<$C>, $crate::__private::RPCError<$C>> {
+                self.send_append_entries(rpc, option).await
+            }
+
+            async fn vote(
+                &mut self,
+                rpc: $crate::__private::VoteRequest<$C>,
+                option: $crate::__private::RPCOption,
+            ) -> Result<$crate::__private::VoteResponse<$C>, $crate::__private::RPCError<$C>> {
+                self.send_vote(rpc, option).await
+            }
+
+            async fn full_snapshot(
+                &mut self,
+                vote: $crate::__private::VoteOf<$C>,
+                snapshot: $crate::__private::Snapshot<$C>,
+                cancel: impl std::future::Future<Output = $crate::__private::ReplicationClosed>
+                    + $crate::__private::OptionalSend
+                    + 'static,
+                option: $crate::__private::RPCOption,
+            ) -> Result<$crate::__private::SnapshotResponse<$C>, $crate::__private::StreamingError<$C>> {
+                self.send_full_snapshot(vote, snapshot, cancel, option).await
+            }
+
+            async fn transfer_leader(
+                &mut self,
+                req: $crate::__private::TransferLeaderRequest<$C>,
+                option: $crate::__private::RPCOption,
+            ) -> Result<(), $crate::__private::RPCError<$C>> {
+                self.send_transfer_leader(req, option).await
+            }
+        }
+    };
+}
@@ -0,0 +1,28 @@
+//! A random number generator that draws from the seeded RNG of the running [`Sim`].
+//!
+//! [`Sim`]: crate::Sim
+
+use rand::RngCore;
+
+use crate::executor::context;
+
+/// Handle to the seeded RNG of the [`Sim`](crate::Sim) running on the current thread.
+///
+/// Every random decision made by Openraft, such as the election timeout, is drawn from it, so that
+/// a simulation is reproducible from its seed.
+#[derive(Debug, Clone, Copy, Default)]
+pub struct SimRng;
+
+impl RngCore for SimRng {
+    fn next_u32(&mut self) -> u32 {
+        context::current().with_rng(|r| r.next_u32())
+    }
+
+    fn next_u64(&mut self) -> u64 {
+        context::current().with_rng(|r| r.next_u64())
+    }
+
+    fn fill_bytes(&mut self, dst: &mut [u8]) {
+        context::current().with_rng(|r| r.fill_bytes(dst))
+    }
+}
@@ -0,0 +1,177 @@
+//! Virtual time: [`SimInstant`], [`Sleep`] and [`Timeout`].
+
+use std::fmt;
+use std::future::Future;
+use std::marker::PhantomData;
+use std::ops::Add;
+use std::ops::AddAssign;
+use std::ops::Sub;
+use std::ops::SubAssign;
+use std::pin::Pin;
+use std::task::Context;
+use std::task::Poll;
+use std::time::Duration;
+
+use openraft::instant;
+
+use crate::executor::context;
+use crate::executor::Handle;
+
+/// An instant on the virtual clock of a [`Sim`](crate::Sim).
+///
+/// It is the virtual time elapsed since the simulation is created.
+#[derive(Clone, Copy, Debug, Eq, PartialEq, Ord, PartialOrd, Hash)]
+pub struct SimInstant(Duration);
+
+impl SimInstant {
+    pub(crate) fn from_elapsed(d: Duration) -> Self {
+        Self(d)
+    }
+
+    /// The virtual time elapsed since the simulation is created until this instant.
+    pub fn since_start(&self) -> Duration {
+        self.0
+    }
+}
+
+impl fmt::Display for SimInstant {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "T+{:?}", self.0)
+    }
+}
+
+impl Add<Duration> for SimInstant {
+    type Output = Self;
+
+    #[inline]
+    fn add(self, rhs: Duration) -> Self::Output {
+        Self(self.0 + rhs)
+    }
+}
+
+impl AddAssign<Duration> for SimInstant {
+    #[inline]
+    fn add_assign(&mut self, rhs: Duration) {
+        self.0 += rhs
+    }
+}
+
+impl Sub<Duration> for SimInstant {
+    type Output = Self;
+
+    #[inline]
+    fn sub(self, rhs: Duration) -> Self::Output {
+        Self(self.0.saturating_sub(rhs))
+    }
+}
+
+impl Sub<Self> for SimInstant {
+    type Output = Duration;
+
+    #[inline]
+    fn sub(self, rhs: Self) -> Self::Output {
+        self.0.saturating_sub(rhs.0)
+    }
+}
+
+impl SubAssign<Duration> for SimInstant {
+    #[inline]
+    fn sub_assign(&mut self, rhs: Duration) {
+        self.0 = self.0.saturating_sub(rhs)
+    }
+}
+
+impl instant::Instant for SimInstant {
+    #[inline]
+    fn now() -> Self {
+        Self(context::current().now())
+    }
+}
+
+/// Future returned by [`SimRuntime::sleep()`](crate::SimRuntime).
+pub struct Sleep {
+    handle: Handle,
+    deadline: Duration,
+
+    /// The key of the registered timer, if any.
+    key: Option<(Duration, u64)>,
+}
+
+impl Sleep {
+    pub(crate) fn until(deadline: SimInstant) -> Self {
+        Self {
+            handle: context::current(),
+            deadline: deadline.0,
+            key: None,
+        }
+    }
+}
+
+impl Future for Sleep {
+    type Output = ();
+
+    fn poll(mut self: Pin<&mut Self>, cx: &mut Context<'_>) -> Poll<Self::Output> {
+        let key = self.handle.register_timer(self.deadline, self.key, cx.waker());
+        self.key = key;
+
+        if key.is_none() {
+            Poll::Ready(())
+        } else {
+            Poll::Pending
+        }
+    }
+}
+
+impl Drop for Sleep {
+    fn drop(&mut self) {
+        if let Some(key) = self.key.take() {
+            self.handle.cancel_timer(key);
+        }
+    }
+}
+
+/// Error returned by [`Timeout`] when the deadline is reached before the future completes.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct Elapsed;
+
+impl fmt::Display for Elapsed {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "deadline has elapsed")
+    }
+}
+
+impl std::error::Error for Elapsed {}
+
+/// Future returned by [`SimRuntime::timeout()`](crate::SimRuntime).
+pub struct Timeout<R, T> {
+    future: Pin<Box<T>>,
+    sleep: Sleep,
+    _p: PhantomData<fn() -> R>,
+}
+
+impl<R, T> Timeout<R, T> {
+    pub(crate) fn new(deadline: SimInstant, future: T) -> Self {
+        Self {
+            future: Box::pin(future),
+            sleep: Sleep::until(deadline),
+            _p: PhantomData,
+        }
+    }
+}
+
+impl<R, T> Future for Timeout<R, T>
+where T: Future<Output = R>
+{
+    type Output = Result<R, Elapsed>;
+
+    fn poll(mut self: Pin<&mut Self>, cx: &mut Context<'_>) -> Poll<Self::Output> {
+        if let Poll::Ready(r) = self.future.as_mut().poll(cx) {
+            return Poll::Ready(Ok(r));
+        }
+
+        match Pin::new(&mut self.sleep).poll(cx) {
+            Poll::Ready(()) => Poll::Ready(Err(Elapsed)),
+            Poll::Pending => Poll::Pending,
+        }
+    }
+}
@@ -0,0 +1,180 @@
+//! A watch channel that wakes its receivers in a deterministic order: [`SimWatch`].
+//!
+//! Tokio's watch channel spreads the waiting receivers over several notifiers picked by a thread
+//! local RNG that is not seeded by the [`Sim`](crate::Sim), so the order in which they are woken
+//! by a `send()` differs from run to run. Here the receivers are woken in the order they started
+//! waiting.
+
+use std::future::poll_fn;
+use std::sync::Arc;
+use std::sync::Mutex;
+use std::sync::MutexGuard;
+use std::sync::RwLock;
+use std::sync::RwLockReadGuard;
+use std::task::Poll;
+use std::task::Waker;
+
+use openraft::async_runtime::watch;
+use openraft::OptionalSend;
+use openraft::OptionalSync;
+
+/// [`Watch`](watch::Watch) implementation of [`SimRuntime`](crate::SimRuntime).
+pub struct SimWatch;
+
+impl watch::Watch for SimWatch {
+    type Sender<T: OptionalSend + OptionalSync> = SimWatchSender<T>;
+    type Receiver<T: OptionalSend + OptionalSync> = SimWatchReceiver<T>;
+
+    type Ref<'a, T: OptionalSend + 'a> = RwLockReadGuard<'a, T>;
+
+    fn channel<T: OptionalSend + OptionalSync>(init: T) -> (Self::Sender<T>, Self::Receiver<T>) {
+        let shared = Arc::new(Shared {
+            value: RwLock::new(init),
+            state: Mutex::new(State {
+                version: 0,
+                closed: false,
+                receivers: 1,
+                waiters: vec![],
+            }),
+        });
+
+        let tx = SimWatchSender { shared: shared.clone() };
+        let rx = SimWatchReceiver { shared, seen: 0 };
+        (tx, rx)
+    }
+}
+
+struct Shared<T> {
+    value: RwLock<T>,
+    state: Mutex<State>,
+}
+
+struct State {
+    /// Incremented every time a new value is sent.
+    version: u64,
+
+    /// Whether the sender is dropped.
+    closed: bool,
+
+    /// The number of receivers alive.
+    receivers: usize,
+
+    /// Receivers waiting for a change, in the order they started waiting.
+    waiters: Vec<Waker>,
+}
+
+impl<T> Shared<T> {
+    fn state(&self) -> MutexGuard<'_, State> {
+        self.state.lock().unwrap()
+    }
+
+    /// Wake up every waiting receiver, optionally bumping the version first.
+    fn notify(&self, f: impl FnOnce(&mut State)) {
+        let waiters = {
+            let mut state = self.state();
+            f(&mut state);
+            std::mem::take(&mut state.waiters)
+        };
+
+        for w in waiters {
+            w.wake();
+        }
+    }
+}
+
+/// The sending half of a [`SimWatch`] channel.
+pub struct SimWatchSender<T> {
+    shared: Arc<Shared<T>>,
+}
+
+impl<T> Drop for SimWatchSender<T> {
+    fn drop(&mut self) {
+        self.shared.notify(|state| state.closed = true);
+    }
+}
+
+impl<T> watch::WatchSender<SimWatch, T> for SimWatchSender<T>
+where T: OptionalSend + OptionalSync
+{
+    fn send(&self, value: T) -> Result<(), watch::SendError<T>> {
+        if self.shared.state().receivers == 0 {
+            return Err(watch::SendError(value));
+        }
+
+        self.send_if_modified(|v| {
+            *v = value;
+            true
+        });
+        Ok(())
+    }
+
+    fn send_if_modified<F>(&self, modify: F) -> bool
+    where F: FnOnce(&mut T) -> bool {
+        let modified = {
+            let mut v = self.shared.value.write().unwrap();
+            modify(&mut v)
+        };
+
+        if modified {
+            self.shared.notify(|state| state.version += 1);
+        }
+        modified
+    }
+
+    fn borrow_watched(&self) -> RwLockReadGuard<'_, T> {
+        self.shared.value.read().unwrap()
+    }
+}
+
+/// The receiving half of a [`SimWatch`] channel.
+pub struct SimWatchReceiver<T> {
+    shared: Arc<Shared<T>>,
+
+    /// The last version this receiver has seen.
+    seen: u64,
+}
+
+impl<T> Clone for SimWatchReceiver<T> {
+    fn clone(&self) -> Self {
+        self.shared.state().receivers += 1;
+        Self {
+            shared: self.shared.clone(),
+            seen: self.seen,
+        }
+    }
+}
+
+impl<T> Drop for SimWatchReceiver<T> {
+    fn drop(&mut self) {
+        self.shared.state().receivers -= 1;
+    }
+}
+
+impl<T> watch::WatchReceiver<SimWatch, T> for SimWatchReceiver<T>
+where T: OptionalSend + OptionalSync
+{
+    async fn changed(&mut self) -> Result<(), watch::RecvError> {
+        poll_fn(|cx| {
+            let mut state = self.shared.state();
+
+            if state.version != self.seen {
+                self.seen = state.version;
+                return Poll::Ready(Ok(()));
+            }
+
+            if state.closed {
+                return Poll::Ready(Err(watch::RecvError(())));
+            }
+
+            if !state.waiters.iter().any(|w| w.will_wake(cx.waker())) {
+                state.waiters.push(cx.waker().clone());
+            }
+            Poll::Pending
+        })
+        .await
+    }
+
+    fn borrow_watched(&self) -> RwLockReadGuard<'_, T> {
+        self.shared.value.read().unwrap()
+    }
+}
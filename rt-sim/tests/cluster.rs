@@ -0,0 +1,303 @@
+//! Run a three node cluster in a [`Sim`] under randomized network faults.
+
+use std::collections::BTreeMap;
+use std::sync::atomic::AtomicBool;
+use std::sync::atomic::Ordering;
+use std::sync::Arc;
+use std::sync::Mutex;
+use std::time::Duration;
+
+use memstore::LogStore;
+use openraft::alias::LogIdOf;
+use openraft::async_runtime::watch::WatchReceiver;
+use openraft::entry::RaftEntry;
+use openraft::storage::RaftStateMachine;
+use openraft::storage::Snapshot;
+use openraft::AsyncRuntime;
+use openraft::BasicNode;
+use openraft::Config;
+use openraft::EntryPayload;
+use openraft::OptionalSend;
+use openraft::Raft;
+use openraft::RaftSnapshotBuilder;
+use openraft::SnapshotMeta;
+use openraft::SnapshotPolicy;
+use openraft::StorageError;
+use openraft::StoredMembership;
+use openraft_rt_sim::Sim;
+use openraft_rt_sim::SimNetwork;
+use openraft_rt_sim::SimRuntime;
+use rand::Rng;
+
+openraft::declare_raft_types!(
+    pub TypeConfig:
+        D = u64,
+        R = (),
+        SnapshotData = Vec<u64>,
+        AsyncRuntime = SimRuntime,
+);
+
+openraft_rt_sim::impl_sim_network!(TypeConfig);
+
+/// A state machine that records every applied value in order.
+#[derive(Clone, Default)]
+struct StateMachine {
+    inner: Arc<Mutex<SmInner>>,
+}
+
+#[derive(Default)]
+struct SmInner {
+    last_applied: Option<LogIdOf<TypeConfig>>,
+    last_membership: StoredMembership<TypeConfig>,
+    values: Vec<u64>,
+    snapshot: Option<(SnapshotMeta<TypeConfig>, Vec<u64>)>,
+    snapshot_idx: u64,
+}
+
+impl StateMachine {
+    fn values(&self) -> Vec<u64> {
+        self.inner.lock().unwrap().values.clone()
+    }
+}
+
+impl RaftSnapshotBuilder<TypeConfig> for StateMachine {
+    async fn build_snapshot(&mut self) -> Result<Snapshot<TypeConfig>, StorageError<TypeConfig>> {
+        let mut sm = self.inner.lock().unwrap();
+        sm.snapshot_idx += 1;
+
+        let meta = SnapshotMeta {
+            last_log_id: sm.last_applied,
+            last_membership: sm.last_membership.clone(),
+            snapshot_id: format!("{}", sm.snapshot_idx),
+        };
+        let data = sm.values.clone();
+        sm.snapshot = Some((meta.clone(), data.clone()));
+
+        Ok(Snapshot {
+            meta,
+            snapshot: Box::new(data),
+        })
+    }
+}
+
+impl RaftStateMachine<TypeConfig> for StateMachine {
+    type SnapshotBuilder = Self;
+
+    async fn applied_state(
+        &mut self,
+    ) -> Result<(Option<LogIdOf<TypeConfig>>, StoredMembership<TypeConfig>), StorageError<TypeConfig>> {
+        let sm = self.inner.lock().unwrap();
+        Ok((sm.last_applied, sm.last_membership.clone()))
+    }
+
+    async fn apply<I>(&mut self, entries: I) -> Result<Vec<()>, StorageError<TypeConfig>>
+    where
+        I: IntoIterator<Item = openraft::Entry<TypeConfig>> + OptionalSend,
+        I::IntoIter: OptionalSend,
+    {
+        let mut sm = self.inner.lock().unwrap();
+        let mut res = vec![];
+
+        for entry in entries {
+            sm.last_applied = Some(entry.log_id());
+            match entry.payload {
+                EntryPayload::Blank => {}
+                EntryPayload::Normal(v) => sm.values.push(v),
+                EntryPayload::Membership(m) => sm.last_membership = StoredMembership::new(Some(entry.log_id), m),
+            }
+            res.push(());
+        }
+        Ok(res)
+    }
+
+    async fn get_snapshot_builder(&mut self) -> Self::SnapshotBuilder {
+        self.clone()
+    }
+
+    async fn begin_receiving_snapshot(&mut self) -> Result<Box<Vec<u64>>, StorageError<TypeConfig>> {
+        Ok(Box::default())
+    }
+
+    async fn install_snapshot(
+        &mut self,
+        meta: &SnapshotMeta<TypeConfig>,
+        snapshot: Box<Vec<u64>>,
+    ) -> Result<(), StorageError<TypeConfig>> {
+        let mut sm = self.inner.lock().unwrap();
+        sm.last_applied = meta.last_log_id;
+        sm.last_membership = meta.last_membership.clone();
+        sm.values = (*snapshot).clone();
+        sm.snapshot = Some((meta.clone(), *snapshot));
+        Ok(())
+    }
+
+    async fn get_current_snapshot(&mut self) -> Result<Option<Snapshot<TypeConfig>>, StorageError<TypeConfig>> {
+        let sm = self.inner.lock().unwrap();
+        Ok(sm.snapshot.clone().map(|(meta, data)| Snapshot {
+            meta,
+            snapshot: Box::new(data),
+        }))
+    }
+}
+
+/// The observable outcome of a simulation, used to check it is reproducible.
+#[derive(Debug, PartialEq, Eq)]
+struct Outcome {
+    elapsed: Duration,
+    polls: u64,
+    last_log_id: Option<LogIdOf<TypeConfig>>,
+    values: Vec<u64>,
+}
+
+/// Build a 3 node cluster, write `n` values while a nemesis randomly partitions nodes and drops
+/// messages, then heal the network and check every node converges to the same state.
+fn run_scenario(seed: u64, n: u64) -> Outcome {
+    let sim = Sim::new(seed);
+
+    let (last_log_id, values) = sim.block_on(async move {
+        let config = Arc::new(
+            Config {
+                heartbeat_interval: 50,
+                election_timeout_min: 150,
+                election_timeout_max: 300,
+                snapshot_policy: SnapshotPolicy::LogsSinceLast(10),
+                max_in_snapshot_log_to_keep: 5,
+                purge_batch_size: 1,
+                ..Default::default()
+            }
+            .validate()
+            .unwrap(),
+        );
+
+        let network = SimNetwork::<TypeConfig>::new();
+        let mut nodes = BTreeMap::new();
+
+        for id in 0..3u64 {
+            let sm = StateMachine::default();
+            let raft = Raft::new(
+                id,
+                config.clone(),
+                network.factory(id),
+                LogStore::<TypeConfig>::default(),
+                sm.clone(),
+            )
+            .await
+            .unwrap();
+            network.add_node(id, raft.clone());
+            nodes.insert(id, (raft, sm));
+        }
+
+        nodes[&0]
+            .0
+            .initialize(nodes.keys().map(|id| (*id, BasicNode::default())).collect::<BTreeMap<_, _>>())
+            .await
+            .unwrap();
+
+        // The nemesis keeps breaking the network until the writes are done.
+        let stop = Arc::new(AtomicBool::new(false));
+        let nemesis = {
+            let network = network.clone();
+            let stop = stop.clone();
+            SimRuntime::spawn(async move {
+                while !stop.load(Ordering::Relaxed) {
+                    let (action, node, pause) = {
+                        let mut rng = SimRuntime::thread_rng();
+                        (
+                            rng.random_range(0..4),
+                            rng.random_range(0..3u64),
+                            rng.random_range(100..1_000),
+                        )
+                    };
+
+                    match action {
+                        0 => network.isolate(node),
+                        1 => network.set_loss_rate(0.2),
+                        _ => {
+                            network.heal();
+                            network.set_loss_rate(0.0);
+                        }
+                    }
+
+                    SimRuntime::sleep(Duration::from_millis(pause)).await;
+                }
+
+                network.heal();
+                network.set_loss_rate(0.0);
+            })
+        };
+
+        let mut written = 0;
+        let mut target = 0;
+        while written < n {
+            let res = nodes[&target].0.client_write(written).await;
+            match res {
+                Ok(_) => written += 1,
+                Err(e) => {
+                    if let Some(fwd) = e.forward_to_leader() {
+                        if let Some(leader) = fwd.leader_id {
+                            target = leader;
+                            continue;
+                        }
+                    }
+                    target = SimRuntime::thread_rng().random_range(0..3u64);
+                    SimRuntime::sleep(Duration::from_millis(100)).await;
+                }
+            }
+        }
+
+        stop.store(true, Ordering::Relaxed);
+        nemesis.await.unwrap();
+
+        let leader_log = nodes[&target].0.metrics().borrow_watched().last_log_index;
+
+        for (raft, _sm) in nodes.values() {
+            raft.wait(Some(Duration::from_secs(10)))
+                .applied_index_at_least(leader_log, "converge")
+                .await
+                .unwrap();
+        }
+
+        let last_log_id = nodes[&target].0.metrics().borrow_watched().last_applied;
+
+        let mut outputs = vec![];
+        for (raft, sm) in nodes.values() {
+            outputs.push(sm.values());
+            raft.shutdown().await.unwrap();
+        }
+
+        for v in outputs.iter() {
+            assert_eq!(&outputs[0], v, "seed={}: every node must apply the same values", seed);
+        }
+
+        (last_log_id, outputs[0].clone())
+    });
+
+    Outcome {
+        elapsed: sim.elapsed(),
+        polls: sim.polls(),
+        last_log_id,
+        values,
+    }
+}
+
+#[test]
+fn test_cluster_converges_under_faults() {
+    for seed in 0..20 {
+        let outcome = run_scenario(seed, 30);
+
+        // Every write is acknowledged at least once; retried writes may be applied twice.
+        for v in 0..30 {
+            assert!(outcome.values.contains(&v), "seed={}: value {} is lost", seed, v);
+        }
+    }
+}
+
+#[test]
+fn test_same_seed_reproduces_run() {
+    let a = run_scenario(7, 20);
+    let b = run_scenario(7, 20);
+    assert_eq!(a, b);
+
+    let c = run_scenario(8, 20);
+    assert_ne!(a, c);
+}
@@ -0,0 +1,65 @@
+use std::fs::File;
+use std::io;
+use std::path::PathBuf;
+use std::sync::mpsc;
+use std::sync::Arc;
+use std::thread;
+
+use openraft::storage::IOFlushed;
+use openraft::RaftTypeConfig;
+
+use crate::fs::Fs;
+use crate::fs::FsOp;
+
+/// A request to call `callback` once `file` is synced to disk.
+pub(crate) struct FlushRequest<C>
+where C: RaftTypeConfig
+{
+    pub(crate) file: Arc<File>,
+    pub(crate) path: PathBuf,
+    pub(crate) callback: IOFlushed<C>,
+}
+
+/// Spawn a thread syncing appended entries to disk.
+///
+/// Requests queued while a sync is in progress are served by the next single sync, so that
+/// concurrent appends share one `fsync`. The thread quits when the sender is dropped.
+pub(crate) fn spawn<C>(fs: Fs) -> Result<mpsc::Sender<FlushRequest<C>>, io::Error>
+where C: RaftTypeConfig {
+    let (tx, rx) = mpsc::channel::<FlushRequest<C>>();
+
+    thread::Builder::new().name("openraft-filestore-flusher".to_string()).spawn(move || {
+        while let Ok(first) = rx.recv() {
+            let mut batch = vec![first];
+            batch.extend(rx.try_iter());
+
+            flush(&fs, batch);
+        }
+    })?;
+
+    Ok(tx)
+}
+
+fn flush<C>(fs: &Fs, batch: Vec<FlushRequest<C>>)
+where C: RaftTypeConfig {
+    tracing::debug!("flush {} appends", batch.len());
+
+    // Consecutive requests on the same segment share one sync.
+    let mut synced: Option<(Arc<File>, Result<(), io::ErrorKind>)> = None;
+
+    for req in batch {
+        let res = match &synced {
+            Some((file, res)) if Arc::ptr_eq(file, &req.file) => *res,
+            _ => {
+                let res = fs.run(FsOp::Sync(&req.path), || req.file.sync_data()).map_err(|e| {
+                    tracing::error!("failed to sync segment: {}", e);
+                    e.kind()
+                });
+                synced = Some((req.file.clone(), res));
+                res
+            }
+        };
+
+        req.callback.io_completed(res.map_err(|kind| io::Error::new(kind, "failed to sync segment")));
+    }
+}
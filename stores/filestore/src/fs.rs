@@ -0,0 +1,93 @@
+//! The changes the store makes to its files, done through [`Fs`].
+//!
+//! Tests install an [`FsHook`] to see every change and to inject crashes between them.
+
+use std::fmt;
+use std::fmt::Debug;
+use std::io;
+use std::path::Path;
+use std::sync::Arc;
+
+/// A change of a file or of the directory of the store.
+#[derive(Debug, Clone, Copy)]
+pub(crate) enum FsOp<'a> {
+    /// Create an empty file.
+    Create(&'a Path),
+
+    /// Append bytes to a file.
+    Write(&'a Path),
+
+    /// Cut a file to a size.
+    SetLen(&'a Path),
+
+    /// Sync the content of a file to disk.
+    Sync(&'a Path),
+
+    /// Remove a file.
+    Remove(&'a Path),
+
+    /// Rename a file, replacing the target.
+    Rename { from: &'a Path, to: &'a Path },
+
+    /// Sync the entries of a directory, e.g., created, removed or renamed files, to disk.
+    SyncDir(&'a Path),
+}
+
+impl fmt::Display for FsOp<'_> {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            FsOp::Create(path) => write!(f, "create {}", path.display()),
+            FsOp::Write(path) => write!(f, "write {}", path.display()),
+            FsOp::SetLen(path) => write!(f, "set length of {}", path.display()),
+            FsOp::Sync(path) => write!(f, "sync {}", path.display()),
+            FsOp::Remove(path) => write!(f, "remove {}", path.display()),
+            FsOp::Rename { from, to } => write!(f, "rename {} to {}", from.display(), to.display()),
+            FsOp::SyncDir(path) => write!(f, "sync directory {}", path.display()),
+        }
+    }
+}
+
+/// Sees every change made by a store to its files.
+pub(crate) trait FsHook: Debug + Send + Sync {
+    /// Make the change `op` by calling `f`.
+    ///
+    /// It may fail `op` without calling `f`.
+    fn run(&self, op: FsOp<'_>, f: &mut dyn FnMut() -> Result<(), io::Error>) -> Result<(), io::Error>;
+}
+
+/// Makes changes to the files of a store, through an [`FsHook`] if there is one.
+#[derive(Debug, Clone, Default)]
+pub(crate) struct Fs {
+    hook: Option<Arc<dyn FsHook>>,
+}
+
+impl Fs {
+    #[cfg(test)]
+    pub(crate) fn with_hook(hook: Arc<dyn FsHook>) -> Self {
+        Self { hook: Some(hook) }
+    }
+
+    /// Make the change `op` by calling `f`.
+    ///
+    /// A returned error tells the failed change.
+    pub(crate) fn run<T>(&self, op: FsOp<'_>, f: impl FnOnce() -> Result<T, io::Error>) -> Result<T, io::Error> {
+        self.run_with_hook(op, f).map_err(|e| io::Error::new(e.kind(), format!("failed to {}: {}", op, e)))
+    }
+
+    fn run_with_hook<T>(&self, op: FsOp<'_>, f: impl FnOnce() -> Result<T, io::Error>) -> Result<T, io::Error> {
+        let Some(hook) = &self.hook else {
+            return f();
+        };
+
+        let mut f = Some(f);
+        let mut output = None;
+
+        hook.run(op, &mut || {
+            let f = f.take().expect("an op is run only once");
+            output = Some(f()?);
+            Ok(())
+        })?;
+
+        Ok(output.expect("the op is run"))
+    }
+}
@@ -0,0 +1,38 @@
+//! A durable [`RaftLogStorage`] implementation that stores logs in files.
+//!
+//! The logs are stored in an append-only write-ahead log in a directory, split into segment files
+//! named by an increasing id, e.g., `00000000000000000003.wal`:
+//!
+//! - Every record in a segment carries a crc32 checksum. When the store is opened, a partially
+//!   written record at the end of the last segment, left by a crash, is cut off.
+//! - [`append()`] returns once the entries are written to the active segment. A background thread
+//!   syncs the segment and calls the [`IOFlushed`] callbacks: appends queued during one `fsync`
+//!   share the next one.
+//! - [`purge()`] removes the segments that contain only purged entries. [`truncate()`] removes the
+//!   segments after the first removed entry and cuts the one that contains it.
+//! - The vote and the last purged log id are atomically replaced in `meta.json`. The committed log
+//!   id is stored as a record in the log, and is repeated at the start of every segment.
+//!
+//! The entries are read from the files on demand, only their locations are kept in memory.
+//!
+//! [`RaftLogStorage`]: openraft::storage::RaftLogStorage
+//! [`IOFlushed`]: openraft::storage::IOFlushed
+//! [`append()`]: openraft::storage::RaftLogStorage::append
+//! [`purge()`]: openraft::storage::RaftLogStorage::purge
+//! [`truncate()`]: openraft::storage::RaftLogStorage::truncate
+#![deny(unused_crate_dependencies)]
+#![deny(unused_qualifications)]
+
+mod flusher;
+mod fs;
+mod log_store;
+mod record;
+mod segment;
+
+#[cfg(test)]
+mod sim_fs;
+#[cfg(test)]
+mod test;
+
+pub use log_store::FileLogConfig;
+pub use log_store::FileLogStore;
@@ -0,0 +1,472 @@
+use std::collections::BTreeMap;
+use std::fmt::Debug;
+use std::fs;
+use std::io;
+use std::io::Write;
+use std::ops::RangeBounds;
+use std::path::Path;
+use std::path::PathBuf;
+use std::sync::mpsc;
+use std::sync::Arc;
+use std::sync::Mutex;
+
+use openraft::alias::LogIdOf;
+use openraft::alias::VoteOf;
+use openraft::entry::RaftEntry;
+use openraft::storage::IOFlushed;
+use openraft::storage::RaftLogStorage;
+use openraft::LogState;
+use openraft::OptionalSend;
+use openraft::RaftLogReader;
+use openraft::RaftTypeConfig;
+use openraft::StorageError;
+use serde::Deserialize;
+use serde::Serialize;
+
+use crate::flusher;
+use crate::flusher::FlushRequest;
+use crate::fs::Fs;
+use crate::fs::FsOp;
+use crate::record::decode_header;
+use crate::record::decode_next;
+use crate::record::Record;
+use crate::record::HEADER_SIZE;
+use crate::segment;
+use crate::segment::Segment;
+
+const META_FILE: &str = "meta.json";
+
+/// Config of a [`FileLogStore`].
+#[derive(Debug, Clone)]
+pub struct FileLogConfig {
+    /// A new segment is started when the active one reaches this size in bytes.
+    ///
+    /// A segment can be purged only when all of its entries are purged.
+    pub segment_size: u64,
+}
+
+impl Default for FileLogConfig {
+    fn default() -> Self {
+        Self {
+            segment_size: 64 * 1024 * 1024,
+        }
+    }
+}
+
+/// A [`RaftLogStorage`] that stores logs in a segmented write-ahead log in a directory.
+///
+/// See the [crate docs](crate) for the layout on disk.
+///
+/// It is cheap to clone: clones share the same underlying store.
+#[derive(Debug)]
+pub struct FileLogStore<C>
+where C: RaftTypeConfig
+{
+    inner: Arc<Mutex<Inner<C>>>,
+}
+
+impl<C> Clone for FileLogStore<C>
+where C: RaftTypeConfig
+{
+    fn clone(&self) -> Self {
+        Self {
+            inner: self.inner.clone(),
+        }
+    }
+}
+
+impl<C> FileLogStore<C>
+where C: RaftTypeConfig
+{
+    /// Open the store in `dir`, creating it if it does not exist.
+    ///
+    /// A partially written record at the end of the log, left by a crash, is discarded.
+    pub fn open(dir: impl AsRef<Path>, config: FileLogConfig) -> Result<Self, StorageError<C>> {
+        Self::open_with_fs(dir, config, Fs::default())
+    }
+
+    /// Open the store in `dir`, making changes to its files through `fs`.
+    pub(crate) fn open_with_fs(dir: impl AsRef<Path>, config: FileLogConfig, fs: Fs) -> Result<Self, StorageError<C>> {
+        let inner = Inner::open(dir.as_ref(), config, fs).map_err(|e| StorageError::read_logs(&e))?;
+
+        Ok(Self {
+            inner: Arc::new(Mutex::new(inner)),
+        })
+    }
+
+    /// Append entries without waiting for them to be synced to disk.
+    pub(crate) fn write_entries(&self, entries: impl IntoIterator<Item = C::Entry>) -> Result<(), io::Error> {
+        let mut inner = self.inner.lock().unwrap();
+
+        for entry in entries {
+            let log_id = entry.log_id();
+            let loc = inner.write(&Record::Entry(entry))?;
+            inner.index.insert(log_id.index(), (loc, log_id));
+        }
+
+        Ok(())
+    }
+
+    /// The ids of the segments on disk.
+    #[cfg(test)]
+    pub(crate) fn segment_ids(&self) -> Vec<u64> {
+        self.inner.lock().unwrap().segments.keys().copied().collect()
+    }
+}
+
+impl<C> RaftLogReader<C> for FileLogStore<C>
+where C: RaftTypeConfig
+{
+    async fn try_get_log_entries<RB: RangeBounds<u64> + Clone + Debug + OptionalSend>(
+        &mut self,
+        range: RB,
+    ) -> Result<Vec<C::Entry>, StorageError<C>> {
+        let inner = self.inner.lock().unwrap();
+
+        let mut res = Vec::new();
+        for (index, (loc, _)) in inner.index.range(range) {
+            let entry = inner.read_entry(loc).map_err(|e| StorageError::read_log_at_index(*index, &e))?;
+            res.push(entry);
+        }
+        Ok(res)
+    }
+
+    async fn read_vote(&mut self) -> Result<Option<VoteOf<C>>, StorageError<C>> {
+        Ok(self.inner.lock().unwrap().meta.vote.clone())
+    }
+}
+
+impl<C> RaftLogStorage<C> for FileLogStore<C>
+where C: RaftTypeConfig
+{
+    type LogReader = Self;
+
+    async fn get_log_state(&mut self) -> Result<LogState<C>, StorageError<C>> {
+        let inner = self.inner.lock().unwrap();
+
+        let last_purged_log_id = inner.meta.last_purged.clone();
+        let last_log_id = match inner.index.last_key_value() {
+            Some((_, (_, log_id))) => Some(log_id.clone()),
+            None => last_purged_log_id.clone(),
+        };
+
+        Ok(LogState {
+            last_purged_log_id,
+            last_log_id,
+        })
+    }
+
+    async fn get_log_reader(&mut self) -> Self::LogReader {
+        self.clone()
+    }
+
+    async fn save_vote(&mut self, vote: &VoteOf<C>) -> Result<(), StorageError<C>> {
+        let mut inner = self.inner.lock().unwrap();
+
+        // Entries appended before the vote must not be lost if the vote is persisted.
+        inner.active().sync().map_err(|e| StorageError::write_logs(&e))?;
+
+        inner.meta.vote = Some(vote.clone());
+        inner.save_meta().map_err(|e| StorageError::write_vote(&e))?;
+        Ok(())
+    }
+
+    async fn save_committed(&mut self, committed: Option<LogIdOf<C>>) -> Result<(), StorageError<C>> {
+        let mut inner = self.inner.lock().unwrap();
+
+        // Not synced: it is persisted along with the next append, and a lagging committed log id
+        // is still correct.
+        inner.write(&Record::Committed(committed.clone())).map_err(|e| StorageError::write_logs(&e))?;
+        inner.committed = committed;
+        Ok(())
+    }
+
+    async fn read_committed(&mut self) -> Result<Option<LogIdOf<C>>, StorageError<C>> {
+        Ok(self.inner.lock().unwrap().committed.clone())
+    }
+
+    async fn append<I>(&mut self, entries: I, callback: IOFlushed<C>) -> Result<(), StorageError<C>>
+    where
+        I: IntoIterator<Item = C::Entry> + OptionalSend,
+        I::IntoIter: OptionalSend,
+    {
+        self.write_entries(entries).map_err(|e| StorageError::write_logs(&e))?;
+
+        let inner = self.inner.lock().unwrap();
+        let req = FlushRequest {
+            file: inner.active().file.clone(),
+            path: inner.active().path.clone(),
+            callback,
+        };
+
+        // If there is error, the callback will be dropped.
+        inner.flush_tx.send(req).map_err(|_e| {
+            let e = io::Error::new(io::ErrorKind::BrokenPipe, "flusher quit");
+            StorageError::write_logs(&e)
+        })?;
+        Ok(())
+    }
+
+    async fn truncate(&mut self, log_id: LogIdOf<C>) -> Result<(), StorageError<C>> {
+        tracing::debug!("truncate: [{:?}, +oo)", log_id);
+
+        let mut inner = self.inner.lock().unwrap();
+        inner.truncate(log_id.index()).map_err(|e| StorageError::write_logs(&e))?;
+        Ok(())
+    }
+
+    async fn purge(&mut self, log_id: LogIdOf<C>) -> Result<(), StorageError<C>> {
+        tracing::debug!("purge: [0, {:?}]", log_id);
+
+        let mut inner = self.inner.lock().unwrap();
+
+        // Persist the last-purged log id before removing any segment.
+        // Entries at and before it are ignored when the store is opened.
+        inner.meta.last_purged = Some(log_id);
+        inner.save_meta().map_err(|e| StorageError::write_logs(&e))?;
+
+        inner.remove_purged().map_err(|e| StorageError::write_logs(&e))?;
+        Ok(())
+    }
+}
+
+/// Data that is stored in [`META_FILE`] instead of in the log.
+#[derive(Debug)]
+#[derive(Serialize, Deserialize)]
+#[serde(bound = "")]
+struct Meta<C>
+where C: RaftTypeConfig
+{
+    vote: Option<VoteOf<C>>,
+    last_purged: Option<LogIdOf<C>>,
+}
+
+impl<C> Default for Meta<C>
+where C: RaftTypeConfig
+{
+    fn default() -> Self {
+        Self {
+            vote: None,
+            last_purged: None,
+        }
+    }
+}
+
+/// Where a record is stored: segment id, offset and size.
+type Location = (u64, u64, u64);
+
+#[derive(Debug)]
+struct Inner<C>
+where C: RaftTypeConfig
+{
+    dir: PathBuf,
+    config: FileLogConfig,
+
+    /// All segments by id. The last one is the active segment.
+    segments: BTreeMap<u64, Segment>,
+
+    /// The location of every present entry, by log index.
+    index: BTreeMap<u64, (Location, LogIdOf<C>)>,
+
+    meta: Meta<C>,
+    committed: Option<LogIdOf<C>>,
+
+    fs: Fs,
+    flush_tx: mpsc::Sender<FlushRequest<C>>,
+}
+
+impl<C> Inner<C>
+where C: RaftTypeConfig
+{
+    fn open(dir: &Path, config: FileLogConfig, fs: Fs) -> Result<Self, io::Error> {
+        fs::create_dir_all(dir)?;
+
+        let meta = match fs::read(dir.join(META_FILE)) {
+            Ok(buf) => serde_json::from_slice(&buf)?,
+            Err(e) if e.kind() == io::ErrorKind::NotFound => Meta::default(),
+            Err(e) => return Err(e),
+        };
+
+        let mut inner = Self {
+            dir: dir.to_path_buf(),
+            config,
+            segments: BTreeMap::new(),
+            index: BTreeMap::new(),
+            meta,
+            committed: None,
+            flush_tx: flusher::spawn(fs.clone())?,
+            fs,
+        };
+
+        let ids = segment::list(dir)?;
+        let last_id = ids.last().copied();
+
+        for id in ids {
+            let (mut seg, buf) = Segment::open(&inner.fs, dir, id)?;
+            let size = inner.replay(id, &buf);
+
+            if size < seg.size {
+                // Only the last segment can be partially written, the others are synced before
+                // the next one is created.
+                if Some(id) != last_id {
+                    return Err(io::Error::new(
+                        io::ErrorKind::InvalidData,
+                        format!("segment {} is corrupted at offset {}", id, size),
+                    ));
+                }
+
+                tracing::warn!(
+                    "discard partially written tail of segment {}: [{}, {})",
+                    id,
+                    size,
+                    seg.size
+                );
+                seg.cut(size)?;
+            }
+
+            inner.segments.insert(id, seg);
+        }
+
+        if inner.segments.is_empty() {
+            inner.roll()?;
+        }
+
+        inner.remove_purged()?;
+
+        Ok(inner)
+    }
+
+    /// Load the valid records in a segment, and return the size they take.
+    fn replay(&mut self, id: u64, buf: &[u8]) -> u64 {
+        let mut offset = 0;
+
+        while let Some((record, len)) = decode_next::<C>(&buf[offset as usize..]) {
+            match record {
+                Record::Entry(entry) => {
+                    let log_id = entry.log_id();
+                    self.index.insert(log_id.index(), ((id, offset, len), log_id));
+                }
+                Record::Committed(committed) => {
+                    self.committed = committed;
+                }
+            }
+            offset += len;
+        }
+
+        offset
+    }
+
+    fn active(&self) -> &Segment {
+        self.segments.last_key_value().unwrap().1
+    }
+
+    /// Append a record to the active segment, starting a new segment if it is full.
+    fn write(&mut self, record: &Record<C>) -> Result<Location, io::Error> {
+        let buf = record.encode()?;
+
+        if self.active().size >= self.config.segment_size {
+            self.roll()?;
+        }
+
+        let seg = self.segments.last_entry().unwrap().into_mut();
+        let offset = seg.write(&buf)?;
+        Ok((seg.id, offset, buf.len() as u64))
+    }
+
+    /// Start a new active segment.
+    fn roll(&mut self) -> Result<(), io::Error> {
+        let id = match self.segments.last_key_value() {
+            Some((id, seg)) => {
+                seg.sync()?;
+                id + 1
+            }
+            None => 0,
+        };
+
+        tracing::info!("start segment {}", id);
+
+        let mut seg = Segment::create(&self.fs, &self.dir, id)?;
+        seg.write(&Record::<C>::Committed(self.committed.clone()).encode()?)?;
+        seg.sync()?;
+        segment::sync_dir(&self.fs, &self.dir)?;
+
+        self.segments.insert(id, seg);
+        Ok(())
+    }
+
+    fn read_entry(&self, loc: &Location) -> Result<C::Entry, io::Error> {
+        let (seg_id, offset, len) = *loc;
+
+        let buf = self.segments[&seg_id].read(offset, len)?;
+        let (_len, crc) = decode_header(&buf);
+
+        match Record::<C>::decode(crc, &buf[HEADER_SIZE as usize..])? {
+            Record::Entry(entry) => Ok(entry),
+            Record::Committed(_) => Err(io::Error::new(io::ErrorKind::InvalidData, "expect an entry record")),
+        }
+    }
+
+    /// Remove entries since `index`, inclusive.
+    fn truncate(&mut self, index: u64) -> Result<(), io::Error> {
+        let Some((_, ((seg_id, offset, _), _))) = self.index.range(index..).next() else {
+            return Ok(());
+        };
+        let (seg_id, offset) = (*seg_id, *offset);
+
+        // Remove the newest segment first: a crash in between leaves no hole.
+        let newer = self.segments.range(seg_id + 1..).map(|(id, _)| *id).collect::<Vec<_>>();
+        for id in newer.iter().rev() {
+            self.segments.remove(id).unwrap().remove()?;
+        }
+        if !newer.is_empty() {
+            segment::sync_dir(&self.fs, &self.dir)?;
+        }
+
+        let seg = self.segments.get_mut(&seg_id).unwrap();
+        seg.cut(offset)?;
+        self.index.split_off(&index);
+
+        // The committed log id saved after the removed entries is cut too.
+        seg.write(&Record::<C>::Committed(self.committed.clone()).encode()?)?;
+        Ok(())
+    }
+
+    /// Remove purged entries, and the segments that contain only purged entries.
+    fn remove_purged(&mut self) -> Result<(), io::Error> {
+        let Some(purged) = &self.meta.last_purged else {
+            return Ok(());
+        };
+
+        self.index = self.index.split_off(&(purged.index() + 1));
+
+        let active = *self.segments.last_key_value().unwrap().0;
+        let keep = match self.index.first_key_value() {
+            Some((_, ((seg_id, _, _), _))) => *seg_id,
+            None => active,
+        };
+
+        // Not synced: a removed segment that reappears after a crash is purged again on open.
+        let removable = self.segments.range(..keep).map(|(id, _)| *id).collect::<Vec<_>>();
+        for id in removable {
+            tracing::info!("remove purged segment {}", id);
+            self.segments.remove(&id).unwrap().remove()?;
+        }
+
+        Ok(())
+    }
+
+    /// Atomically replace [`META_FILE`].
+    fn save_meta(&self) -> Result<(), io::Error> {
+        let buf = serde_json::to_vec(&self.meta)?;
+
+        let tmp = self.dir.join(format!("{}.tmp", META_FILE));
+        let path = self.dir.join(META_FILE);
+
+        let mut f = self.fs.run(FsOp::Create(&tmp), || fs::File::create(&tmp))?;
+        self.fs.run(FsOp::Write(&tmp), || f.write_all(&buf))?;
+        self.fs.run(FsOp::Sync(&tmp), || f.sync_all())?;
+        self.fs.run(FsOp::Rename { from: &tmp, to: &path }, || fs::rename(&tmp, &path))?;
+        segment::sync_dir(&self.fs, &self.dir)?;
+        Ok(())
+    }
+}
@@ -0,0 +1,78 @@
+//! Framing of the records in a segment file.
+//!
+//! A record is stored as `[len: u32][crc32: u32][payload]`, both integers in little endian, where
+//! `crc32` is the checksum of the `len` bytes of `payload`.
+
+use std::io;
+
+use openraft::alias::LogIdOf;
+use openraft::RaftTypeConfig;
+use serde::Deserialize;
+use serde::Serialize;
+
+/// Size of the `len` and `crc32` before the payload.
+pub(crate) const HEADER_SIZE: u64 = 8;
+
+#[derive(Debug)]
+#[derive(Serialize, Deserialize)]
+#[serde(bound = "")]
+pub(crate) enum Record<C>
+where C: RaftTypeConfig
+{
+    /// A log entry.
+    Entry(C::Entry),
+
+    /// The last committed log id.
+    ///
+    /// It is also the first record of every segment, so that it survives purging the segments
+    /// before it.
+    Committed(Option<LogIdOf<C>>),
+}
+
+impl<C> Record<C>
+where C: RaftTypeConfig
+{
+    /// Serialize the record with its header.
+    pub(crate) fn encode(&self) -> Result<Vec<u8>, io::Error> {
+        let payload = serde_json::to_vec(self)?;
+
+        let mut buf = Vec::with_capacity(HEADER_SIZE as usize + payload.len());
+        buf.extend_from_slice(&(payload.len() as u32).to_le_bytes());
+        buf.extend_from_slice(&crc32fast::hash(&payload).to_le_bytes());
+        buf.extend_from_slice(&payload);
+        Ok(buf)
+    }
+
+    /// Deserialize the payload of a record, verifying it against the checksum in the header.
+    pub(crate) fn decode(crc: u32, payload: &[u8]) -> Result<Self, io::Error> {
+        if crc32fast::hash(payload) != crc {
+            return Err(io::Error::new(io::ErrorKind::InvalidData, "record checksum mismatch"));
+        }
+
+        let record = serde_json::from_slice(payload)?;
+        Ok(record)
+    }
+}
+
+/// Decode the record at the start of `buf`.
+///
+/// It returns the record and the number of bytes it takes, or `None` if `buf` does not start with
+/// a complete and valid record, e.g., the tail of a segment that is partially written before a
+/// crash.
+pub(crate) fn decode_next<C>(buf: &[u8]) -> Option<(Record<C>, u64)>
+where C: RaftTypeConfig {
+    let (len, crc) = decode_header(buf.get(..HEADER_SIZE as usize)?);
+
+    let end = HEADER_SIZE as usize + len as usize;
+    let payload = buf.get(HEADER_SIZE as usize..end)?;
+
+    let record = Record::decode(crc, payload).ok()?;
+    Some((record, end as u64))
+}
+
+/// Parse `len` and `crc32` from a record header.
+pub(crate) fn decode_header(header: &[u8]) -> (u32, u32) {
+    let len = u32::from_le_bytes(header[0..4].try_into().unwrap());
+    let crc = u32::from_le_bytes(header[4..8].try_into().unwrap());
+    (len, crc)
+}
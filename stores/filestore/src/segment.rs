@@ -0,0 +1,142 @@
+use std::fs;
+use std::fs::File;
+use std::fs::OpenOptions;
+use std::io;
+use std::io::Read;
+use std::io::Seek;
+use std::io::SeekFrom;
+use std::io::Write;
+use std::path::Path;
+use std::path::PathBuf;
+use std::sync::Arc;
+
+use crate::fs::Fs;
+use crate::fs::FsOp;
+
+const SUFFIX: &str = ".wal";
+
+/// A file of the write-ahead log.
+///
+/// Segments are named by an increasing id. Records are only appended to the last one, the active
+/// segment.
+#[derive(Debug)]
+pub(crate) struct Segment {
+    pub(crate) id: u64,
+    pub(crate) path: PathBuf,
+
+    /// Shared with the flusher, which syncs it in background.
+    pub(crate) file: Arc<File>,
+
+    /// The size of the valid records in this segment.
+    pub(crate) size: u64,
+
+    fs: Fs,
+}
+
+impl Segment {
+    /// Create an empty segment.
+    pub(crate) fn create(fs: &Fs, dir: &Path, id: u64) -> Result<Self, io::Error> {
+        let path = dir.join(file_name(id));
+        let file = fs.run(FsOp::Create(&path), || {
+            OpenOptions::new().read(true).append(true).create_new(true).open(&path)
+        })?;
+
+        Ok(Self {
+            id,
+            path,
+            file: Arc::new(file),
+            size: 0,
+            fs: fs.clone(),
+        })
+    }
+
+    /// Open an existing segment and read all of its content.
+    pub(crate) fn open(fs: &Fs, dir: &Path, id: u64) -> Result<(Self, Vec<u8>), io::Error> {
+        let path = dir.join(file_name(id));
+        let mut file = OpenOptions::new().read(true).append(true).open(&path)?;
+
+        let mut buf = Vec::new();
+        file.read_to_end(&mut buf)?;
+
+        let segment = Self {
+            id,
+            path,
+            file: Arc::new(file),
+            size: buf.len() as u64,
+            fs: fs.clone(),
+        };
+        Ok((segment, buf))
+    }
+
+    /// Append bytes and return the offset they are written at.
+    ///
+    /// The bytes are not synced to disk.
+    pub(crate) fn write(&mut self, buf: &[u8]) -> Result<u64, io::Error> {
+        let offset = self.size;
+        self.fs.run(FsOp::Write(&self.path), || (&*self.file).write_all(buf))?;
+        self.size += buf.len() as u64;
+        Ok(offset)
+    }
+
+    /// Read `len` bytes at `offset`.
+    pub(crate) fn read(&self, offset: u64, len: u64) -> Result<Vec<u8>, io::Error> {
+        let mut f = &*self.file;
+        f.seek(SeekFrom::Start(offset))?;
+
+        let mut buf = vec![0; len as usize];
+        f.read_exact(&mut buf)?;
+        Ok(buf)
+    }
+
+    /// Discard everything since `offset` and sync the change to disk.
+    pub(crate) fn cut(&mut self, offset: u64) -> Result<(), io::Error> {
+        self.fs.run(FsOp::SetLen(&self.path), || self.file.set_len(offset))?;
+        self.fs.run(FsOp::Sync(&self.path), || self.file.sync_all())?;
+        self.size = offset;
+        Ok(())
+    }
+
+    pub(crate) fn sync(&self) -> Result<(), io::Error> {
+        self.fs.run(FsOp::Sync(&self.path), || self.file.sync_data())
+    }
+
+    /// Delete the segment file. The deletion is not synced to disk.
+    pub(crate) fn remove(self) -> Result<(), io::Error> {
+        self.fs.run(FsOp::Remove(&self.path), || fs::remove_file(&self.path))
+    }
+}
+
+/// List the ids of the segments in `dir`, in ascending order.
+pub(crate) fn list(dir: &Path) -> Result<Vec<u64>, io::Error> {
+    let mut ids = vec![];
+
+    for ent in fs::read_dir(dir)? {
+        let name = ent?.file_name();
+        let id = name.to_str().and_then(|n| n.strip_suffix(SUFFIX)).and_then(|n| n.parse().ok());
+
+        if let Some(id) = id {
+            ids.push(id);
+        }
+    }
+
+    ids.sort();
+    Ok(ids)
+}
+
+/// Sync the entries of a directory, e.g., created or deleted files, to disk.
+pub(crate) fn sync_dir(fs: &Fs, dir: &Path) -> Result<(), io::Error> {
+    fs.run(FsOp::SyncDir(dir), || {
+        #[cfg(unix)]
+        File::open(dir)?.sync_all()?;
+
+        // Directories can not be opened as a file on other platforms.
+        #[cfg(not(unix))]
+        let _ = dir;
+
+        Ok(())
+    })
+}
+
+fn file_name(id: u64) -> String {
+    format!("{:020}{}", id, SUFFIX)
+}
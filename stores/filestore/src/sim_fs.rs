@@ -0,0 +1,158 @@
+//! An [`FsHook`] that keeps track of what is synced to disk, to simulate what a crash leaves.
+
+use std::collections::BTreeMap;
+use std::fs;
+use std::io;
+use std::path::Path;
+use std::path::PathBuf;
+use std::sync::Mutex;
+
+use openraft::testing::log::CrashMode;
+
+use crate::fs::FsHook;
+use crate::fs::FsOp;
+
+/// Tracks the files of a store and their content synced to disk, and fails every change since a
+/// crash point.
+///
+/// A file is identified by an id, so that its content follows it when it is renamed. The content
+/// of a file is synced by [`FsOp::Sync`], while creating, removing or renaming it is synced by
+/// [`FsOp::SyncDir`].
+#[derive(Debug, Default)]
+pub(crate) struct SimFs {
+    state: Mutex<State>,
+}
+
+#[derive(Debug, Default)]
+struct State {
+    /// The number of changes made or failed.
+    ops: u64,
+
+    /// Crash at this change.
+    crash_at: Option<u64>,
+
+    /// Whether it crashed. Every change fails after a crash.
+    crashed: bool,
+
+    next_id: u64,
+
+    /// Files in the directory, by path.
+    files: BTreeMap<PathBuf, u64>,
+
+    /// Files in the directory that are synced to disk.
+    synced_files: BTreeMap<PathBuf, u64>,
+
+    /// Content of files synced to disk, by file id.
+    synced_content: BTreeMap<u64, Vec<u8>>,
+}
+
+impl FsHook for SimFs {
+    fn run(&self, op: FsOp<'_>, f: &mut dyn FnMut() -> Result<(), io::Error>) -> Result<(), io::Error> {
+        let mut state = self.state.lock().unwrap();
+
+        if state.crashed {
+            return Err(crashed());
+        }
+
+        state.ops += 1;
+        if Some(state.ops) == state.crash_at {
+            tracing::info!("crash at: {}", op);
+            state.crashed = true;
+            return Err(crashed());
+        }
+
+        f()?;
+
+        match op {
+            FsOp::Create(path) => {
+                let id = state.next_id;
+                state.next_id += 1;
+                state.files.insert(path.to_path_buf(), id);
+            }
+            FsOp::Write(_) | FsOp::SetLen(_) => {}
+            FsOp::Sync(path) => {
+                // A removed file may still be synced by an open handle.
+                if let Some(id) = state.files.get(path).copied() {
+                    let content = fs::read(path)?;
+                    state.synced_content.insert(id, content);
+                }
+            }
+            FsOp::Remove(path) => {
+                state.files.remove(path);
+            }
+            FsOp::Rename { from, to } => {
+                if let Some(id) = state.files.remove(from) {
+                    state.files.insert(to.to_path_buf(), id);
+                }
+            }
+            FsOp::SyncDir(_) => {
+                state.synced_files = state.files.clone();
+            }
+        }
+
+        Ok(())
+    }
+}
+
+impl SimFs {
+    /// Crash at the `n`-th change from now on.
+    pub(crate) fn crash_at(&self, n: u64) {
+        let mut state = self.state.lock().unwrap();
+        state.crash_at = Some(state.ops + n);
+    }
+
+    pub(crate) fn crashed(&self) -> bool {
+        self.state.lock().unwrap().crashed
+    }
+
+    /// Crash now: every change after it fails.
+    pub(crate) fn crash(&self) {
+        self.state.lock().unwrap().crashed = true;
+    }
+
+    /// Replace the files in `dir` with what is synced to disk, and accept changes again.
+    ///
+    /// With [`CrashMode::TornWrite`], the first half of the bytes appended to a file since it is
+    /// synced are kept.
+    pub(crate) fn recover(&self, dir: &Path, mode: CrashMode) -> Result<(), io::Error> {
+        let mut state = self.state.lock().unwrap();
+
+        let mut current = BTreeMap::new();
+        for (path, id) in state.files.iter() {
+            current.insert(*id, fs::read(path)?);
+        }
+
+        for ent in fs::read_dir(dir)? {
+            fs::remove_file(ent?.path())?;
+        }
+
+        let mut recovered = BTreeMap::new();
+
+        for (path, id) in state.synced_files.iter() {
+            let mut content = state.synced_content.get(id).cloned().unwrap_or_default();
+
+            if mode == CrashMode::TornWrite {
+                if let Some(cur) = current.get(id) {
+                    if cur.len() > content.len() && cur.starts_with(&content) {
+                        let torn = (cur.len() - content.len()).div_ceil(2);
+                        content.extend_from_slice(&cur[content.len()..content.len() + torn]);
+                    }
+                }
+            }
+
+            fs::write(path, &content)?;
+            recovered.insert(*id, content);
+        }
+
+        state.files = state.synced_files.clone();
+        state.synced_content = recovered;
+        state.crash_at = None;
+        state.crashed = false;
+
+        Ok(())
+    }
+}
+
+fn crashed() -> io::Error {
+    io::Error::other("crashed")
+}
@@ -0,0 +1,225 @@
+use std::fs;
+use std::io::Write;
+use std::sync::Arc;
+
+use openraft::storage::RaftLogStorage;
+use openraft::testing::blank_ent;
+use openraft::testing::log::CrashMode;
+use openraft::testing::log::CrashStoreBuilder;
+use openraft::testing::log::CrashSuite;
+use openraft::testing::log::StoreBuilder;
+use openraft::testing::log::Suite;
+use openraft::testing::log_id;
+use openraft::Entry;
+use openraft::LogId;
+use openraft::RaftLogReader;
+use openraft::StorageError;
+use openraft::Vote;
+use openraft_memstore::MemStateMachine;
+use openraft_memstore::TypeConfig;
+use tempfile::TempDir;
+
+use crate::fs::Fs;
+use crate::sim_fs::SimFs;
+use crate::FileLogConfig;
+use crate::FileLogStore;
+
+struct FileStoreBuilder {
+    config: FileLogConfig,
+}
+
+/// The directory of a store built by [`FileStoreBuilder`], and the [`SimFs`] its files are changed
+/// through.
+struct StoreDir {
+    dir: TempDir,
+    fs: Arc<SimFs>,
+}
+
+impl FileStoreBuilder {
+    fn open(&self, guard: &StoreDir) -> Result<FileLogStore<TypeConfig>, StorageError<TypeConfig>> {
+        let fs = Fs::with_hook(guard.fs.clone());
+        FileLogStore::open_with_fs(guard.dir.path(), self.config.clone(), fs)
+    }
+}
+
+impl StoreBuilder<TypeConfig, FileLogStore<TypeConfig>, Arc<MemStateMachine>, StoreDir> for FileStoreBuilder {
+    async fn build(
+        &self,
+    ) -> Result<(StoreDir, FileLogStore<TypeConfig>, Arc<MemStateMachine>), StorageError<TypeConfig>> {
+        let guard = StoreDir {
+            dir: TempDir::new().expect("couldn't create temp dir"),
+            fs: Arc::new(SimFs::default()),
+        };
+        let log_store = self.open(&guard)?;
+        let (_, sm) = openraft_memstore::new_mem_store();
+        Ok((guard, log_store, sm))
+    }
+}
+
+impl CrashStoreBuilder<TypeConfig, FileLogStore<TypeConfig>, Arc<MemStateMachine>, StoreDir> for FileStoreBuilder {
+    fn crash_at(&self, guard: &StoreDir, n: u64) {
+        guard.fs.crash_at(n);
+    }
+
+    fn crashed(&self, guard: &StoreDir) -> bool {
+        guard.fs.crashed()
+    }
+
+    async fn restart(
+        &self,
+        guard: &StoreDir,
+        log_store: FileLogStore<TypeConfig>,
+        _sm: Arc<MemStateMachine>,
+        mode: CrashMode,
+    ) -> Result<(FileLogStore<TypeConfig>, Arc<MemStateMachine>), StorageError<TypeConfig>> {
+        // Nothing reaches the disk after the crash, including the syncs still queued in the
+        // flusher.
+        guard.fs.crash();
+        drop(log_store);
+
+        guard.fs.recover(guard.dir.path(), mode).map_err(|e| StorageError::read_logs(&e))?;
+
+        let log_store = self.open(guard)?;
+        let (_, sm) = openraft_memstore::new_mem_store();
+        Ok((log_store, sm))
+    }
+}
+
+#[tokio::test]
+pub async fn test_file_log_store() -> Result<(), StorageError<TypeConfig>> {
+    Suite::test_all(FileStoreBuilder {
+        config: FileLogConfig::default(),
+    })
+    .await?;
+    Ok(())
+}
+
+/// Run the suite with every entry in its own segment.
+#[tokio::test]
+pub async fn test_file_log_store_small_segments() -> Result<(), StorageError<TypeConfig>> {
+    Suite::test_all(FileStoreBuilder {
+        config: small_segments(),
+    })
+    .await?;
+    Ok(())
+}
+
+#[tokio::test]
+pub async fn test_file_log_store_crash() -> Result<(), StorageError<TypeConfig>> {
+    CrashSuite::test_all(FileStoreBuilder {
+        config: FileLogConfig::default(),
+    })
+    .await?;
+    CrashSuite::test_all(FileStoreBuilder {
+        config: small_segments(),
+    })
+    .await?;
+    Ok(())
+}
+
+#[tokio::test]
+pub async fn test_reopen() -> anyhow::Result<()> {
+    let td = TempDir::new()?;
+
+    {
+        let mut store = FileLogStore::<TypeConfig>::open(td.path(), small_segments())?;
+        store.write_entries((0..10).map(|i| blank_ent(1, 0, i)))?;
+        store.save_vote(&Vote::new(2, 1)).await?;
+        store.save_committed(Some(log_id(1, 0, 7))).await?;
+        store.purge(log_id(1, 0, 3)).await?;
+        store.truncate(log_id(1, 0, 8)).await?;
+    }
+
+    let mut store = FileLogStore::<TypeConfig>::open(td.path(), small_segments())?;
+
+    let state = store.get_log_state().await?;
+    assert_eq!(Some(log_id(1, 0, 3)), state.last_purged_log_id);
+    assert_eq!(Some(log_id(1, 0, 7)), state.last_log_id);
+
+    assert_eq!(Some(Vote::new(2, 1)), store.read_vote().await?);
+    assert_eq!(Some(log_id(1, 0, 7)), store.read_committed().await?);
+
+    let entries = store.try_get_log_entries(0..100).await?;
+    assert_eq!((4..8).map(|i| log_id(1, 0, i)).collect::<Vec<_>>(), log_ids(entries));
+
+    Ok(())
+}
+
+#[tokio::test]
+pub async fn test_purge_removes_segments() -> anyhow::Result<()> {
+    let td = TempDir::new()?;
+
+    let mut store = FileLogStore::<TypeConfig>::open(td.path(), small_segments())?;
+    store.write_entries((0..10).map(|i| blank_ent(1, 0, i)))?;
+    // Segment 0 holds only the committed log id, entry `i` is in segment `i+1`.
+    assert_eq!((0..=10).collect::<Vec<_>>(), store.segment_ids());
+
+    store.purge(log_id(1, 0, 3)).await?;
+    assert_eq!((5..=10).collect::<Vec<_>>(), store.segment_ids());
+
+    store.truncate(log_id(1, 0, 6)).await?;
+    assert_eq!(vec![5, 6, 7], store.segment_ids(), "segment 7 is cut");
+
+    store.purge(log_id(1, 0, 20)).await?;
+    assert_eq!(vec![7], store.segment_ids(), "the active segment is kept");
+
+    store.write_entries([blank_ent(1, 0, 21)])?;
+    let entries = store.try_get_log_entries(0..100).await?;
+    assert_eq!(vec![log_id(1, 0, 21)], log_ids(entries));
+
+    Ok(())
+}
+
+#[tokio::test]
+pub async fn test_torn_tail_is_cut_off() -> anyhow::Result<()> {
+    let td = TempDir::new()?;
+
+    {
+        let store = FileLogStore::<TypeConfig>::open(td.path(), FileLogConfig::default())?;
+        store.write_entries((0..3).map(|i| blank_ent(1, 0, i)))?;
+    }
+
+    let path = td.path().join("00000000000000000000.wal");
+    let size = fs::metadata(&path)?.len();
+
+    tracing::info!("--- a partially written record");
+    {
+        let mut f = fs::OpenOptions::new().append(true).open(&path)?;
+        f.write_all(&[100, 0, 0, 0, 1, 2, 3, 4, b'{'])?;
+
+        let mut store = FileLogStore::<TypeConfig>::open(td.path(), FileLogConfig::default())?;
+        assert_eq!(size, fs::metadata(&path)?.len());
+
+        let entries = store.try_get_log_entries(0..100).await?;
+        assert_eq!((0..3).map(|i| log_id(1, 0, i)).collect::<Vec<_>>(), log_ids(entries));
+    }
+
+    tracing::info!("--- a record with mismatching checksum");
+    {
+        let mut buf = fs::read(&path)?;
+        let last = buf.len() - 2;
+        buf[last] ^= 0xff;
+        fs::write(&path, buf)?;
+
+        let mut store = FileLogStore::<TypeConfig>::open(td.path(), FileLogConfig::default())?;
+
+        let entries = store.try_get_log_entries(0..100).await?;
+        assert_eq!((0..2).map(|i| log_id(1, 0, i)).collect::<Vec<_>>(), log_ids(entries));
+
+        store.write_entries([blank_ent(1, 0, 2)])?;
+    }
+
+    let mut store = FileLogStore::<TypeConfig>::open(td.path(), FileLogConfig::default())?;
+    let entries = store.try_get_log_entries(0..100).await?;
+    assert_eq!((0..3).map(|i| log_id(1, 0, i)).collect::<Vec<_>>(), log_ids(entries));
+
+    Ok(())
+}
+
+fn log_ids(entries: Vec<Entry<TypeConfig>>) -> Vec<LogId<TypeConfig>> {
+    entries.into_iter().map(|e| e.log_id).collect()
+}
+
+fn small_segments() -> FileLogConfig {
+    FileLogConfig { segment_size: 1 }
+}
@@ -15,7 +15,10 @@ mod t13_get_snapshot;
 mod t13_install_full_snapshot;
 mod t13_trigger_snapshot;
 mod t14_transfer_leader;
+mod t15_client_write_forwarded;
 mod t16_with_raft_state;
 mod t16_with_state_machine;
+mod t17_apply_backpressure;
+mod t18_admission_control;
 mod t50_lagging_network_write;
 mod t51_write_when_leader_quit;
@@ -0,0 +1,95 @@
+use std::sync::Arc;
+use std::time::Duration;
+
+use anyhow::Result;
+use maplit::btreeset;
+use openraft::error::ClientWriteError;
+use openraft::error::RaftError;
+use openraft::Config;
+use openraft::ServerState;
+use openraft::TokioInstant;
+use openraft_memstore::ClientRequest;
+use openraft_memstore::IntoMemClientRequest;
+
+use crate::fixtures::ut_harness;
+use crate::fixtures::RaftRouter;
+
+/// Call [`client_write_forwarded`](openraft::raft::Raft::client_write_forwarded) on a follower:
+///
+/// - The write is forwarded to the Leader and the Leader's response is returned.
+/// - When the Leader is gone, it retries until a new Leader is elected.
+/// - When no Leader can be elected, it returns `ForwardToLeader` after the timeout.
+#[tracing::instrument]
+#[test_harness::test(harness = ut_harness)]
+async fn client_write_forwarded() -> Result<()> {
+    let config = Arc::new(
+        Config {
+            election_timeout_min: 150,
+            election_timeout_max: 300,
+            ..Default::default()
+        }
+        .validate()?,
+    );
+
+    let mut router = RaftRouter::new(config.clone());
+
+    tracing::info!("--- initializing cluster");
+    let mut log_index = router.new_cluster(btreeset! {0,1,2}, btreeset! {}).await?;
+
+    let n1 = router.get_raft_handle(&1)?;
+
+    tracing::info!(log_index, "--- a write on a follower is forwarded to the Leader");
+    {
+        let resp = n1.client_write_forwarded(ClientRequest::make_request("c", 1), timeout()).await?;
+        log_index += 1;
+
+        assert_eq!(log_index, resp.log_id.index);
+        assert_eq!(None, resp.data.0, "no previous value");
+
+        router.wait(&0, Some(timeout())).applied_index(Some(log_index), "applied on the Leader").await?;
+    }
+
+    tracing::info!(
+        log_index,
+        "--- shut down the Leader, the write is retried with the next Leader"
+    );
+    {
+        let (n0, _, _) = router.remove_node(0).unwrap();
+        n0.shutdown().await?;
+
+        let resp = n1.client_write_forwarded(ClientRequest::make_request("c", 2), Duration::from_secs(5)).await?;
+        assert!(resp.log_id.index > log_index);
+        assert_eq!(Some("request-1".to_string()), resp.data.0, "previous value of client c");
+
+        let m = n1.metrics().borrow().clone();
+        assert_ne!(Some(0), m.current_leader);
+    }
+
+    tracing::info!("--- without a quorum, ForwardToLeader is returned after the timeout");
+    {
+        let leader = router.leader().unwrap();
+        let follower = if leader == 1 { 2 } else { 1 };
+
+        let (l, _, _) = router.remove_node(leader).unwrap();
+        l.shutdown().await?;
+
+        let f = router.get_raft_handle(&follower)?;
+        f.wait(Some(timeout())).state(ServerState::Candidate, "no Leader, start election").await.ok();
+
+        let now = TokioInstant::now();
+        let res = f.client_write_forwarded(ClientRequest::make_request("c", 3), Duration::from_millis(500)).await;
+
+        assert!(
+            matches!(res, Err(RaftError::APIError(ClientWriteError::ForwardToLeader(_)))),
+            "got: {:?}",
+            res
+        );
+        assert!(now.elapsed() >= Duration::from_millis(500));
+    }
+
+    Ok(())
+}
+
+fn timeout() -> Duration {
+    Duration::from_millis(1_000)
+}
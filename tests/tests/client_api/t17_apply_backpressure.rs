@@ -0,0 +1,156 @@
+use std::sync::Arc;
+use std::time::Duration;
+
+use anyhow::Result;
+use maplit::btreeset;
+use openraft::error::ClientWriteError;
+use openraft::error::RaftError;
+use openraft::Config;
+use openraft_memstore::ClientRequest;
+use openraft_memstore::IntoMemClientRequest;
+use tokio::sync::oneshot;
+
+use crate::fixtures::ut_harness;
+use crate::fixtures::MemRaft;
+use crate::fixtures::MemStateMachine;
+use crate::fixtures::RaftRouter;
+
+/// When the state machine lags more than `max_apply_lag` entries behind, client writes are held
+/// back until it catches up.
+#[tracing::instrument]
+#[test_harness::test(harness = ut_harness)]
+async fn apply_backpressure_hold_back() -> Result<()> {
+    let config = Arc::new(
+        Config {
+            enable_heartbeat: false,
+            max_apply_lag: 2,
+            ..Default::default()
+        }
+        .validate()?,
+    );
+
+    let mut router = RaftRouter::new(config.clone());
+
+    tracing::info!("--- initializing cluster");
+    let mut log_index = router.new_cluster(btreeset! {0}, btreeset! {}).await?;
+
+    let n0 = router.get_raft_handle(&0)?;
+    let unblock = block_state_machine(&n0);
+
+    tracing::info!(log_index, "--- write until the apply lag exceeds max_apply_lag");
+    for i in 1..=3 {
+        let _rx = n0.client_write_ff(ClientRequest::make_request("foo", i)).await?;
+        log_index += 1;
+        router.wait(&0, timeout()).metrics(|m| m.apply_lag == i, format!("apply_lag = {}", i)).await?;
+    }
+
+    tracing::info!(log_index, "--- more writes are held back");
+    let rx4 = n0.client_write_ff(ClientRequest::make_request("foo", 4)).await?;
+    let rx5 = n0.client_write_ff(ClientRequest::make_request("foo", 5)).await?;
+    {
+        tokio::time::sleep(Duration::from_millis(500)).await;
+
+        let m = n0.metrics().borrow().clone();
+        assert_eq!(Some(log_index), m.last_log_index, "no more entries are appended");
+        assert_eq!(3, m.apply_lag);
+        assert!(m.apply_throttled_ms > 0);
+    }
+
+    tracing::info!(log_index, "--- no more than max_apply_lag writes are held back");
+    {
+        let res = n0.client_write(ClientRequest::make_request("foo", 6)).await;
+        let Err(RaftError::APIError(ClientWriteError::Overloaded(err))) = res else {
+            panic!("expect Overloaded, got: {:?}", res);
+        };
+        tracing::info!("got: {}", err);
+    }
+
+    tracing::info!(
+        log_index,
+        "--- held back writes are written once the state machine catches up"
+    );
+    {
+        unblock.send(()).unwrap();
+
+        let resp4 = rx4.await??;
+        let resp5 = rx5.await??;
+        log_index += 2;
+        assert_eq!(log_index - 1, resp4.log_id.index);
+        assert_eq!(log_index, resp5.log_id.index);
+
+        router.wait(&0, timeout()).applied_index(Some(log_index), "all applied").await?;
+        let m = router.wait(&0, timeout()).metrics(|m| m.apply_lag == 0, "no apply lag").await?;
+        assert!(m.apply_throttled_ms >= 500);
+    }
+
+    Ok(())
+}
+
+/// With `reject_on_apply_lag`, client writes are rejected with `Overloaded` when the state machine
+/// lags more than `max_apply_lag` entries behind.
+#[tracing::instrument]
+#[test_harness::test(harness = ut_harness)]
+async fn apply_backpressure_reject() -> Result<()> {
+    let config = Arc::new(
+        Config {
+            enable_heartbeat: false,
+            max_apply_lag: 2,
+            reject_on_apply_lag: true,
+            ..Default::default()
+        }
+        .validate()?,
+    );
+
+    let mut router = RaftRouter::new(config.clone());
+
+    tracing::info!("--- initializing cluster");
+    let mut log_index = router.new_cluster(btreeset! {0}, btreeset! {}).await?;
+
+    let n0 = router.get_raft_handle(&0)?;
+    let unblock = block_state_machine(&n0);
+
+    tracing::info!(log_index, "--- write until the apply lag exceeds max_apply_lag");
+    for i in 1..=3 {
+        let _rx = n0.client_write_ff(ClientRequest::make_request("foo", i)).await?;
+        log_index += 1;
+        router.wait(&0, timeout()).metrics(|m| m.apply_lag == i, format!("apply_lag = {}", i)).await?;
+    }
+
+    tracing::info!(log_index, "--- more writes are rejected");
+    {
+        let res = n0.client_write(ClientRequest::make_request("foo", 4)).await;
+        let Err(RaftError::APIError(ClientWriteError::Overloaded(err))) = res else {
+            panic!("expect Overloaded, got: {:?}", res);
+        };
+        tracing::info!("got: {}", err);
+
+        assert_eq!(Some(log_index), n0.metrics().borrow().last_log_index);
+    }
+
+    tracing::info!(log_index, "--- writes are accepted once the state machine catches up");
+    {
+        unblock.send(()).unwrap();
+        router.wait(&0, timeout()).applied_index(Some(log_index), "all applied").await?;
+
+        n0.client_write(ClientRequest::make_request("foo", 4)).await?;
+    }
+
+    Ok(())
+}
+
+/// Block the state machine worker until the returned sender is triggered.
+fn block_state_machine(raft: &MemRaft) -> oneshot::Sender<()> {
+    let (tx, rx) = oneshot::channel::<()>();
+
+    raft.external_state_machine_request(|_sm: &mut MemStateMachine| {
+        Box::pin(async move {
+            let _ = rx.await;
+        })
+    });
+
+    tx
+}
+
+fn timeout() -> Option<Duration> {
+    Some(Duration::from_millis(5_000))
+}
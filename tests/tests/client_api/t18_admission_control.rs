@@ -0,0 +1,99 @@
+use std::sync::Arc;
+use std::time::Duration;
+
+use anyhow::Result;
+use maplit::btreeset;
+use openraft::error::ClientWriteError;
+use openraft::error::RaftError;
+use openraft::Config;
+use openraft_memstore::ClientRequest;
+use openraft_memstore::IntoMemClientRequest;
+use tokio::sync::oneshot;
+
+use crate::fixtures::ut_harness;
+use crate::fixtures::MemRaft;
+use crate::fixtures::MemStateMachine;
+use crate::fixtures::RaftRouter;
+
+/// When there are `max_in_flight_proposals` client writes not yet responded, `try_client_write`
+/// fails at once with `Overloaded`, and `client_write` waits for an in-flight write to finish.
+#[tracing::instrument]
+#[test_harness::test(harness = ut_harness)]
+async fn admission_control() -> Result<()> {
+    let config = Arc::new(
+        Config {
+            enable_heartbeat: false,
+            max_in_flight_proposals: 2,
+            ..Default::default()
+        }
+        .validate()?,
+    );
+
+    let mut router = RaftRouter::new(config.clone());
+
+    tracing::info!("--- initializing cluster");
+    let mut log_index = router.new_cluster(btreeset! {0}, btreeset! {}).await?;
+
+    let n0 = router.get_raft_handle(&0)?;
+    let unblock = block_state_machine(&n0);
+
+    tracing::info!(log_index, "--- fill up in-flight proposals");
+    let rx1 = n0.client_write_ff(ClientRequest::make_request("foo", 1)).await?;
+    let rx2 = n0.client_write_ff(ClientRequest::make_request("foo", 2)).await?;
+    log_index += 2;
+    router.wait(&0, timeout()).metrics(|m| m.in_flight_proposals == 2, "2 in-flight proposals").await?;
+
+    tracing::info!(log_index, "--- try_client_write fails fast");
+    {
+        let res = n0.try_client_write(ClientRequest::make_request("foo", 3)).await;
+        let Err(RaftError::APIError(ClientWriteError::Overloaded(err))) = res else {
+            panic!("expect Overloaded, got: {:?}", res);
+        };
+        tracing::info!("got: {}", err);
+    }
+
+    tracing::info!(log_index, "--- client_write waits for an in-flight proposal to finish");
+    let h = {
+        let n0 = n0.clone();
+        tokio::spawn(async move { n0.client_write(ClientRequest::make_request("foo", 3)).await })
+    };
+    {
+        tokio::time::sleep(Duration::from_millis(500)).await;
+        assert!(!h.is_finished());
+        assert_eq!(Some(log_index), n0.metrics().borrow().last_log_index);
+    }
+
+    tracing::info!(log_index, "--- all writes finish once the state machine catches up");
+    {
+        unblock.send(()).unwrap();
+
+        rx1.await??;
+        rx2.await??;
+        let resp3 = h.await??;
+        log_index += 1;
+        assert_eq!(log_index, resp3.log_id.index);
+
+        router.wait(&0, timeout()).metrics(|m| m.in_flight_proposals == 0, "no in-flight proposals").await?;
+
+        n0.try_client_write(ClientRequest::make_request("foo", 4)).await?;
+    }
+
+    Ok(())
+}
+
+/// Block the state machine worker until the returned sender is triggered.
+fn block_state_machine(raft: &MemRaft) -> oneshot::Sender<()> {
+    let (tx, rx) = oneshot::channel::<()>();
+
+    raft.external_state_machine_request(|_sm: &mut MemStateMachine| {
+        Box::pin(async move {
+            let _ = rx.await;
+        })
+    });
+
+    tx
+}
+
+fn timeout() -> Option<Duration> {
+    Some(Duration::from_millis(5_000))
+}
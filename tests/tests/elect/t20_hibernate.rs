@@ -0,0 +1,238 @@
+use std::sync::atomic::AtomicU64;
+use std::sync::atomic::Ordering;
+use std::sync::Arc;
+use std::time::Duration;
+
+use anyerror::AnyError;
+use anyhow::Result;
+use maplit::btreeset;
+use openraft::error::NetworkError;
+use openraft::error::RPCError;
+use openraft::Config;
+use openraft::RPCTypes;
+use openraft_memstore::ClientRequest;
+use openraft_memstore::IntoMemClientRequest;
+
+use crate::fixtures::ut_harness;
+use crate::fixtures::RPCRequest;
+use crate::fixtures::RaftRouter;
+
+/// An idle Leader hibernates: it stops sending heartbeats and its followers do not elect.
+///
+/// - A client write wakes the group up, and it hibernates again when idle.
+/// - A follower woken up by the application elects when the Leader is lost.
+#[tracing::instrument]
+#[test_harness::test(harness = ut_harness)]
+async fn hibernate_idle_group() -> Result<()> {
+    let config = Arc::new(
+        Config {
+            hibernate_timeout: 500,
+            ..Default::default()
+        }
+        .validate()?,
+    );
+
+    let mut router = RaftRouter::new(config.clone());
+
+    tracing::info!("--- initializing cluster");
+    let mut log_index = router.new_cluster(btreeset! {0,1,2}, btreeset! {}).await?;
+
+    let hibernates = |router: &RaftRouter| router.get_rpc_count().get(&RPCTypes::Hibernate).copied().unwrap_or(0);
+    let append_entries =
+        |router: &RaftRouter| router.get_rpc_count().get(&RPCTypes::AppendEntries).copied().unwrap_or(0);
+
+    tracing::info!(log_index, "--- Leader hibernates when idle");
+    {
+        wait_for(|| hibernates(&router) >= 2).await?;
+
+        let term = router.get_metrics(&0)?.current_term;
+        let sent = append_entries(&router);
+
+        // Several election timeouts.
+        tokio::time::sleep(Duration::from_millis(1_500)).await;
+
+        assert_eq!(sent, append_entries(&router), "no heartbeat is sent");
+        for id in [0, 1, 2] {
+            let m = router.get_metrics(&id)?;
+            assert_eq!(term, m.current_term, "node-{} does not elect", id);
+            assert_eq!(Some(0), m.current_leader);
+        }
+    }
+
+    tracing::info!(log_index, "--- a write wakes the group up, then it hibernates again");
+    {
+        let n0 = router.get_raft_handle(&0)?;
+        n0.client_write(ClientRequest::make_request("foo", 1)).await?;
+        log_index += 1;
+
+        for id in [0, 1, 2] {
+            router.wait(&id, timeout()).applied_index(Some(log_index), "write is replicated").await?;
+        }
+
+        let sent = append_entries(&router);
+        tokio::time::sleep(Duration::from_millis(200)).await;
+        assert!(append_entries(&router) > sent, "heartbeats are resumed");
+
+        wait_for(|| hibernates(&router) >= 4).await?;
+
+        // A Hibernate RPC is counted before it is delivered.
+        tokio::time::sleep(Duration::from_millis(200)).await;
+    }
+
+    tracing::info!(
+        log_index,
+        "--- the Leader is lost, a follower woken up by the application elects"
+    );
+    {
+        let term = router.get_metrics(&1)?.current_term;
+
+        router.set_network_error(0, true);
+
+        let n1 = router.get_raft_handle(&1)?;
+        n1.trigger().wake_up().await?;
+
+        n1.wait(timeout()).metrics(|m| m.current_term > term, "node-1 elects").await?;
+        n1.wait(timeout())
+            .metrics(
+                |m| m.current_leader.is_some() && m.current_leader != Some(0),
+                "a new Leader is elected",
+            )
+            .await?;
+    }
+
+    Ok(())
+}
+
+/// A client request to a hibernating follower does not resume its election timer: it is not a sign
+/// that the Leader is lost.
+#[tracing::instrument]
+#[test_harness::test(harness = ut_harness)]
+async fn hibernate_follower_ignores_client_write() -> Result<()> {
+    let config = Arc::new(
+        Config {
+            hibernate_timeout: 500,
+            ..Default::default()
+        }
+        .validate()?,
+    );
+
+    let mut router = RaftRouter::new(config.clone());
+
+    tracing::info!("--- initializing cluster");
+    let log_index = router.new_cluster(btreeset! {0,1,2}, btreeset! {}).await?;
+
+    let hibernates = |router: &RaftRouter| router.get_rpc_count().get(&RPCTypes::Hibernate).copied().unwrap_or(0);
+
+    tracing::info!(log_index, "--- Leader hibernates when idle");
+    wait_for(|| hibernates(&router) >= 2).await?;
+    tokio::time::sleep(Duration::from_millis(200)).await;
+
+    tracing::info!(log_index, "--- write to a hibernating follower");
+    {
+        let term = router.get_metrics(&0)?.current_term;
+
+        let n1 = router.get_raft_handle(&1)?;
+        let res = n1.client_write(ClientRequest::make_request("foo", 1)).await;
+        let err = res.unwrap_err();
+        assert_eq!(Some(0), err.forward_to_leader().unwrap().leader_id);
+
+        // Several election timeouts.
+        tokio::time::sleep(Duration::from_millis(1_500)).await;
+
+        for id in [0, 1, 2] {
+            let m = router.get_metrics(&id)?;
+            assert_eq!(term, m.current_term, "node-{} does not elect", id);
+            assert_eq!(Some(0), m.current_leader);
+        }
+    }
+
+    Ok(())
+}
+
+/// A hibernating follower told to elect leaves hibernation: if the election fails, its election
+/// timer starts another one.
+#[tracing::instrument]
+#[test_harness::test(harness = ut_harness)]
+async fn hibernate_follower_retries_elect() -> Result<()> {
+    let config = Arc::new(
+        Config {
+            hibernate_timeout: 500,
+            ..Default::default()
+        }
+        .validate()?,
+    );
+
+    let mut router = RaftRouter::new(config.clone());
+
+    tracing::info!("--- initializing cluster");
+    let log_index = router.new_cluster(btreeset! {0,1,2}, btreeset! {}).await?;
+
+    let hibernates = |router: &RaftRouter| router.get_rpc_count().get(&RPCTypes::Hibernate).copied().unwrap_or(0);
+
+    tracing::info!(log_index, "--- Leader hibernates when idle");
+    wait_for(|| hibernates(&router) >= 2).await?;
+    tokio::time::sleep(Duration::from_millis(200)).await;
+
+    tracing::info!(
+        log_index,
+        "--- the Leader is lost, node-1 elects but its vote requests are dropped"
+    );
+    let elect_term = {
+        let term = router.get_metrics(&1)?.current_term;
+
+        let dropped = Arc::new(AtomicU64::new(0));
+
+        router.set_network_error(0, true);
+        router.set_rpc_pre_hook(RPCTypes::Vote, {
+            let dropped = dropped.clone();
+            move |_router: &_, _req: RPCRequest<_>, from, _target| {
+                if from == 1 {
+                    dropped.fetch_add(1, Ordering::Relaxed);
+                    let any_err = AnyError::error("drop vote request from node-1");
+                    return Err(RPCError::Network(NetworkError::new(&any_err)));
+                }
+                Ok(())
+            }
+        });
+
+        let n1 = router.get_raft_handle(&1)?;
+        n1.trigger().elect().await?;
+
+        let m = n1.wait(timeout()).metrics(|m| m.current_term > term, "node-1 elects").await?;
+        wait_for(|| dropped.load(Ordering::Relaxed) >= 2).await?;
+
+        m.current_term
+    };
+
+    tracing::info!(
+        log_index,
+        "--- vote requests are delivered again, node-1 retries the election"
+    );
+    {
+        router.rpc_pre_hook(RPCTypes::Vote, None);
+
+        let n1 = router.get_raft_handle(&1)?;
+        n1.wait(timeout())
+            .metrics(
+                |m| m.current_term > elect_term && m.current_leader == Some(1),
+                "node-1 elects again and becomes the Leader",
+            )
+            .await?;
+    }
+
+    Ok(())
+}
+
+async fn wait_for(f: impl Fn() -> bool) -> Result<()> {
+    for _ in 0..100 {
+        if f() {
+            return Ok(());
+        }
+        tokio::time::sleep(Duration::from_millis(50)).await;
+    }
+    anyhow::bail!("condition is not met in 5 seconds")
+}
+
+fn timeout() -> Option<Duration> {
+    Some(Duration::from_millis(5_000))
+}
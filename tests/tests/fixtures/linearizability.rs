@@ -0,0 +1,387 @@
+//! Record client histories against a [`TypedRaftRouter`] and check them for linearizability.
+//!
+//! The model is the key-value store implemented by memstore: a write of `ClientRequest{client,
+//! status}` sets key `client` to `status` and returns the previous value; a read returns the
+//! current value after [`TypedRaftRouter::ensure_linearizable`].
+//!
+//! The checker is a Wing & Gong style search with memoization, as used by Knossos and Porcupine.
+//! Keys are independent registers, thus the history is partitioned by key and every key is checked
+//! separately.
+
+use std::collections::BTreeMap;
+use std::collections::HashSet;
+use std::fmt;
+use std::sync::Arc;
+use std::sync::Mutex;
+use std::time::Duration;
+
+use openraft::error::ClientWriteError;
+use openraft::error::RaftError;
+use openraft_memstore::ClientRequest;
+use openraft_memstore::MemNodeId;
+use openraft_memstore::TypeConfig as MemConfig;
+use rand::rngs::StdRng;
+use rand::Rng;
+use rand::SeedableRng;
+
+use crate::fixtures::Direction;
+use crate::fixtures::RPCErrorType;
+use crate::fixtures::TypedRaftRouter;
+
+/// An operation on the key-value model.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum KvInput {
+    Write { key: String, value: String },
+    Read { key: String },
+}
+
+impl KvInput {
+    pub fn key(&self) -> &str {
+        match self {
+            KvInput::Write { key, .. } => key,
+            KvInput::Read { key } => key,
+        }
+    }
+}
+
+impl fmt::Display for KvInput {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            KvInput::Write { key, value } => write!(f, "write({}, {})", key, value),
+            KvInput::Read { key } => write!(f, "read({})", key),
+        }
+    }
+}
+
+/// An operation recorded in a [`History`].
+///
+/// `call` and `ret` are logical timestamps: an operation precedes another one in real time iff
+/// its `ret` is smaller than the `call` of the other.
+#[derive(Debug, Clone)]
+pub struct Operation {
+    /// The process that issued this operation. Operations of a process do not overlap.
+    pub process: u64,
+    pub input: KvInput,
+    pub call: u64,
+
+    /// When the response is received, or `None` if the outcome is unknown: the operation may or
+    /// may not take effect at any time after `call`.
+    pub ret: Option<u64>,
+
+    /// The value before a write, or the value returned by a read.
+    pub output: Option<String>,
+}
+
+impl fmt::Display for Operation {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "[{}, ", self.call)?;
+        match self.ret {
+            Some(ret) => write!(f, "{}]", ret)?,
+            None => write!(f, "?]")?,
+        }
+        write!(f, " p{} {}", self.process, self.input)?;
+        match self.ret {
+            Some(_) => write!(f, " -> {:?}", self.output),
+            None => write!(f, " -> unknown"),
+        }
+    }
+}
+
+#[derive(Default)]
+struct HistoryInner {
+    clock: u64,
+    ops: BTreeMap<u64, Operation>,
+}
+
+/// A concurrent history of client operations.
+#[derive(Clone, Default)]
+pub struct History {
+    inner: Arc<Mutex<HistoryInner>>,
+}
+
+impl History {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Record the invocation of an operation and return its id.
+    pub fn invoke(&self, process: u64, input: KvInput) -> u64 {
+        let mut inner = self.inner.lock().unwrap();
+        inner.clock += 1;
+
+        let id = inner.clock;
+        inner.ops.insert(id, Operation {
+            process,
+            input,
+            call: id,
+            ret: None,
+            output: None,
+        });
+        id
+    }
+
+    /// Record the successful response of an operation.
+    pub fn ok(&self, id: u64, output: Option<String>) {
+        let mut inner = self.inner.lock().unwrap();
+        inner.clock += 1;
+
+        let ret = inner.clock;
+        let op = inner.ops.get_mut(&id).unwrap();
+        op.ret = Some(ret);
+        op.output = output;
+    }
+
+    /// Record that an operation did not take effect; it is removed from the history.
+    pub fn fail(&self, id: u64) {
+        self.inner.lock().unwrap().ops.remove(&id);
+    }
+
+    /// Return all the recorded operations, ordered by invocation.
+    ///
+    /// An operation without a response is an operation with unknown outcome.
+    pub fn operations(&self) -> Vec<Operation> {
+        self.inner.lock().unwrap().ops.values().cloned().collect()
+    }
+
+    /// Write `value` to `key` through `target`, following `ForwardToLeader` like
+    /// [`TypedRaftRouter::client_request`] does. Every attempt is recorded as an operation.
+    ///
+    /// `ForwardToLeader` means the entry is not proposed or is truncated, thus the attempt did not
+    /// take effect. Any other error leaves the outcome unknown.
+    pub async fn write(
+        &self,
+        router: &TypedRaftRouter,
+        process: u64,
+        mut target: MemNodeId,
+        key: &str,
+        value: &str,
+    ) -> Result<Option<String>, RaftError<MemConfig, ClientWriteError<MemConfig>>> {
+        let mut last_err = None;
+
+        for _ in 0..3 {
+            let id = self.invoke(process, KvInput::Write {
+                key: key.to_string(),
+                value: value.to_string(),
+            });
+
+            let req = ClientRequest {
+                client: key.to_string(),
+                serial: id,
+                status: value.to_string(),
+            };
+
+            let err = match router.send_client_request(target, req).await {
+                Ok(resp) => {
+                    self.ok(id, resp.0.clone());
+                    return Ok(resp.0);
+                }
+                Err(err) => err,
+            };
+
+            let RaftError::APIError(ClientWriteError::ForwardToLeader(fwd)) = &err else {
+                return Err(err);
+            };
+
+            self.fail(id);
+
+            let Some(leader_id) = fwd.leader_id else {
+                return Err(err);
+            };
+            target = leader_id;
+            last_err = Some(err);
+        }
+
+        Err(last_err.unwrap())
+    }
+
+    /// Read `key` from the state machine of `target` after
+    /// [`TypedRaftRouter::ensure_linearizable`].
+    ///
+    /// A failed read has no effect and is not recorded.
+    pub async fn read(&self, router: &TypedRaftRouter, process: u64, target: MemNodeId, key: &str) -> Option<String> {
+        let id = self.invoke(process, KvInput::Read { key: key.to_string() });
+
+        if router.ensure_linearizable(target).await.is_err() {
+            self.fail(id);
+            return None;
+        }
+
+        let (_log_store, sm) = router.get_storage_handle(&target).unwrap();
+        let value = sm.get_state_machine().await.client_status.get(key).cloned();
+
+        self.ok(id, value.clone());
+        value
+    }
+
+    /// Check the recorded history for linearizability.
+    pub fn check(&self) -> anyhow::Result<()> {
+        check_kv(&self.operations())
+    }
+}
+
+/// Check whether a key-value history is linearizable, with every key absent initially.
+pub fn check_kv(ops: &[Operation]) -> anyhow::Result<()> {
+    let mut by_key: BTreeMap<&str, Vec<&Operation>> = BTreeMap::new();
+    for op in ops {
+        by_key.entry(op.input.key()).or_default().push(op);
+    }
+
+    for (key, ops) in by_key {
+        if !Register::new(&ops).check() {
+            let lines = ops.iter().map(|op| format!("  {}", op)).collect::<Vec<_>>();
+            anyhow::bail!("history of key {} is not linearizable:\n{}", key, lines.join("\n"));
+        }
+    }
+
+    Ok(())
+}
+
+/// Linearizability search on the history of a single register.
+struct Register<'a> {
+    ops: &'a [&'a Operation],
+
+    /// Number of operations with a known outcome; all of them have to be linearized.
+    n_completed: usize,
+
+    /// Visited `(linearized-set, state)` pairs that do not lead to a linearization.
+    visited: HashSet<(Vec<u64>, Option<String>)>,
+}
+
+impl<'a> Register<'a> {
+    fn new(ops: &'a [&'a Operation]) -> Self {
+        Self {
+            ops,
+            n_completed: ops.iter().filter(|op| op.ret.is_some()).count(),
+            visited: HashSet::new(),
+        }
+    }
+
+    fn check(&mut self) -> bool {
+        let mut linearized = vec![0u64; self.ops.len().div_ceil(64)];
+        self.search(&mut linearized, 0, None)
+    }
+
+    /// Try to extend a partial linearization in which `n_done` completed operations are
+    /// linearized and the register holds `state`.
+    fn search(&mut self, linearized: &mut Vec<u64>, n_done: usize, state: Option<String>) -> bool {
+        if n_done == self.n_completed {
+            return true;
+        }
+
+        if self.visited.contains(&(linearized.clone(), state.clone())) {
+            return false;
+        }
+
+        let is_linearized = |bits: &[u64], i: usize| bits[i / 64] & (1 << (i % 64)) != 0;
+
+        // An operation can be linearized next only if it is invoked before every pending operation
+        // returns.
+        let min_ret = (0..self.ops.len())
+            .filter(|i| !is_linearized(linearized, *i))
+            .filter_map(|i| self.ops[i].ret)
+            .min()
+            .unwrap_or(u64::MAX);
+
+        for i in 0..self.ops.len() {
+            let op = self.ops[i];
+
+            if is_linearized(linearized, i) || op.call > min_ret {
+                continue;
+            }
+
+            let completed = op.ret.is_some();
+            if completed && op.output != state {
+                continue;
+            }
+
+            let next = match &op.input {
+                KvInput::Write { value, .. } => Some(value.clone()),
+                KvInput::Read { .. } => state.clone(),
+            };
+
+            linearized[i / 64] |= 1 << (i % 64);
+            let found = self.search(linearized, n_done + completed as usize, next);
+            linearized[i / 64] &= !(1 << (i % 64));
+
+            if found {
+                return true;
+            }
+        }
+
+        self.visited.insert((linearized.clone(), state));
+        false
+    }
+}
+
+/// A fault injected into the router network.
+#[derive(Debug, Clone)]
+pub enum Fault {
+    /// RPCs from and to the node return `Unreachable`.
+    Unreachable(MemNodeId),
+    /// RPCs in one direction of the node return `NetworkError`.
+    NetworkError(MemNodeId, Direction),
+    /// Every RPC is delayed randomly by up to this many milliseconds.
+    SendDelay(u64),
+    /// Remove all faults.
+    Heal,
+}
+
+/// A randomized, reproducible sequence of faults, each of which lasts for a while.
+#[derive(Debug, Clone)]
+pub struct FaultSchedule {
+    pub seed: u64,
+    pub nodes: Vec<MemNodeId>,
+    pub steps: Vec<(Fault, Duration)>,
+}
+
+impl FaultSchedule {
+    /// Build `n` random steps over `nodes` from `seed`. Each step lasts 50 to 500 ms.
+    pub fn random(seed: u64, nodes: &[MemNodeId], n: usize) -> Self {
+        let mut rng = StdRng::seed_from_u64(seed);
+
+        let steps = (0..n)
+            .map(|_| {
+                let node = nodes[rng.random_range(0..nodes.len())];
+                let fault = match rng.random_range(0..5) {
+                    0 => Fault::Unreachable(node),
+                    1 => Fault::NetworkError(node, Direction::NetSend),
+                    2 => Fault::NetworkError(node, Direction::NetRecv),
+                    3 => Fault::SendDelay(rng.random_range(1..50)),
+                    _ => Fault::Heal,
+                };
+                (fault, Duration::from_millis(rng.random_range(50..500)))
+            })
+            .collect();
+
+        Self {
+            seed,
+            nodes: nodes.to_vec(),
+            steps,
+        }
+    }
+
+    /// Apply every step in order, then heal the network.
+    pub async fn run(&self, mut router: TypedRaftRouter) {
+        for (fault, duration) in self.steps.iter() {
+            tracing::info!(seed = self.seed, "--- apply fault: {:?} for {:?}", fault, duration);
+
+            match fault {
+                Fault::Unreachable(id) => router.set_unreachable(*id, true),
+                Fault::NetworkError(id, dir) => router.set_rpc_failure(*id, *dir, Some(RPCErrorType::NetworkError)),
+                Fault::SendDelay(ms) => router.network_send_delay(*ms),
+                Fault::Heal => self.heal(&mut router),
+            }
+
+            tokio::time::sleep(*duration).await;
+        }
+
+        self.heal(&mut router);
+    }
+
+    fn heal(&self, router: &mut TypedRaftRouter) {
+        for id in self.nodes.iter() {
+            router.set_unreachable(*id, false);
+        }
+        router.network_send_delay(0);
+    }
+}
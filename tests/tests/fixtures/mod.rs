@@ -11,6 +11,7 @@ use std::env;
 use std::fmt;
 use std::future::Future;
 use std::panic::PanicHookInfo;
+use std::sync::atomic::AtomicBool;
 use std::sync::atomic::AtomicU64;
 use std::sync::atomic::Ordering;
 use std::sync::Arc;
@@ -30,15 +31,24 @@ use openraft::error::NetworkError;
 use openraft::error::PayloadTooLarge;
 use openraft::error::RPCError;
 use openraft::error::RaftError;
+use openraft::error::RemoteError;
 use openraft::error::ReplicationClosed;
 use openraft::error::StreamingError;
 use openraft::error::Unreachable;
 use openraft::metrics::Wait;
+use openraft::network::retry::DefaultRetryPolicy;
+use openraft::network::retry::RetryPolicy;
+use openraft::network::ProtocolVersion;
 use openraft::network::RPCOption;
 use openraft::network::RaftNetworkFactory;
 use openraft::raft::AppendEntriesRequest;
 use openraft::raft::AppendEntriesResponse;
+use openraft::raft::CatchUpRequest;
+use openraft::raft::CatchUpResponse;
 use openraft::raft::ClientWriteResponse;
+use openraft::raft::HandshakeRequest;
+use openraft::raft::HandshakeResponse;
+use openraft::raft::HibernateRequest;
 use openraft::raft::InstallSnapshotRequest;
 use openraft::raft::SnapshotResponse;
 use openraft::raft::TransferLeaderRequest;
@@ -73,8 +83,13 @@ use pretty_assertions::assert_ne;
 use tracing_appender::non_blocking::WorkerGuard;
 
 use crate::fixtures::logging::init_file_logging;
+use crate::fixtures::network_faults::FaultyNetwork;
+use crate::fixtures::network_faults::NetworkFaults;
 
+pub mod linearizability;
 pub mod logging;
+pub mod network_faults;
+pub mod parallel_sm;
 
 pub type MemLogStore = Arc<LogStoreInner>;
 pub type MemStateMachine = Arc<SMInner>;
@@ -206,8 +221,8 @@ pub enum RPCErrorType {
     Unreachable,
     /// Returns [`NetworkError`](`openraft::error::NetworkError`).
     NetworkError,
-    /// Returns [`PayloadTooLarge`](`openraft::error::PayloadTooLarge`).
-    PayloadTooLarge { action: RPCTypes, entries_hint: u64 },
+    /// Returns [`PayloadTooLarge`](`openraft::error::PayloadTooLarge`) of an AppendEntries RPC.
+    PayloadTooLarge { entries_hint: u64 },
 }
 
 impl RPCErrorType {
@@ -218,18 +233,7 @@ impl RPCErrorType {
         match self {
             RPCErrorType::Unreachable => Unreachable::new(&AnyError::error(msg)).into(),
             RPCErrorType::NetworkError => NetworkError::new(&AnyError::error(msg)).into(),
-            RPCErrorType::PayloadTooLarge { action, entries_hint } => match action {
-                RPCTypes::Vote => {
-                    unreachable!("Vote RPC should not be too large")
-                }
-                RPCTypes::AppendEntries => PayloadTooLarge::new_entries_hint(*entries_hint).into(),
-                RPCTypes::InstallSnapshot => {
-                    unreachable!("InstallSnapshot RPC should not be too large")
-                }
-                RPCTypes::TransferLeader => {
-                    unreachable!("TransferLeader RPC should not be too large")
-                }
-            },
+            RPCErrorType::PayloadTooLarge { entries_hint } => PayloadTooLarge::new_entries_hint(*entries_hint).into(),
         }
     }
 }
@@ -247,6 +251,8 @@ where C::SnapshotData: fmt::Debug
     InstallFullSnapshot(Snapshot<C>),
     Vote(VoteRequest<C>),
     TransferLeader(TransferLeaderRequest<C>),
+    CatchUp(CatchUpRequest<C>),
+    Hibernate(HibernateRequest<C>),
 }
 
 impl<C: RaftTypeConfig> RPCRequest<C>
@@ -259,6 +265,8 @@ where C::SnapshotData: fmt::Debug
             RPCRequest::InstallFullSnapshot(_) => RPCTypes::InstallSnapshot,
             RPCRequest::Vote(_) => RPCTypes::Vote,
             RPCRequest::TransferLeader(_) => RPCTypes::TransferLeader,
+            RPCRequest::CatchUp(_) => RPCTypes::CatchUp,
+            RPCRequest::Hibernate(_) => RPCTypes::Hibernate,
         }
     }
 }
@@ -299,14 +307,31 @@ pub struct TypedRaftRouter {
 
     /// A hook function to be called when before an RPC is sent to target node.
     rpc_pre_hook: Arc<Mutex<HashMap<RPCTypes, RPCPreHook>>>,
+
+    /// Per-link policies to drop, reorder or duplicate messages.
+    network_faults: NetworkFaults,
+
+    /// The protocol version a node claims in handshakes, to simulate a node running an older
+    /// version. A node not in it claims the current version.
+    protocol_versions: Arc<Mutex<HashMap<MemNodeId, ProtocolVersion>>>,
+
+    /// If true, the networks behave like one that does not implement handshake.
+    handshake_unsupported: Arc<AtomicBool>,
+
+    /// Builds the retry policy of every network, [`DefaultRetryPolicy`] if it is `None`.
+    retry_policy: Arc<Mutex<Option<RetryPolicyBuilder>>>,
 }
 
+/// Builds a [`RetryPolicy`] for a network connection.
+pub type RetryPolicyBuilder = Arc<dyn Fn() -> Box<dyn RetryPolicy<MemConfig>> + Send + Sync + 'static>;
+
 /// Default `RaftRouter` for memstore.
 pub type RaftRouter = TypedRaftRouter;
 
 pub struct Builder {
     config: Arc<Config>,
     send_delay: u64,
+    fault_seed: u64,
 }
 
 impl Builder {
@@ -315,6 +340,12 @@ impl Builder {
         self
     }
 
+    /// Seed of the RNG that decides which messages are affected by the network fault policies.
+    pub fn fault_seed(mut self, seed: u64) -> Self {
+        self.fault_seed = seed;
+        self
+    }
+
     pub fn build(self) -> TypedRaftRouter {
         let send_delay = {
             let send_delay = env::var("OPENRAFT_NETWORK_SEND_DELAY").ok();
@@ -335,13 +366,21 @@ impl Builder {
             append_entries_quota: Arc::new(Mutex::new(None)),
             rpc_count: Default::default(),
             rpc_pre_hook: Default::default(),
+            network_faults: NetworkFaults::new(self.fault_seed),
+            protocol_versions: Default::default(),
+            handshake_unsupported: Default::default(),
+            retry_policy: Default::default(),
         }
     }
 }
 
 impl TypedRaftRouter {
     pub fn builder(config: Arc<Config>) -> Builder {
-        Builder { config, send_delay: 0 }
+        Builder {
+            config,
+            send_delay: 0,
+            fault_seed: 0,
+        }
     }
 
     /// Create a new instance.
@@ -380,6 +419,31 @@ impl TypedRaftRouter {
         self.rpc_count.lock().unwrap().clone()
     }
 
+    /// Make node `id` claim protocol `version` in handshakes, as if it runs an older Openraft.
+    pub fn set_protocol_version(&self, id: MemNodeId, version: ProtocolVersion) {
+        self.protocol_versions.lock().unwrap().insert(id, version);
+    }
+
+    fn protocol_version(&self, id: MemNodeId) -> Option<ProtocolVersion> {
+        self.protocol_versions.lock().unwrap().get(&id).copied()
+    }
+
+    /// Make every network answer a handshake with `Ok(None)`, as if it does not implement it.
+    pub fn set_handshake_unsupported(&self, unsupported: bool) {
+        self.handshake_unsupported.store(unsupported, Ordering::Relaxed);
+    }
+
+    /// Use the [`RetryPolicy`] built by `f` for the networks created from now on.
+    pub fn set_retry_policy<F>(&self, f: F)
+    where F: Fn() -> Box<dyn RetryPolicy<MemConfig>> + Send + Sync + 'static {
+        *self.retry_policy.lock().unwrap() = Some(Arc::new(f));
+    }
+
+    /// The fault policies applied to the messages between nodes.
+    pub fn network_faults(&self) -> &NetworkFaults {
+        &self.network_faults
+    }
+
     /// Create a cluster: 0 is the initial leader, others are voters and learners
     ///
     /// NOTE: it create a single node cluster first, then change it to a multi-voter cluster.
@@ -496,10 +560,23 @@ impl TypedRaftRouter {
     #[tracing::instrument(level = "debug", skip_all)]
     pub async fn new_raft_node_with_sto(&mut self, id: MemNodeId, log_store: MemLogStore, sm: MemStateMachine) {
         let node = Raft::new(id, self.config.clone(), self.clone(), log_store.clone(), sm.clone()).await.unwrap();
+        node.enable_log_cache();
         let mut rt = self.nodes.lock().unwrap();
         rt.insert(id, (node, log_store, sm));
     }
 
+    /// Create a node that applies logs to `sm`.
+    ///
+    /// The `MemStateMachine` in the storage handle of this node is not used.
+    pub async fn new_raft_node_with_sm<SM>(&mut self, id: MemNodeId, sm: SM)
+    where SM: RaftStateMachine<MemConfig> {
+        let (log_store, mem_sm) = self.new_store();
+        let node = Raft::new(id, self.config.clone(), self.clone(), log_store.clone(), sm).await.unwrap();
+        node.enable_log_cache();
+        let mut rt = self.nodes.lock().unwrap();
+        rt.insert(id, (node, log_store, mem_sm));
+    }
+
     /// Remove the target node from the routing table & isolation.
     pub fn remove_node(&mut self, id: MemNodeId) -> Option<(MemRaft, MemLogStore, MemStateMachine)> {
         let opt_handles = {
@@ -1009,16 +1086,22 @@ impl TypedRaftRouter {
 }
 
 impl RaftNetworkFactory<MemConfig> for TypedRaftRouter {
-    type Network = RaftRouterNetwork;
+    type Network = FaultyNetwork<RaftRouterNetwork>;
 
     async fn new_client(&mut self, target: MemNodeId, _node: &()) -> Self::Network {
-        RaftRouterNetwork {
+        let network = RaftRouterNetwork {
             target,
             owner: self.clone(),
-        }
+        };
+        self.network_faults.wrap(network, target)
+    }
+
+    fn protocol_version(&self) -> ProtocolVersion {
+        ProtocolVersion::CURRENT
     }
 }
 
+#[derive(Clone)]
 pub struct RaftRouterNetwork {
     target: MemNodeId,
     owner: TypedRaftRouter,
@@ -1169,6 +1252,106 @@ impl RaftNetworkV2<MemConfig> for RaftRouterNetwork {
             ))))
         })
     }
+
+    async fn catch_up(
+        &mut self,
+        rpc: CatchUpRequest<MemConfig>,
+        _option: RPCOption,
+    ) -> Result<CatchUpResponse<MemConfig>, RPCError<MemConfig>> {
+        let from_id = rpc.vote.to_leader_node_id().unwrap();
+
+        self.owner.count_rpc(RPCTypes::CatchUp);
+        self.owner.call_rpc_pre_hook(rpc.clone(), from_id, self.target)?;
+        self.owner.emit_rpc_error(from_id, self.target)?;
+        self.owner.rand_send_delay().await;
+
+        let node = self.owner.get_raft_handle(&self.target)?;
+
+        let resp = node.handle_catch_up(rpc).await;
+        resp.map_err(|e| {
+            RPCError::Unreachable(Unreachable::new(&AnyError::error(format!(
+                "error: {} target={}",
+                e, self.target
+            ))))
+        })
+    }
+
+    async fn hibernate(
+        &mut self,
+        rpc: HibernateRequest<MemConfig>,
+        _option: RPCOption,
+    ) -> Result<(), RPCError<MemConfig>> {
+        let from_id = rpc.vote.to_leader_node_id().unwrap();
+
+        self.owner.count_rpc(RPCTypes::Hibernate);
+        self.owner.call_rpc_pre_hook(rpc.clone(), from_id, self.target)?;
+        self.owner.emit_rpc_error(from_id, self.target)?;
+        self.owner.rand_send_delay().await;
+
+        let node = self.owner.get_raft_handle(&self.target)?;
+
+        let resp = node.handle_hibernate(rpc).await;
+        resp.map_err(|e| {
+            RPCError::Unreachable(Unreachable::new(&AnyError::error(format!(
+                "error: {} target={}",
+                e, self.target
+            ))))
+        })
+    }
+
+    async fn forward_client_write(
+        &mut self,
+        app_data: ClientRequest,
+        _option: RPCOption,
+    ) -> Result<ClientWriteResponse<MemConfig>, RPCError<MemConfig, RaftError<MemConfig, ClientWriteError<MemConfig>>>>
+    {
+        self.owner.count_rpc(RPCTypes::ClientWrite);
+        self.owner.rand_send_delay().await;
+
+        let node = self.owner.get_raft_handle(&self.target)?;
+
+        let resp = node.handle_forwarded_client_write(app_data).await;
+        resp.map_err(|e| RPCError::RemoteError(RemoteError::new(self.target, e)))
+    }
+
+    async fn handshake(
+        &mut self,
+        mut req: HandshakeRequest<MemConfig>,
+        _option: RPCOption,
+    ) -> Result<Option<HandshakeResponse>, RPCError<MemConfig>> {
+        self.owner.count_rpc(RPCTypes::Handshake);
+        self.owner.rand_send_delay().await;
+
+        if self.owner.handshake_unsupported.load(Ordering::Relaxed) {
+            return Ok(None);
+        }
+
+        if let Some(v) = self.owner.protocol_version(req.from) {
+            req.version = v;
+        }
+
+        let node = self.owner.get_raft_handle(&self.target)?;
+
+        let mut resp = node.handle_handshake(req).await.map_err(|e| {
+            RPCError::Unreachable(Unreachable::new(&AnyError::error(format!(
+                "error: {} target={}",
+                e, self.target
+            ))))
+        })?;
+
+        if let Some(v) = self.owner.protocol_version(self.target) {
+            resp.version = v;
+        }
+        Ok(Some(resp))
+    }
+
+    fn retry_policy(&self) -> Box<dyn RetryPolicy<MemConfig>> {
+        let builder = self.owner.retry_policy.lock().unwrap().clone();
+        match builder {
+            Some(f) => f(),
+            None => Box::new(DefaultRetryPolicy),
+        }
+    }
 }
 
 pub enum ValueTest<T> {
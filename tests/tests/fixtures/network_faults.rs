@@ -0,0 +1,408 @@
+//! Message level fault injection for any [`RaftNetworkV2`] implementation.
+//!
+//! [`FaultyNetwork`] wraps a connection and, according to the [`FaultPolicy`] of the link, drops a
+//! request, drops the response after the request is applied, delivers a request twice, or holds a
+//! request back so that it is delivered after newer ones. All the decisions are drawn from a
+//! seeded RNG shared by every link of a [`NetworkFaults`].
+
+use std::collections::BTreeMap;
+use std::fmt;
+use std::future::Future;
+use std::sync::Arc;
+use std::sync::Mutex;
+use std::time::Duration;
+
+use anyerror::AnyError;
+use openraft::error::ClientWriteError;
+use openraft::error::NetworkError;
+use openraft::error::RPCError;
+use openraft::error::RaftError;
+use openraft::error::ReplicationClosed;
+use openraft::error::StreamingError;
+use openraft::network::retry::RetryPolicy;
+use openraft::network::v2::RaftNetworkV2;
+use openraft::network::Backoff;
+use openraft::network::RPCOption;
+use openraft::raft::AppendEntriesRequest;
+use openraft::raft::AppendEntriesResponse;
+use openraft::raft::CatchUpRequest;
+use openraft::raft::CatchUpResponse;
+use openraft::raft::ClientWriteResponse;
+use openraft::raft::HandshakeRequest;
+use openraft::raft::HandshakeResponse;
+use openraft::raft::HibernateRequest;
+use openraft::raft::SnapshotResponse;
+use openraft::raft::TransferLeaderRequest;
+use openraft::raft::VoteRequest;
+use openraft::raft::VoteResponse;
+use openraft::storage::Snapshot;
+use openraft::OptionalSend;
+use openraft::RPCTypes;
+use openraft::Vote;
+use openraft_memstore::ClientRequest;
+use openraft_memstore::MemNodeId;
+use openraft_memstore::TypeConfig as MemConfig;
+use rand::rngs::StdRng;
+use rand::Rng;
+use rand::SeedableRng;
+
+use crate::fixtures::TestingVoteExt;
+
+/// Probabilities of the faults injected into the messages of a link.
+///
+/// At most one of `drop_request`, `reorder` and `drop_response` applies to a message;
+/// `duplicate` applies to a delivered request independently.
+#[derive(Debug, Clone, Default)]
+pub struct FaultPolicy {
+    /// The request is not delivered and the sender gets a [`NetworkError`].
+    pub drop_request: f64,
+
+    /// The request is delivered in the background after a random delay up to `max_delay`, while
+    /// the sender gets a [`NetworkError`] at once and moves on to newer requests.
+    pub reorder: f64,
+
+    /// The request is delivered and applied, but the sender gets a [`NetworkError`].
+    pub drop_response: f64,
+
+    /// The request is delivered once more in the background after a random delay up to
+    /// `max_delay`.
+    pub duplicate: f64,
+
+    /// The upper bound of the delay of a reordered or duplicated request.
+    pub max_delay: Duration,
+
+    /// Every request that is delivered is sent after this delay, as if on a slow link.
+    pub latency: Duration,
+}
+
+impl FaultPolicy {
+    /// A policy that applies every kind of fault with the same probability `p`.
+    pub fn uniform(p: f64, max_delay: Duration) -> Self {
+        Self {
+            drop_request: p,
+            reorder: p,
+            drop_response: p,
+            duplicate: p,
+            max_delay,
+            latency: Duration::ZERO,
+        }
+    }
+}
+
+/// Count of the injected faults.
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
+pub struct FaultStats {
+    pub dropped_requests: u64,
+    pub reordered: u64,
+    pub dropped_responses: u64,
+    pub duplicated: u64,
+}
+
+/// What to do with a single request.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+enum Fate {
+    Deliver,
+    DropRequest,
+    Reorder(Duration),
+    DropResponse,
+}
+
+struct Inner {
+    rng: StdRng,
+    default_policy: FaultPolicy,
+    link_policies: BTreeMap<(MemNodeId, MemNodeId), FaultPolicy>,
+    stats: FaultStats,
+}
+
+/// Seeded fault policies of every link `(from, to)` of a network.
+#[derive(Clone)]
+pub struct NetworkFaults {
+    inner: Arc<Mutex<Inner>>,
+}
+
+impl NetworkFaults {
+    /// Create a network without faults, with the RNG seeded by `seed`.
+    pub fn new(seed: u64) -> Self {
+        Self {
+            inner: Arc::new(Mutex::new(Inner {
+                rng: StdRng::seed_from_u64(seed),
+                default_policy: FaultPolicy::default(),
+                link_policies: BTreeMap::new(),
+                stats: FaultStats::default(),
+            })),
+        }
+    }
+
+    /// Set the policy of every link that does not have its own.
+    pub fn set_default_policy(&self, policy: FaultPolicy) {
+        self.inner.lock().unwrap().default_policy = policy;
+    }
+
+    /// Set or remove the policy of the link from `from` to `to`.
+    pub fn set_link_policy(&self, from: MemNodeId, to: MemNodeId, policy: Option<FaultPolicy>) {
+        let mut inner = self.inner.lock().unwrap();
+        if let Some(policy) = policy {
+            inner.link_policies.insert((from, to), policy);
+        } else {
+            inner.link_policies.remove(&(from, to));
+        }
+    }
+
+    /// Remove all policies.
+    pub fn clear(&self) {
+        let mut inner = self.inner.lock().unwrap();
+        inner.default_policy = FaultPolicy::default();
+        inner.link_policies.clear();
+    }
+
+    pub fn stats(&self) -> FaultStats {
+        self.inner.lock().unwrap().stats
+    }
+
+    /// Decide the fate of a request and whether to duplicate it, and after how long.
+    ///
+    /// It also returns the latency of the link.
+    fn decide(&self, from: MemNodeId, to: MemNodeId) -> (Fate, Option<Duration>, Duration) {
+        let mut inner = self.inner.lock().unwrap();
+        let inner = &mut *inner;
+
+        let policy = inner.link_policies.get(&(from, to)).unwrap_or(&inner.default_policy);
+        let rng = &mut inner.rng;
+
+        let delay = |rng: &mut StdRng| {
+            let max = policy.max_delay.as_millis() as u64;
+            Duration::from_millis(rng.random_range(0..=max))
+        };
+
+        let fate = if rng.random_bool(policy.drop_request) {
+            inner.stats.dropped_requests += 1;
+            Fate::DropRequest
+        } else if rng.random_bool(policy.reorder) {
+            inner.stats.reordered += 1;
+            Fate::Reorder(delay(rng))
+        } else if rng.random_bool(policy.drop_response) {
+            inner.stats.dropped_responses += 1;
+            Fate::DropResponse
+        } else {
+            Fate::Deliver
+        };
+
+        let duplicate = if fate != Fate::DropRequest && rng.random_bool(policy.duplicate) {
+            inner.stats.duplicated += 1;
+            Some(delay(rng))
+        } else {
+            None
+        };
+
+        (fate, duplicate, policy.latency)
+    }
+
+    /// Wrap a connection to `target`.
+    pub fn wrap<N>(&self, inner: N, target: MemNodeId) -> FaultyNetwork<N> {
+        FaultyNetwork {
+            target,
+            inner,
+            faults: self.clone(),
+        }
+    }
+}
+
+/// A [`RaftNetworkV2`] that injects faults into the messages sent by the wrapped connection.
+///
+/// The source of a message is the leader in the vote it carries. Reordered and duplicated
+/// requests are sent through clones of the wrapped connection.
+pub struct FaultyNetwork<N> {
+    target: MemNodeId,
+    inner: N,
+    faults: NetworkFaults,
+}
+
+impl<N> FaultyNetwork<N>
+where N: Clone + Send + 'static
+{
+    async fn call<Req, Resp, E, F, Fut>(&mut self, from: MemNodeId, typ: RPCTypes, req: Req, f: F) -> Result<Resp, E>
+    where
+        Req: Clone + Send + 'static,
+        Resp: Send + 'static,
+        E: From<RPCError<MemConfig>> + Send + 'static,
+        F: Fn(N, Req) -> Fut + Send + Clone + 'static,
+        Fut: Future<Output = Result<Resp, E>> + Send + 'static,
+    {
+        let (fate, duplicate, latency) = self.faults.decide(from, self.target);
+
+        if fate != Fate::Deliver {
+            tracing::debug!("inject fault {:?}: {} {} -> {}", fate, typ, from, self.target);
+        }
+
+        if let Some(delay) = duplicate {
+            self.deliver_later(delay, req.clone(), f.clone());
+        }
+
+        if !latency.is_zero() && matches!(fate, Fate::Deliver | Fate::DropResponse) {
+            tokio::time::sleep(latency).await;
+        }
+
+        match fate {
+            Fate::Deliver => f(self.inner.clone(), req).await,
+            Fate::DropRequest => Err(self.error(typ, "request dropped").into()),
+            Fate::Reorder(delay) => {
+                self.deliver_later(delay, req, f);
+                Err(self.error(typ, "request delayed").into())
+            }
+            Fate::DropResponse => {
+                let _ = f(self.inner.clone(), req).await;
+                Err(self.error(typ, "response dropped").into())
+            }
+        }
+    }
+
+    fn deliver_later<Req, Resp, E, F, Fut>(&self, delay: Duration, req: Req, f: F)
+    where
+        Req: Send + 'static,
+        Resp: Send + 'static,
+        E: Send + 'static,
+        F: Fn(N, Req) -> Fut + Send + 'static,
+        Fut: Future<Output = Result<Resp, E>> + Send + 'static,
+    {
+        let inner = self.inner.clone();
+        tokio::spawn(async move {
+            tokio::time::sleep(delay).await;
+            let _ = f(inner, req).await;
+        });
+    }
+
+    fn error(&self, typ: RPCTypes, reason: &str) -> RPCError<MemConfig> {
+        let msg = format!("{}: {} to {}", reason, typ, self.target);
+        NetworkError::new(&AnyError::error(msg)).into()
+    }
+}
+
+impl<N> RaftNetworkV2<MemConfig> for FaultyNetwork<N>
+where N: RaftNetworkV2<MemConfig> + Clone + Send + 'static
+{
+    async fn append_entries(
+        &mut self,
+        rpc: AppendEntriesRequest<MemConfig>,
+        option: RPCOption,
+    ) -> Result<AppendEntriesResponse<MemConfig>, RPCError<MemConfig>> {
+        let from = leader_of(&rpc.vote);
+        self.call(from, RPCTypes::AppendEntries, rpc, move |mut n, rpc| {
+            let option = option.clone();
+            async move { n.append_entries(rpc, option).await }
+        })
+        .await
+    }
+
+    async fn vote(
+        &mut self,
+        rpc: VoteRequest<MemConfig>,
+        option: RPCOption,
+    ) -> Result<VoteResponse<MemConfig>, RPCError<MemConfig>> {
+        let from = leader_of(&rpc.vote);
+        self.call(from, RPCTypes::Vote, rpc, move |mut n, rpc| {
+            let option = option.clone();
+            async move { n.vote(rpc, option).await }
+        })
+        .await
+    }
+
+    async fn full_snapshot(
+        &mut self,
+        vote: Vote<MemConfig>,
+        snapshot: Snapshot<MemConfig>,
+        _cancel: impl Future<Output = ReplicationClosed> + OptionalSend + 'static,
+        option: RPCOption,
+    ) -> Result<SnapshotResponse<MemConfig>, StreamingError<MemConfig>> {
+        let from = leader_of(&vote);
+        self.call(from, RPCTypes::InstallSnapshot, snapshot, move |mut n, snapshot| {
+            let option = option.clone();
+            async move { n.full_snapshot(vote, snapshot, futures::future::pending(), option).await }
+        })
+        .await
+    }
+
+    async fn transfer_leader(
+        &mut self,
+        req: TransferLeaderRequest<MemConfig>,
+        option: RPCOption,
+    ) -> Result<(), RPCError<MemConfig>> {
+        let from = leader_of(req.from_leader());
+        self.call(from, RPCTypes::TransferLeader, req, move |mut n, req| {
+            let option = option.clone();
+            async move { n.transfer_leader(req, option).await }
+        })
+        .await
+    }
+
+    async fn catch_up(
+        &mut self,
+        rpc: CatchUpRequest<MemConfig>,
+        option: RPCOption,
+    ) -> Result<CatchUpResponse<MemConfig>, RPCError<MemConfig>> {
+        let from = leader_of(&rpc.vote);
+        self.call(from, RPCTypes::CatchUp, rpc, move |mut n, rpc| {
+            let option = option.clone();
+            async move { n.catch_up(rpc, option).await }
+        })
+        .await
+    }
+
+    async fn hibernate(
+        &mut self,
+        rpc: HibernateRequest<MemConfig>,
+        option: RPCOption,
+    ) -> Result<(), RPCError<MemConfig>> {
+        let from = leader_of(&rpc.vote);
+        self.call(from, RPCTypes::Hibernate, rpc, move |mut n, rpc| {
+            let option = option.clone();
+            async move { n.hibernate(rpc, option).await }
+        })
+        .await
+    }
+
+    /// The sender of a client write is not known, thus no fault is injected.
+    async fn forward_client_write(
+        &mut self,
+        app_data: ClientRequest,
+        option: RPCOption,
+    ) -> Result<ClientWriteResponse<MemConfig>, RPCError<MemConfig, RaftError<MemConfig, ClientWriteError<MemConfig>>>>
+    {
+        self.inner.forward_client_write(app_data, option).await
+    }
+
+    async fn handshake(
+        &mut self,
+        req: HandshakeRequest<MemConfig>,
+        option: RPCOption,
+    ) -> Result<Option<HandshakeResponse>, RPCError<MemConfig>> {
+        let from = req.from;
+        self.call(from, RPCTypes::Handshake, req, move |mut n, req| {
+            let option = option.clone();
+            async move { n.handshake(req, option).await }
+        })
+        .await
+    }
+
+    fn backoff(&self) -> Backoff {
+        self.inner.backoff()
+    }
+
+    fn retry_policy(&self) -> Box<dyn RetryPolicy<MemConfig>> {
+        self.inner.retry_policy()
+    }
+}
+
+fn leader_of(vote: &Vote<MemConfig>) -> MemNodeId {
+    vote.to_leader_node_id().unwrap()
+}
+
+impl fmt::Debug for NetworkFaults {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        let inner = self.inner.lock().unwrap();
+        f.debug_struct("NetworkFaults")
+            .field("default_policy", &inner.default_policy)
+            .field("link_policies", &inner.link_policies)
+            .field("stats", &inner.stats)
+            .finish()
+    }
+}
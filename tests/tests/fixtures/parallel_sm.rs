@@ -0,0 +1,223 @@
+//! A state machine that applies requests of different clients in parallel.
+//!
+//! It implements the same key-value store as memstore: a request `ClientRequest{client, status}`
+//! sets key `client` to `status` and returns the previous value. Every key has its own lock, so
+//! that the entries of a wave accepted by [`RaftStateMachine::try_apply_parallel()`] do not wait
+//! for each other.
+
+use std::collections::BTreeMap;
+use std::hash::DefaultHasher;
+use std::hash::Hash;
+use std::hash::Hasher;
+use std::io::Cursor;
+use std::sync::Arc;
+use std::sync::Mutex;
+
+use openraft::base::BoxFuture;
+use openraft::storage::RaftSnapshotBuilder;
+use openraft::storage::RaftStateMachine;
+use openraft::storage::Snapshot;
+use openraft::Entry;
+use openraft::EntryPayload;
+use openraft::LogId;
+use openraft::LogIdOptionExt;
+use openraft::OptionalSend;
+use openraft::SnapshotMeta;
+use openraft::StorageError;
+use openraft::StoredMembership;
+use openraft_memstore::ClientResponse;
+use openraft_memstore::TypeConfig as MemConfig;
+use tokio::sync::Barrier;
+
+/// A key-value state machine that applies requests of different clients in parallel.
+#[derive(Debug, Clone, Default)]
+pub struct ParallelStateMachine {
+    inner: Arc<Inner>,
+}
+
+#[derive(Debug, Default)]
+struct Inner {
+    /// The status of every client, each behind its own lock.
+    statuses: Mutex<BTreeMap<String, Arc<Mutex<Option<String>>>>>,
+
+    applied: Mutex<Applied>,
+
+    /// A barrier and the number of entries applied in parallel that still have to wait on it.
+    barrier: Mutex<Option<(Arc<Barrier>, usize)>>,
+
+    /// The last built or installed snapshot.
+    snapshot: Mutex<Option<(SnapshotMeta<MemConfig>, Vec<u8>)>>,
+}
+
+#[derive(Debug, Default)]
+struct Applied {
+    last_applied: Option<LogId<MemConfig>>,
+
+    last_membership: StoredMembership<MemConfig>,
+
+    /// Entries applied in parallel that are done, but not all the entries before them.
+    done: BTreeMap<u64, LogId<MemConfig>>,
+}
+
+impl ParallelStateMachine {
+    /// Let the next `n` entries applied in parallel wait until all of them are being applied.
+    ///
+    /// The entries are never applied if they are not run in parallel.
+    pub fn set_barrier(&self, n: usize) {
+        *self.inner.barrier.lock().unwrap() = Some((Arc::new(Barrier::new(n)), n));
+    }
+
+    /// Returns the status of every client.
+    pub fn statuses(&self) -> BTreeMap<String, String> {
+        let statuses = self.inner.statuses.lock().unwrap();
+        statuses.iter().filter_map(|(k, v)| Some((k.clone(), v.lock().unwrap().clone()?))).collect()
+    }
+
+    /// Returns the lock of the status of a client.
+    fn status(&self, client: &str) -> Arc<Mutex<Option<String>>> {
+        let mut statuses = self.inner.statuses.lock().unwrap();
+        statuses.entry(client.to_string()).or_default().clone()
+    }
+
+    fn apply_entry(&self, entry: Entry<MemConfig>) -> ClientResponse {
+        let previous = match entry.payload {
+            EntryPayload::Blank => None,
+            EntryPayload::Normal(ref data) => {
+                let status = self.status(&data.client);
+                let mut status = status.lock().unwrap();
+                status.replace(data.status.clone())
+            }
+            EntryPayload::Membership(ref mem) => {
+                let mut applied = self.inner.applied.lock().unwrap();
+                applied.last_membership = StoredMembership::new(Some(entry.log_id), mem.clone());
+                None
+            }
+        };
+
+        // Entries applied in parallel may complete out of order,
+        // the last applied log id advances only when all the entries before it are applied.
+        let mut applied = self.inner.applied.lock().unwrap();
+        let Applied { last_applied, done, .. } = &mut *applied;
+        done.insert(entry.log_id.index, entry.log_id);
+        while let Some(first) = done.first_entry() {
+            if *first.key() != last_applied.next_index() {
+                break;
+            }
+            *last_applied = Some(first.remove());
+        }
+
+        ClientResponse(previous)
+    }
+
+    async fn apply_parallel(self, entry: Entry<MemConfig>) -> Result<ClientResponse, StorageError<MemConfig>> {
+        tracing::debug!(%entry.log_id, "apply in parallel");
+
+        let barrier = {
+            let mut b = self.inner.barrier.lock().unwrap();
+            match b.as_mut() {
+                Some((barrier, n)) if *n > 0 => {
+                    *n -= 1;
+                    Some(barrier.clone())
+                }
+                _ => None,
+            }
+        };
+        if let Some(barrier) = barrier {
+            barrier.wait().await;
+        }
+
+        Ok(self.apply_entry(entry))
+    }
+}
+
+impl RaftSnapshotBuilder<MemConfig> for ParallelStateMachine {
+    async fn build_snapshot(&mut self) -> Result<Snapshot<MemConfig>, StorageError<MemConfig>> {
+        let (last_applied, last_membership) = self.applied_state().await?;
+
+        let data = serde_json::to_vec(&self.statuses()).map_err(|e| StorageError::read_state_machine(&e))?;
+
+        let meta = SnapshotMeta {
+            last_log_id: last_applied,
+            last_membership,
+            snapshot_id: format!("{}", last_applied.next_index()),
+        };
+
+        *self.inner.snapshot.lock().unwrap() = Some((meta.clone(), data.clone()));
+
+        Ok(Snapshot {
+            meta,
+            snapshot: Box::new(Cursor::new(data)),
+        })
+    }
+}
+
+impl RaftStateMachine<MemConfig> for ParallelStateMachine {
+    type SnapshotBuilder = Self;
+
+    async fn applied_state(
+        &mut self,
+    ) -> Result<(Option<LogId<MemConfig>>, StoredMembership<MemConfig>), StorageError<MemConfig>> {
+        let applied = self.inner.applied.lock().unwrap();
+        Ok((applied.last_applied, applied.last_membership.clone()))
+    }
+
+    async fn apply<I>(&mut self, entries: I) -> Result<Vec<ClientResponse>, StorageError<MemConfig>>
+    where
+        I: IntoIterator<Item = Entry<MemConfig>> + OptionalSend,
+        I::IntoIter: OptionalSend,
+    {
+        Ok(entries.into_iter().map(|entry| self.apply_entry(entry)).collect())
+    }
+
+    /// Requests of different clients update different keys, thus they are applied in parallel.
+    fn try_apply_parallel(
+        &self,
+        entry: Entry<MemConfig>,
+    ) -> Result<(u64, BoxFuture<'static, Result<ClientResponse, StorageError<MemConfig>>>), Entry<MemConfig>> {
+        let EntryPayload::Normal(ref data) = entry.payload else {
+            return Err(entry);
+        };
+
+        let mut hasher = DefaultHasher::new();
+        data.client.hash(&mut hasher);
+
+        Ok((hasher.finish(), Box::pin(self.clone().apply_parallel(entry))))
+    }
+
+    async fn get_snapshot_builder(&mut self) -> Self::SnapshotBuilder {
+        self.clone()
+    }
+
+    async fn begin_receiving_snapshot(&mut self) -> Result<Box<Cursor<Vec<u8>>>, StorageError<MemConfig>> {
+        Ok(Box::new(Cursor::new(Vec::new())))
+    }
+
+    async fn install_snapshot(
+        &mut self,
+        meta: &SnapshotMeta<MemConfig>,
+        snapshot: Box<Cursor<Vec<u8>>>,
+    ) -> Result<(), StorageError<MemConfig>> {
+        let data = snapshot.into_inner();
+        let statuses: BTreeMap<String, String> =
+            serde_json::from_slice(&data).map_err(|e| StorageError::read_snapshot(Some(meta.signature()), &e))?;
+
+        *self.inner.statuses.lock().unwrap() =
+            statuses.into_iter().map(|(k, v)| (k, Arc::new(Mutex::new(Some(v))))).collect();
+        *self.inner.applied.lock().unwrap() = Applied {
+            last_applied: meta.last_log_id,
+            last_membership: meta.last_membership.clone(),
+            done: BTreeMap::new(),
+        };
+        *self.inner.snapshot.lock().unwrap() = Some((meta.clone(), data));
+
+        Ok(())
+    }
+
+    async fn get_current_snapshot(&mut self) -> Result<Option<Snapshot<MemConfig>>, StorageError<MemConfig>> {
+        let snapshot = self.inner.snapshot.lock().unwrap();
+        Ok(snapshot.as_ref().map(|(meta, data)| Snapshot {
+            meta: meta.clone(),
+            snapshot: Box::new(Cursor::new(data.clone())),
+        }))
+    }
+}
@@ -9,6 +9,7 @@ mod fixtures;
 
 mod t10_initialization;
 mod t11_shutdown;
+mod t20_multi_raft;
 mod t50_follower_restart_does_not_interrupt;
 mod t50_single_follower_restart;
 mod t50_single_leader_restart_re_apply_logs;
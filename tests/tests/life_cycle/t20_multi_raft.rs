@@ -0,0 +1,233 @@
+use std::collections::BTreeMap;
+use std::future::Future;
+use std::sync::Arc;
+use std::sync::Mutex;
+use std::time::Duration;
+
+use anyerror::AnyError;
+use anyhow::Result;
+use maplit::btreemap;
+use openraft::alias::VoteOf;
+use openraft::error::RPCError;
+use openraft::error::ReplicationClosed;
+use openraft::error::StreamingError;
+use openraft::error::Unreachable;
+use openraft::multi_raft::GroupNotFound;
+use openraft::multi_raft::MultiRaft;
+use openraft::multi_raft::MultiRaftError;
+use openraft::multi_raft::MultiRaftNetwork;
+use openraft::multi_raft::MultiRaftStorage;
+use openraft::network::v2::RaftNetworkV2;
+use openraft::network::RPCOption;
+use openraft::raft::AppendEntriesRequest;
+use openraft::raft::AppendEntriesResponse;
+use openraft::raft::SnapshotResponse;
+use openraft::raft::VoteRequest;
+use openraft::raft::VoteResponse;
+use openraft::Config;
+use openraft::RaftNetworkFactory;
+use openraft::Snapshot;
+use openraft::StorageError;
+use openraft_memstore::ClientRequest;
+use openraft_memstore::IntoMemClientRequest;
+use openraft_memstore::MemLogStore;
+use openraft_memstore::MemNodeId;
+use openraft_memstore::MemStateMachine;
+use openraft_memstore::TypeConfig as MemConfig;
+
+use crate::fixtures::ut_harness;
+
+type Host = MultiRaft<MemConfig, u64, HostNetwork, HostStorage>;
+
+type Hosts = Arc<Mutex<BTreeMap<MemNodeId, Arc<Host>>>>;
+
+/// Routes the RPCs of every group to the host of the target node.
+#[derive(Clone)]
+struct HostNetwork {
+    hosts: Hosts,
+}
+
+impl MultiRaftNetwork<MemConfig, u64> for HostNetwork {
+    type Factory = GroupNetwork;
+
+    fn group_network(&self, group: &u64) -> GroupNetwork {
+        GroupNetwork {
+            hosts: self.hosts.clone(),
+            group: *group,
+            target: 0,
+        }
+    }
+}
+
+#[derive(Clone)]
+struct GroupNetwork {
+    hosts: Hosts,
+    group: u64,
+    target: MemNodeId,
+}
+
+impl GroupNetwork {
+    fn target(&self) -> Result<Arc<Host>, Unreachable> {
+        let hosts = self.hosts.lock().unwrap();
+        let host = hosts.get(&self.target).cloned();
+        host.ok_or_else(|| Unreachable::new(&AnyError::error(format!("host {} not found", self.target))))
+    }
+}
+
+impl RaftNetworkFactory<MemConfig> for GroupNetwork {
+    type Network = GroupNetwork;
+
+    async fn new_client(&mut self, target: MemNodeId, _node: &()) -> Self::Network {
+        GroupNetwork { target, ..self.clone() }
+    }
+}
+
+impl RaftNetworkV2<MemConfig> for GroupNetwork {
+    async fn append_entries(
+        &mut self,
+        rpc: AppendEntriesRequest<MemConfig>,
+        _option: RPCOption,
+    ) -> Result<AppendEntriesResponse<MemConfig>, RPCError<MemConfig>> {
+        let host = self.target()?;
+        host.append_entries(&self.group, rpc).await.map_err(|e| RPCError::Unreachable(Unreachable::new(&e)))
+    }
+
+    async fn vote(
+        &mut self,
+        rpc: VoteRequest<MemConfig>,
+        _option: RPCOption,
+    ) -> Result<VoteResponse<MemConfig>, RPCError<MemConfig>> {
+        let host = self.target()?;
+        host.vote(&self.group, rpc).await.map_err(|e| RPCError::Unreachable(Unreachable::new(&e)))
+    }
+
+    async fn full_snapshot(
+        &mut self,
+        vote: VoteOf<MemConfig>,
+        snapshot: Snapshot<MemConfig>,
+        _cancel: impl Future<Output = ReplicationClosed> + Send + 'static,
+        _option: RPCOption,
+    ) -> Result<SnapshotResponse<MemConfig>, StreamingError<MemConfig>> {
+        let host = self.target()?;
+        host.install_full_snapshot(&self.group, vote, snapshot)
+            .await
+            .map_err(|e| StreamingError::Unreachable(Unreachable::new(&e)))
+    }
+}
+
+type MemStore = (Arc<MemLogStore>, Arc<MemStateMachine>);
+
+/// Keeps a memstore for each group.
+#[derive(Default)]
+struct HostStorage {
+    stores: Mutex<BTreeMap<u64, MemStore>>,
+}
+
+impl MultiRaftStorage<MemConfig, u64> for HostStorage {
+    type LogStorage = Arc<MemLogStore>;
+    type StateMachine = Arc<MemStateMachine>;
+
+    async fn open_group(&self, group: &u64) -> Result<(Self::LogStorage, Self::StateMachine), StorageError<MemConfig>> {
+        let mut stores = self.stores.lock().unwrap();
+        Ok(stores.entry(*group).or_insert_with(openraft_memstore::new_mem_store).clone())
+    }
+
+    async fn destroy_group(&self, group: &u64) -> Result<(), StorageError<MemConfig>> {
+        self.stores.lock().unwrap().remove(group);
+        Ok(())
+    }
+}
+
+/// Run two groups on three hosts, then remove one of them at runtime.
+#[tracing::instrument]
+#[test_harness::test(harness = ut_harness)]
+async fn multi_raft_add_and_remove_groups() -> Result<()> {
+    let config = Arc::new(
+        Config {
+            election_timeout_min: 500,
+            election_timeout_max: 1000,
+            ..Default::default()
+        }
+        .validate()?,
+    );
+
+    let hosts: Hosts = Default::default();
+
+    tracing::info!("--- start 3 hosts, each runs group 1 and 2");
+    {
+        for id in [0, 1, 2] {
+            let network = HostNetwork { hosts: hosts.clone() };
+            let host = Arc::new(Host::new(
+                id,
+                Duration::from_millis(75),
+                network,
+                HostStorage::default(),
+            ));
+            hosts.lock().unwrap().insert(id, host.clone());
+
+            for group in [1, 2] {
+                host.add_group(group, config.clone()).await?;
+            }
+        }
+    }
+
+    let host = |id: MemNodeId| hosts.lock().unwrap().get(&id).unwrap().clone();
+
+    tracing::info!("--- initialize group 1 on host 0 and group 2 on host 1");
+    {
+        let members = btreemap! {0=>(), 1=>(), 2=>()};
+        host(0).group(&1).unwrap().initialize(members.clone()).await?;
+        host(1).group(&2).unwrap().initialize(members).await?;
+    }
+
+    tracing::info!("--- every group elects its own leader and replicates its own logs");
+    {
+        for (group, leader) in [(1, 0), (2, 1)] {
+            let raft = host(leader).group(&group).unwrap();
+            raft.wait(timeout()).current_leader(leader, "leader elected").await?;
+
+            raft.client_write(ClientRequest::make_request("foo", group)).await?;
+            let log_index = raft.metrics().borrow().last_log_index;
+
+            for id in [0, 1, 2] {
+                host(id)
+                    .group(&group)
+                    .unwrap()
+                    .wait(timeout())
+                    .applied_index(log_index, format!("group {} on host {}", group, id))
+                    .await?;
+            }
+        }
+    }
+
+    tracing::info!("--- remove group 2 from every host, group 1 keeps working");
+    {
+        for id in [0, 1, 2] {
+            host(id).remove_group(&2).await?;
+            assert_eq!(vec![1], host(id).groups());
+        }
+
+        let rpc = AppendEntriesRequest {
+            vote: Default::default(),
+            prev_log_id: None,
+            leader_commit: None,
+            entries: vec![],
+        };
+        let res = host(0).append_entries(&2, rpc).await;
+        assert_eq!(MultiRaftError::GroupNotFound(GroupNotFound::new(2)), res.unwrap_err());
+
+        let raft = host(0).group(&1).unwrap();
+        raft.client_write(ClientRequest::make_request("foo", 3)).await?;
+    }
+
+    for id in [0, 1, 2] {
+        host(id).shutdown().await;
+    }
+    hosts.lock().unwrap().clear();
+
+    Ok(())
+}
+
+fn timeout() -> Option<Duration> {
+    Some(Duration::from_millis(5_000))
+}
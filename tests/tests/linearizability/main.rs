@@ -0,0 +1,11 @@
+#![cfg_attr(feature = "bt", feature(error_generic_member_access))]
+
+#[macro_use]
+#[path = "../fixtures/mod.rs"]
+mod fixtures;
+
+// The number indicate the preferred running order for these case.
+// See ./README.md
+
+mod t10_checker;
+mod t50_kv_under_faults;
@@ -0,0 +1,132 @@
+use crate::fixtures::linearizability::History;
+use crate::fixtures::linearizability::KvInput;
+
+fn w(key: &str, value: &str) -> KvInput {
+    KvInput::Write {
+        key: key.to_string(),
+        value: value.to_string(),
+    }
+}
+
+fn r(key: &str) -> KvInput {
+    KvInput::Read { key: key.to_string() }
+}
+
+fn some(v: &str) -> Option<String> {
+    Some(v.to_string())
+}
+
+#[test]
+fn checker_sequential_history() -> anyhow::Result<()> {
+    let h = History::new();
+
+    let a = h.invoke(1, w("x", "1"));
+    h.ok(a, None);
+    let b = h.invoke(1, w("x", "2"));
+    h.ok(b, some("1"));
+    let c = h.invoke(2, r("x"));
+    h.ok(c, some("2"));
+
+    h.check()?;
+
+    let h = History::new();
+
+    let a = h.invoke(1, w("x", "1"));
+    h.ok(a, None);
+    let b = h.invoke(1, w("x", "2"));
+    // The previous value returned by a write must be the last written one.
+    h.ok(b, None);
+
+    h.check().expect_err("write returns wrong previous value");
+
+    Ok(())
+}
+
+#[test]
+fn checker_concurrent_read() -> anyhow::Result<()> {
+    for observed in [None, some("1")] {
+        let h = History::new();
+
+        let a = h.invoke(1, w("x", "1"));
+        let b = h.invoke(2, r("x"));
+        h.ok(b, observed);
+        h.ok(a, None);
+
+        h.check()?;
+    }
+
+    Ok(())
+}
+
+#[test]
+fn checker_stale_read() -> anyhow::Result<()> {
+    let h = History::new();
+
+    let a = h.invoke(1, w("x", "1"));
+    h.ok(a, None);
+    let b = h.invoke(2, r("x"));
+    h.ok(b, None);
+
+    let err = h.check().expect_err("read after a completed write must see it");
+    assert!(
+        err.to_string().contains("history of key x is not linearizable"),
+        "{}",
+        err
+    );
+
+    // Keys are checked separately.
+    let h = History::new();
+
+    let a = h.invoke(1, w("x", "1"));
+    h.ok(a, None);
+    let b = h.invoke(2, r("y"));
+    h.ok(b, None);
+
+    h.check()?;
+
+    Ok(())
+}
+
+#[test]
+fn checker_unknown_outcome() -> anyhow::Result<()> {
+    // An operation with unknown outcome may take effect after the client gives up on it.
+    let h = History::new();
+
+    let _a = h.invoke(1, w("x", "1"));
+    let b = h.invoke(2, r("x"));
+    h.ok(b, None);
+    let c = h.invoke(2, r("x"));
+    h.ok(c, some("1"));
+
+    h.check()?;
+
+    // Or never take effect.
+    let h = History::new();
+
+    let _a = h.invoke(1, w("x", "1"));
+    let b = h.invoke(2, w("x", "2"));
+    h.ok(b, None);
+
+    h.check()?;
+
+    // But it does not take effect before it is invoked.
+    let h = History::new();
+
+    let a = h.invoke(2, r("x"));
+    h.ok(a, some("1"));
+    let _b = h.invoke(1, w("x", "1"));
+
+    h.check().expect_err("read a value before it is written");
+
+    // A failed operation is removed from the history.
+    let h = History::new();
+
+    let a = h.invoke(1, w("x", "1"));
+    h.fail(a);
+    let b = h.invoke(2, r("x"));
+    h.ok(b, some("1"));
+
+    h.check().expect_err("read a value that is never written");
+
+    Ok(())
+}
@@ -0,0 +1,100 @@
+use std::sync::Arc;
+use std::time::Duration;
+
+use anyhow::Result;
+use maplit::btreeset;
+use openraft::Config;
+use rand::rngs::StdRng;
+use rand::Rng;
+use rand::SeedableRng;
+
+use crate::fixtures::linearizability::FaultSchedule;
+use crate::fixtures::linearizability::History;
+use crate::fixtures::ut_harness;
+use crate::fixtures::RaftRouter;
+
+/// Linearizability of a key-value history under randomized network faults.
+///
+/// What does this test do?
+///
+/// - bring up a 3-node cluster.
+/// - run concurrent clients that write and linearizable-read a few shared keys, while a seeded
+///   schedule of faults (unreachable nodes, one-way network errors, random send delay) is applied.
+/// - check the recorded history is linearizable.
+#[tracing::instrument]
+#[test_harness::test(harness = ut_harness)]
+async fn kv_under_faults() -> Result<()> {
+    let config = Arc::new(
+        Config {
+            heartbeat_interval: 50,
+            election_timeout_min: 150,
+            election_timeout_max: 300,
+            ..Default::default()
+        }
+        .validate()?,
+    );
+
+    for seed in 0..3 {
+        tracing::info!(seed, "--- run with seed");
+
+        let mut router = RaftRouter::new(config.clone());
+        router.new_cluster(btreeset! {0,1,2}, btreeset! {}).await?;
+
+        let history = History::new();
+        let schedule = FaultSchedule::random(seed, &[0, 1, 2], 8);
+
+        let faults = tokio::spawn({
+            let schedule = schedule.clone();
+            let router = router.clone();
+            async move { schedule.run(router).await }
+        });
+
+        let mut clients = vec![];
+        for process in 0..4 {
+            clients.push(tokio::spawn(run_client(
+                router.clone(),
+                history.clone(),
+                seed,
+                process,
+                30,
+            )));
+        }
+
+        for c in clients {
+            c.await?;
+        }
+        faults.await?;
+
+        let ops = history.operations();
+        let completed = ops.iter().filter(|op| op.ret.is_some()).count();
+        tracing::info!(seed, "--- check {} operations, {} completed", ops.len(), completed);
+        assert!(completed > 0, "seed={}: no operation completed", seed);
+
+        history.check().map_err(|e| e.context(format!("seed={} schedule={:?}", seed, schedule)))?;
+    }
+
+    Ok(())
+}
+
+/// Issue `n` random operations on keys `k0..k2`, each against the current leader.
+///
+/// An operation that does not finish in time is abandoned and left with unknown outcome.
+async fn run_client(router: RaftRouter, history: History, seed: u64, process: u64, n: u64) {
+    let mut rng = StdRng::seed_from_u64(seed * 100 + process);
+
+    for i in 0..n {
+        let key = format!("k{}", rng.random_range(0..3));
+        let target = router.leader().unwrap_or_else(|| rng.random_range(0..3));
+
+        let op_timeout = Duration::from_millis(1_000);
+
+        if rng.random_bool(0.6) {
+            let value = format!("p{}-{}", process, i);
+            let _ = tokio::time::timeout(op_timeout, history.write(&router, process, target, &key, &value)).await;
+        } else {
+            let _ = tokio::time::timeout(op_timeout, history.read(&router, process, target, &key)).await;
+        }
+
+        tokio::time::sleep(Duration::from_millis(rng.random_range(0..50))).await;
+    }
+}
@@ -0,0 +1,597 @@
+use std::collections::hash_map::DefaultHasher;
+use std::collections::BTreeMap;
+use std::fmt;
+use std::fmt::Debug;
+use std::hash::Hash;
+use std::hash::Hasher;
+use std::io::Cursor;
+use std::ops::RangeBounds;
+use std::sync::atomic::AtomicBool;
+use std::sync::atomic::AtomicU64;
+use std::sync::atomic::Ordering;
+use std::sync::Arc;
+use std::sync::Mutex;
+use std::time::Duration;
+
+use anyerror::AnyError;
+use anyhow::Result;
+use maplit::btreeset;
+use openraft::alias::LogIdOf;
+use openraft::alias::VoteOf;
+use openraft::entry::RaftEntry;
+use openraft::entry::RaftPayload;
+use openraft::error::Fatal;
+use openraft::error::NetworkError;
+use openraft::error::RPCError;
+use openraft::error::ReplicationClosed;
+use openraft::error::StreamingError;
+use openraft::error::Unreachable;
+use openraft::network::v2::RaftNetworkV2;
+use openraft::network::RPCOption;
+use openraft::network::RaftNetworkFactory;
+use openraft::raft::AppendEntriesRequest;
+use openraft::raft::AppendEntriesResponse;
+use openraft::raft::SnapshotResponse;
+use openraft::raft::VoteRequest;
+use openraft::raft::VoteResponse;
+use openraft::storage::IOFlushed;
+use openraft::storage::RaftLogStorage;
+use openraft::storage::RaftStateMachine;
+use openraft::storage::Snapshot;
+use openraft::storage::SnapshotMeta;
+use openraft::vote::RaftLeaderIdExt;
+use openraft::Config;
+use openraft::Entry;
+use openraft::EntryPayload;
+use openraft::ErrorSubject;
+use openraft::LogState;
+use openraft::Membership;
+use openraft::OptionalSend;
+use openraft::Raft;
+use openraft::RaftLogReader;
+use openraft::RaftSnapshotBuilder;
+use openraft::RaftTypeConfig;
+use openraft::StorageError;
+use openraft::StoredMembership;
+use openraft::Vote;
+
+use crate::fixtures::ut_harness;
+
+openraft::declare_raft_types!(
+    pub ChecksumConfig:
+        D = u64,
+        R = u64,
+        Node = (),
+        Entry = ChecksumEntry,
+);
+
+type C = ChecksumConfig;
+
+/// A corrupted entry received by a follower is not persisted.
+///
+/// The follower accepts only the entries before it, and responds with `PartialSuccess`, so that
+/// the Leader resends the rest.
+#[tracing::instrument]
+#[test_harness::test(harness = ut_harness)]
+async fn follower_refuses_corrupted_entries() -> Result<()> {
+    let (raft, log_store, sm) = new_node().await?;
+
+    tracing::info!("--- append entries with a corrupted one");
+    {
+        let mut corrupted = normal(2, 20);
+        corrupted.entry.payload = EntryPayload::Normal(21);
+
+        let resp = raft
+            .append_entries(AppendEntriesRequest {
+                vote: leader_vote(),
+                prev_log_id: None,
+                entries: vec![membership(0), normal(1, 10), corrupted],
+                leader_commit: None,
+            })
+            .await?;
+
+        assert_eq!(AppendEntriesResponse::PartialSuccess(Some(log_id(1))), resp);
+        assert_eq!(vec![0, 1], log_store.indexes());
+
+        let m = raft.wait(timeout()).metrics(|m| m.last_checksum_error.is_some(), "checksum error").await?;
+        assert_eq!(
+            &ErrorSubject::Checksum(log_id(2)),
+            m.last_checksum_error.unwrap().subject()
+        );
+    }
+
+    tracing::info!("--- the resent entry is appended and applied");
+    {
+        let resp = raft
+            .append_entries(AppendEntriesRequest {
+                vote: leader_vote(),
+                prev_log_id: Some(log_id(1)),
+                entries: vec![normal(2, 20)],
+                leader_commit: Some(log_id(2)),
+            })
+            .await?;
+
+        assert_eq!(AppendEntriesResponse::Success, resp);
+        raft.wait(timeout()).applied_index(Some(2), "all applied").await?;
+        assert_eq!(30, sm.sum());
+    }
+
+    raft.shutdown().await?;
+    Ok(())
+}
+
+/// When a follower accepts none of the entries because the first one is corrupted in transit, the
+/// Leader does not resend them at once: the refusal is an error for the retry policy.
+#[tracing::instrument]
+#[test_harness::test(harness = ut_harness)]
+async fn leader_does_not_resend_refused_entries_at_once() -> Result<()> {
+    let config = Arc::new(
+        Config {
+            enable_elect: false,
+            enable_heartbeat: false,
+            ..Default::default()
+        }
+        .validate()?,
+    );
+
+    let (n1, _log_store, sm) = new_node().await?;
+
+    let network = CorruptingNetwork {
+        target: n1.clone(),
+        corrupt: Arc::new(AtomicBool::new(true)),
+        sent: Arc::new(AtomicU64::new(0)),
+    };
+    let n0 = Raft::new(
+        0,
+        config,
+        network.clone(),
+        LogStore::default(),
+        SumStateMachine::default(),
+    )
+    .await?;
+
+    tracing::info!("--- initialize node-0 as Leader");
+    {
+        n0.initialize(btreeset! {0}).await?;
+        n0.wait(timeout()).current_leader(0, "node-0 is Leader").await?;
+    }
+
+    tracing::info!("--- the first entry with a checksum sent to node-1 is always corrupted");
+    {
+        n0.add_learner(1, (), false).await?;
+
+        let m = n1.wait(timeout()).metrics(|m| m.last_checksum_error.is_some(), "checksum error").await?;
+        let err = m.last_checksum_error.unwrap();
+        assert!(matches!(err.subject(), ErrorSubject::Checksum(log_id) if log_id.index == 1));
+
+        tokio::time::sleep(Duration::from_millis(500)).await;
+
+        let sent = network.sent.load(Ordering::Relaxed);
+        assert!(sent <= 3, "refused entries are not resent at once, sent: {}", sent);
+    }
+
+    tracing::info!("--- entries are replicated when they are no longer corrupted");
+    {
+        network.corrupt.store(false, Ordering::Relaxed);
+        n0.client_write(5).await?;
+
+        n1.wait(timeout()).applied_index(Some(3), "replicated to node-1").await?;
+        assert_eq!(5, sm.sum());
+    }
+
+    n0.shutdown().await?;
+    n1.shutdown().await?;
+    Ok(())
+}
+
+/// A committed entry corrupted in the log store is not applied, and the node stops with a
+/// checksum error.
+#[tracing::instrument]
+#[test_harness::test(harness = ut_harness)]
+async fn corrupted_entry_is_not_applied() -> Result<()> {
+    let (raft, log_store, sm) = new_node().await?;
+
+    tracing::info!("--- append entries without committing them");
+    {
+        let resp = raft
+            .append_entries(AppendEntriesRequest {
+                vote: leader_vote(),
+                prev_log_id: None,
+                entries: vec![membership(0), normal(1, 10)],
+                leader_commit: None,
+            })
+            .await?;
+        assert_eq!(AppendEntriesResponse::Success, resp);
+    }
+
+    tracing::info!("--- corrupt a stored entry, then commit it");
+    {
+        log_store.corrupt(1);
+
+        raft.append_entries(AppendEntriesRequest {
+            vote: leader_vote(),
+            prev_log_id: Some(log_id(1)),
+            entries: vec![],
+            leader_commit: Some(log_id(1)),
+        })
+        .await?;
+
+        let m = raft.wait(timeout()).metrics(|m| m.running_state.is_err(), "node stops").await?;
+
+        let Err(Fatal::StorageError(err)) = m.running_state else {
+            panic!("expect a storage error, got: {:?}", m.running_state);
+        };
+        assert_eq!(&ErrorSubject::Checksum(log_id(1)), err.subject());
+        assert_eq!(0, sm.sum(), "nothing is applied");
+    }
+
+    Ok(())
+}
+
+/// Create node-1, a follower of node-0, that receives AppendEntries only from the test.
+async fn new_node() -> Result<(Raft<C>, LogStore, SumStateMachine)> {
+    let config = Arc::new(
+        Config {
+            enable_elect: false,
+            enable_heartbeat: false,
+            ..Default::default()
+        }
+        .validate()?,
+    );
+
+    let log_store = LogStore::default();
+    let sm = SumStateMachine::default();
+    let raft = Raft::new(1, config, NoNetwork, log_store.clone(), sm.clone()).await?;
+
+    Ok((raft, log_store, sm))
+}
+
+fn log_id(index: u64) -> LogIdOf<C> {
+    LogIdOf::<C>::new(<C as RaftTypeConfig>::LeaderId::new_committed(1, 0), index)
+}
+
+fn leader_vote() -> VoteOf<C> {
+    Vote::new_committed(1, 0)
+}
+
+fn membership(index: u64) -> ChecksumEntry {
+    let m = Membership::new_with_defaults(vec![btreeset! {0,1}], []);
+    ChecksumEntry::sealed(Entry::new_membership(log_id(index), m))
+}
+
+fn normal(index: u64, data: u64) -> ChecksumEntry {
+    ChecksumEntry::sealed(Entry::new_normal(log_id(index), data))
+}
+
+fn timeout() -> Option<Duration> {
+    Some(Duration::from_millis(5_000))
+}
+
+/// A log entry that carries a checksum of its log id and payload.
+#[derive(Debug, Clone)]
+#[derive(serde::Deserialize, serde::Serialize)]
+pub struct ChecksumEntry {
+    entry: Entry<C>,
+    checksum: Option<u64>,
+}
+
+impl ChecksumEntry {
+    fn sealed(entry: Entry<C>) -> Self {
+        let mut ent = Self { entry, checksum: None };
+        ent.checksum = ent.compute_checksum();
+        ent
+    }
+}
+
+impl fmt::Display for ChecksumEntry {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "{}", self.entry)
+    }
+}
+
+impl RaftPayload<C> for ChecksumEntry {
+    fn get_membership(&self) -> Option<Membership<C>> {
+        self.entry.get_membership()
+    }
+}
+
+impl RaftEntry<C> for ChecksumEntry {
+    fn new(log_id: LogIdOf<C>, payload: EntryPayload<C>) -> Self {
+        Self {
+            entry: Entry::new(log_id, payload),
+            checksum: None,
+        }
+    }
+
+    fn log_id_parts(&self) -> (&<C as RaftTypeConfig>::LeaderId, u64) {
+        self.entry.log_id_parts()
+    }
+
+    fn set_log_id(&mut self, new: LogIdOf<C>) {
+        self.entry.set_log_id(new);
+    }
+
+    fn checksum(&self) -> Option<u64> {
+        self.checksum
+    }
+
+    fn compute_checksum(&self) -> Option<u64> {
+        let mut hasher = DefaultHasher::new();
+        self.entry.log_id.to_string().hash(&mut hasher);
+        match &self.entry.payload {
+            EntryPayload::Blank => {}
+            EntryPayload::Normal(x) => x.hash(&mut hasher),
+            EntryPayload::Membership(m) => m.to_string().hash(&mut hasher),
+        }
+        Some(hasher.finish())
+    }
+
+    fn set_checksum(&mut self, checksum: u64) {
+        self.checksum = Some(checksum);
+    }
+}
+
+/// An in-memory log store whose entries can be corrupted by a test.
+#[derive(Debug, Clone, Default)]
+struct LogStore {
+    inner: Arc<Mutex<LogStoreInner>>,
+}
+
+#[derive(Debug, Default)]
+struct LogStoreInner {
+    vote: Option<VoteOf<C>>,
+    log: BTreeMap<u64, ChecksumEntry>,
+}
+
+impl LogStore {
+    fn indexes(&self) -> Vec<u64> {
+        self.inner.lock().unwrap().log.keys().copied().collect()
+    }
+
+    /// Change the payload of a stored entry without updating its checksum.
+    fn corrupt(&self, index: u64) {
+        let mut inner = self.inner.lock().unwrap();
+        let ent = inner.log.get_mut(&index).unwrap();
+        ent.entry.payload = EntryPayload::Normal(999);
+    }
+}
+
+impl RaftLogReader<C> for LogStore {
+    async fn try_get_log_entries<RB: RangeBounds<u64> + Clone + Debug + OptionalSend>(
+        &mut self,
+        range: RB,
+    ) -> Result<Vec<ChecksumEntry>, StorageError<C>> {
+        let inner = self.inner.lock().unwrap();
+        Ok(inner.log.range(range).map(|(_, ent)| ent.clone()).collect())
+    }
+
+    async fn read_vote(&mut self) -> Result<Option<VoteOf<C>>, StorageError<C>> {
+        Ok(self.inner.lock().unwrap().vote)
+    }
+}
+
+impl RaftLogStorage<C> for LogStore {
+    type LogReader = Self;
+
+    async fn get_log_state(&mut self) -> Result<LogState<C>, StorageError<C>> {
+        let inner = self.inner.lock().unwrap();
+        Ok(LogState {
+            last_purged_log_id: None,
+            last_log_id: inner.log.values().next_back().map(|ent| ent.log_id()),
+        })
+    }
+
+    async fn get_log_reader(&mut self) -> Self::LogReader {
+        self.clone()
+    }
+
+    async fn save_vote(&mut self, vote: &VoteOf<C>) -> Result<(), StorageError<C>> {
+        self.inner.lock().unwrap().vote = Some(*vote);
+        Ok(())
+    }
+
+    async fn append<I>(&mut self, entries: I, callback: IOFlushed<C>) -> Result<(), StorageError<C>>
+    where
+        I: IntoIterator<Item = ChecksumEntry> + OptionalSend,
+        I::IntoIter: OptionalSend,
+    {
+        {
+            let mut inner = self.inner.lock().unwrap();
+            for ent in entries {
+                inner.log.insert(ent.index(), ent);
+            }
+        }
+        callback.io_completed(Ok(()));
+        Ok(())
+    }
+
+    async fn truncate(&mut self, log_id: LogIdOf<C>) -> Result<(), StorageError<C>> {
+        self.inner.lock().unwrap().log.split_off(&log_id.index);
+        Ok(())
+    }
+
+    async fn purge(&mut self, log_id: LogIdOf<C>) -> Result<(), StorageError<C>> {
+        let mut inner = self.inner.lock().unwrap();
+        inner.log = inner.log.split_off(&(log_id.index + 1));
+        Ok(())
+    }
+}
+
+/// Adds up the applied values.
+#[derive(Debug, Clone, Default)]
+struct SumStateMachine {
+    inner: Arc<Mutex<SumState>>,
+}
+
+#[derive(Debug, Clone, Default)]
+struct SumState {
+    last_applied: Option<LogIdOf<C>>,
+    membership: StoredMembership<C>,
+    sum: u64,
+}
+
+impl SumStateMachine {
+    fn sum(&self) -> u64 {
+        self.inner.lock().unwrap().sum
+    }
+}
+
+impl RaftSnapshotBuilder<C> for SumStateMachine {
+    async fn build_snapshot(&mut self) -> Result<Snapshot<C>, StorageError<C>> {
+        let state = self.inner.lock().unwrap().clone();
+        Ok(Snapshot {
+            meta: SnapshotMeta {
+                last_log_id: state.last_applied,
+                last_membership: state.membership,
+                snapshot_id: "sum".to_string(),
+            },
+            snapshot: Box::new(Cursor::new(state.sum.to_be_bytes().to_vec())),
+        })
+    }
+}
+
+impl RaftStateMachine<C> for SumStateMachine {
+    type SnapshotBuilder = Self;
+
+    async fn applied_state(&mut self) -> Result<(Option<LogIdOf<C>>, StoredMembership<C>), StorageError<C>> {
+        let inner = self.inner.lock().unwrap();
+        Ok((inner.last_applied, inner.membership.clone()))
+    }
+
+    async fn apply<I>(&mut self, entries: I) -> Result<Vec<u64>, StorageError<C>>
+    where
+        I: IntoIterator<Item = ChecksumEntry> + OptionalSend,
+        I::IntoIter: OptionalSend,
+    {
+        let mut inner = self.inner.lock().unwrap();
+        let mut res = vec![];
+        for ent in entries {
+            inner.last_applied = Some(ent.log_id());
+            match ent.entry.payload {
+                EntryPayload::Blank => {}
+                EntryPayload::Normal(x) => inner.sum += x,
+                EntryPayload::Membership(m) => inner.membership = StoredMembership::new(inner.last_applied, m),
+            }
+            res.push(inner.sum);
+        }
+        Ok(res)
+    }
+
+    async fn get_snapshot_builder(&mut self) -> Self::SnapshotBuilder {
+        self.clone()
+    }
+
+    async fn begin_receiving_snapshot(&mut self) -> Result<Box<Cursor<Vec<u8>>>, StorageError<C>> {
+        Ok(Box::new(Cursor::new(vec![])))
+    }
+
+    async fn install_snapshot(
+        &mut self,
+        meta: &SnapshotMeta<C>,
+        snapshot: Box<Cursor<Vec<u8>>>,
+    ) -> Result<(), StorageError<C>> {
+        let sum = u64::from_be_bytes(snapshot.into_inner().try_into().unwrap());
+        *self.inner.lock().unwrap() = SumState {
+            last_applied: meta.last_log_id,
+            membership: meta.last_membership.clone(),
+            sum,
+        };
+        Ok(())
+    }
+
+    async fn get_current_snapshot(&mut self) -> Result<Option<Snapshot<C>>, StorageError<C>> {
+        Ok(None)
+    }
+}
+
+/// Node-1 does not send any RPC in these tests.
+struct NoNetwork;
+
+impl RaftNetworkFactory<C> for NoNetwork {
+    type Network = NoNetwork;
+
+    async fn new_client(&mut self, _target: u64, _node: &()) -> Self::Network {
+        NoNetwork
+    }
+}
+
+impl RaftNetworkV2<C> for NoNetwork {
+    async fn append_entries(
+        &mut self,
+        _rpc: AppendEntriesRequest<C>,
+        _option: RPCOption,
+    ) -> Result<AppendEntriesResponse<C>, RPCError<C>> {
+        Err(RPCError::Unreachable(Unreachable::new(&AnyError::error("no network"))))
+    }
+
+    async fn vote(&mut self, _rpc: VoteRequest<C>, _option: RPCOption) -> Result<VoteResponse<C>, RPCError<C>> {
+        Err(RPCError::Unreachable(Unreachable::new(&AnyError::error("no network"))))
+    }
+
+    async fn full_snapshot(
+        &mut self,
+        _vote: VoteOf<C>,
+        _snapshot: Snapshot<C>,
+        _cancel: impl std::future::Future<Output = ReplicationClosed> + OptionalSend + 'static,
+        _option: RPCOption,
+    ) -> Result<SnapshotResponse<C>, StreamingError<C>> {
+        Err(StreamingError::Unreachable(Unreachable::new(&AnyError::error(
+            "no network",
+        ))))
+    }
+}
+
+/// Node-0 sends AppendEntries to node-1 and corrupts the first entry with a checksum while
+/// `corrupt` is set.
+#[derive(Clone)]
+struct CorruptingNetwork {
+    target: Raft<C>,
+    corrupt: Arc<AtomicBool>,
+
+    /// The number of AppendEntries with entries sent.
+    sent: Arc<AtomicU64>,
+}
+
+impl RaftNetworkFactory<C> for CorruptingNetwork {
+    type Network = CorruptingNetwork;
+
+    async fn new_client(&mut self, _target: u64, _node: &()) -> Self::Network {
+        self.clone()
+    }
+}
+
+impl RaftNetworkV2<C> for CorruptingNetwork {
+    async fn append_entries(
+        &mut self,
+        mut rpc: AppendEntriesRequest<C>,
+        _option: RPCOption,
+    ) -> Result<AppendEntriesResponse<C>, RPCError<C>> {
+        if !rpc.entries.is_empty() {
+            self.sent.fetch_add(1, Ordering::Relaxed);
+        }
+
+        if self.corrupt.load(Ordering::Relaxed) {
+            if let Some(ent) = rpc.entries.iter_mut().find(|ent| ent.checksum.is_some()) {
+                ent.checksum = ent.checksum.map(|x| x.wrapping_add(1));
+            }
+        }
+
+        self.target.append_entries(rpc).await.map_err(|e| RPCError::Network(NetworkError::new(&e)))
+    }
+
+    async fn vote(&mut self, _rpc: VoteRequest<C>, _option: RPCOption) -> Result<VoteResponse<C>, RPCError<C>> {
+        Err(RPCError::Unreachable(Unreachable::new(&AnyError::error("no network"))))
+    }
+
+    async fn full_snapshot(
+        &mut self,
+        _vote: VoteOf<C>,
+        _snapshot: Snapshot<C>,
+        _cancel: impl std::future::Future<Output = ReplicationClosed> + OptionalSend + 'static,
+        _option: RPCOption,
+    ) -> Result<SnapshotResponse<C>, StreamingError<C>> {
+        Err(StreamingError::Unreachable(Unreachable::new(&AnyError::error(
+            "no network",
+        ))))
+    }
+}
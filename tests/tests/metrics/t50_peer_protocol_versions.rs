@@ -0,0 +1,185 @@
+use std::sync::Arc;
+use std::time::Duration;
+
+use anyhow::Result;
+use maplit::btreemap;
+use maplit::btreeset;
+use openraft::error::ClientWriteError;
+use openraft::network::ProtocolVersion;
+use openraft::type_config::TypeConfigExt;
+use openraft::Config;
+use openraft::RPCTypes;
+use openraft_memstore::ClientRequest;
+use openraft_memstore::IntoMemClientRequest;
+use openraft_memstore::TypeConfig;
+
+use crate::fixtures::ut_harness;
+use crate::fixtures::RaftRouter;
+
+/// Nodes exchange protocol versions by handshake, and the versions are reported in metrics.
+#[tracing::instrument]
+#[test_harness::test(harness = ut_harness)]
+async fn peer_protocol_versions() -> Result<()> {
+    let config = Arc::new(
+        Config {
+            enable_heartbeat: false,
+            ..Default::default()
+        }
+        .validate()?,
+    );
+
+    let mut router = RaftRouter::new(config.clone());
+
+    tracing::info!("--- initializing cluster");
+    let _log_index = router.new_cluster(btreeset! {0,1,2}, btreeset! {}).await?;
+
+    let current = ProtocolVersion::CURRENT;
+
+    tracing::info!("--- the Leader learns the versions of the followers");
+    {
+        let m = router
+            .wait(&0, timeout())
+            .metrics(
+                |m| m.peer_versions.len() == 2,
+                "node-0 knows versions of node-1 and node-2",
+            )
+            .await?;
+
+        assert_eq!(btreemap! {1=>current, 2=>current}, m.peer_versions);
+        assert!(!m.is_mixed_version());
+    }
+
+    tracing::info!("--- the followers learn the version of the Leader");
+    {
+        for id in [1, 2] {
+            let m = router
+                .wait(&id, timeout())
+                .metrics(
+                    |m| m.peer_versions.contains_key(&0),
+                    format!("node-{} knows version of node-0", id),
+                )
+                .await?;
+
+            assert_eq!(Some(&current), m.peer_versions.get(&0));
+        }
+    }
+
+    Ok(())
+}
+
+/// A node running an older protocol version is shown in metrics, and a feature it does not
+/// support is not used with it.
+#[tracing::instrument]
+#[test_harness::test(harness = ut_harness)]
+async fn mixed_version_cluster() -> Result<()> {
+    let config = Arc::new(
+        Config {
+            election_timeout_min: 150,
+            election_timeout_max: 300,
+            ..Default::default()
+        }
+        .validate()?,
+    );
+
+    let mut router = RaftRouter::new(config.clone());
+    router.set_protocol_version(0, ProtocolVersion::V1);
+
+    tracing::info!("--- initializing cluster, the Leader node-0 runs protocol v1");
+    let _log_index = router.new_cluster(btreeset! {0,1,2}, btreeset! {}).await?;
+
+    tracing::info!("--- a follower sees a mixed-version cluster");
+    {
+        let m = router
+            .wait(&1, timeout())
+            .metrics(|m| m.peer_versions.contains_key(&0), "node-1 knows version of node-0")
+            .await?;
+
+        assert_eq!(Some(&ProtocolVersion::V1), m.peer_versions.get(&0));
+        assert!(m.is_mixed_version());
+    }
+
+    tracing::info!("--- node-1 does not forward a client write to node-0");
+    {
+        let before = router.get_rpc_count().get(&RPCTypes::ClientWrite).copied().unwrap_or_default();
+
+        let n1 = router.get_raft_handle(&1)?;
+        let res = n1.client_write_forwarded(ClientRequest::make_request("c", 1), Duration::from_millis(200)).await;
+
+        let err = res.unwrap_err();
+        assert!(
+            matches!(err.api_error(), Some(ClientWriteError::ForwardToLeader(_))),
+            "got: {}",
+            err
+        );
+
+        let after = router.get_rpc_count().get(&RPCTypes::ClientWrite).copied().unwrap_or_default();
+        assert_eq!(before, after, "ForwardClientWrite is not sent to node-0");
+    }
+
+    Ok(())
+}
+
+/// A network that does not implement handshake makes every peer taken as speaking
+/// [`ProtocolVersion::V1`], and the handshake is not retried.
+#[tracing::instrument]
+#[test_harness::test(harness = ut_harness)]
+async fn handshake_unsupported_by_network() -> Result<()> {
+    let config = Arc::new(
+        Config {
+            heartbeat_interval: 50,
+            election_timeout_min: 150,
+            election_timeout_max: 300,
+            ..Default::default()
+        }
+        .validate()?,
+    );
+
+    let mut router = RaftRouter::new(config.clone());
+    router.set_handshake_unsupported(true);
+
+    tracing::info!("--- initializing cluster");
+    let _log_index = router.new_cluster(btreeset! {0,1,2}, btreeset! {}).await?;
+
+    tracing::info!("--- every peer is taken as speaking protocol v1");
+    {
+        let m = router
+            .wait(&0, timeout())
+            .metrics(
+                |m| m.peer_versions.len() == 2,
+                "node-0 knows versions of node-1 and node-2",
+            )
+            .await?;
+        assert_eq!(
+            btreemap! {1=>ProtocolVersion::V1, 2=>ProtocolVersion::V1},
+            m.peer_versions
+        );
+
+        for id in [1, 2] {
+            let m = router
+                .wait(&id, timeout())
+                .metrics(
+                    |m| m.peer_versions.contains_key(&0),
+                    format!("node-{} knows version of node-0", id),
+                )
+                .await?;
+            assert_eq!(Some(&ProtocolVersion::V1), m.peer_versions.get(&0));
+        }
+    }
+
+    tracing::info!("--- handshakes are not retried");
+    {
+        let before = router.get_rpc_count().get(&RPCTypes::Handshake).copied().unwrap_or_default();
+
+        // Heartbeats keep being sent to the followers in the meantime.
+        TypeConfig::sleep(Duration::from_millis(1_000)).await;
+
+        let after = router.get_rpc_count().get(&RPCTypes::Handshake).copied().unwrap_or_default();
+        assert_eq!(before, after, "no handshake is sent once the versions are known");
+    }
+
+    Ok(())
+}
+
+fn timeout() -> Option<Duration> {
+    Some(Duration::from_millis(1_000))
+}
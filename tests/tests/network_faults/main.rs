@@ -0,0 +1,12 @@
+#![cfg_attr(feature = "bt", feature(error_generic_member_access))]
+
+#[macro_use]
+#[path = "../fixtures/mod.rs"]
+mod fixtures;
+
+// The number indicate the preferred running order for these case.
+// See ./README.md
+
+mod t10_drop_response;
+mod t10_duplicate_and_reorder;
+mod t50_writes_under_message_faults;
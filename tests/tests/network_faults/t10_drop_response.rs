@@ -0,0 +1,89 @@
+use std::sync::Arc;
+use std::time::Duration;
+
+use anyhow::Result;
+use maplit::btreeset;
+use openraft::Config;
+
+use crate::fixtures::network_faults::FaultPolicy;
+use crate::fixtures::ut_harness;
+use crate::fixtures::RaftRouter;
+
+/// Responses of AppendEntries are lost after the followers applied the request.
+///
+/// What does this test do?
+///
+/// - bring up a 3-node cluster.
+/// - drop every response sent to the leader, write one log.
+/// - assert the followers receive the log but the leader can not commit it.
+/// - heal the network and assert the log is committed.
+#[tracing::instrument]
+#[test_harness::test(harness = ut_harness)]
+async fn drop_response() -> Result<()> {
+    let config = Arc::new(
+        Config {
+            heartbeat_interval: 50,
+            election_timeout_min: 500,
+            election_timeout_max: 1_000,
+            ..Default::default()
+        }
+        .validate()?,
+    );
+
+    let mut router = RaftRouter::new(config.clone());
+
+    tracing::info!("--- initializing cluster");
+    let mut log_index = router.new_cluster(btreeset! {0,1,2}, btreeset! {}).await?;
+
+    tracing::info!(log_index, "--- drop all responses to the leader, write one log");
+    let faults = router.network_faults().clone();
+    let drop_response = FaultPolicy {
+        drop_response: 1.0,
+        ..Default::default()
+    };
+    faults.set_link_policy(0, 1, Some(drop_response.clone()));
+    faults.set_link_policy(0, 2, Some(drop_response));
+
+    let write = {
+        let router = router.clone();
+        tokio::spawn(async move { router.client_request(0, "foo", 1).await })
+    };
+    log_index += 1;
+
+    for id in [1, 2] {
+        router
+            .wait(&id, timeout())
+            .log_index_at_least(Some(log_index), "follower accepted the log whose response is lost")
+            .await?;
+    }
+
+    tokio::time::sleep(Duration::from_millis(500)).await;
+    {
+        let m = router.get_metrics(&0)?;
+        assert!(
+            m.last_applied.map(|x| x.index) < Some(log_index),
+            "leader can not commit without responses: {:?}",
+            m.last_applied
+        );
+        assert!(faults.stats().dropped_responses > 0);
+    }
+
+    tracing::info!(log_index, "--- heal the network, the log is committed");
+    faults.clear();
+
+    write.await??;
+    router
+        .wait_for_log(
+            &btreeset![0, 1, 2],
+            Some(log_index),
+            timeout(),
+            "committed after healing",
+        )
+        .await?;
+
+    Ok(())
+}
+
+fn timeout() -> Option<Duration> {
+    Some(Duration::from_millis(5_000))
+}
@@ -0,0 +1,85 @@
+use std::sync::Arc;
+use std::time::Duration;
+
+use anyhow::Result;
+use maplit::btreeset;
+use openraft::Config;
+
+use crate::fixtures::network_faults::FaultPolicy;
+use crate::fixtures::ut_harness;
+use crate::fixtures::RaftRouter;
+
+/// Requests delivered twice, or delivered after newer requests, must not corrupt a follower.
+///
+/// What does this test do?
+///
+/// - bring up a 3-node cluster.
+/// - duplicate half of the requests and hold back some of them, so that followers receive stale
+///   AppendEntries and votes after newer ones.
+/// - write logs, heal the network, and assert every node has the same state machine.
+#[tracing::instrument]
+#[test_harness::test(harness = ut_harness)]
+async fn duplicate_and_reorder() -> Result<()> {
+    let config = Arc::new(
+        Config {
+            heartbeat_interval: 100,
+            election_timeout_min: 500,
+            election_timeout_max: 1_000,
+            max_payload_entries: 3,
+            ..Default::default()
+        }
+        .validate()?,
+    );
+
+    let mut router = RaftRouter::builder(config.clone()).fault_seed(1).build();
+
+    tracing::info!("--- initializing cluster");
+    let mut log_index = router.new_cluster(btreeset! {0,1,2}, btreeset! {}).await?;
+
+    tracing::info!(log_index, "--- duplicate and reorder requests, write logs");
+    let faults = router.network_faults().clone();
+    faults.set_default_policy(FaultPolicy {
+        reorder: 0.3,
+        duplicate: 0.5,
+        max_delay: Duration::from_millis(200),
+        ..Default::default()
+    });
+
+    let leader = router.leader().unwrap();
+    log_index += router.client_request_many(leader, "foo", 30).await?;
+
+    let stats = faults.stats();
+    tracing::info!("--- injected faults: {:?}", stats);
+    assert!(stats.duplicated > 0);
+    assert!(stats.reordered > 0);
+
+    tracing::info!(log_index, "--- heal the network, all nodes converge");
+    faults.clear();
+
+    let leader = router.leader().unwrap();
+    log_index += router.client_request_many(leader, "foo", 1).await?;
+    let last_log_index = router
+        .wait(&leader, timeout())
+        .metrics(|m| m.last_log_index >= Some(log_index), "leader appended all logs")
+        .await?
+        .last_log_index;
+
+    for id in [0, 1, 2] {
+        router.wait(&id, timeout()).applied_index(last_log_index, "converged").await?;
+    }
+
+    let mut state_machines = vec![];
+    for id in [0, 1, 2] {
+        let (_log_store, sm) = router.get_storage_handle(&id)?;
+        let sm = sm.get_state_machine().await;
+        state_machines.push((sm.last_applied_log, sm.client_status));
+    }
+    assert_eq!(state_machines[0], state_machines[1]);
+    assert_eq!(state_machines[0], state_machines[2]);
+
+    Ok(())
+}
+
+fn timeout() -> Option<Duration> {
+    Some(Duration::from_millis(10_000))
+}
@@ -0,0 +1,73 @@
+use std::sync::Arc;
+use std::time::Duration;
+
+use anyhow::Result;
+use maplit::btreeset;
+use openraft::Config;
+
+use crate::fixtures::linearizability::History;
+use crate::fixtures::network_faults::FaultPolicy;
+use crate::fixtures::ut_harness;
+use crate::fixtures::RaftRouter;
+
+/// Client histories stay linearizable when messages are dropped, reordered and duplicated.
+///
+/// What does this test do?
+///
+/// - bring up a 3-node cluster with every kind of message fault on every link.
+/// - run concurrent clients that write and read a shared key.
+/// - check the recorded history is linearizable.
+#[tracing::instrument]
+#[test_harness::test(harness = ut_harness)]
+async fn writes_under_message_faults() -> Result<()> {
+    let config = Arc::new(
+        Config {
+            heartbeat_interval: 50,
+            election_timeout_min: 150,
+            election_timeout_max: 300,
+            ..Default::default()
+        }
+        .validate()?,
+    );
+
+    for seed in 0..3 {
+        tracing::info!(seed, "--- run with seed");
+
+        let mut router = RaftRouter::builder(config.clone()).fault_seed(seed).build();
+        router.new_cluster(btreeset! {0,1,2}, btreeset! {}).await?;
+
+        router.network_faults().set_default_policy(FaultPolicy::uniform(0.1, Duration::from_millis(100)));
+
+        let history = History::new();
+
+        let mut clients = vec![];
+        for process in 0..3 {
+            let router = router.clone();
+            let history = history.clone();
+
+            clients.push(tokio::spawn(async move {
+                for i in 0..20 {
+                    let target = router.leader().unwrap_or(0);
+                    let op_timeout = Duration::from_millis(1_000);
+
+                    if i % 3 == 2 {
+                        let _ = tokio::time::timeout(op_timeout, history.read(&router, process, target, "x")).await;
+                    } else {
+                        let value = format!("p{}-{}", process, i);
+                        let write = history.write(&router, process, target, "x", &value);
+                        let _ = tokio::time::timeout(op_timeout, write).await;
+                    }
+                }
+            }));
+        }
+
+        for c in clients {
+            c.await?;
+        }
+
+        tracing::info!(seed, "--- injected faults: {:?}", router.network_faults().stats());
+        history.check().map_err(|e| e.context(format!("seed={}", seed)))?;
+    }
+
+    Ok(())
+}
@@ -8,5 +8,9 @@ mod t10_append_entries_partial_success;
 mod t50_append_entries_backoff;
 mod t50_append_entries_backoff_rejoin;
 mod t51_append_entries_too_large;
+mod t52_append_entries_retry_policy;
+mod t53_append_entries_adaptive_batch;
+mod t54_delegated_catch_up;
+mod t55_log_cache;
 mod t60_feature_loosen_follower_log_revert;
 mod t61_allow_follower_log_revert;
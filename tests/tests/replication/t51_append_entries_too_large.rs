@@ -74,12 +74,18 @@ async fn append_entries_too_large() -> Result<()> {
     }
 
     assert_eq!(
-        15,
+        14,
         count.load(Ordering::Relaxed),
-        "13 logs: M,B,normal*10,M; 2 failed RPC due to too-large, because each hint is used 10 times"
+        "13 logs: M,B,normal*10,M; 1 failed RPC due to too-large, because the batch size stays at the hint"
     );
     assert_eq!(13, count_small.load(Ordering::Relaxed), "13 logs: M,B,normal*10,M");
 
+    tracing::info!(log_index, "--- the batch size to node-1 is reported in metrics");
+    {
+        let m = router.get_metrics(&0)?;
+        assert_eq!(Some(&1), m.replication_batch_size.unwrap().get(&1));
+    }
+
     Ok(())
 }
 
@@ -0,0 +1,88 @@
+use std::sync::Arc;
+use std::time::Duration;
+
+use anyerror::AnyError;
+use anyhow::Result;
+use maplit::btreeset;
+use openraft::error::NetworkError;
+use openraft::error::RPCError;
+use openraft::network::retry::DefaultRetryPolicy;
+use openraft::network::retry::RPCErrorKind;
+use openraft::network::retry::RetryDecision;
+use openraft::network::retry::RetryPolicy;
+use openraft::raft::AppendEntriesRequest;
+use openraft::Config;
+use openraft::RPCTypes;
+use openraft_memstore::TypeConfig;
+
+use crate::fixtures::ut_harness;
+use crate::fixtures::RaftRouter;
+
+/// Retry at once with fewer entries upon a network error.
+struct ShrinkOnNetworkError;
+
+impl RetryPolicy<TypeConfig> for ShrinkOnNetworkError {
+    fn on_error(&mut self, rpc_type: RPCTypes, err: &RPCError<TypeConfig>, attempt: u64) -> RetryDecision {
+        match RPCErrorKind::of(err) {
+            RPCErrorKind::Network => RetryDecision::retry().with_shrink_payload(),
+            _ => RetryPolicy::<TypeConfig>::on_error(&mut DefaultRetryPolicy, rpc_type, err, attempt),
+        }
+    }
+}
+
+/// A custom [`RetryPolicy`] decides to shrink the payload upon an error other than
+/// `PayloadTooLarge`.
+///
+/// In this test, RaftNetwork::append_entries() returns a `NetworkError` if the number of entries is
+/// greater than 1, which the default policy would keep sending.
+#[tracing::instrument]
+#[test_harness::test(harness = ut_harness)]
+async fn append_entries_retry_policy() -> Result<()> {
+    let config = Arc::new(
+        Config {
+            enable_heartbeat: false,
+            ..Default::default()
+        }
+        .validate()?,
+    );
+
+    let mut router = RaftRouter::new(config.clone());
+    router.set_retry_policy(|| Box::new(ShrinkOnNetworkError));
+
+    tracing::info!("--- initializing cluster of 1 node");
+    let mut log_index = router.new_cluster(btreeset! {0}, btreeset! {}).await?;
+
+    let n = 10u64;
+
+    tracing::info!(log_index, "--- write {} entries to leader", n);
+    {
+        log_index += router.client_request_many(0, "0", n as usize).await?;
+        router.wait(&0, timeout()).applied_index(Some(log_index), format!("{} writes", n)).await?;
+    }
+
+    tracing::info!(log_index, "--- node-1 fails an rpc of more than 1 entry");
+    {
+        router.set_rpc_pre_hook(RPCTypes::AppendEntries, move |_router, req, _id, target| {
+            let r: AppendEntriesRequest<_> = req.try_into().unwrap();
+            if target == 1 && r.entries.len() > 1 {
+                return Err(NetworkError::new(&AnyError::error("too many entries")).into());
+            }
+            Ok(())
+        });
+    }
+
+    tracing::info!(log_index, "--- add node-1 as learner, it catches up with smaller rpc");
+    {
+        router.new_raft_node(1).await;
+        router.add_learner(0, 1).await?;
+        log_index += 1;
+
+        router.wait(&1, timeout()).applied_index(Some(log_index), "1 node added").await?;
+    }
+
+    Ok(())
+}
+
+fn timeout() -> Option<Duration> {
+    Some(Duration::from_millis(1_000))
+}
@@ -0,0 +1,68 @@
+use std::sync::Arc;
+use std::time::Duration;
+
+use anyhow::Result;
+use maplit::btreemap;
+use maplit::btreeset;
+use openraft::Config;
+
+use crate::fixtures::network_faults::FaultPolicy;
+use crate::fixtures::ut_harness;
+use crate::fixtures::RaftRouter;
+
+/// The batch size to a follower with a long link latency is not shrunk because of the latency
+/// alone, and the batch size to every target is reported in metrics.
+///
+/// - Learner-1 is on a LAN, learner-2 is on a WAN whose latency is more than half of the RPC
+///   timeout.
+/// - Both of them catch up, with the batch size kept at `max_payload_entries`.
+#[tracing::instrument]
+#[test_harness::test(harness = ut_harness)]
+async fn append_entries_adaptive_batch() -> Result<()> {
+    let config = Arc::new(
+        Config {
+            enable_heartbeat: false,
+            heartbeat_interval: 100,
+            max_payload_entries: 16,
+            ..Default::default()
+        }
+        .validate()?,
+    );
+
+    let mut router = RaftRouter::new(config.clone());
+
+    router.network_faults().set_link_policy(
+        0,
+        2,
+        Some(FaultPolicy {
+            latency: Duration::from_millis(60),
+            ..Default::default()
+        }),
+    );
+
+    tracing::info!("--- initializing cluster of 1 voter and 2 learners");
+    let mut log_index = router.new_cluster(btreeset! {0}, btreeset! {1,2}).await?;
+
+    let n = 64u64;
+
+    tracing::info!(log_index, "--- write {} entries to leader", n);
+    {
+        log_index += router.client_request_many(0, "0", n as usize).await?;
+
+        for id in [1, 2] {
+            router.wait(&id, timeout()).applied_index(Some(log_index), format!("{} writes", n)).await?;
+        }
+    }
+
+    tracing::info!(log_index, "--- the batch size to either learner is not shrunk");
+    {
+        let m = router.get_metrics(&0)?;
+        assert_eq!(Some(btreemap! {1=>16, 2=>16}), m.replication_batch_size);
+    }
+
+    Ok(())
+}
+
+fn timeout() -> Option<Duration> {
+    Some(Duration::from_millis(5_000))
+}
@@ -0,0 +1,75 @@
+use std::sync::Arc;
+use std::sync::Mutex;
+use std::time::Duration;
+
+use anyhow::Result;
+use maplit::btreeset;
+use openraft::Config;
+use openraft::RPCTypes;
+
+use crate::fixtures::ut_harness;
+use crate::fixtures::RPCRequest;
+use crate::fixtures::RaftRouter;
+
+/// A lagging learner is brought up to date by an up-to-date follower, on behalf of the Leader.
+///
+/// - Leader-0 and follower-1 have 50 more logs than a newly added learner-2.
+/// - Leader-0 asks follower-1 to send the committed logs to learner-2, and learner-2 catches up.
+#[tracing::instrument]
+#[test_harness::test(harness = ut_harness)]
+async fn delegated_catch_up() -> Result<()> {
+    let config = Arc::new(
+        Config {
+            enable_heartbeat: false,
+            max_payload_entries: 8,
+            catch_up_delegation_threshold: 10,
+            ..Default::default()
+        }
+        .validate()?,
+    );
+
+    let mut router = RaftRouter::new(config.clone());
+
+    let catch_ups = Arc::new(Mutex::new(vec![]));
+    {
+        let catch_ups = catch_ups.clone();
+        router.set_rpc_pre_hook(RPCTypes::CatchUp, move |_router, req, from, to| {
+            if let RPCRequest::CatchUp(req) = req {
+                catch_ups.lock().unwrap().push((from, to, req.target, req.prev_log_id.map(|x| x.index)));
+            }
+            Ok(())
+        });
+    }
+
+    tracing::info!("--- initializing cluster of 2 voters");
+    let mut log_index = router.new_cluster(btreeset! {0,1}, btreeset! {}).await?;
+
+    let n = 50;
+
+    tracing::info!(log_index, "--- write {} entries to leader", n);
+    {
+        log_index += router.client_request_many(0, "0", n).await?;
+        router.wait(&1, timeout()).applied_index(Some(log_index), format!("{} writes", n)).await?;
+    }
+
+    tracing::info!(log_index, "--- add learner-2, it is caught up by follower-1");
+    {
+        router.new_raft_node(2).await;
+        router.add_learner(0, 2).await?;
+        log_index += 1;
+
+        router.wait(&2, timeout()).applied_index(Some(log_index), "learner-2 caught up").await?;
+
+        let catch_ups = catch_ups.lock().unwrap().clone();
+        tracing::info!("catch-up requests: {:?}", catch_ups);
+
+        assert!(!catch_ups.is_empty());
+        assert!(catch_ups.iter().all(|(from, to, target, _)| (*from, *to, *target) == (0, 1, 2)));
+    }
+
+    Ok(())
+}
+
+fn timeout() -> Option<Duration> {
+    Some(Duration::from_millis(5_000))
+}
@@ -0,0 +1,85 @@
+use std::sync::Arc;
+use std::time::Duration;
+
+use anyhow::Result;
+use maplit::btreeset;
+use openraft::Config;
+use openraft::RaftLogReader;
+
+use crate::fixtures::ut_harness;
+use crate::fixtures::RaftRouter;
+
+/// Replication streams read recently appended entries from the Leader's log cache, and read older
+/// entries from the log store.
+///
+/// - With the cache enabled, entries written to the Leader are replicated from the cache.
+/// - A learner added later needs the initial membership entry, which is appended before node-0
+///   becomes Leader and thus is not cached, and reads it from the log store.
+#[tracing::instrument]
+#[test_harness::test(harness = ut_harness)]
+async fn log_cache() -> Result<()> {
+    let config = Arc::new(
+        Config {
+            enable_heartbeat: false,
+            log_cache_size: 100,
+            ..Default::default()
+        }
+        .validate()?,
+    );
+
+    let mut router = RaftRouter::new(config.clone());
+
+    tracing::info!("--- initializing cluster of 1 voter and 1 learner");
+    let mut log_index = router.new_cluster(btreeset! {0}, btreeset! {1}).await?;
+
+    let m = router.get_metrics(&0)?;
+    let (hits, misses) = (m.log_cache_hits, m.log_cache_misses);
+
+    tracing::info!(log_index, "--- write to leader, replicated from the cache");
+    {
+        log_index += router.client_request_many(0, "0", 10).await?;
+        router.wait(&1, timeout()).applied_index(Some(log_index), "learner-1 applied").await?;
+
+        let m = router
+            .wait(&0, timeout())
+            .metrics(|m| m.log_cache_hits > hits, "leader reads from the cache")
+            .await?;
+        assert_eq!(misses, m.log_cache_misses, "no entries are read from the log store");
+    }
+
+    tracing::info!(
+        log_index,
+        "--- add learner-2, early entries are read from the log store"
+    );
+    {
+        router.new_raft_node(2).await;
+        router.add_learner(0, 2).await?;
+        log_index += 1;
+
+        router.wait(&2, timeout()).applied_index(Some(log_index), "learner-2 applied").await?;
+
+        router
+            .wait(&0, timeout())
+            .metrics(|m| m.log_cache_misses > misses, "leader reads from the log store")
+            .await?;
+    }
+
+    tracing::info!(log_index, "--- learners have the same logs as the leader");
+    {
+        let (mut leader_store, _) = router.get_storage_handle(&0)?;
+        let want = leader_store.try_get_log_entries(..).await?;
+
+        for id in [1, 2] {
+            let (mut sto, _) = router.get_storage_handle(&id)?;
+            let got = sto.try_get_log_entries(..).await?;
+            let ids = |v: &Vec<openraft::Entry<_>>| v.iter().map(|e| e.log_id).collect::<Vec<_>>();
+            assert_eq!(ids(&want), ids(&got), "learner-{} logs", id);
+        }
+    }
+
+    Ok(())
+}
+
+fn timeout() -> Option<Duration> {
+    Some(Duration::from_millis(5_000))
+}
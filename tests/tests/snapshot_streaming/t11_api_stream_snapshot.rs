@@ -0,0 +1,159 @@
+use std::sync::Arc;
+use std::time::Duration;
+
+use anyhow::Result;
+use maplit::btreeset;
+use openraft::error::NetworkError;
+use openraft::error::RaftError;
+use openraft::error::StreamingError;
+use openraft::network::compression::Compression;
+use openraft::network::v2::snapshot_stream::send_snapshot;
+use openraft::network::v2::snapshot_stream::ReceiveSnapshotError;
+use openraft::network::v2::snapshot_stream::SnapshotAssembler;
+use openraft::network::v2::snapshot_stream::SnapshotChunk;
+use openraft::network::v2::snapshot_stream::SnapshotChunkSink;
+use openraft::network::RPCOption;
+use openraft::raft::SnapshotResponse;
+use openraft::Config;
+use openraft::Vote;
+use openraft_memstore::TypeConfig;
+use tokio::sync::mpsc;
+
+use crate::fixtures::log_id;
+use crate::fixtures::ut_harness;
+use crate::fixtures::RaftRouter;
+
+/// Sends chunks to a task running a [`SnapshotAssembler`], and receives the acknowledgements.
+struct ChannelSink {
+    tx: mpsc::UnboundedSender<SnapshotChunk<TypeConfig>>,
+    rx: mpsc::UnboundedReceiver<Result<SnapshotResponse<TypeConfig>, RaftError<TypeConfig, ReceiveSnapshotError>>>,
+}
+
+impl SnapshotChunkSink<TypeConfig> for ChannelSink {
+    async fn send_chunk(&mut self, chunk: SnapshotChunk<TypeConfig>) -> Result<(), StreamingError<TypeConfig>> {
+        self.tx.send(chunk).map_err(|e| NetworkError::new(&e))?;
+        Ok(())
+    }
+
+    async fn recv_ack(&mut self) -> Result<SnapshotResponse<TypeConfig>, StreamingError<TypeConfig>> {
+        let res = self.rx.recv().await.ok_or_else(|| NetworkError::new(&anyerror::AnyError::error("closed")))?;
+        res.map_err(|e| NetworkError::new(&e).into())
+    }
+
+    fn compression(&self) -> Compression {
+        Compression::supported()[0]
+    }
+}
+
+/// API test: stream a snapshot in chunks with `send_snapshot()` and install it on the receiver
+/// with `SnapshotAssembler`.
+#[tracing::instrument]
+#[test_harness::test(harness = ut_harness)]
+async fn api_stream_snapshot() -> Result<()> {
+    let config = Arc::new(
+        Config {
+            enable_heartbeat: false,
+            ..Default::default()
+        }
+        .validate()?,
+    );
+
+    let mut router = RaftRouter::new(config.clone());
+
+    tracing::info!("--- initializing cluster");
+    let mut log_index = router.new_cluster(btreeset! {0}, btreeset! {}).await?;
+
+    tracing::info!(log_index, "--- build a snapshot on node-0");
+    let n0 = router.get_raft_handle(&0)?;
+    {
+        log_index += router.client_request_many(0, "0", 10).await?;
+        router.wait(&0, timeout()).applied_index(Some(log_index), "node-0 applied all requests").await?;
+
+        n0.trigger().snapshot().await?;
+        router.wait(&0, timeout()).snapshot(log_id(1, 0, log_index), "node-0 snapshot").await?;
+    }
+
+    tracing::info!(log_index, "--- add a standalone node-1");
+    router.new_raft_node(1).await;
+    let n1 = router.get_raft_handle(&1)?;
+
+    tracing::info!(log_index, "--- chunks that do not continue the stream are rejected");
+    {
+        let is_mismatch = |res: &Result<_, RaftError<TypeConfig, ReceiveSnapshotError>>| {
+            matches!(res, Err(RaftError::APIError(ReceiveSnapshotError::SnapshotMismatch(_))))
+        };
+
+        let mut assembler = SnapshotAssembler::new(n1.clone());
+        let data = |offset| SnapshotChunk::Data {
+            offset,
+            compression: Compression::None,
+            data: vec![1, 2, 3],
+        };
+
+        let res = assembler.receive(data(0)).await;
+        assert!(is_mismatch(&res), "Data without Start, got: {:?}", res);
+
+        let snapshot = n0.get_snapshot().await?.unwrap();
+        assembler
+            .receive(SnapshotChunk::Start {
+                vote: Vote::new_committed(1, 0),
+                meta: snapshot.meta,
+            })
+            .await?;
+        assembler.receive(data(0)).await?;
+
+        // Not a zstd frame, or zstd is not enabled.
+        let res = assembler
+            .receive(SnapshotChunk::Data {
+                offset: 3,
+                compression: Compression::Zstd,
+                data: vec![1, 2, 3],
+            })
+            .await;
+        assert!(
+            matches!(res, Err(RaftError::APIError(ReceiveSnapshotError::Compression(_)))),
+            "Data that can not be decompressed, got: {:?}",
+            res
+        );
+
+        let res = assembler.receive(data(5)).await;
+        assert!(is_mismatch(&res), "Data at a wrong offset, got: {:?}", res);
+
+        let res = assembler.receive(SnapshotChunk::Finish { size: 4 }).await;
+        assert!(is_mismatch(&res), "Finish with a wrong size, got: {:?}", res);
+    }
+
+    tracing::info!(log_index, "--- stream the snapshot from node-0 to node-1");
+    {
+        let (chunk_tx, mut chunk_rx) = mpsc::unbounded_channel();
+        let (ack_tx, ack_rx) = mpsc::unbounded_channel();
+
+        let mut assembler = SnapshotAssembler::new(n1.clone());
+        tokio::spawn(async move {
+            while let Some(chunk) = chunk_rx.recv().await {
+                let _ = ack_tx.send(assembler.receive(chunk).await);
+            }
+        });
+
+        let snapshot = n0.get_snapshot().await?.unwrap();
+        let vote = Vote::new_committed(1, 0);
+
+        let option = RPCOption::new(Duration::from_millis(1_000));
+
+        let mut sink = ChannelSink {
+            tx: chunk_tx,
+            rx: ack_rx,
+        };
+        let resp = send_snapshot(&mut sink, vote, snapshot, futures::future::pending(), &option, 3).await?;
+        assert_eq!(vote, resp.vote);
+
+        router.wait(&1, timeout()).snapshot(log_id(1, 0, log_index), "node-1 snapshot").await?;
+        router.wait(&1, timeout()).applied_index(Some(log_index), "node-1 applied snapshot").await?;
+    }
+
+    Ok(())
+}
+
+fn timeout() -> Option<Duration> {
+    Some(Duration::from_millis(1_000))
+}
@@ -0,0 +1,128 @@
+use std::sync::Arc;
+use std::time::Duration;
+
+use anyhow::Result;
+use futures::future::try_join_all;
+use openraft::raft::AppendEntriesRequest;
+use openraft::storage::RaftStateMachine;
+use openraft::Config;
+use openraft::RaftLogReader;
+use openraft::Vote;
+use openraft_memstore::ClientRequest;
+use openraft_memstore::IntoMemClientRequest;
+
+use crate::fixtures::log_id;
+use crate::fixtures::parallel_sm::ParallelStateMachine;
+use crate::fixtures::ut_harness;
+use crate::fixtures::RaftRouter;
+
+/// Entries of different clients are applied in parallel by `ParallelStateMachine`, while the
+/// responses and the last applied log id are still in log order.
+///
+/// - Several clients write concurrently, so that a committed batch contains entries of different
+///   clients, and entries of the same client.
+/// - Every response contains the previous status of the same client.
+/// - Two entries of different clients in one batch are applied at the same time: each waits for the
+///   other on a barrier.
+#[tracing::instrument]
+#[test_harness::test(harness = ut_harness)]
+async fn parallel_apply() -> Result<()> {
+    let config = Arc::new(
+        Config {
+            enable_heartbeat: false,
+            ..Default::default()
+        }
+        .validate()?,
+    );
+
+    let mut router = RaftRouter::new(config.clone());
+
+    let sms = [ParallelStateMachine::default(), ParallelStateMachine::default()];
+
+    tracing::info!("--- initializing cluster");
+    let mut log_index = {
+        router.new_raft_node_with_sm(0, sms[0].clone()).await;
+        router.initialize(0).await?;
+        router.wait(&0, timeout()).applied_index(Some(1), "init").await?;
+
+        router.new_raft_node_with_sm(1, sms[1].clone()).await;
+        router.add_learner(0, 1).await?;
+        router.wait(&1, timeout()).applied_index(Some(2), "learner added").await?;
+        2
+    };
+
+    let n_clients = 5;
+    let n_requests = 20;
+
+    tracing::info!(log_index, "--- write concurrently from {} clients", n_clients);
+    {
+        let clients = (0..n_clients).map(|c| {
+            let router = router.clone();
+            async move {
+                for serial in 0..n_requests {
+                    let req = ClientRequest::make_request(format!("client-{}", c), serial);
+                    let resp = router.send_client_request(0, req).await?;
+
+                    let want = serial.checked_sub(1).map(|prev| format!("request-{}", prev));
+                    assert_eq!(want, resp.0, "client-{} serial-{}", c, serial);
+                }
+                anyhow::Ok(())
+            }
+        });
+        try_join_all(clients).await?;
+
+        log_index += n_clients * n_requests;
+    }
+
+    for id in [0, 1] {
+        router.wait(&id, timeout()).applied_index(Some(log_index), "all applied").await?;
+
+        let mut sm = sms[id as usize].clone();
+        let (last_applied, _) = sm.applied_state().await?;
+        assert_eq!(Some(log_index), last_applied.map(|x| x.index));
+
+        let statuses = sm.statuses();
+        assert_eq!(n_clients as usize, statuses.len());
+        for status in statuses.values() {
+            assert_eq!(&format!("request-{}", n_requests - 1), status);
+        }
+    }
+
+    tracing::info!(log_index, "--- entries of two clients are applied at the same time");
+    {
+        sms[1].set_barrier(2);
+
+        // Cut the learner off from the Leader and send it both committed entries in one
+        // AppendEntries, otherwise they may be replicated, and then applied, one by one.
+        router.set_network_error(1, true);
+        for c in 0..2 {
+            let req = ClientRequest::make_request(format!("client-{}", c), n_requests);
+            router.send_client_request(0, req).await?;
+        }
+
+        let (mut sto0, _sm0) = router.get_storage_handle(&0)?;
+        let entries = sto0.try_get_log_entries(log_index + 1..).await?;
+        assert_eq!(2, entries.len());
+
+        let n1 = router.get_raft_handle(&1)?;
+        let resp = n1
+            .append_entries(AppendEntriesRequest {
+                vote: Vote::new_committed(1, 0),
+                prev_log_id: Some(log_id(1, 0, log_index)),
+                entries,
+                leader_commit: Some(log_id(1, 0, log_index + 2)),
+            })
+            .await?;
+        assert!(resp.is_success());
+        log_index += 2;
+
+        router.wait(&1, timeout()).applied_index(Some(log_index), "applied in parallel").await?;
+        router.set_network_error(1, false);
+    }
+
+    Ok(())
+}
+
+fn timeout() -> Option<Duration> {
+    Some(Duration::from_millis(5_000))
+}